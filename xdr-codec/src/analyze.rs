@@ -0,0 +1,483 @@
+//! Decoding of captured RPC traffic
+//!
+//! An `Analyzer` decodes the records of an ONC RPC conversation (RFC 5531): it decodes each
+//! record's `rpc_msg` header, then uses a `Schema` of procedure argument and result types to
+//! decode the call's arguments or the reply's results. Replies are matched to their calls by
+//! `xid`, so calls must be seen before their replies.
+//!
+//! `Analyzer::analyze()` reads a record-marked TCP byte stream, such as one direction of a
+//! connection reassembled from a packet capture, and writes each message as a line of JSON.
+//! To decode both directions of a connection, pass their records to `Analyzer::decode()` in
+//! the order they were captured.
+//!
+//! ```
+//! use xdr_codec::analyze::{Analyzer, Procedure, Schema};
+//! use xdr_codec::describe::{Descriptor, Field, Kind, TypeDesc};
+//! use xdr_codec::record::XdrRecordWriter;
+//! use xdr_codec::{pack, Write};
+//!
+//! fn args() -> Descriptor {
+//!     Descriptor {
+//!         name: "args",
+//!         kind: Kind::Struct(vec![Field { name: "n", ty: TypeDesc::Int }]),
+//!     }
+//! }
+//!
+//! let schema = Schema::new().procedure(100, 1, 1, Procedure {
+//!     name: "DOUBLE",
+//!     args: Some(args()),
+//!     results: None,
+//! });
+//!
+//! // xid 7, CALL, rpcvers 2, prog 100, vers 1, proc 1, AUTH_NONE cred and verf, n = 21
+//! let mut stream = Vec::new();
+//! {
+//!     let mut wr = XdrRecordWriter::new(&mut stream);
+//!     for v in &[7u32, 0, 2, 100, 1, 1, 0, 0, 0, 0, 21] {
+//!         pack(v, &mut wr).unwrap();
+//!     }
+//!     wr.end_record().unwrap();
+//! }
+//!
+//! let mut out = Vec::new();
+//! Analyzer::new(schema).analyze(&stream[..], &mut out).unwrap();
+//! let line = String::from_utf8(out).unwrap();
+//! assert!(line.starts_with(r#"{"xid":7,"procedure":"DOUBLE","#));
+//! assert!(line.ends_with("\"body\":{\"n\":21}}\n"));
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+
+use describe::{Case, Descriptor, EnumValue, Field, Kind, Size, TypeDesc};
+use error::*;
+use record::XdrRecordReader;
+use value::{self, Value};
+
+/// The types of a procedure's arguments and results. `None` is `void`.
+#[derive(Debug, Clone)]
+pub struct Procedure {
+    pub name: &'static str,
+    pub args: Option<Descriptor>,
+    pub results: Option<Descriptor>,
+}
+
+/// The procedures of one or more RPC programs.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    procs: BTreeMap<(u32, u32, u32), Procedure>,
+}
+
+impl Schema {
+    /// Create an empty schema.
+    pub fn new() -> Schema {
+        Default::default()
+    }
+
+    /// Add a procedure of a program version.
+    pub fn procedure(mut self, prog: u32, vers: u32, proc_: u32, p: Procedure) -> Schema {
+        let _ = self.procs.insert((prog, vers, proc_), p);
+        self
+    }
+
+    /// Find a procedure of a program version.
+    pub fn lookup(&self, prog: u32, vers: u32, proc_: u32) -> Option<&Procedure> {
+        self.procs.get(&(prog, vers, proc_))
+    }
+}
+
+/// The body of a message, following its header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Body {
+    /// There is no body: the procedure's arguments or results are `void`, or the reply isn't
+    /// successful.
+    None,
+    /// The decoded arguments or results.
+    Value(Value),
+    /// The body's type isn't known, because the procedure isn't in the schema or the reply's
+    /// call wasn't seen.
+    Unknown(Vec<u8>),
+    /// The body couldn't be decoded as its type.
+    Invalid(Vec<u8>, String),
+}
+
+/// A decoded RPC message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// The message's `xid`.
+    pub xid: u32,
+    /// The `rpc_msg` header.
+    pub header: Value,
+    /// The name of the procedure called or replied to, if known.
+    pub procedure: Option<&'static str>,
+    pub body: Body,
+}
+
+impl Message {
+    /// Format this message as a JSON object, with `xid`, `procedure`, `header` and `body`
+    /// members. Bodies which couldn't be decoded are `null`, with the raw body as hex in an
+    /// `undecoded` member, and the reason in an `error` member if it was invalid.
+    pub fn to_json(&self) -> String {
+        let procedure = match self.procedure {
+            Some(name) => Value::String(name.to_string()).to_json(),
+            None => "null".to_string(),
+        };
+        let mut out = format!(
+            r#"{{"xid":{},"procedure":{},"header":{},"body":"#,
+            self.xid,
+            procedure,
+            self.header.to_json()
+        );
+
+        match self.body {
+            Body::None => out.push_str("null"),
+            Body::Value(ref v) => out.push_str(&v.to_json()),
+            Body::Unknown(ref raw) => {
+                out.push_str(r#"null,"undecoded":"#);
+                out.push_str(&Value::Opaque(raw.clone()).to_json());
+            }
+            Body::Invalid(ref raw, ref err) => {
+                out.push_str(r#"null,"undecoded":"#);
+                out.push_str(&Value::Opaque(raw.clone()).to_json());
+                out.push_str(r#","error":"#);
+                out.push_str(&Value::String(err.clone()).to_json());
+            }
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// Decoder for the records of an RPC conversation.
+#[derive(Debug)]
+pub struct Analyzer {
+    schema: Schema,
+    // Procedure of each call awaiting a reply, by xid
+    calls: HashMap<u32, (u32, u32, u32)>,
+}
+
+impl Analyzer {
+    /// Create an analyzer using `schema` to decode message bodies.
+    pub fn new(schema: Schema) -> Analyzer {
+        Analyzer {
+            schema,
+            calls: HashMap::new(),
+        }
+    }
+
+    /// Decode one record.
+    ///
+    /// This only fails if the record doesn't start with a valid `rpc_msg` header. Bodies which
+    /// can't be decoded are returned as `Body::Invalid`.
+    pub fn decode(&mut self, record: &[u8]) -> Result<Message> {
+        let mut input = record;
+        let (header, _) = value::unpack(&rpc_msg(), &mut input)?;
+
+        let xid = header.get("xid").and_then(Value::as_i64).unwrap_or(0) as u32;
+        let num = |path: &str| header.get(path).and_then(Value::as_i64).unwrap_or(0) as u32;
+
+        let (procedure, desc) = match header.get("body.mtype").and_then(Value::as_i64) {
+            Some(0) => {
+                let key = (
+                    num("body.cbody.prog"),
+                    num("body.cbody.vers"),
+                    num("body.cbody.proc"),
+                );
+                let _ = self.calls.insert(xid, key);
+                let p = self.schema.lookup(key.0, key.1, key.2);
+                (p.map(|p| p.name), p.map(|p| p.args.as_ref()))
+            }
+            _ => {
+                let key = self.calls.remove(&xid);
+                let p = key.and_then(|k| self.schema.lookup(k.0, k.1, k.2));
+                let success = header.get("body.rbody.stat").and_then(Value::as_i64) == Some(0)
+                    && header
+                        .get("body.rbody.areply.reply_data.stat")
+                        .and_then(Value::as_i64)
+                        == Some(0);
+                let results = if success {
+                    p.map(|p| p.results.as_ref())
+                } else {
+                    Some(None)
+                };
+                (p.map(|p| p.name), results)
+            }
+        };
+
+        let body = match desc {
+            None => Body::Unknown(input.to_vec()),
+            Some(None) => Body::None,
+            Some(Some(desc)) => match value::unpack(desc, &mut &input[..]) {
+                Ok((v, _)) => Body::Value(v),
+                Err(e) => Body::Invalid(input.to_vec(), e.to_string()),
+            },
+        };
+
+        Ok(Message {
+            xid,
+            header,
+            procedure,
+            body,
+        })
+    }
+
+    /// Decode the records of a record-marked stream, writing each message to `out` as a line
+    /// of JSON.
+    ///
+    /// Records which aren't RPC messages are written as an object with an `error` member and
+    /// the raw `record` in hex. Returns the number of records read.
+    pub fn analyze<R: BufRead, W: Write>(&mut self, input: R, out: &mut W) -> Result<usize> {
+        let mut count = 0;
+
+        for rec in XdrRecordReader::new(input) {
+            let rec = rec?;
+            count += 1;
+
+            let line = match self.decode(&rec) {
+                Ok(msg) => msg.to_json(),
+                Err(e) => format!(
+                    r#"{{"error":{},"record":{}}}"#,
+                    Value::String(e.to_string()).to_json(),
+                    Value::Opaque(rec).to_json()
+                ),
+            };
+            writeln!(out, "{}", line)?;
+        }
+
+        Ok(count)
+    }
+}
+
+fn enum_desc(name: &'static str, values: &[(&'static str, i64)]) -> Descriptor {
+    Descriptor {
+        name,
+        kind: Kind::Enum(
+            values
+                .iter()
+                .map(|v| EnumValue {
+                    name: v.0,
+                    value: v.1,
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn field(name: &'static str, ty: TypeDesc) -> Field {
+    Field { name, ty }
+}
+
+fn case(value: Option<i64>, variant: &'static str, field: Option<Field>) -> Case {
+    Case {
+        value,
+        variant,
+        field,
+    }
+}
+
+/// Describe the header of an RPC message (RFC 5531's `rpc_msg`), up to the call's arguments or
+/// the reply's results.
+///
+/// Parts of the header can be found with paths like `body.cbody.prog` for a call's program, or
+/// `body.rbody.areply.reply_data.stat` for an accepted reply's status.
+pub fn rpc_msg() -> Descriptor {
+    Descriptor {
+        name: "rpc_msg",
+        kind: Kind::Struct(vec![
+            field("xid", TypeDesc::UInt),
+            field("body", TypeDesc::Named("rpc_msg_body", rpc_msg_body)),
+        ]),
+    }
+}
+
+fn rpc_msg_body() -> Descriptor {
+    Descriptor {
+        name: "rpc_msg_body",
+        kind: Kind::Union {
+            selector: field("mtype", TypeDesc::Named("msg_type", msg_type)),
+            cases: vec![
+                case(
+                    Some(0),
+                    "CALL",
+                    Some(field("cbody", TypeDesc::Named("call_body", call_body))),
+                ),
+                case(
+                    Some(1),
+                    "REPLY",
+                    Some(field("rbody", TypeDesc::Named("reply_body", reply_body))),
+                ),
+            ],
+        },
+    }
+}
+
+fn msg_type() -> Descriptor {
+    enum_desc("msg_type", &[("CALL", 0), ("REPLY", 1)])
+}
+
+fn call_body() -> Descriptor {
+    Descriptor {
+        name: "call_body",
+        kind: Kind::Struct(vec![
+            field("rpcvers", TypeDesc::UInt),
+            field("prog", TypeDesc::UInt),
+            field("vers", TypeDesc::UInt),
+            field("proc", TypeDesc::UInt),
+            field("cred", TypeDesc::Named("opaque_auth", opaque_auth)),
+            field("verf", TypeDesc::Named("opaque_auth", opaque_auth)),
+        ]),
+    }
+}
+
+fn opaque_auth() -> Descriptor {
+    Descriptor {
+        name: "opaque_auth",
+        kind: Kind::Struct(vec![
+            field("flavor", TypeDesc::Named("auth_flavor", auth_flavor)),
+            field("body", TypeDesc::Opaque(Size::Flex(Some(400)))),
+        ]),
+    }
+}
+
+fn auth_flavor() -> Descriptor {
+    enum_desc(
+        "auth_flavor",
+        &[
+            ("AUTH_NONE", 0),
+            ("AUTH_SYS", 1),
+            ("AUTH_SHORT", 2),
+            ("AUTH_DH", 3),
+            ("RPCSEC_GSS", 6),
+        ],
+    )
+}
+
+fn reply_body() -> Descriptor {
+    Descriptor {
+        name: "reply_body",
+        kind: Kind::Union {
+            selector: field("stat", TypeDesc::Named("reply_stat", reply_stat)),
+            cases: vec![
+                case(
+                    Some(0),
+                    "MSG_ACCEPTED",
+                    Some(field(
+                        "areply",
+                        TypeDesc::Named("accepted_reply", accepted_reply),
+                    )),
+                ),
+                case(
+                    Some(1),
+                    "MSG_DENIED",
+                    Some(field(
+                        "rreply",
+                        TypeDesc::Named("rejected_reply", rejected_reply),
+                    )),
+                ),
+            ],
+        },
+    }
+}
+
+fn reply_stat() -> Descriptor {
+    enum_desc("reply_stat", &[("MSG_ACCEPTED", 0), ("MSG_DENIED", 1)])
+}
+
+fn accepted_reply() -> Descriptor {
+    Descriptor {
+        name: "accepted_reply",
+        kind: Kind::Struct(vec![
+            field("verf", TypeDesc::Named("opaque_auth", opaque_auth)),
+            field("reply_data", TypeDesc::Named("reply_data", reply_data)),
+        ]),
+    }
+}
+
+fn reply_data() -> Descriptor {
+    Descriptor {
+        name: "reply_data",
+        kind: Kind::Union {
+            selector: field("stat", TypeDesc::Named("accept_stat", accept_stat)),
+            cases: vec![
+                // The results follow the header
+                case(Some(0), "SUCCESS", None),
+                case(
+                    Some(2),
+                    "PROG_MISMATCH",
+                    Some(field(
+                        "mismatch_info",
+                        TypeDesc::Named("mismatch_info", mismatch_info),
+                    )),
+                ),
+                case(None, "default", None),
+            ],
+        },
+    }
+}
+
+fn accept_stat() -> Descriptor {
+    enum_desc(
+        "accept_stat",
+        &[
+            ("SUCCESS", 0),
+            ("PROG_UNAVAIL", 1),
+            ("PROG_MISMATCH", 2),
+            ("PROC_UNAVAIL", 3),
+            ("GARBAGE_ARGS", 4),
+            ("SYSTEM_ERR", 5),
+        ],
+    )
+}
+
+fn mismatch_info() -> Descriptor {
+    Descriptor {
+        name: "mismatch_info",
+        kind: Kind::Struct(vec![
+            field("low", TypeDesc::UInt),
+            field("high", TypeDesc::UInt),
+        ]),
+    }
+}
+
+fn rejected_reply() -> Descriptor {
+    Descriptor {
+        name: "rejected_reply",
+        kind: Kind::Union {
+            selector: field("stat", TypeDesc::Named("reject_stat", reject_stat)),
+            cases: vec![
+                case(
+                    Some(0),
+                    "RPC_MISMATCH",
+                    Some(field(
+                        "mismatch_info",
+                        TypeDesc::Named("mismatch_info", mismatch_info),
+                    )),
+                ),
+                case(
+                    Some(1),
+                    "AUTH_ERROR",
+                    Some(field("auth_stat", TypeDesc::Named("auth_stat", auth_stat))),
+                ),
+            ],
+        },
+    }
+}
+
+fn reject_stat() -> Descriptor {
+    enum_desc("reject_stat", &[("RPC_MISMATCH", 0), ("AUTH_ERROR", 1)])
+}
+
+fn auth_stat() -> Descriptor {
+    enum_desc(
+        "auth_stat",
+        &[
+            ("AUTH_OK", 0),
+            ("AUTH_BADCRED", 1),
+            ("AUTH_REJECTEDCRED", 2),
+            ("AUTH_BADVERF", 3),
+            ("AUTH_REJECTEDVERF", 4),
+            ("AUTH_TOOWEAK", 5),
+            ("AUTH_INVALIDRESP", 6),
+            ("AUTH_FAILED", 7),
+        ],
+    )
+}
@@ -0,0 +1,322 @@
+//! Arbitrary values for property testing and fuzzing
+//!
+//! With the `arbitrary` feature, this implements
+//! [`Arbitrary`](https://docs.rs/arbitrary) for this crate's types, and provides helpers for
+//! generating values which respect XDR size limits, so they can always be packed. xdrgen's
+//! `--arbitrary` option uses these to implement `Arbitrary` for every generated type.
+//!
+//! The `arbitrary` crate's `Arbitrary`, `Unstructured` and `Result` are re-exported here so that
+//! generated code doesn't need a direct dependency on it.
+//!
+//! Dynamic `Value`s (see the `value` module) can be generated either freely, with any shape, or
+//! with `value()` as values of a described type.
+//!
+//! ```
+//! # use xdr_codec::arbitrary::{self, Unstructured};
+//! let mut u = Unstructured::new(&[200, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+//! let v: Vec<u32> = arbitrary::flex(&mut u, Some(2)).unwrap();
+//! assert!(v.len() <= 2);
+//! ```
+use std::cmp::min;
+use std::convert::TryFrom;
+
+pub use arbitrary_crate::{Arbitrary, Error, Result, Unstructured};
+
+use describe::{Descriptor, Kind, Size, TypeDesc};
+use value::Value;
+
+use super::{BoundedVec, Hyper128, Opaque, OptArray, Quadruple, StatusUnion, UHyper128, XdrString};
+
+// Length of a flexible array of `T`, limited to `maxsz`
+fn flex_len<'a, T: Arbitrary<'a>>(u: &mut Unstructured<'a>, maxsz: Option<usize>) -> Result<usize> {
+    let len = u.arbitrary_len::<T>()?;
+    Ok(match maxsz {
+        Some(m) => min(len, m),
+        None => len,
+    })
+}
+
+/// Generate a flexible array with at most `maxsz` elements.
+pub fn flex<'a, T: Arbitrary<'a>>(
+    u: &mut Unstructured<'a>,
+    maxsz: Option<usize>,
+) -> Result<Vec<T>> {
+    let len = flex_len::<T>(u, maxsz)?;
+    (0..len).map(|_| T::arbitrary(u)).collect()
+}
+
+/// Generate a flexible opaque with at most `maxsz` bytes.
+pub fn opaque_flex(u: &mut Unstructured, maxsz: Option<usize>) -> Result<Vec<u8>> {
+    let len = flex_len::<u8>(u, maxsz)?;
+    u.bytes(len).map(|b| b.to_vec())
+}
+
+/// Generate a string of at most `maxsz` bytes of UTF-8.
+pub fn string(u: &mut Unstructured, maxsz: Option<usize>) -> Result<String> {
+    let mut s = String::arbitrary(u)?;
+    if let Some(m) = maxsz {
+        let mut end = min(m, s.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+    Ok(s)
+}
+
+/// Generate a string of at most `maxsz` printable ASCII characters, suitable for
+/// `unpack_string_strict()`.
+pub fn ascii_string(u: &mut Unstructured, maxsz: Option<usize>) -> Result<String> {
+    let bytes = opaque_flex(u, maxsz)?;
+    Ok(bytes.into_iter().map(|b| (b' ' + b % 95) as char).collect())
+}
+
+/// Generate an `XdrString` of at most `maxsz` bytes, which may not be valid UTF-8.
+pub fn xdrstring(u: &mut Unstructured, maxsz: Option<usize>) -> Result<XdrString> {
+    opaque_flex(u, maxsz).map(XdrString)
+}
+
+impl<'a> Arbitrary<'a> for XdrString {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        xdrstring(u, None)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Opaque<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = flex_len::<u8>(u, None)?;
+        u.bytes(len).map(Opaque::borrowed)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Quadruple {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        <[u8; 16]>::arbitrary(u).map(Quadruple)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <[u8; 16]>::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Hyper128 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        i128::arbitrary(u).map(Hyper128)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        i128::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for UHyper128 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u128::arbitrary(u).map(UHyper128)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u128::size_hint(depth)
+    }
+}
+
+impl<'a, T: Arbitrary<'a>, const MAX: usize> Arbitrary<'a> for BoundedVec<T, MAX> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let v = flex(u, Some(MAX))?;
+        Ok(BoundedVec::try_from(v).expect("flex exceeded limit"))
+    }
+}
+
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for OptArray<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Option::<T>::arbitrary(u).map(OptArray)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        Option::<T>::size_hint(depth)
+    }
+}
+
+impl<'a, T: Arbitrary<'a>, E: Arbitrary<'a>> Arbitrary<'a> for StatusUnion<T, E> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if bool::arbitrary(u)? {
+            Ok(StatusUnion::ok(T::arbitrary(u)?))
+        } else {
+            // Zero is success, so can't be an error status
+            let status = match i32::arbitrary(u)? {
+                0 => 1,
+                s => s,
+            };
+            Ok(StatusUnion::err(status, E::arbitrary(u)?))
+        }
+    }
+}
+
+/// Generate a value of the type described by `desc`.
+///
+/// The value respects the type's size limits, and enums and unions only take the values their
+/// descriptions allow, so it's the same as unpacking an encoding of the type would give.
+pub fn value(u: &mut Unstructured, desc: &Descriptor) -> Result<Value> {
+    match desc.kind {
+        Kind::Enum(ref values) => {
+            let v = u.choose(values)?;
+            Ok(Value::Enum(v.value as i32, Some(v.name)))
+        }
+
+        Kind::Struct(ref fields) => fields
+            .iter()
+            .map(|f| typed(u, &f.ty).map(|v| (f.name, v)))
+            .collect::<Result<_>>()
+            .map(Value::Struct),
+
+        Kind::Union {
+            ref selector,
+            ref cases,
+        } => {
+            // A default case takes any discriminant, which may select one of the other cases
+            let disc = match u.choose(cases)?.value {
+                Some(v) => v as i32,
+                None => i32::arbitrary(u)?,
+            };
+            let case = desc.case(disc as i64).expect("chosen case");
+
+            let sel = match selector.ty {
+                TypeDesc::UInt => Value::UInt(disc as u32),
+                TypeDesc::Bool => Value::Bool(disc != 0),
+                TypeDesc::Named(_, describe) => {
+                    Value::Enum(disc, describe().enum_name(disc as i64))
+                }
+                _ => Value::Int(disc),
+            };
+            let arm = match case.field {
+                Some(ref f) => Some((f.name, Box::new(typed(u, &f.ty)?))),
+                None => None,
+            };
+
+            Ok(Value::Union {
+                selector: (selector.name, Box::new(sel)),
+                variant: case.variant,
+                arm,
+            })
+        }
+
+        Kind::Newtype(ref ty) => typed(u, ty),
+    }
+}
+
+fn typed(u: &mut Unstructured, ty: &TypeDesc) -> Result<Value> {
+    Ok(match *ty {
+        TypeDesc::Int => Value::Int(u.arbitrary()?),
+        TypeDesc::UInt => Value::UInt(u.arbitrary()?),
+        TypeDesc::Hyper => Value::Hyper(u.arbitrary()?),
+        TypeDesc::UHyper => Value::UHyper(u.arbitrary()?),
+        TypeDesc::Float => Value::Float(u.arbitrary()?),
+        TypeDesc::Double => Value::Double(u.arbitrary()?),
+        TypeDesc::Quadruple => Value::Quadruple(u.arbitrary()?),
+        TypeDesc::Bool => Value::Bool(u.arbitrary()?),
+
+        TypeDesc::Opaque(Size::Fixed(len)) => {
+            let mut v = vec![0; len];
+            u.fill_buffer(&mut v)?;
+            Value::Opaque(v)
+        }
+        TypeDesc::Opaque(Size::Flex(max)) => Value::Opaque(opaque_flex(u, max)?),
+
+        // Padded with spaces to its full length
+        TypeDesc::String(Size::Fixed(len)) => {
+            let mut s = string(u, Some(len))?;
+            s.extend((s.len()..len).map(|_| ' '));
+            Value::String(s)
+        }
+        TypeDesc::String(Size::Flex(max)) => Value::String(string(u, max)?),
+
+        TypeDesc::Array(ref elem, size) => {
+            let len = match size {
+                Size::Fixed(len) => len,
+                Size::Flex(max) => flex_len::<u32>(u, max)?,
+            };
+            (0..len)
+                .map(|_| typed(u, elem))
+                .collect::<Result<_>>()
+                .map(Value::Array)?
+        }
+
+        TypeDesc::Option(ref ty) => Value::Option(if bool::arbitrary(u)? {
+            Some(Box::new(typed(u, ty)?))
+        } else {
+            None
+        }),
+
+        TypeDesc::Named(_, describe) => value(u, &describe())?,
+    })
+}
+
+// Names for the fields of freely generated structs and unions
+const NAMES: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "h"];
+
+// How deeply freely generated values nest
+const MAX_DEPTH: usize = 4;
+
+fn free(u: &mut Unstructured, depth: usize) -> Result<Value> {
+    // The last four kinds contain other values
+    let kinds = if depth < MAX_DEPTH { 15 } else { 11 };
+
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Int(u.arbitrary()?),
+        1 => Value::UInt(u.arbitrary()?),
+        2 => Value::Hyper(u.arbitrary()?),
+        3 => Value::UHyper(u.arbitrary()?),
+        4 => Value::Float(u.arbitrary()?),
+        5 => Value::Double(u.arbitrary()?),
+        6 => Value::Quadruple(u.arbitrary()?),
+        7 => Value::Bool(u.arbitrary()?),
+        8 => Value::Opaque(u.arbitrary()?),
+        9 => Value::String(u.arbitrary()?),
+        10 => {
+            let name = if bool::arbitrary(u)? {
+                Some(*u.choose(&NAMES)?)
+            } else {
+                None
+            };
+            Value::Enum(u.arbitrary()?, name)
+        }
+        11 => {
+            let len = u.arbitrary_len::<u32>()?;
+            Value::Array(
+                (0..len)
+                    .map(|_| free(u, depth + 1))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        12 => Value::Option(if bool::arbitrary(u)? {
+            Some(Box::new(free(u, depth + 1)?))
+        } else {
+            None
+        }),
+        13 => {
+            let len = u.int_in_range(0..=NAMES.len())?;
+            Value::Struct(
+                NAMES[..len]
+                    .iter()
+                    .map(|&name| free(u, depth + 1).map(|v| (name, v)))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        _ => Value::Union {
+            selector: ("selector", Box::new(Value::Int(u.arbitrary()?))),
+            variant: u.choose(&NAMES)?,
+            arm: if bool::arbitrary(u)? {
+                Some((*u.choose(&NAMES)?, Box::new(free(u, depth + 1)?)))
+            } else {
+                None
+            },
+        },
+    })
+}
+
+/// Values of any shape, not necessarily of any particular type. Use `value()` to generate
+/// values of a described type.
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        free(u, 0)
+    }
+}
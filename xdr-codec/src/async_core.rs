@@ -0,0 +1,391 @@
+// Runtime-independent asynchronous record marking
+//
+// Tokio and futures-io have equivalent IO traits which differ only in naming, so the record
+// marking state machines are written once here against `PollBufRead` and `PollWrite`. Each
+// runtime implements those for its own traits, distinguished by a marker type so the blanket
+// impls don't overlap, and the `async_record` and `futures_record` modules wrap the state
+// machines up with the runtime's traits.
+use std::cmp::min;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use record::{EmptyFragments, LAST_REC};
+
+pub(crate) trait PollBufRead<M> {
+    fn poll_fill_buf(&mut self, cx: &mut Context) -> Poll<io::Result<&[u8]>>;
+    fn consume(&mut self, amt: usize);
+}
+
+pub(crate) trait PollWrite<M> {
+    fn poll_write(&mut self, cx: &mut Context, data: &[u8]) -> Poll<io::Result<usize>>;
+    fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>>;
+}
+
+// Marker for Tokio's IO traits
+#[cfg(feature = "tokio")]
+pub(crate) enum Tokio {}
+
+#[cfg(feature = "tokio")]
+impl<R: ::tokio::io::AsyncBufRead + Unpin> PollBufRead<Tokio> for R {
+    fn poll_fill_buf(&mut self, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        ::tokio::io::AsyncBufRead::poll_fill_buf(Pin::new(self), cx)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        ::tokio::io::AsyncBufRead::consume(Pin::new(self), amt)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: ::tokio::io::AsyncWrite + Unpin> PollWrite<Tokio> for W {
+    fn poll_write(&mut self, cx: &mut Context, data: &[u8]) -> Poll<io::Result<usize>> {
+        ::tokio::io::AsyncWrite::poll_write(Pin::new(self), cx, data)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        ::tokio::io::AsyncWrite::poll_flush(Pin::new(self), cx)
+    }
+}
+
+// Marker for the futures-io traits
+#[cfg(feature = "futures-io")]
+pub(crate) enum Futures {}
+
+#[cfg(feature = "futures-io")]
+impl<R: ::futures_io::AsyncBufRead + Unpin> PollBufRead<Futures> for R {
+    fn poll_fill_buf(&mut self, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        ::futures_io::AsyncBufRead::poll_fill_buf(Pin::new(self), cx)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        ::futures_io::AsyncBufRead::consume(Pin::new(self), amt)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: ::futures_io::AsyncWrite + Unpin> PollWrite<Futures> for W {
+    fn poll_write(&mut self, cx: &mut Context, data: &[u8]) -> Poll<io::Result<usize>> {
+        ::futures_io::AsyncWrite::poll_write(Pin::new(self), cx, data)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        ::futures_io::AsyncWrite::poll_flush(Pin::new(self), cx)
+    }
+}
+
+/// Record reader state.
+#[derive(Debug)]
+pub(crate) struct ReadState {
+    size: usize,     // record size
+    consumed: usize, // bytes consumed
+    eor: bool,       // is last record
+
+    hdr: [u8; 4], // partially read fragment header
+    hdrlen: usize,
+
+    next_offset: usize,    // record data before next fragment
+    empty: EmptyFragments, // empty fragment policy
+}
+
+impl ReadState {
+    pub fn new() -> ReadState {
+        ReadState {
+            size: 0,
+            consumed: 0,
+            eor: false,
+            hdr: [0; 4],
+            hdrlen: 0,
+            next_offset: 0,
+            empty: EmptyFragments::default(),
+        }
+    }
+
+    // read next fragment header, returns true on EOF
+    fn poll_nextrec<M, R: PollBufRead<M>>(
+        &mut self,
+        reader: &mut R,
+        cx: &mut Context,
+    ) -> Poll<io::Result<bool>> {
+        assert_eq!(self.consumed, self.size);
+
+        loop {
+            while self.hdrlen < self.hdr.len() {
+                let n = {
+                    let data = ready!(reader.poll_fill_buf(cx))?;
+                    if data.is_empty() {
+                        // EOF, possibly in the middle of a header
+                        self.hdrlen = 0;
+                        return Poll::Ready(Ok(true));
+                    }
+                    let n = min(data.len(), self.hdr.len() - self.hdrlen);
+                    self.hdr[self.hdrlen..self.hdrlen + n].copy_from_slice(&data[..n]);
+                    n
+                };
+                reader.consume(n);
+                self.hdrlen += n;
+            }
+
+            let rechdr = u32::from_be_bytes(self.hdr);
+            self.hdrlen = 0;
+
+            let size = (rechdr & !LAST_REC) as usize;
+            let eor = (rechdr & LAST_REC) != 0;
+
+            if self.empty.skip(size, eor, self.next_offset)? {
+                continue;
+            }
+
+            #[cfg(feature = "metrics")]
+            {
+                if self.next_offset == 0 {
+                    ::metrics::record_read();
+                }
+            }
+
+            self.size = size;
+            self.consumed = 0;
+            self.eor = eor;
+            self.next_offset = if eor { 0 } else { self.next_offset + size };
+
+            return Poll::Ready(Ok(false));
+        }
+    }
+
+    pub fn set_empty_fragments(&mut self, policy: EmptyFragments) {
+        self.empty = policy;
+    }
+
+    fn totremains(&self) -> usize {
+        self.size - self.consumed
+    }
+
+    pub fn eor(&self) -> bool {
+        self.eor
+    }
+
+    // Whether it's part way through reading a fragment header
+    pub fn in_header(&self) -> bool {
+        self.hdrlen != 0
+    }
+
+    // Get the available data in the current fragment, moving on to the next fragment if needed
+    pub fn poll_fill_buf<'a, M, R: PollBufRead<M>>(
+        &mut self,
+        reader: &'a mut R,
+        cx: &mut Context,
+    ) -> Poll<io::Result<&'a [u8]>> {
+        while self.totremains() == 0 {
+            if ready!(self.poll_nextrec(reader, cx))? {
+                return Poll::Ready(Ok(&[]));
+            }
+        }
+
+        let remains = self.totremains();
+        let data = ready!(reader.poll_fill_buf(cx))?;
+        Poll::Ready(Ok(&data[..min(data.len(), remains)]))
+    }
+
+    pub fn consume<M, R: PollBufRead<M>>(&mut self, reader: &mut R, sz: usize) {
+        assert!(sz <= self.totremains());
+        self.consumed += sz;
+        reader.consume(sz);
+    }
+
+    // Poll to read a complete record into `buf`, keeping track of whether it has been started
+    pub fn poll_read_record<M, R: PollBufRead<M>>(
+        &mut self,
+        reader: &mut R,
+        cx: &mut Context,
+        buf: &mut Vec<u8>,
+        started: &mut bool,
+    ) -> Poll<io::Result<Option<Vec<u8>>>> {
+        // loop over fragments until we get a complete record
+        loop {
+            if self.totremains() == 0 {
+                if *started && self.eor {
+                    *started = false;
+                    return Poll::Ready(Ok(Some(::std::mem::take(buf))));
+                }
+
+                if ready!(self.poll_nextrec(reader, cx))? {
+                    if *started {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "unterminated record",
+                        )));
+                    }
+                    return Poll::Ready(Ok(None));
+                }
+                *started = true;
+                continue;
+            }
+
+            let remains = self.totremains();
+            let n = {
+                let data = ready!(reader.poll_fill_buf(cx))?;
+                if data.is_empty() {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "short record fragment",
+                    )));
+                }
+                let n = min(data.len(), remains);
+                buf.extend_from_slice(&data[..n]);
+                n
+            };
+            self.consumed += n;
+            reader.consume(n);
+        }
+    }
+}
+
+/// Record writer state.
+#[derive(Debug)]
+pub(crate) struct WriteState {
+    buf: Vec<u8>,           // fragment header space + accumulated record fragment
+    bufsz: usize,           // max fragment size
+    eor: bool,              // last fragment was eor
+    inflight: Option<bool>, // fragment being written, and whether it's eor
+    wpos: usize,            // amount of inflight fragment written
+    ending: bool,           // end_record in progress
+}
+
+impl WriteState {
+    // Panics if `bufsz` is zero
+    pub fn new(bufsz: usize) -> WriteState {
+        if bufsz == 0 {
+            panic!("bufsz must be non-zero")
+        }
+        let mut buf = Vec::with_capacity(bufsz + 4);
+        buf.extend_from_slice(&[0; 4]);
+        WriteState {
+            buf,
+            bufsz,
+            eor: false,
+            inflight: None,
+            wpos: 0,
+            ending: false,
+        }
+    }
+
+    fn fraglen(&self) -> usize {
+        self.buf.len() - 4
+    }
+
+    // Set up the current buffer to be written as a fragment
+    fn start(&mut self, eor: bool) {
+        assert!(self.inflight.is_none());
+
+        let rechdr = self.fraglen() as u32 | (if eor { LAST_REC } else { 0 });
+        self.buf[..4].copy_from_slice(&rechdr.to_be_bytes());
+        self.inflight = Some(eor);
+        self.wpos = 0;
+    }
+
+    // Write out the inflight fragment, returning whether it was eor
+    fn poll_drive<M, W: PollWrite<M>>(
+        &mut self,
+        writer: &mut W,
+        cx: &mut Context,
+    ) -> Poll<io::Result<bool>> {
+        let eor = self.inflight.expect("no fragment in flight");
+
+        while self.wpos < self.buf.len() {
+            let n = ready!(writer.poll_write(cx, &self.buf[self.wpos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole fragment",
+                )));
+            }
+            self.wpos += n;
+        }
+
+        #[cfg(feature = "metrics")]
+        ::metrics::fragment_written(self.fraglen(), eor);
+        self.buf.truncate(4);
+        self.inflight = None;
+        self.eor = eor;
+
+        Poll::Ready(Ok(eor))
+    }
+
+    pub fn poll_end_record<M, W: PollWrite<M>>(
+        &mut self,
+        writer: &mut W,
+        cx: &mut Context,
+    ) -> Poll<io::Result<()>> {
+        if !self.ending {
+            self.ending = true;
+            if self.inflight.is_none() {
+                self.start(true);
+            }
+        }
+
+        while self.inflight.is_some() {
+            if !ready!(self.poll_drive(writer, cx))? {
+                // finished a previous fragment; now send the final one
+                self.start(true);
+            }
+        }
+
+        ready!(writer.poll_flush(cx))?;
+        self.ending = false;
+
+        Poll::Ready(Ok(()))
+    }
+
+    pub fn poll_write<M, W: PollWrite<M>>(
+        &mut self,
+        writer: &mut W,
+        cx: &mut Context,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if self.inflight.is_some() {
+            ready!(self.poll_drive(writer, cx))?;
+        }
+
+        if self.fraglen() == self.bufsz {
+            self.start(false);
+            ready!(self.poll_drive(writer, cx))?;
+        }
+
+        let n = min(data.len(), self.bufsz - self.fraglen());
+        self.buf.extend_from_slice(&data[..n]);
+
+        Poll::Ready(Ok(n))
+    }
+
+    pub fn poll_flush<M, W: PollWrite<M>>(
+        &mut self,
+        writer: &mut W,
+        cx: &mut Context,
+    ) -> Poll<io::Result<()>> {
+        if self.inflight.is_none() && self.fraglen() > 0 {
+            self.start(false);
+        }
+        if self.inflight.is_some() {
+            ready!(self.poll_drive(writer, cx))?;
+        }
+
+        writer.poll_flush(cx)
+    }
+
+    // End any unterminated record before the underlying writer is shut down
+    pub fn poll_finish<M, W: PollWrite<M>>(
+        &mut self,
+        writer: &mut W,
+        cx: &mut Context,
+    ) -> Poll<io::Result<()>> {
+        if self.ending || self.fraglen() > 0 || !self.eor {
+            ready!(self.poll_end_record(writer, cx))?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,244 @@
+//! Asynchronous XDR record marking
+//!
+//! This is the equivalent of the `record` module for Tokio's `AsyncBufRead` and `AsyncWrite`,
+//! with the same fragmenting semantics: an `AsyncXdrRecordReader` reads up to the end of the
+//! current fragment and no further, and an `AsyncXdrRecordWriter` buffers data into fragments
+//! and only ends a record when asked to.
+//!
+//! This module is only available with the `tokio` feature. The `futures_record` module has the
+//! same thing for the futures-io traits used by async-std and smol.
+use std::cmp::min;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use async_core::{ReadState, Tokio, WriteState};
+use record::EmptyFragments;
+
+/// Read records from an asynchronous bytestream.
+///
+/// As with `XdrRecordReader`, reads will read up to the end of the current fragment, and not
+/// beyond. Use `eor()` to determine record ends, or `read_record()` to read a whole record at
+/// once.
+#[derive(Debug)]
+pub struct AsyncXdrRecordReader<R> {
+    state: ReadState,
+    reader: R, // reader
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncXdrRecordReader<R> {
+    /// Wrap a record reader around an existing implementation of `AsyncBufRead`, such as
+    /// `tokio::io::BufReader`.
+    pub fn new(rd: R) -> AsyncXdrRecordReader<R> {
+        AsyncXdrRecordReader {
+            state: ReadState::new(),
+            reader: rd,
+        }
+    }
+
+    /// Set how empty fragments and records are handled. The default is
+    /// `EmptyFragments::Accept`.
+    pub fn set_empty_fragments(&mut self, policy: EmptyFragments) {
+        self.state.set_empty_fragments(policy);
+    }
+
+    /// Current fragment is the end of the record.
+    pub fn eor(&self) -> bool {
+        self.state.eor()
+    }
+
+    /// Read a complete record.
+    ///
+    /// The future resolves to `None` at the end of the stream, or an `UnexpectedEof` error if the
+    /// stream ends part way through a record.
+    pub fn read_record(&mut self) -> ReadRecord<'_, R> {
+        ReadRecord {
+            reader: self,
+            buf: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly will corrupt the record stream unless it's at a fragment
+    /// boundary.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap this `AsyncXdrRecordReader`, returning the underlying reader.
+    ///
+    /// Any unread part of the current fragment is left in the reader. Panics if it's part way
+    /// through reading a fragment header.
+    pub fn into_inner(self) -> R {
+        assert!(!self.state.in_header(), "partial fragment header");
+        self.reader
+    }
+
+    // Poll to read a complete record into `buf`, keeping track of whether it has been started
+    pub(crate) fn poll_read_record(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut Vec<u8>,
+        started: &mut bool,
+    ) -> Poll<io::Result<Option<Vec<u8>>>> {
+        self.state
+            .poll_read_record::<Tokio, _>(&mut self.reader, cx, buf, started)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for AsyncXdrRecordReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let nread = {
+            let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+            let len = min(buf.remaining(), data.len());
+
+            buf.put_slice(&data[..len]);
+            len
+        };
+
+        self.consume(nread);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for AsyncXdrRecordReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        this.state.poll_fill_buf::<Tokio, _>(&mut this.reader, cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, sz: usize) {
+        let this = self.get_mut();
+        this.state.consume::<Tokio, _>(&mut this.reader, sz)
+    }
+}
+
+/// Future returned by `AsyncXdrRecordReader::read_record()`.
+#[derive(Debug)]
+pub struct ReadRecord<'a, R: 'a> {
+    reader: &'a mut AsyncXdrRecordReader<R>,
+    buf: Vec<u8>,
+    started: bool, // have first fragment header
+}
+
+impl<'a, R: AsyncBufRead + Unpin> Future for ReadRecord<'a, R> {
+    type Output = io::Result<Option<Vec<u8>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.reader
+            .poll_read_record(cx, &mut this.buf, &mut this.started)
+    }
+}
+
+const WRBUF: usize = 65536;
+
+/// Write records into an asynchronous bytestream.
+///
+/// As with `XdrRecordWriter`, data is accumulated into fragments which are sent when the buffer
+/// fills or on `flush()`, and records are only ended by `end_record()`.
+///
+/// Unlike `XdrRecordWriter` this can't end the record when dropped, so make sure to call
+/// `end_record()` (or `shutdown()`, which ends any unterminated record) first.
+#[derive(Debug)]
+pub struct AsyncXdrRecordWriter<W> {
+    state: WriteState,
+    writer: W, // writer we're passing on to
+}
+
+impl<W: AsyncWrite + Unpin> AsyncXdrRecordWriter<W> {
+    /// Create a new `AsyncXdrRecordWriter` wrapped around an `AsyncWrite` implementation, using a
+    /// default buffer size (64k).
+    pub fn new(w: W) -> AsyncXdrRecordWriter<W> {
+        AsyncXdrRecordWriter::with_buffer(w, WRBUF)
+    }
+
+    /// Create an instance with a specific buffer size. Panics if the size is zero.
+    pub fn with_buffer(w: W, bufsz: usize) -> AsyncXdrRecordWriter<W> {
+        AsyncXdrRecordWriter {
+            state: WriteState::new(bufsz),
+            writer: w,
+        }
+    }
+
+    /// Poll to end the current record.
+    ///
+    /// This sends any buffered data as the final fragment of the record, and flushes the
+    /// underlying stream. Once it has started, it must be polled to completion before anything
+    /// more is written.
+    pub fn poll_end_record(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.state.poll_end_record::<Tokio, _>(&mut self.writer, cx)
+    }
+
+    /// End the current record.
+    ///
+    /// As with `XdrRecordWriter::end_record()`, anything written after this is part of a new
+    /// record, and ending a record with nothing written sends an empty record.
+    pub fn end_record(&mut self) -> EndRecord<'_, W> {
+        EndRecord(self)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly will corrupt the record stream unless it's at a record boundary.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwrap this `AsyncXdrRecordWriter`, returning the underlying writer.
+    ///
+    /// Any data which hasn't been sent yet is lost, so end the record (or at least flush) first.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncXdrRecordWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, data: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        this.state
+            .poll_write::<Tokio, _>(&mut this.writer, cx, data)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.state.poll_flush::<Tokio, _>(&mut this.writer, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.state.poll_finish::<Tokio, _>(&mut this.writer, cx))?;
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
+/// Future returned by `AsyncXdrRecordWriter::end_record()`.
+#[derive(Debug)]
+pub struct EndRecord<'a, W: 'a>(&'a mut AsyncXdrRecordWriter<W>);
+
+impl<'a, W: AsyncWrite + Unpin> Future for EndRecord<'a, W> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().0.poll_end_record(cx)
+    }
+}
@@ -0,0 +1,206 @@
+//! `Stream` and `Sink` adapters for asynchronous records
+//!
+//! `RecordStream` unpacks a value from each record read by an `AsyncXdrRecordReader`, and
+//! `RecordSink` packs each value sent to it as a record written by an `AsyncXdrRecordWriter`.
+//! They implement `futures::Stream` and `futures::Sink`, so they can be used with the standard
+//! combinators for buffering, rate limiting, forwarding or `select`ing instead of hand-written
+//! read and write loops.
+//!
+//! `RecordSink` applies backpressure: it holds at most one packed value, and isn't ready for
+//! another until that one has been written out and its record ended.
+//!
+//! This module is only available with the `futures` feature.
+use std::io::{self, Cursor};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncBufRead, AsyncWrite};
+
+use async_record::{AsyncXdrRecordReader, AsyncXdrRecordWriter};
+use error::*;
+
+use super::{pack, Pack, Unpack};
+
+/// Stream of values unpacked from records.
+///
+/// Each record must contain exactly one value. A record which can't be unpacked yields an
+/// error, and the stream carries on with the next record; an IO error ends the stream.
+#[derive(Debug)]
+pub struct RecordStream<R, T> {
+    reader: AsyncXdrRecordReader<R>,
+    buf: Vec<u8>,
+    started: bool, // record being read
+    done: bool,    // EOF or IO error
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<R: AsyncBufRead + Unpin, T> RecordStream<R, T> {
+    /// Create a stream reading records from `reader`, which must be at a record boundary.
+    pub fn new(reader: AsyncXdrRecordReader<R>) -> RecordStream<R, T> {
+        RecordStream {
+            reader,
+            buf: Vec::new(),
+            started: false,
+            done: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Get a reference to the record reader.
+    pub fn get_ref(&self) -> &AsyncXdrRecordReader<R> {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the record reader.
+    pub fn get_mut(&mut self) -> &mut AsyncXdrRecordReader<R> {
+        &mut self.reader
+    }
+
+    /// Unwrap this `RecordStream`, returning the record reader. Any partly read record is lost.
+    pub fn into_inner(self) -> AsyncXdrRecordReader<R> {
+        self.reader
+    }
+}
+
+impl<R, T> Stream for RecordStream<R, T>
+where
+    R: AsyncBufRead + Unpin,
+    T: Unpack<Cursor<Vec<u8>>>,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<T>>> {
+        let this = self.get_mut();
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        let rec = match ready!(this
+            .reader
+            .poll_read_record(cx, &mut this.buf, &mut this.started))
+        {
+            Ok(Some(rec)) => rec,
+            Ok(None) => {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            Err(e) => {
+                this.done = true;
+                return Poll::Ready(Some(Err(e.into())));
+            }
+        };
+
+        let len = rec.len();
+        let mut input = Cursor::new(rec);
+        let res = T::unpack(&mut input).and_then(|(val, _)| {
+            let used = input.position() as usize;
+            if used != len {
+                bail!("{} bytes of trailing data in record", len - used);
+            }
+            Ok(val)
+        });
+        Poll::Ready(Some(res))
+    }
+}
+
+/// Sink packing values as records.
+///
+/// Each value is sent as a complete record, and the underlying writer is flushed after each one.
+#[derive(Debug)]
+pub struct RecordSink<W> {
+    writer: AsyncXdrRecordWriter<W>,
+    buf: Vec<u8>, // packed value
+    pos: usize,   // amount of buf written
+    ending: bool, // record needs ending
+}
+
+impl<W: AsyncWrite + Unpin> RecordSink<W> {
+    /// Create a sink writing records to `writer`, which must be at a record boundary.
+    pub fn new(writer: AsyncXdrRecordWriter<W>) -> RecordSink<W> {
+        RecordSink {
+            writer,
+            buf: Vec::new(),
+            pos: 0,
+            ending: false,
+        }
+    }
+
+    /// Get a reference to the record writer.
+    pub fn get_ref(&self) -> &AsyncXdrRecordWriter<W> {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the record writer.
+    pub fn get_mut(&mut self) -> &mut AsyncXdrRecordWriter<W> {
+        &mut self.writer
+    }
+
+    /// Unwrap this `RecordSink`, returning the record writer. Any value which hasn't been
+    /// completely written is lost, so flush the sink first.
+    pub fn into_inner(self) -> AsyncXdrRecordWriter<W> {
+        self.writer
+    }
+
+    // Write out the pending value, if any, and end its record
+    fn poll_send(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while self.pos < self.buf.len() {
+            let n = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.buf[self.pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole record",
+                )));
+            }
+            self.pos += n;
+        }
+
+        if self.ending {
+            ready!(self.writer.poll_end_record(cx))?;
+            self.ending = false;
+            self.buf.clear();
+            self.pos = 0;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin, T: Pack<Vec<u8>>> Sink<T> for RecordSink<W> {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        self.get_mut().poll_send(cx).map_err(Error::from)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<()> {
+        let this = self.get_mut();
+        assert!(!this.ending, "start_send without poll_ready");
+
+        if let Err(e) = pack(&item, &mut this.buf) {
+            this.buf.clear();
+            return Err(e);
+        }
+        this.ending = true;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        Pin::new(&mut this.writer)
+            .poll_flush(cx)
+            .map_err(Error::from)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        Pin::new(&mut this.writer)
+            .poll_shutdown(cx)
+            .map_err(Error::from)
+    }
+}
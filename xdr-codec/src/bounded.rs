@@ -0,0 +1,152 @@
+//! Length-limited element arrays
+//!
+//! An XDR flexible array such as `int values<10>` can be represented by a `Vec<i32>`, but then the
+//! limit is only checked when the value is packed or unpacked. `BoundedVec<i32, 10>` carries the
+//! limit in its type instead, and enforces it whenever the length can change.
+//!
+//! ```
+//! # use xdr_codec::BoundedVec;
+//! let mut v = BoundedVec::<u32, 2>::new();
+//!
+//! v.push(1).unwrap();
+//! v.push(2).unwrap();
+//! assert!(v.push(3).is_err());
+//! assert_eq!(&v[..], &[1, 2]);
+//! ```
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+
+use error::*;
+
+use super::{pack_flex, skip_flex, unpack_flex, Pack, Skip, Unpack};
+
+/// A `Vec` of at most `MAX` elements.
+///
+/// This packs and unpacks as a flexible array with a `<MAX>` limit. It dereferences to a slice,
+/// so elements can be read and modified in place, but the length can only be changed with
+/// methods which check the limit.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedVec<T, const MAX: usize>(Vec<T>);
+
+impl<T, const MAX: usize> BoundedVec<T, MAX> {
+    /// The maximum number of elements.
+    pub const MAX: usize = MAX;
+
+    /// Create a new, empty `BoundedVec`.
+    pub fn new() -> Self {
+        BoundedVec(Vec::new())
+    }
+
+    /// Append an element, failing with `ErrorKind::InvalidLen` if it's already full.
+    pub fn push(&mut self, v: T) -> Result<()> {
+        if self.0.len() >= MAX {
+            bail!(ErrorKind::InvalidLen(MAX));
+        }
+        self.0.push(v);
+        Ok(())
+    }
+
+    /// Remove and return the last element, if any.
+    pub fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+
+    /// Shorten to at most `len` elements.
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len)
+    }
+
+    /// Remove all elements.
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    /// Unwrap into the underlying `Vec`.
+    pub fn into_vec(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T, const MAX: usize> Default for BoundedVec<T, MAX> {
+    fn default() -> Self {
+        BoundedVec::new()
+    }
+}
+
+impl<T, const MAX: usize> Deref for BoundedVec<T, MAX> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T, const MAX: usize> DerefMut for BoundedVec<T, MAX> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+impl<T, const MAX: usize> AsRef<[T]> for BoundedVec<T, MAX> {
+    fn as_ref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+/// Fails with `ErrorKind::InvalidLen` if the `Vec` has more than `MAX` elements.
+impl<T, const MAX: usize> TryFrom<Vec<T>> for BoundedVec<T, MAX> {
+    type Error = Error;
+
+    fn try_from(v: Vec<T>) -> Result<Self> {
+        if v.len() > MAX {
+            bail!(ErrorKind::InvalidLen(MAX));
+        }
+        Ok(BoundedVec(v))
+    }
+}
+
+impl<T, const MAX: usize> From<BoundedVec<T, MAX>> for Vec<T> {
+    fn from(v: BoundedVec<T, MAX>) -> Self {
+        v.0
+    }
+}
+
+impl<'a, T, const MAX: usize> IntoIterator for &'a BoundedVec<T, MAX> {
+    type Item = &'a T;
+    type IntoIter = ::std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T, const MAX: usize> IntoIterator for BoundedVec<T, MAX> {
+    type Item = T;
+    type IntoIter = ::std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<Out: Write, T: Pack<Out>, const MAX: usize> Pack<Out> for BoundedVec<T, MAX> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_flex(&self.0, Some(MAX), out)
+    }
+}
+
+impl<In: Read, T: Unpack<In>, const MAX: usize> Unpack<In> for BoundedVec<T, MAX> {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        unpack_flex(input, Some(MAX)).map(|(v, sz)| (BoundedVec(v), sz))
+    }
+}
+
+impl<In: Read, T: Skip<In>, const MAX: usize> Skip<In> for BoundedVec<T, MAX> {
+    #[inline]
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_flex::<In, T>(input, Some(MAX))
+    }
+}
@@ -0,0 +1,173 @@
+//! Allocation budgets for unpacking
+//!
+//! Length limits in a specification bound each array individually, but nesting multiplies
+//! them: a message of arrays of arrays can still claim far more memory than it's worth, and
+//! unbounded (`<>`) arrays aren't limited at all. A budget bounds the total allocation for a
+//! whole decode instead.
+//!
+//! While a budget is active, the unpacking functions in this crate charge it for the memory
+//! they're about to allocate for variable-sized data (flexible arrays, opaques, strings and boxed
+//! values), and fail with `ErrorKind::BudgetExceeded` once it runs out. The check happens before
+//! allocating, so a hostile length prefix can't cause a huge allocation. Without a budget
+//! nothing is charged.
+//!
+//! `Budgeted` carries a budget along with an input, and applies it to each value unpacked from
+//! that input. The budget is part of the `Budgeted` value, so it can be moved to another thread
+//! or held across an `.await`, and what's left of it carries over from one value to the next:
+//!
+//! ```
+//! # use xdr_codec::budget::Budgeted;
+//! let buf = [0u8, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 3];
+//!
+//! let mut input = Budgeted::new(&buf[..], 12);
+//! let (v, _): (Vec<u32>, _) = input.unpack().unwrap();
+//! assert_eq!(v, vec![1, 2]);
+//! assert_eq!(input.remaining(), 4);
+//! assert!(input.unpack::<Vec<u32>>().is_ok());
+//! assert_eq!(input.remaining(), 0);
+//! ```
+//!
+//! `with_budget()` and `unpack_with_budget()` instead set a budget for everything unpacked on the
+//! calling thread until they return. It only applies on that thread: unpacking in threads spawned
+//! from it, or in async tasks, isn't charged to it, and runs with no budget unless they set their
+//! own.
+use std::cell::Cell;
+
+use error::*;
+
+use super::{Read, Unpack};
+
+thread_local! {
+    static BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// Restore the enclosing budget, less whatever was used, when a scope ends
+struct Scope {
+    outer: Option<usize>,
+    start: usize,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let remaining = BUDGET.with(|b| b.get()).unwrap_or(0);
+        let used = self.start - remaining;
+
+        BUDGET.with(|b| b.set(self.outer.map(|outer| outer - used)));
+    }
+}
+
+/// Run `f` with an allocation budget of `budget` bytes on the current thread.
+///
+/// The budget isn't applied to work `f` hands off to other threads or async tasks; use
+/// `Budgeted` for a budget which goes wherever the input does. Budgets nest: an inner budget can't be larger than what remains of the outer one, and
+/// anything charged to it is also charged to the outer budget.
+pub fn with_budget<F, R>(budget: usize, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let outer = remaining();
+    let start = match outer {
+        Some(outer) if outer < budget => outer,
+        _ => budget,
+    };
+
+    BUDGET.with(|b| b.set(Some(start)));
+    let _scope = Scope { outer, start };
+
+    f()
+}
+
+/// Unpack a value with an allocation budget of `budget` bytes.
+///
+/// The budget only applies to this call, which is equivalent to unpacking it from a new
+/// `Budgeted` input.
+pub fn unpack_with_budget<In: Read, T: Unpack<In>>(
+    input: &mut In,
+    budget: usize,
+) -> Result<(T, usize)> {
+    with_budget(budget, || T::unpack(input))
+}
+
+/// The budget remaining on the current thread, or `None` if there's no budget.
+pub fn remaining() -> Option<usize> {
+    BUDGET.with(|b| b.get())
+}
+
+/// Charge `bytes` to the current budget, if any.
+///
+/// This is for implementations of `Unpack` which allocate, and should be called before the
+/// allocation is made.
+pub fn charge(bytes: usize) -> Result<()> {
+    BUDGET.with(|b| match b.get() {
+        None => Ok(()),
+        Some(remaining) if bytes <= remaining => {
+            b.set(Some(remaining - bytes));
+            Ok(())
+        }
+        Some(remaining) => Err(Error::budgetexceeded(bytes, remaining)),
+    })
+}
+
+/// Charge for `elems` elements of type `T`.
+pub fn charge_elems<T>(elems: usize) -> Result<()> {
+    charge(elems.saturating_mul(::std::mem::size_of::<T>()))
+}
+
+/// An input with an allocation budget.
+///
+/// Each value unpacked with `unpack()` is charged to the budget, and whatever it leaves is
+/// available to the next one.
+///
+/// The budget is applied while `unpack()` runs. Values unpacked from `get_mut()` directly aren't
+/// charged to it.
+#[derive(Debug)]
+pub struct Budgeted<R> {
+    input: R,
+    remaining: usize,
+}
+
+impl<R: Read> Budgeted<R> {
+    /// Wrap `input` with a budget of `budget` bytes.
+    pub fn new(input: R, budget: usize) -> Budgeted<R> {
+        Budgeted {
+            input,
+            remaining: budget,
+        }
+    }
+
+    /// Unpack a value, charging its allocations to the budget.
+    ///
+    /// If a `with_budget()` scope is active on this thread, the value is charged to that too,
+    /// and limited by whichever has less remaining.
+    pub fn unpack<T: Unpack<R>>(&mut self) -> Result<(T, usize)> {
+        let input = &mut self.input;
+        let remaining = &mut self.remaining;
+
+        with_budget(*remaining, || {
+            let start = self::remaining().unwrap_or(0);
+            let res = T::unpack(input);
+            *remaining -= start - self::remaining().unwrap_or(0);
+            res
+        })
+    }
+
+    /// The budget remaining.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Get a reference to the underlying input.
+    pub fn get_ref(&self) -> &R {
+        &self.input
+    }
+
+    /// Get a mutable reference to the underlying input.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.input
+    }
+
+    /// Unwrap this `Budgeted`, returning the underlying input.
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
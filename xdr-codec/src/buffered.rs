@@ -0,0 +1,110 @@
+//! Fast unpacking from buffered input
+//!
+//! Unpacking reads every primitive with `Read::read_exact()`. Some `BufRead`s, such as
+//! `std::io::BufReader`, already implement it by copying out of their buffer, but the default
+//! implementation goes through `read()` for each field. `read_exact()` here copies straight out
+//! of the `BufRead`'s buffer with `fill_buf()` and `consume()`, only refilling it when a value
+//! straddles the end of the buffer.
+//!
+//! The readers in this crate which implement `BufRead`, such as `XdrRecordReader` and
+//! `SliceCursor`, already read this way. `BufInput` wraps a `BufRead` which doesn't, so that
+//! unpacking from it does the same:
+//!
+//! ```
+//! # use std::io::Read;
+//! # use xdr_codec::buffered::BufInput;
+//! # use xdr_codec::unpack;
+//! let data = [0, 0, 0, 1, 0, 0, 0, 2];
+//! let mut input = BufInput::new((&data[..2]).chain(&data[2..]));
+//! assert_eq!(unpack::<_, u32>(&mut input).unwrap(), 1);
+//! assert_eq!(unpack::<_, u32>(&mut input).unwrap(), 2);
+//! ```
+use std::cmp::min;
+use std::io::{self, BufRead, Read};
+
+/// Fill `buf` from `input`'s buffer, refilling it as needed.
+///
+/// This is `Read::read_exact()` for a `BufRead`, for use in its own `Read` implementation. As
+/// with `read_exact()`, it fails with `UnexpectedEof` if the input ends first, having consumed
+/// whatever there was.
+pub fn read_exact<R: BufRead + ?Sized>(input: &mut R, mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        let len = match input.fill_buf() {
+            Ok([]) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "failed to fill whole buffer",
+                ))
+            }
+            Ok(data) => {
+                let len = min(buf.len(), data.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                len
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        input.consume(len);
+        buf = &mut buf[len..];
+    }
+    Ok(())
+}
+
+/// Adapter which unpacks directly from a `BufRead`'s buffer.
+///
+/// Its `read_exact()` copies from the wrapped reader's buffer with `fill_buf()` and `consume()`,
+/// and never calls the wrapped reader's `read()`.
+#[derive(Debug)]
+pub struct BufInput<R>(R);
+
+impl<R: BufRead> BufInput<R> {
+    /// Wrap a `BufRead`.
+    pub fn new(input: R) -> BufInput<R> {
+        BufInput(input)
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.0
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.0
+    }
+
+    /// Unwrap this `BufInput`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<R: BufRead> Read for BufInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let data = self.0.fill_buf()?;
+            let len = min(buf.len(), data.len());
+
+            buf[..len].copy_from_slice(&data[..len]);
+            len
+        };
+
+        self.0.consume(len);
+        Ok(len)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        read_exact(&mut self.0, buf)
+    }
+}
+
+impl<R: BufRead> BufRead for BufInput<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+
+    fn consume(&mut self, sz: usize) {
+        self.0.consume(sz)
+    }
+}
@@ -0,0 +1,135 @@
+//! Bulk packing of primitive arrays
+//!
+//! Packing a `Vec<u32>` normally packs each element in turn, with a separate write for every
+//! one. The functions here convert a whole slice at a time through a
+//! fixed-size buffer instead, which is much faster for large numeric arrays. xdrgen uses them for
+//! variable-length arrays of `int`, `unsigned int`, `hyper`, `unsigned hyper`, `float` and
+//! `double`.
+//!
+//! The encoding is exactly the same as packing element by element, including in canonical mode
+//! (see the `canonical` module).
+//!
+//! ```
+//! # use xdr_codec::bulk::{pack_u32_slice, unpack_u32_vec};
+//! let mut buf = Vec::new();
+//! assert_eq!(pack_u32_slice(&[1, 2, 3], &mut buf).unwrap(), 12);
+//! assert_eq!(buf, &[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]);
+//!
+//! assert_eq!(unpack_u32_vec(3, &mut &buf[..]).unwrap(), (vec![1, 2, 3], 12));
+//! ```
+use std::cmp::min;
+use std::convert::TryInto;
+use std::io::{Read, Write};
+
+use error::*;
+
+use super::{budget, canonical, prealloc, Pack, Unpack};
+
+// Size of the conversion buffer, in bytes
+const CHUNK: usize = 4096;
+
+/// Primitive types which can be packed and unpacked in bulk.
+pub trait Bulk: Sized {
+    /// Pack the elements of `val`, without a length. This is the same as packing each element
+    /// in turn.
+    fn pack_slice<Out: Write>(val: &[Self], out: &mut Out) -> Result<usize>;
+
+    /// Unpack `len` elements. This is the same as unpacking each element in turn.
+    fn unpack_vec<In: Read>(len: usize, input: &mut In) -> Result<(Vec<Self>, usize)>;
+}
+
+macro_rules! bulk {
+    ($ty:ident, $size:expr, $bits:expr, $pack:ident, $unpack:ident) => {
+        impl Bulk for $ty {
+            fn pack_slice<Out: Write>(val: &[$ty], out: &mut Out) -> Result<usize> {
+                let mut buf = [0; CHUNK];
+
+                for chunk in val.chunks(CHUNK / $size) {
+                    for (v, b) in chunk.iter().zip(buf.chunks_exact_mut($size)) {
+                        b.copy_from_slice(&$bits(*v)?.to_be_bytes());
+                    }
+                    out.write_all(&buf[..chunk.len() * $size])?;
+                }
+
+                Ok(val.len() * $size)
+            }
+
+            fn unpack_vec<In: Read>(len: usize, input: &mut In) -> Result<(Vec<$ty>, usize)> {
+                budget::charge_elems::<$ty>(len)?;
+
+                let mut out = Vec::with_capacity(prealloc::<$ty>(len));
+                let mut buf = [0; CHUNK];
+                let mut left = len;
+
+                while left > 0 {
+                    let n = min(left, CHUNK / $size);
+                    let bytes = &mut buf[..n * $size];
+
+                    input.read_exact(bytes)?;
+                    out.extend(
+                        bytes
+                            .chunks_exact($size)
+                            .map(|b| $ty::from_be_bytes(b.try_into().unwrap())),
+                    );
+                    left -= n;
+                }
+
+                Ok((out, len * $size))
+            }
+        }
+
+        #[doc = concat!("Pack a slice of `", stringify!($ty), "` without a length.")]
+        #[inline]
+        pub fn $pack<Out: Write>(val: &[$ty], out: &mut Out) -> Result<usize> {
+            $ty::pack_slice(val, out)
+        }
+
+        #[doc = concat!("Unpack `len` elements of `", stringify!($ty), "`.")]
+        #[inline]
+        pub fn $unpack<In: Read>(len: usize, input: &mut In) -> Result<(Vec<$ty>, usize)> {
+            $ty::unpack_vec(len, input)
+        }
+    };
+}
+
+bulk!(u32, 4, Ok::<u32, Error>, pack_u32_slice, unpack_u32_vec);
+bulk!(i32, 4, Ok::<i32, Error>, pack_i32_slice, unpack_i32_vec);
+bulk!(u64, 8, Ok::<u64, Error>, pack_u64_slice, unpack_u64_vec);
+bulk!(i64, 8, Ok::<i64, Error>, pack_i64_slice, unpack_i64_vec);
+bulk!(f32, 4, canonical::f32_bits, pack_f32_slice, unpack_f32_vec);
+bulk!(f64, 8, canonical::f64_bits, pack_f64_slice, unpack_f64_vec);
+
+/// Pack a variable-length array of primitives, with a size limit check.
+///
+/// This is the same as `xdr_codec::pack_flex()`, but packs the elements in bulk.
+pub fn pack_flex<Out: Write, T: Bulk>(
+    val: &[T],
+    maxsz: Option<usize>,
+    out: &mut Out,
+) -> Result<usize> {
+    if maxsz.is_some_and(|m| val.len() > m) {
+        bail!(ErrorKind::InvalidLen(maxsz.unwrap()));
+    }
+    if val.len() > u32::MAX as usize {
+        bail!(ErrorKind::InvalidLen(val.len()));
+    }
+
+    Ok((val.len() as u32).pack(out)? + T::pack_slice(val, out)?)
+}
+
+/// Unpack a variable-length array of primitives, with an optional size limit.
+///
+/// This is the same as `xdr_codec::unpack_flex()`, but unpacks the elements in bulk.
+pub fn unpack_flex<In: Read, T: Bulk>(
+    input: &mut In,
+    maxsz: Option<usize>,
+) -> Result<(Vec<T>, usize)> {
+    let (elems, sz) = usize::unpack(input)?;
+
+    if maxsz.is_some_and(|m| elems > m) {
+        bail!(ErrorKind::InvalidLen(maxsz.unwrap()));
+    }
+
+    let (v, vsz) = T::unpack_vec(elems, input)?;
+    Ok((v, sz + vsz))
+}
@@ -0,0 +1,114 @@
+//! Packing into shared `bytes` buffers
+//!
+//! `BytesPacker` packs values into a single `BytesMut`, splitting each encoding off as a frozen
+//! `Bytes`. The split-off `Bytes` share the packer's allocation rather than copying out of it,
+//! and once they've all been dropped the space is reclaimed for later values. Since `Bytes`
+//! implements `bytes::Buf`, each one can be passed straight to Tokio's `write_all_buf()`, or
+//! queued for a vectored write, without further copies.
+//!
+//! `pack_record()` includes a record marking header, so its output can be sent as-is on a stream
+//! read by `record::XdrRecordReader` or `async_record::AsyncXdrRecordReader`.
+//!
+//! ```
+//! # use xdr_codec::bytes_buf::BytesPacker;
+//! let mut packer = BytesPacker::new();
+//!
+//! let a = packer.pack(&1u32).unwrap();
+//! let b = packer.pack_record(&2u32).unwrap();
+//! assert_eq!(&a[..], &[0, 0, 0, 1]);
+//! assert_eq!(&b[..], &[0x80, 0, 0, 4, 0, 0, 0, 2]);
+//! ```
+//!
+//! This module is only available with the `bytes` feature.
+use bytes::buf::Writer;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use error::*;
+
+use super::record::LAST_REC;
+use super::Pack;
+
+/// Packs values into a shared buffer, splitting off each encoding.
+#[derive(Debug)]
+pub struct BytesPacker {
+    buf: Writer<BytesMut>,
+}
+
+impl Default for BytesPacker {
+    fn default() -> BytesPacker {
+        BytesPacker::new()
+    }
+}
+
+impl BytesPacker {
+    /// Create a new `BytesPacker` with an empty buffer.
+    pub fn new() -> BytesPacker {
+        BytesPacker::with_capacity(0)
+    }
+
+    /// Create a new `BytesPacker` with a buffer preallocated to `cap` bytes.
+    pub fn with_capacity(cap: usize) -> BytesPacker {
+        BytesPacker {
+            buf: BytesMut::with_capacity(cap).writer(),
+        }
+    }
+
+    /// Pack `val`, returning its encoding.
+    ///
+    /// If packing fails, anything partially packed is discarded.
+    pub fn pack<T: Pack<Writer<BytesMut>> + ?Sized>(&mut self, val: &T) -> Result<Bytes> {
+        let buf = self.pack_split(val)?;
+
+        #[cfg(feature = "metrics")]
+        ::metrics::packed(buf.len());
+
+        Ok(buf.freeze())
+    }
+
+    /// Pack `val` as a single record, returning its encoding preceded by a record marking
+    /// header.
+    ///
+    /// Fails with `ErrorKind::InvalidLen` if the encoding is too large for a single fragment.
+    pub fn pack_record<T: Pack<Writer<BytesMut>> + ?Sized>(&mut self, val: &T) -> Result<Bytes> {
+        // Placeholder for the header, filled in once the length is known
+        self.buf.get_mut().put_u32(0);
+
+        let mut buf = self.pack_split(val)?;
+        let len = buf.len() - 4;
+        if len >= LAST_REC as usize {
+            bail!(ErrorKind::InvalidLen(len));
+        }
+        buf[..4].copy_from_slice(&(len as u32 | LAST_REC).to_be_bytes());
+
+        #[cfg(feature = "metrics")]
+        {
+            ::metrics::packed(len);
+            ::metrics::fragment_written(len, true);
+        }
+
+        Ok(buf.freeze())
+    }
+
+    // Pack `val` and split off everything in the buffer, or discard it if packing fails
+    fn pack_split<T: Pack<Writer<BytesMut>> + ?Sized>(&mut self, val: &T) -> Result<BytesMut> {
+        if let Err(err) = val.pack(&mut self.buf) {
+            self.buf.get_mut().clear();
+            return Err(err);
+        }
+
+        Ok(self.buf.get_mut().split())
+    }
+
+    /// Current spare capacity of the buffer, which will be used for the next value.
+    pub fn capacity(&self) -> usize {
+        self.buf.get_ref().capacity()
+    }
+
+    /// Reserve space for at least `additional` more bytes.
+    ///
+    /// This reclaims space from previously split-off encodings which have been dropped, if it
+    /// can, rather than allocating.
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.get_mut().reserve(additional)
+    }
+}
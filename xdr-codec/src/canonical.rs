@@ -0,0 +1,143 @@
+//! Canonical encoding
+//!
+//! XDR gives almost every value exactly one encoding, so packing the same value twice normally
+//! gives the same bytes. The exceptions are:
+//!
+//! - `HashSet`s, whose elements are packed in an unspecified order, and
+//! - floating-point NaNs, which can have many bit patterns but are all "the same" NaN.
+//!
+//! Padding is always packed as zero bytes, so it isn't a source of differences.
+//!
+//! `pack_canonical()` removes these exceptions, so its output only depends on the value being
+//! packed. This makes it suitable for hashing or signing encoded messages. While it's active,
+//! `HashSet` elements are packed sorted by their encoding, and NaNs are handled according to a
+//! `NanPolicy`.
+//!
+//! ```
+//! # use std::collections::HashSet;
+//! # use xdr_codec::canonical::pack_canonical;
+//! let set: HashSet<u32> = vec![3, 1, 2].into_iter().collect();
+//! let mut buf = Vec::new();
+//! pack_canonical(&set, &mut buf).unwrap();
+//! assert_eq!(buf, &[0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]);
+//!
+//! let mut nan = Vec::new();
+//! pack_canonical(&f32::from_bits(0xffc0_1234), &mut nan).unwrap();
+//! assert_eq!(nan, &[0x7f, 0xc0, 0, 0]);
+//! ```
+use std::cell::Cell;
+use std::fmt;
+use std::io::Write;
+
+use error::*;
+
+use super::Pack;
+
+/// The bit pattern all NaN `f32`s are packed as, by default.
+pub const NAN32_BITS: u32 = 0x7fc0_0000;
+/// The bit pattern all NaN `f64`s are packed as, by default.
+pub const NAN64_BITS: u64 = 0x7ff8_0000_0000_0000;
+
+/// How canonical packing handles NaNs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// Pack every NaN as the canonical quiet NaN (`NAN32_BITS` or `NAN64_BITS`).
+    Canonicalize,
+    /// Fail with `ErrorKind::NonCanonical` on any NaN other than the canonical quiet NaN, so
+    /// that values which would change when canonicalized are noticed.
+    Reject,
+}
+
+thread_local! {
+    static CANONICAL: Cell<Option<NanPolicy>> = const { Cell::new(None) };
+}
+
+// Restore the enclosing mode when a scope ends
+struct Scope {
+    outer: Option<NanPolicy>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CANONICAL.with(|c| c.set(self.outer));
+    }
+}
+
+/// Run `f` with canonical packing on the current thread, handling NaNs according to `nan`.
+pub fn with_canonical<F, R>(nan: NanPolicy, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let outer = CANONICAL.with(|c| c.replace(Some(nan)));
+    let _scope = Scope { outer };
+
+    f()
+}
+
+/// The current NaN policy, or `None` if canonical packing isn't active on the current thread.
+#[inline]
+pub fn nan_policy() -> Option<NanPolicy> {
+    CANONICAL.with(|c| c.get())
+}
+
+/// Pack a value canonically, packing any NaN as the canonical quiet NaN.
+pub fn pack_canonical<Out: Write, T: Pack<Out>>(val: &T, out: &mut Out) -> Result<()> {
+    with_canonical(NanPolicy::Canonicalize, || super::pack(val, out))
+}
+
+// Apply the NaN policy to a float's bits
+fn nan_bits<B: PartialEq + fmt::LowerHex>(bits: B, canonical: B) -> Result<B> {
+    match nan_policy() {
+        None => Ok(bits),
+        Some(NanPolicy::Canonicalize) => Ok(canonical),
+        Some(NanPolicy::Reject) if bits == canonical => Ok(bits),
+        Some(NanPolicy::Reject) => {
+            Err(ErrorKind::NonCanonical(format!("NaN with bits {:#x}", bits)).into())
+        }
+    }
+}
+
+pub(crate) fn f32_bits(v: f32) -> Result<u32> {
+    if v.is_nan() {
+        nan_bits(v.to_bits(), NAN32_BITS)
+    } else {
+        Ok(v.to_bits())
+    }
+}
+
+pub(crate) fn f64_bits(v: f64) -> Result<u64> {
+    if v.is_nan() {
+        nan_bits(v.to_bits(), NAN64_BITS)
+    } else {
+        Ok(v.to_bits())
+    }
+}
+
+/// Pack the elements of an unordered collection in the order of their encodings, as a
+/// variable-sized array.
+pub(crate) fn pack_sorted<'a, Out, T, I>(len: usize, elems: I, out: &mut Out) -> Result<usize>
+where
+    Out: Write,
+    T: 'a + Pack<Vec<u8>>,
+    I: IntoIterator<Item = &'a T>,
+{
+    if len > u32::MAX as usize {
+        bail!(ErrorKind::InvalidLen(len));
+    }
+
+    let mut encoded = Vec::with_capacity(len);
+    for it in elems {
+        let mut buf = Vec::new();
+        let _ = it.pack(&mut buf)?;
+        encoded.push(buf);
+    }
+    encoded.sort();
+
+    let mut sz = (len as u32).pack(out)?;
+    for buf in encoded {
+        out.write_all(&buf)?;
+        sz += buf.len();
+    }
+
+    Ok(sz)
+}
@@ -0,0 +1,390 @@
+//! Compressed records
+//!
+//! Large streams of records, such as event logs, can often be made much smaller by compressing
+//! them. `CompressedRecordWriter` compresses each record's payload independently and sends it as a
+//! single record-marked record, and `CompressedRecordReader` decompresses them again. Since each
+//! record is compressed separately, a reader can start at any record boundary, and a damaged
+//! record doesn't affect its neighbours.
+//!
+//! This is not part of ONC RPC, so both ends must agree on the compression used.
+//!
+//! The `deflate` feature enables raw Deflate (RFC1951) compression, and `zstd` enables Zstandard.
+use std::cmp::min;
+use std::io::{self, BufRead, Read, Write};
+use std::mem;
+
+#[cfg(feature = "deflate")]
+use flate2::read::DeflateDecoder;
+#[cfg(feature = "deflate")]
+use flate2::write::DeflateEncoder;
+#[cfg(feature = "deflate")]
+use flate2::Compression as DeflateLevel;
+
+use buffered;
+use error::*;
+use record::{XdrRecordReader, XdrRecordWriter};
+
+use super::{Pack, Unpack};
+
+/// Default maximum record size accepted by `CompressedRecordReader`.
+pub const DEFAULT_MAX_RECORD: usize = 16 * 1024 * 1024;
+
+/// Compression algorithm and level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Raw Deflate, with a level from 0 (none) to 9 (best).
+    #[cfg(feature = "deflate")]
+    Deflate(u32),
+    /// Zstandard, with a level from 1 to 22. 0 selects the library's default level.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+enum Encoder<W: Write> {
+    #[cfg(feature = "deflate")]
+    Deflate(DeflateEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(::zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(compression: Compression, w: W) -> io::Result<Encoder<W>> {
+        match compression {
+            #[cfg(feature = "deflate")]
+            Compression::Deflate(level) => Ok(Encoder::Deflate(DeflateEncoder::new(
+                w,
+                DeflateLevel::new(level),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => Ok(Encoder::Zstd(::zstd::Encoder::new(w, level)?)),
+        }
+    }
+
+    fn finish(self) -> io::Result<W> {
+        match self {
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(enc) => enc.finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(enc) => enc.finish(),
+        }
+    }
+
+    fn get_ref(&self) -> &W {
+        match *self {
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(ref enc) => enc.get_ref(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref enc) => enc.get_ref(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(ref mut enc) => enc.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut enc) => enc.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(ref mut enc) => enc.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(ref mut enc) => enc.flush(),
+        }
+    }
+}
+
+// Decompress `data` into `buf`, failing if the result is longer than `limit`
+fn decompress(
+    compression: Compression,
+    data: &[u8],
+    buf: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<()> {
+    let len = match compression {
+        #[cfg(feature = "deflate")]
+        Compression::Deflate(_) => DeflateDecoder::new(data)
+            .take(limit as u64 + 1)
+            .read_to_end(buf)?,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd(_) => ::zstd::Decoder::with_buffer(data)?
+            .take(limit as u64 + 1)
+            .read_to_end(buf)?,
+    };
+
+    if len > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "record too large",
+        ));
+    }
+    Ok(())
+}
+
+/// Write compressed records into a bytestream.
+///
+/// Data written is compressed as it goes, and passed on to an `XdrRecordWriter`. `end_record()`
+/// completes the compressed payload and ends the record. If a record is still open when the
+/// writer is dropped, it's ended then, but any error is lost.
+pub struct CompressedRecordWriter<W: Write> {
+    state: WriterState<W>,
+    compression: Compression,
+}
+
+enum WriterState<W: Write> {
+    Idle(XdrRecordWriter<W>),            // at record boundary
+    Active(Encoder<XdrRecordWriter<W>>), // compressing a record
+    Taken,                               // writer lost or taken by into_inner
+}
+
+impl<W: Write> CompressedRecordWriter<W> {
+    /// Create a new `CompressedRecordWriter` using the given compression.
+    pub fn new(w: W, compression: Compression) -> CompressedRecordWriter<W> {
+        CompressedRecordWriter::from_record_writer(XdrRecordWriter::new(w), compression)
+    }
+
+    /// Create a new `CompressedRecordWriter` on top of an existing `XdrRecordWriter`, which must
+    /// be at a record boundary.
+    pub fn from_record_writer(
+        w: XdrRecordWriter<W>,
+        compression: Compression,
+    ) -> CompressedRecordWriter<W> {
+        CompressedRecordWriter {
+            state: WriterState::Idle(w.at_boundary()),
+            compression,
+        }
+    }
+
+    // Get the encoder for the current record, starting one if needed
+    fn encoder(&mut self) -> io::Result<&mut Encoder<XdrRecordWriter<W>>> {
+        if let WriterState::Idle(_) = self.state {
+            match mem::replace(&mut self.state, WriterState::Taken) {
+                WriterState::Idle(w) => {
+                    self.state = WriterState::Active(Encoder::new(self.compression, w)?)
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        match self.state {
+            WriterState::Active(ref mut enc) => Ok(enc),
+            _ => panic!("writer taken"),
+        }
+    }
+
+    // Complete the current record, returning the record writer
+    fn finish_record(&mut self) -> io::Result<XdrRecordWriter<W>> {
+        self.encoder()?;
+        let mut writer = match mem::replace(&mut self.state, WriterState::Taken) {
+            WriterState::Active(enc) => enc.finish()?,
+            _ => unreachable!(),
+        };
+        writer.end_record()?;
+        Ok(writer)
+    }
+
+    /// End the current record. If nothing has been written since the previous record was ended,
+    /// this sends a record with an empty payload.
+    ///
+    /// If this fails, the writer can't be used again.
+    pub fn end_record(&mut self) -> io::Result<()> {
+        let writer = self.finish_record()?;
+        self.state = WriterState::Idle(writer);
+        Ok(())
+    }
+
+    /// Pack a value as a complete record.
+    pub fn pack_record<T: Pack<Self>>(&mut self, val: &T) -> Result<usize> {
+        let sz = val.pack(self)?;
+        self.end_record()?;
+        Ok(sz)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        match self.state {
+            WriterState::Idle(ref w) => w.get_ref(),
+            WriterState::Active(ref enc) => enc.get_ref().get_ref(),
+            WriterState::Taken => panic!("writer taken"),
+        }
+    }
+
+    /// Unwrap this `CompressedRecordWriter`, returning the underlying writer. Any unterminated
+    /// record is ended first.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        let writer = match mem::replace(&mut self.state, WriterState::Taken) {
+            WriterState::Idle(w) => w,
+            state => {
+                self.state = state;
+                self.finish_record()?
+            }
+        };
+        writer.into_inner().map_err(io::Error::from)
+    }
+}
+
+impl<W: Write> Drop for CompressedRecordWriter<W> {
+    fn drop(&mut self) {
+        if let WriterState::Active(_) = self.state {
+            let _ = self.finish_record();
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedRecordWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.state {
+            WriterState::Active(ref mut enc) => enc.flush(),
+            WriterState::Idle(ref mut w) => w.flush(),
+            WriterState::Taken => panic!("writer taken"),
+        }
+    }
+}
+
+/// Read compressed records from a bytestream.
+///
+/// `next_record()` reads and decompresses the next record. Its payload can then be read with
+/// `Read` or `BufRead`, which return EOF at the end of the record. Both the compressed record and
+/// its decompressed payload are limited to the maximum record size.
+#[derive(Debug)]
+pub struct CompressedRecordReader<R: BufRead> {
+    reader: XdrRecordReader<R>,
+    compression: Compression,
+    max_record: usize,
+    compressed: Vec<u8>, // compressed record
+    buf: Vec<u8>,        // current record payload
+    pos: usize,          // read position in buf
+}
+
+impl<R: BufRead> CompressedRecordReader<R> {
+    /// Create a new `CompressedRecordReader` using the given compression. The level is ignored.
+    pub fn new(rd: R, compression: Compression) -> CompressedRecordReader<R> {
+        CompressedRecordReader::from_record_reader(XdrRecordReader::new(rd), compression)
+    }
+
+    /// Create a new `CompressedRecordReader` on top of an existing `XdrRecordReader`, which must
+    /// be at a record boundary.
+    pub fn from_record_reader(
+        rd: XdrRecordReader<R>,
+        compression: Compression,
+    ) -> CompressedRecordReader<R> {
+        CompressedRecordReader {
+            reader: rd,
+            compression,
+            max_record: DEFAULT_MAX_RECORD,
+            compressed: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Set the maximum accepted record size. The default is `DEFAULT_MAX_RECORD`.
+    pub fn set_max_record(&mut self, max_record: usize) {
+        self.max_record = max_record;
+    }
+
+    /// Read and decompress the next record, discarding any unread part of the current one.
+    ///
+    /// Returns false at EOF. Fails with `InvalidData` if the record is too large, or can't be
+    /// decompressed.
+    pub fn next_record(&mut self) -> io::Result<bool> {
+        self.buf.clear();
+        self.compressed.clear();
+        self.pos = 0;
+
+        if !self
+            .reader
+            .read_record_into(&mut self.compressed, self.max_record)?
+        {
+            return Ok(false);
+        }
+
+        if let Err(err) = decompress(
+            self.compression,
+            &self.compressed,
+            &mut self.buf,
+            self.max_record,
+        ) {
+            self.buf.clear();
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    /// Read and decompress the next record, returning its payload, or `None` at EOF.
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.next_record()? {
+            self.pos = self.buf.len();
+            Ok(Some(self.buf.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Read and decompress the next record, and unpack a value from it. The value must use the
+    /// whole record. Returns `None` at EOF.
+    pub fn unpack_record<T: Unpack<Self>>(&mut self) -> Result<Option<T>> {
+        if !self.next_record()? {
+            return Ok(None);
+        }
+
+        let (val, _) = T::unpack(self)?;
+        if self.pos != self.buf.len() {
+            bail!(
+                "{} bytes of trailing data in record",
+                self.buf.len() - self.pos
+            );
+        }
+        Ok(Some(val))
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    /// Unwrap this `CompressedRecordReader`, returning the underlying reader. Any unread part of
+    /// the current record is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R: BufRead> Read for CompressedRecordReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let data = self.fill_buf()?;
+            let len = min(buf.len(), data.len());
+
+            buf[..len].copy_from_slice(&data[..len]);
+            len
+        };
+
+        self.consume(len);
+        Ok(len)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        buffered::read_exact(self, buf)
+    }
+}
+
+impl<R: BufRead> BufRead for CompressedRecordReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, sz: usize) {
+        assert!(sz <= self.buf.len() - self.pos);
+        self.pos += sz;
+    }
+}
@@ -0,0 +1,300 @@
+//! Conformance test vectors
+//!
+//! Canonical encodings for each of the data types in
+//! [RFC4506 section 4](https://tools.ietf.org/html/rfc4506#section-4), including edge cases like
+//! padding, empty values and extreme integers. Each vector pairs a value with its only valid
+//! encoding; as XDR has exactly one encoding for each value, an implementation must produce
+//! these bytes when packing, and recover the value when unpacking.
+//!
+//! The vectors are plain data so they can be used to test other implementations. `check_all()`
+//! checks this crate's own codecs against them.
+use std::fmt::Debug;
+
+use error::*;
+
+use super::{
+    pack_array, pack_opaque_array, pack_opaque_flex, pack_string, unpack_array,
+    unpack_opaque_array, unpack_opaque_flex, unpack_string, Pack, Unpack,
+};
+
+/// A value and its canonical encoding.
+#[derive(Debug, Clone, Copy)]
+pub struct Vector<T: 'static> {
+    /// Short description of the case.
+    pub name: &'static str,
+    /// The value.
+    pub value: T,
+    /// Its encoding.
+    pub encoded: &'static [u8],
+}
+
+macro_rules! vectors {
+    ($($name:expr, $value:expr => [$($b:expr),*];)*) => {
+        &[$(Vector { name: $name, value: $value, encoded: &[$($b),*] }),*]
+    };
+}
+
+/// `int` (section 4.1).
+pub const INTS: &[Vector<i32>] = vectors! {
+    "zero", 0 => [0, 0, 0, 0];
+    "one", 1 => [0, 0, 0, 1];
+    "minus one", -1 => [0xff, 0xff, 0xff, 0xff];
+    "max", i32::MAX => [0x7f, 0xff, 0xff, 0xff];
+    "min", i32::MIN => [0x80, 0, 0, 0];
+    "byte order", 0x01020304 => [1, 2, 3, 4];
+};
+
+/// `unsigned int` (section 4.2).
+pub const UINTS: &[Vector<u32>] = vectors! {
+    "zero", 0 => [0, 0, 0, 0];
+    "max", u32::MAX => [0xff, 0xff, 0xff, 0xff];
+    "top bit", 0x80000000 => [0x80, 0, 0, 0];
+    "byte order", 0x01020304 => [1, 2, 3, 4];
+};
+
+/// `enum` (section 4.3), as its `int` value.
+pub const ENUMS: &[Vector<i32>] = vectors! {
+    "RED = 2", 2 => [0, 0, 0, 2];
+    "negative", -3 => [0xff, 0xff, 0xff, 0xfd];
+};
+
+/// `bool` (section 4.4).
+pub const BOOLS: &[Vector<bool>] = vectors! {
+    "FALSE", false => [0, 0, 0, 0];
+    "TRUE", true => [0, 0, 0, 1];
+};
+
+/// `hyper` (section 4.5).
+pub const HYPERS: &[Vector<i64>] = vectors! {
+    "zero", 0 => [0, 0, 0, 0, 0, 0, 0, 0];
+    "minus one", -1 => [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    "max", i64::MAX => [0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    "min", i64::MIN => [0x80, 0, 0, 0, 0, 0, 0, 0];
+    "byte order", 0x0102030405060708 => [1, 2, 3, 4, 5, 6, 7, 8];
+};
+
+/// `unsigned hyper` (section 4.5).
+pub const UHYPERS: &[Vector<u64>] = vectors! {
+    "zero", 0 => [0, 0, 0, 0, 0, 0, 0, 0];
+    "max", u64::MAX => [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    "low word", 0xffffffff => [0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+};
+
+/// `float` (section 4.6).
+pub const FLOATS: &[Vector<f32>] = vectors! {
+    "zero", 0.0 => [0, 0, 0, 0];
+    "negative zero", -0.0 => [0x80, 0, 0, 0];
+    "one", 1.0 => [0x3f, 0x80, 0, 0];
+    "minus two and a half", -2.5 => [0xc0, 0x20, 0, 0];
+    "infinity", f32::INFINITY => [0x7f, 0x80, 0, 0];
+};
+
+/// `double` (section 4.7).
+pub const DOUBLES: &[Vector<f64>] = vectors! {
+    "zero", 0.0 => [0, 0, 0, 0, 0, 0, 0, 0];
+    "negative zero", -0.0 => [0x80, 0, 0, 0, 0, 0, 0, 0];
+    "one", 1.0 => [0x3f, 0xf0, 0, 0, 0, 0, 0, 0];
+    "pi", ::std::f64::consts::PI => [0x40, 0x09, 0x21, 0xfb, 0x54, 0x44, 0x2d, 0x18];
+    "negative infinity", f64::NEG_INFINITY => [0xff, 0xf0, 0, 0, 0, 0, 0, 0];
+};
+
+/// Fixed-length `opaque[n]` (section 4.9), where `n` is the length of the value.
+pub const FIXED_OPAQUES: &[Vector<&[u8]>] = vectors! {
+    "empty", b"" => [];
+    "one byte", b"\x01" => [1, 0, 0, 0];
+    "three bytes", b"\x01\x02\x03" => [1, 2, 3, 0];
+    "four bytes", b"\x01\x02\x03\x04" => [1, 2, 3, 4];
+    "five bytes", b"\x01\x02\x03\x04\x05" => [1, 2, 3, 4, 5, 0, 0, 0];
+};
+
+/// Variable-length `opaque<>` (section 4.10).
+pub const OPAQUES: &[Vector<&[u8]>] = vectors! {
+    "empty", b"" => [0, 0, 0, 0];
+    "one byte", b"\xff" => [0, 0, 0, 1, 0xff, 0, 0, 0];
+    "four bytes", b"\x01\x02\x03\x04" => [0, 0, 0, 4, 1, 2, 3, 4];
+    "six bytes", b"\x01\x02\x03\x04\x05\x06" => [0, 0, 0, 6, 1, 2, 3, 4, 5, 6, 0, 0];
+};
+
+/// `string<>` (section 4.11).
+pub const STRINGS: &[Vector<&str>] = vectors! {
+    "empty", "" => [0, 0, 0, 0];
+    "sillyprog", "sillyprog" => [0, 0, 0, 9, 0x73, 0x69, 0x6c, 0x6c, 0x79, 0x70, 0x72, 0x6f, 0x67,
+                                  0, 0, 0];
+    "four chars", "abcd" => [0, 0, 0, 4, 0x61, 0x62, 0x63, 0x64];
+};
+
+/// Fixed-length `int[n]` (section 4.12), where `n` is the length of the value.
+pub const FIXED_ARRAYS: &[Vector<&[i32]>] = vectors! {
+    "empty", &[] => [];
+    "three", &[1, -1, 2] => [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 2];
+};
+
+/// Variable-length `int<>` (section 4.13).
+pub const ARRAYS: &[Vector<&[i32]>] = vectors! {
+    "empty", &[] => [0, 0, 0, 0];
+    "two", &[3, 4] => [0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4];
+};
+
+/// `struct { int a; string b<>; }` (section 4.14), as `(a, b)`.
+pub const STRUCTS: &[Vector<(i32, &str)>] = vectors! {
+    "empty string", (1, "") => [0, 0, 0, 1, 0, 0, 0, 0];
+    "padded string", (-2, "xy") => [0xff, 0xff, 0xff, 0xfe, 0, 0, 0, 2, 0x78, 0x79, 0, 0];
+};
+
+/// `union switch (int d) { case 1: unsigned int v; default: void; }` (section 4.15), as
+/// `(d, v)`, where `v` is present if and only if `d` is 1.
+pub const UNIONS: &[Vector<(i32, Option<u32>)>] = vectors! {
+    "arm", (1, Some(0xdeadbeef)) => [0, 0, 0, 1, 0xde, 0xad, 0xbe, 0xef];
+    "default void", (7, None) => [0, 0, 0, 7];
+};
+
+/// `int *` (section 4.19), the same encoding as `int<1>`.
+pub const OPTIONALS: &[Vector<Option<i32>>] = vectors! {
+    "null", None => [0, 0, 0, 0];
+    "present", Some(5) => [0, 0, 0, 1, 0, 0, 0, 5];
+};
+
+// Check one set of vectors, with `pack` packing a value, and `unpack` returning whether it
+// unpacked an equal value
+fn check<T, P, U>(kind: &str, vectors: &[Vector<T>], pack: P, unpack: U) -> Result<()>
+where
+    T: Debug,
+    P: Fn(&T, &mut Vec<u8>) -> Result<usize>,
+    U: Fn(&T, &mut &[u8]) -> Result<(bool, usize)>,
+{
+    for v in vectors {
+        let mut buf = Vec::new();
+        let sz = pack(&v.value, &mut buf)?;
+        if buf != v.encoded || sz != buf.len() {
+            bail!(
+                "{} {}: {:?} packed as {:?} ({} bytes), expected {:?}",
+                kind,
+                v.name,
+                v.value,
+                buf,
+                sz,
+                v.encoded
+            );
+        }
+
+        let mut input = v.encoded;
+        let (same, sz) = unpack(&v.value, &mut input)?;
+        if !same || sz != v.encoded.len() || !input.is_empty() {
+            bail!(
+                "{} {}: {:?} didn't unpack as {:?}",
+                kind,
+                v.name,
+                v.encoded,
+                v.value
+            );
+        }
+    }
+    Ok(())
+}
+
+fn check_simple<T>(kind: &str, vectors: &[Vector<T>]) -> Result<()>
+where
+    T: Debug + PartialEq + Pack<Vec<u8>> + for<'a> Unpack<&'a [u8]>,
+{
+    check(
+        kind,
+        vectors,
+        |v, out| v.pack(out),
+        |v, input| T::unpack(input).map(|(u, sz)| (u == *v, sz)),
+    )
+}
+
+/// Check this crate's `Pack` and `Unpack` implementations against all the vectors.
+///
+/// Returns an error describing the first mismatch.
+pub fn check_all() -> Result<()> {
+    check_simple("int", INTS)?;
+    check_simple("unsigned int", UINTS)?;
+    check_simple("enum", ENUMS)?;
+    check_simple("bool", BOOLS)?;
+    check_simple("hyper", HYPERS)?;
+    check_simple("unsigned hyper", UHYPERS)?;
+    check(
+        "float",
+        FLOATS,
+        |v, out| v.pack(out),
+        |v, input| f32::unpack(input).map(|(u, sz)| (u.to_bits() == v.to_bits(), sz)),
+    )?;
+    check(
+        "double",
+        DOUBLES,
+        |v, out| v.pack(out),
+        |v, input| f64::unpack(input).map(|(u, sz)| (u.to_bits() == v.to_bits(), sz)),
+    )?;
+    check(
+        "fixed opaque",
+        FIXED_OPAQUES,
+        |v, out| pack_opaque_array(v, v.len(), out),
+        |v, input| {
+            let mut buf = vec![0; v.len()];
+            unpack_opaque_array(input, &mut buf, v.len()).map(|sz| (buf == *v, sz))
+        },
+    )?;
+    check(
+        "opaque",
+        OPAQUES,
+        |v, out| pack_opaque_flex(v, None, out),
+        |v, input| unpack_opaque_flex(input, None).map(|(u, sz)| (u == *v, sz)),
+    )?;
+    check(
+        "string",
+        STRINGS,
+        |v, out| pack_string(v, None, out),
+        |v, input| unpack_string(input, None).map(|(u, sz)| (u == *v, sz)),
+    )?;
+    check(
+        "fixed array",
+        FIXED_ARRAYS,
+        |v, out| pack_array(v, v.len(), out, None),
+        |v, input| {
+            let mut buf = vec![0; v.len()];
+            unpack_array(input, &mut buf, v.len(), None).map(|sz| (buf == *v, sz))
+        },
+    )?;
+    check(
+        "array",
+        ARRAYS,
+        |v, out| v.pack(out),
+        |v, input| Vec::<i32>::unpack(input).map(|(u, sz)| (u == *v, sz)),
+    )?;
+    check(
+        "struct",
+        STRUCTS,
+        |v, out| Ok(v.0.pack(out)? + pack_string(v.1, None, out)?),
+        |v, input| {
+            let (a, asz) = i32::unpack(input)?;
+            let (b, bsz) = unpack_string(input, None)?;
+            Ok((a == v.0 && b == v.1, asz + bsz))
+        },
+    )?;
+    check(
+        "union",
+        UNIONS,
+        |v, out| {
+            let mut sz = v.0.pack(out)?;
+            if let Some(arm) = v.1 {
+                sz += arm.pack(out)?;
+            }
+            Ok(sz)
+        },
+        |v, input| {
+            let (d, mut sz) = i32::unpack(input)?;
+            let arm = if d == 1 {
+                let (arm, asz) = u32::unpack(input)?;
+                sz += asz;
+                Some(arm)
+            } else {
+                None
+            };
+            Ok(((d, arm) == *v, sz))
+        },
+    )?;
+    check_simple("optional", OPTIONALS)?;
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+//! Runtime descriptions of XDR types
+//!
+//! A `Descriptor` describes the shape of a named XDR type: the fields of a struct, the values of
+//! an enum, or the cases of a union, along with their types and size limits. This lets generic
+//! code work with any type, for example to pretty-print or compare decoded values, or to inspect
+//! a protocol's schema at runtime.
+//!
+//! Types provide their descriptor by implementing `Describe`. xdrgen's `--describe` option
+//! implements it for every generated type.
+//!
+//! ```
+//! use xdr_codec::describe::{Describe, Descriptor, Field, Kind, TypeDesc};
+//!
+//! struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! impl Describe for Point {
+//!     fn describe() -> Descriptor {
+//!         Descriptor {
+//!             name: "point",
+//!             kind: Kind::Struct(vec![
+//!                 Field { name: "x", ty: TypeDesc::Int },
+//!                 Field { name: "y", ty: TypeDesc::Int },
+//!             ]),
+//!         }
+//!     }
+//! }
+//!
+//! let desc = Point::describe();
+//! assert_eq!(desc.field("y").map(|f| f.name), Some("y"));
+//! ```
+
+/// Implemented by types which can describe their XDR representation.
+pub trait Describe {
+    /// Describe this type.
+    fn describe() -> Descriptor;
+}
+
+/// Description of a named XDR type.
+#[derive(Debug, Clone)]
+pub struct Descriptor {
+    /// The type's name in the specification.
+    pub name: &'static str,
+    /// What kind of type it is.
+    pub kind: Kind,
+}
+
+/// The kinds of named type.
+#[derive(Debug, Clone)]
+pub enum Kind {
+    /// An enum, with its values.
+    Enum(Vec<EnumValue>),
+    /// A struct, with its fields in order.
+    Struct(Vec<Field>),
+    /// A discriminated union. The `selector` is the discriminant, and each case has the value
+    /// which selects it, except for the `default` case, if any.
+    Union { selector: Field, cases: Vec<Case> },
+    /// A named array, opaque or string, represented as a single-field tuple struct.
+    Newtype(TypeDesc),
+}
+
+/// A value of an enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnumValue {
+    pub name: &'static str,
+    pub value: i64,
+}
+
+/// A struct field, or union selector or arm.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: TypeDesc,
+}
+
+/// A union case.
+#[derive(Debug, Clone)]
+pub struct Case {
+    /// The discriminant value, or `None` for the `default` case.
+    pub value: Option<i64>,
+    /// The name of the Rust enum variant.
+    pub variant: &'static str,
+    /// The arm's value, or `None` if it's `void`.
+    pub field: Option<Field>,
+}
+
+/// The size of an array, opaque or string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// Exactly this many elements.
+    Fixed(usize),
+    /// Any number of elements up to an optional limit.
+    Flex(Option<usize>),
+}
+
+/// Description of the type of a field.
+#[derive(Debug, Clone)]
+pub enum TypeDesc {
+    Int,
+    UInt,
+    Hyper,
+    UHyper,
+    Float,
+    Double,
+    Quadruple,
+    Bool,
+    Opaque(Size),
+    String(Size),
+    Array(Box<TypeDesc>, Size),
+    Option(Box<TypeDesc>),
+    /// A named type, with a function to describe it. Its description is only made when needed,
+    /// so recursive types can be described.
+    Named(&'static str, fn() -> Descriptor),
+}
+
+impl Descriptor {
+    /// Find a struct field by name.
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        match self.kind {
+            Kind::Struct(ref fields) => fields.iter().find(|f| f.name == name),
+            _ => None,
+        }
+    }
+
+    /// Find the name of an enum value.
+    pub fn enum_name(&self, value: i64) -> Option<&'static str> {
+        match self.kind {
+            Kind::Enum(ref values) => values.iter().find(|v| v.value == value).map(|v| v.name),
+            _ => None,
+        }
+    }
+
+    /// Find the union case selected by a discriminant value, falling back to the `default`
+    /// case.
+    pub fn case(&self, value: i64) -> Option<&Case> {
+        match self.kind {
+            Kind::Union { ref cases, .. } => cases
+                .iter()
+                .find(|c| c.value == Some(value))
+                .or_else(|| cases.iter().find(|c| c.value.is_none())),
+            _ => None,
+        }
+    }
+}
+
+impl TypeDesc {
+    /// Describe a named type, or `None` for anything else.
+    pub fn descriptor(&self) -> Option<Descriptor> {
+        match *self {
+            TypeDesc::Named(_, describe) => Some(describe()),
+            _ => None,
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 error_chain! {
     foreign_links {
         IOError(::std::io::Error);
@@ -17,6 +19,42 @@ error_chain! {
             description("invalid array len")
             display("invalid array len: '{}'", v)
         }
+        InvalidChar(v: u32) {
+            description("invalid char value")
+            display("invalid char value: '{:#x}'", v)
+        }
+        InvalidZero {
+            description("invalid zero value")
+            display("invalid zero value for non-zero type")
+        }
+        InvalidStringChar(pos: usize, c: u8) {
+            description("invalid string character")
+            display("invalid string character: '{:#x}' at offset {}", c, pos)
+        }
+        Validation(msg: String) {
+            description("validation failed")
+            display("validation failed: {}", msg)
+        }
+        BudgetExceeded(need: usize, remaining: usize) {
+            description("allocation budget exceeded")
+            display("allocation budget exceeded: need {} bytes, {} remaining", need, remaining)
+        }
+        NonCanonical(msg: String) {
+            description("non-canonical value")
+            display("non-canonical value: {}", msg)
+        }
+        OutOfRange(v: String) {
+            description("value out of range")
+            display("value out of range for its XDR type: {}", v)
+        }
+        NoCommonVersion {
+            description("no common protocol version")
+            display("no protocol version supported by both sides")
+        }
+        Context(msg: String) {
+            description("error context")
+            display("{}", msg)
+        }
     }
 }
 
@@ -34,4 +72,118 @@ impl Error {
     pub fn invalidlen(v: usize) -> Error {
         ErrorKind::InvalidLen(v).into()
     }
+
+    pub fn invalidchar(v: u32) -> Error {
+        ErrorKind::InvalidChar(v).into()
+    }
+
+    pub fn invalidzero() -> Error {
+        ErrorKind::InvalidZero.into()
+    }
+
+    pub fn invalidstringchar(pos: usize, c: u8) -> Error {
+        ErrorKind::InvalidStringChar(pos, c).into()
+    }
+
+    pub fn validation<S: Into<String>>(msg: S) -> Error {
+        ErrorKind::Validation(msg.into()).into()
+    }
+
+    pub fn budgetexceeded(need: usize, remaining: usize) -> Error {
+        ErrorKind::BudgetExceeded(need, remaining).into()
+    }
+
+    /// Wrap this error with a message describing what was being done when it happened.
+    ///
+    /// The result is an `ErrorKind::Context` error, chained to this one. Use `root_kind()` to
+    /// find out what actually went wrong, and `chained()` to display the whole chain.
+    pub fn context<S: Into<String>>(self, msg: S) -> Error {
+        Error::with_chain(self, ErrorKind::Context(msg.into()))
+    }
+
+    /// Wrap this error with the name of the field being packed or unpacked.
+    ///
+    /// xdrgen's generated code uses this to identify the field of a struct (as `type.field`) or
+    /// the arm of a union (as `type::arm`) which failed.
+    pub fn with_field(self, field: &str) -> Error {
+        self.context(field)
+    }
+
+    /// The kind of the innermost error, ignoring any context added by `context()` or
+    /// `with_field()`.
+    pub fn root_kind(&self) -> &ErrorKind {
+        let mut err = self;
+        while let ErrorKind::Context(_) = *err.kind() {
+            match err
+                .1
+                .next_error
+                .as_ref()
+                .and_then(|e| e.downcast_ref::<Error>())
+            {
+                Some(next) => err = next,
+                None => break,
+            }
+        }
+        err.kind()
+    }
+
+    /// Return a `Display` adapter which shows the whole chain of errors, outermost first,
+    /// separated by `: `.
+    ///
+    /// ```
+    /// # use xdr_codec::Error;
+    /// let err = Error::invalidlen(3).with_field("inner.v").with_field("outer.i");
+    /// assert_eq!(err.chained().to_string(), "outer.i: inner.v: invalid array len: '3'");
+    /// ```
+    pub fn chained(&self) -> Chained<'_> {
+        Chained(self)
+    }
+}
+
+/// Display adapter for a chain of errors; see `Error::chained()`.
+#[derive(Debug)]
+pub struct Chained<'a>(&'a Error);
+
+impl<'a> fmt::Display for Chained<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, err) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ": ")?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Call `f`, adding `field` to any error it returns with `Error::with_field()`.
+///
+/// This is used by generated code to annotate errors while unpacking each field.
+#[inline]
+pub fn in_field<T, F>(field: &str, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    f().with_field(field)
+}
+
+/// Add context to the error of a `Result`.
+pub trait ResultContext<T> {
+    /// Wrap any error with `Error::context()`.
+    fn context<S: Into<String>>(self, msg: S) -> Result<T>;
+
+    /// Wrap any error with `Error::with_field()`.
+    fn with_field(self, field: &str) -> Result<T>;
+}
+
+impl<T> ResultContext<T> for Result<T> {
+    #[inline]
+    fn context<S: Into<String>>(self, msg: S) -> Result<T> {
+        self.map_err(|e| e.context(msg))
+    }
+
+    #[inline]
+    fn with_field(self, field: &str) -> Result<T> {
+        self.map_err(|e| e.with_field(field))
+    }
 }
@@ -0,0 +1,443 @@
+//! Checksummed records
+//!
+//! Record marking (see `record`) delimits records, but does nothing to detect corruption. That's
+//! fine over a reliable transport, but not for data stored at rest. This module adds a checksum
+//! trailer to each record: the record payload is followed by a big-endian checksum of the payload,
+//! and the whole lot is sent as a single record-marked record. This isn't part of ONC RPC, so both
+//! ends must agree to use it.
+//!
+//! `FramedWriter` checksums data as it's written, and adds the trailer when the record is ended.
+//! `FramedReader` reads each record in full, and verifies its length and checksum before making
+//! any of it available, so corrupt data is never unpacked.
+//!
+//! A checksum only detects accidental corruption. With the `hmac` feature, the trailer can
+//! instead be an HMAC-SHA256 of the payload under a key shared by both ends, which also detects
+//! deliberate tampering on links where RPCSEC_GSS or TLS would be overkill. It doesn't stop
+//! records from being replayed, dropped or reordered; include a sequence number in each record
+//! if that matters.
+//!
+//! This module is only available with the `framed` feature. CRC32 is always available; the
+//! `xxhash` feature adds xxHash64.
+use std::cmp::min;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use byteorder::{BigEndian, ByteOrder};
+use crc32fast::Hasher as Crc32;
+#[cfg(feature = "hmac")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "hmac")]
+use sha2::Sha256;
+#[cfg(feature = "xxhash")]
+use xxhash_rust::xxh64::Xxh64;
+
+use buffered;
+use error::*;
+use record::{XdrRecordReader, XdrRecordWriter};
+
+use super::{Pack, Unpack};
+
+/// Default maximum record size accepted by `FramedReader`, not including the checksum.
+pub const DEFAULT_MAX_RECORD: usize = 16 * 1024 * 1024;
+
+/// Checksum algorithm used for the record trailer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    /// CRC32 (IEEE), as a 4 byte trailer.
+    Crc32,
+    /// xxHash64 with a zero seed, as an 8 byte trailer.
+    #[cfg(feature = "xxhash")]
+    Xxh64,
+}
+
+impl Checksum {
+    /// Size of the checksum trailer in bytes.
+    pub fn trailer_len(self) -> usize {
+        match self {
+            Checksum::Crc32 => 4,
+            #[cfg(feature = "xxhash")]
+            Checksum::Xxh64 => 8,
+        }
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            Checksum::Crc32 => Hasher::Crc32(Crc32::new()),
+            #[cfg(feature = "xxhash")]
+            Checksum::Xxh64 => Hasher::Xxh64(Box::new(Xxh64::new(0))),
+        }
+    }
+}
+
+/// HMAC-SHA256 with a key, as a 32 byte trailer.
+///
+/// The key isn't shown by `Debug`.
+#[cfg(feature = "hmac")]
+#[derive(Clone)]
+pub struct HmacSha256 {
+    mac: Hmac<Sha256>,
+}
+
+#[cfg(feature = "hmac")]
+impl HmacSha256 {
+    /// Create an HMAC-SHA256 trailer using `key`, which may be any length.
+    pub fn new(key: &[u8]) -> HmacSha256 {
+        HmacSha256 {
+            mac: Hmac::new_from_slice(key).expect("HMAC accepts any key length"),
+        }
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl fmt::Debug for HmacSha256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("HmacSha256")
+    }
+}
+
+/// What the record trailer contains: a checksum, or with the `hmac` feature, an HMAC.
+#[derive(Clone)]
+pub enum Trailer {
+    Checksum(Checksum),
+    #[cfg(feature = "hmac")]
+    HmacSha256(HmacSha256),
+}
+
+impl Trailer {
+    /// Size of the trailer in bytes.
+    pub fn trailer_len(&self) -> usize {
+        match *self {
+            Trailer::Checksum(c) => c.trailer_len(),
+            #[cfg(feature = "hmac")]
+            Trailer::HmacSha256(_) => 32,
+        }
+    }
+
+    fn hasher(&self) -> Hasher {
+        match *self {
+            Trailer::Checksum(c) => c.hasher(),
+            #[cfg(feature = "hmac")]
+            Trailer::HmacSha256(ref h) => Hasher::HmacSha256(Box::new(h.mac.clone())),
+        }
+    }
+
+    // Check `data` against its trailer, in constant time for an HMAC
+    fn verify(&self, data: &[u8], trailer: &[u8]) -> bool {
+        match *self {
+            #[cfg(feature = "hmac")]
+            Trailer::HmacSha256(ref h) => {
+                let mut mac = h.mac.clone();
+                mac.update(data);
+                mac.verify_slice(trailer).is_ok()
+            }
+            _ => {
+                let mut hasher = self.hasher();
+                hasher.update(data);
+                hasher.finish() == trailer
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Trailer::Checksum(ref c) => c.fmt(f),
+            #[cfg(feature = "hmac")]
+            Trailer::HmacSha256(ref h) => h.fmt(f),
+        }
+    }
+}
+
+impl From<Checksum> for Trailer {
+    fn from(c: Checksum) -> Trailer {
+        Trailer::Checksum(c)
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl From<HmacSha256> for Trailer {
+    fn from(h: HmacSha256) -> Trailer {
+        Trailer::HmacSha256(h)
+    }
+}
+
+enum Hasher {
+    Crc32(Crc32),
+    #[cfg(feature = "xxhash")]
+    Xxh64(Box<Xxh64>),
+    #[cfg(feature = "hmac")]
+    HmacSha256(Box<Hmac<Sha256>>),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::Crc32(ref mut h) => h.update(data),
+            #[cfg(feature = "xxhash")]
+            Hasher::Xxh64(ref mut h) => h.update(data),
+            #[cfg(feature = "hmac")]
+            Hasher::HmacSha256(ref mut h) => h.update(data),
+        }
+    }
+
+    // Return the checksum as trailer bytes
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Crc32(h) => {
+                let mut buf = vec![0; 4];
+                BigEndian::write_u32(&mut buf, h.finalize());
+                buf
+            }
+            #[cfg(feature = "xxhash")]
+            Hasher::Xxh64(h) => {
+                let mut buf = vec![0; 8];
+                BigEndian::write_u64(&mut buf, h.digest());
+                buf
+            }
+            #[cfg(feature = "hmac")]
+            Hasher::HmacSha256(h) => h.finalize().into_bytes().to_vec(),
+        }
+    }
+}
+
+/// Write checksummed records into a bytestream.
+///
+/// Data written is passed through to an `XdrRecordWriter`. `end_record()` appends the checksum
+/// and ends the record. If a record is still open when the writer is dropped, it's ended then,
+/// but any error is lost.
+pub struct FramedWriter<W: Write> {
+    writer: Option<XdrRecordWriter<W>>,
+    trailer: Trailer,
+    hasher: Hasher,
+    pending: bool, // written since last end of record
+}
+
+impl<W: Write> FramedWriter<W> {
+    /// Create a new `FramedWriter` using the given checksum or HMAC.
+    pub fn new<T: Into<Trailer>>(w: W, trailer: T) -> FramedWriter<W> {
+        FramedWriter::from_record_writer(XdrRecordWriter::new(w), trailer)
+    }
+
+    /// Create a new `FramedWriter` on top of an existing `XdrRecordWriter`, which must be at a
+    /// record boundary.
+    pub fn from_record_writer<T: Into<Trailer>>(
+        w: XdrRecordWriter<W>,
+        trailer: T,
+    ) -> FramedWriter<W> {
+        let trailer = trailer.into();
+        FramedWriter {
+            writer: Some(w.at_boundary()),
+            hasher: trailer.hasher(),
+            trailer,
+            pending: false,
+        }
+    }
+
+    /// End the current record, appending its checksum. If nothing has been written since the
+    /// previous record was ended, this sends an empty record.
+    pub fn end_record(&mut self) -> io::Result<()> {
+        let hasher = ::std::mem::replace(&mut self.hasher, self.trailer.hasher());
+        let writer = self.writer.as_mut().expect("writer taken");
+
+        writer.write_all(&hasher.finish())?;
+        self.pending = false;
+        writer.end_record()
+    }
+
+    /// Pack a value as a complete record.
+    pub fn pack_record<T: Pack<Self>>(&mut self, val: &T) -> Result<usize> {
+        let sz = val.pack(self)?;
+        self.end_record()?;
+        Ok(sz)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.writer.as_ref().expect("writer taken").get_ref()
+    }
+
+    /// Unwrap this `FramedWriter`, returning the underlying writer. Any unterminated record is
+    /// ended first.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        if self.pending {
+            self.end_record()?;
+        }
+        self.writer
+            .take()
+            .expect("writer taken")
+            .into_inner()
+            .map_err(io::Error::from)
+    }
+}
+
+impl<W: Write> Drop for FramedWriter<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() && self.pending {
+            let _ = self.end_record();
+        }
+    }
+}
+
+impl<W: Write> Write for FramedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let sz = self.writer.as_mut().expect("writer taken").write(buf)?;
+        self.hasher.update(&buf[..sz]);
+        self.pending = true;
+        Ok(sz)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().expect("writer taken").flush()
+    }
+}
+
+/// Read checksummed records from a bytestream.
+///
+/// `next_record()` reads and verifies the next record. Its payload can then be read with `Read`
+/// or `BufRead`, which return EOF at the end of the record. Records larger than the maximum size
+/// are rejected without being read in full.
+#[derive(Debug)]
+pub struct FramedReader<R: BufRead> {
+    reader: XdrRecordReader<R>,
+    trailer: Trailer,
+    max_record: usize,
+    buf: Vec<u8>, // current record payload
+    pos: usize,   // read position in buf
+}
+
+impl<R: BufRead> FramedReader<R> {
+    /// Create a new `FramedReader` using the given checksum or HMAC.
+    pub fn new<T: Into<Trailer>>(rd: R, trailer: T) -> FramedReader<R> {
+        FramedReader::from_record_reader(XdrRecordReader::new(rd), trailer)
+    }
+
+    /// Create a new `FramedReader` on top of an existing `XdrRecordReader`, which must be at a
+    /// record boundary.
+    pub fn from_record_reader<T: Into<Trailer>>(
+        rd: XdrRecordReader<R>,
+        trailer: T,
+    ) -> FramedReader<R> {
+        FramedReader {
+            reader: rd,
+            trailer: trailer.into(),
+            max_record: DEFAULT_MAX_RECORD,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Set the maximum accepted record size, not including the checksum. The default is
+    /// `DEFAULT_MAX_RECORD`.
+    pub fn set_max_record(&mut self, max_record: usize) {
+        self.max_record = max_record;
+    }
+
+    /// Read and verify the next record, discarding any unread part of the current one.
+    ///
+    /// Returns false at EOF. Fails with `InvalidData` if the record is too large or too short to
+    /// hold a checksum, or if the checksum doesn't match.
+    pub fn next_record(&mut self) -> io::Result<bool> {
+        let tlen = self.trailer.trailer_len();
+
+        self.buf.clear();
+        self.pos = 0;
+
+        if !self
+            .reader
+            .read_record_into(&mut self.buf, self.max_record + tlen)?
+        {
+            return Ok(false);
+        }
+
+        if self.buf.len() < tlen {
+            self.buf.clear();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "record too short for checksum",
+            ));
+        }
+
+        let len = self.buf.len() - tlen;
+        if !self.trailer.verify(&self.buf[..len], &self.buf[len..]) {
+            #[cfg(feature = "metrics")]
+            ::metrics::checksum_error();
+            self.buf.clear();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "record checksum mismatch",
+            ));
+        }
+
+        self.buf.truncate(len);
+        Ok(true)
+    }
+
+    /// Read and verify the next record, returning its payload, or `None` at EOF.
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.next_record()? {
+            self.pos = self.buf.len();
+            Ok(Some(self.buf.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Read and verify the next record, and unpack a value from it. The value must use the
+    /// whole record. Returns `None` at EOF.
+    pub fn unpack_record<T: Unpack<Self>>(&mut self) -> Result<Option<T>> {
+        if !self.next_record()? {
+            return Ok(None);
+        }
+
+        let (val, _) = T::unpack(self)?;
+        if self.pos != self.buf.len() {
+            bail!(
+                "{} bytes of trailing data in record",
+                self.buf.len() - self.pos
+            );
+        }
+        Ok(Some(val))
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    /// Unwrap this `FramedReader`, returning the underlying reader. Any unread part of the current
+    /// record is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R: BufRead> Read for FramedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let data = self.fill_buf()?;
+            let len = min(buf.len(), data.len());
+
+            buf[..len].copy_from_slice(&data[..len]);
+            len
+        };
+
+        self.consume(len);
+        Ok(len)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        buffered::read_exact(self, buf)
+    }
+}
+
+impl<R: BufRead> BufRead for FramedReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, sz: usize) {
+        assert!(sz <= self.buf.len() - self.pos);
+        self.pos += sz;
+    }
+}
@@ -0,0 +1,76 @@
+//! Decoding a struct's header on its own
+//!
+//! Messages are often a small header followed by a large body, and something routing them only
+//! needs the header to decide where each one goes. `UnpackHeader` is implemented by types whose
+//! leading fields can be unpacked as a separate header type, leaving the input at the start of
+//! the rest of the value. The rest can then be unpacked later, given the header, or passed on
+//! without being decoded at all.
+//!
+//! xdrgen implements this for structs with a `/* @header */` comment between two of their fields,
+//! which ends the header. For `struct msg { ... }`, the header is a `msgHeader` struct of the
+//! fields before the comment.
+//!
+//! ```
+//! # use xdr_codec::header::{unpack_header, UnpackHeader};
+//! # use xdr_codec::{pack, Read, Result, Unpack};
+//! #[derive(Debug, PartialEq)]
+//! struct Msg {
+//!     kind: u32,
+//!     body: Vec<u32>,
+//! }
+//!
+//! impl<In: Read> Unpack<In> for Msg {
+//!     fn unpack(input: &mut In) -> Result<(Msg, usize)> {
+//!         let (hdr, hsz) = Msg::unpack_header(input)?;
+//!         let (msg, sz) = Msg::unpack_rest(hdr, input)?;
+//!         Ok((msg, hsz + sz))
+//!     }
+//! }
+//!
+//! impl<In: Read> UnpackHeader<In> for Msg {
+//!     type Header = u32;
+//!
+//!     fn unpack_rest(kind: u32, input: &mut In) -> Result<(Msg, usize)> {
+//!         let (body, sz) = Unpack::unpack(input)?;
+//!         Ok((Msg { kind, body }, sz))
+//!     }
+//! }
+//!
+//! let mut buf = Vec::new();
+//! pack(&7u32, &mut buf).unwrap();
+//! pack(&vec![1u32, 2, 3], &mut buf).unwrap();
+//!
+//! let (kind, rest) = unpack_header::<Msg>(&buf).unwrap();
+//! assert_eq!(kind, 7);
+//! assert_eq!(rest.len(), 16);
+//! ```
+use error::*;
+
+use super::{Read, Unpack};
+
+/// A type whose leading fields can be unpacked as a separate header.
+pub trait UnpackHeader<In: Read>: Unpack<In> {
+    /// The leading fields.
+    type Header: Unpack<In>;
+
+    /// Unpack just the header, returning it and the number of bytes it used, which is the offset
+    /// of the rest of the value.
+    fn unpack_header(input: &mut In) -> Result<(Self::Header, usize)> {
+        Self::Header::unpack(input)
+    }
+
+    /// Unpack the rest of the value following `header`, returning the whole value and the number
+    /// of bytes the rest used.
+    fn unpack_rest(header: Self::Header, input: &mut In) -> Result<(Self, usize)>;
+}
+
+/// Unpack the header of a `T` from the start of `buf`, returning it and the rest of `buf` from
+/// where the header ends.
+pub fn unpack_header<'a, T>(buf: &'a [u8]) -> Result<(T::Header, &'a [u8])>
+where
+    T: UnpackHeader<&'a [u8]>,
+{
+    let mut input = buf;
+    let (header, _) = T::unpack_header(&mut input)?;
+    Ok((header, input))
+}
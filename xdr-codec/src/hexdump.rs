@@ -0,0 +1,65 @@
+//! Hex dumps of encoded data
+//!
+//! `HexDump` formats bytes in the usual offset/hex/ASCII layout, with the hex grouped into 4 byte
+//! XDR units so field boundaries are easy to pick out:
+//!
+//! ```
+//! # use xdr_codec::hexdump::HexDump;
+//! let dump = format!("{}", HexDump::new(&[0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o', 0, 0, 0]));
+//! assert_eq!(dump, "00000000  00 00 00 05  68 65 6c 6c  6f 00 00 00              |....hello...|");
+//! ```
+use std::fmt;
+
+const LINE: usize = 16;
+
+/// Display adapter for a hex dump of some bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct HexDump<'a> {
+    data: &'a [u8],
+    base: usize,
+}
+
+impl<'a> HexDump<'a> {
+    /// Dump `data`, with offsets starting at 0.
+    pub fn new(data: &'a [u8]) -> HexDump<'a> {
+        HexDump::with_base(data, 0)
+    }
+
+    /// Dump `data`, with offsets starting at `base`.
+    pub fn with_base(data: &'a [u8], base: usize) -> HexDump<'a> {
+        HexDump { data, base }
+    }
+}
+
+impl<'a> fmt::Display for HexDump<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.data.chunks(LINE).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:08x} ", self.base + i * LINE)?;
+
+            for j in 0..LINE {
+                if j % 4 == 0 {
+                    write!(f, " ")?;
+                }
+                match line.get(j) {
+                    Some(b) => write!(f, "{:02x} ", b)?,
+                    None => write!(f, "   ")?,
+                }
+            }
+
+            write!(f, "|")?;
+            for &b in line {
+                let c = if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                };
+                write!(f, "{}", c)?;
+            }
+            write!(f, "|")?;
+        }
+        Ok(())
+    }
+}
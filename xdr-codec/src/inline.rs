@@ -0,0 +1,130 @@
+//! Inline storage for small arrays
+//!
+//! Unpacking a flexible array into a `Vec` allocates, even when it only ever has a handful of
+//! elements. With the `smallvec` and `arrayvec` features, this module implements `Pack`, `Unpack`
+//! and `Skip` for [`SmallVec`](https://docs.rs/smallvec) and
+//! [`ArrayVec`](https://docs.rs/arrayvec), which keep their elements inline:
+//!
+//! - `SmallVec<[T; N]>` holds up to `N` elements inline, and moves them to the heap if there are
+//!   more. It has no limit of its own, so `unpack_smallvec()` takes one.
+//! - `ArrayVec<T, N>` holds at most `N` elements and never allocates. This is also its limit:
+//!   unpacking more than `N` elements fails with `ErrorKind::InvalidLen`.
+//!
+//! xdrgen's `--inline-vecs` option uses `ArrayVec` for element arrays with small limits.
+//!
+//! Both types are re-exported here so that generated code doesn't need a direct dependency on
+//! them. They don't implement `arbitrary::Arbitrary`, but can be collected from the result of
+//! `arbitrary::flex()`.
+use std::io::{Read, Write};
+
+#[cfg(feature = "arrayvec")]
+pub use arrayvec::ArrayVec;
+#[cfg(feature = "smallvec")]
+pub use smallvec::SmallVec;
+
+use error::*;
+
+#[cfg(feature = "smallvec")]
+use super::budget;
+use super::{pack_flex, prealloc, skip_flex, Pack, Skip, Unpack};
+
+/// Unpack a (perhaps) length-limited array into a `SmallVec`.
+///
+/// This only allocates, and charges the current budget, if there are more than `N` elements.
+///
+/// ```
+/// # use xdr_codec::inline::{unpack_smallvec, SmallVec};
+/// let buf = [0u8, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2];
+///
+/// let (v, _): (SmallVec<[u32; 4]>, _) = unpack_smallvec(&mut &buf[..], Some(4)).unwrap();
+/// assert_eq!(&v[..], &[1, 2]);
+/// assert!(!v.spilled());
+/// assert!(unpack_smallvec::<_, u32, 4>(&mut &buf[..], Some(1)).is_err());
+/// ```
+#[cfg(feature = "smallvec")]
+pub fn unpack_smallvec<In: Read, T: Unpack<In>, const N: usize>(
+    input: &mut In,
+    maxsz: Option<usize>,
+) -> Result<(SmallVec<[T; N]>, usize)> {
+    let (elems, mut sz) = Unpack::unpack(input)?;
+
+    if let Some(m) = maxsz {
+        if elems > m {
+            bail!(ErrorKind::InvalidLen(m));
+        }
+    }
+
+    if elems > N {
+        budget::charge_elems::<T>(elems)?;
+    }
+    let mut out = SmallVec::with_capacity(prealloc::<T>(elems));
+
+    for _ in 0..elems {
+        let (e, esz) = Unpack::unpack(input)?;
+        out.push(e);
+        sz += esz;
+    }
+
+    Ok((out, sz))
+}
+
+#[cfg(feature = "smallvec")]
+impl<Out: Write, T: Pack<Out>, const N: usize> Pack<Out> for SmallVec<[T; N]> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_flex(&self[..], None, out)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<In: Read, T: Unpack<In>, const N: usize> Unpack<In> for SmallVec<[T; N]> {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        unpack_smallvec(input, None)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<In: Read, T: Skip<In>, const N: usize> Skip<In> for SmallVec<[T; N]> {
+    #[inline]
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_flex::<In, T>(input, None)
+    }
+}
+
+#[cfg(feature = "arrayvec")]
+impl<Out: Write, T: Pack<Out>, const N: usize> Pack<Out> for ArrayVec<T, N> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_flex(&self[..], Some(N), out)
+    }
+}
+
+#[cfg(feature = "arrayvec")]
+impl<In: Read, T: Unpack<In>, const N: usize> Unpack<In> for ArrayVec<T, N> {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (elems, mut sz): (usize, _) = Unpack::unpack(input)?;
+
+        if elems > N {
+            bail!(ErrorKind::InvalidLen(N));
+        }
+
+        let mut out = ArrayVec::new();
+
+        for _ in 0..elems {
+            let (e, esz) = Unpack::unpack(input)?;
+            out.push(e);
+            sz += esz;
+        }
+
+        Ok((out, sz))
+    }
+}
+
+#[cfg(feature = "arrayvec")]
+impl<In: Read, T: Skip<In>, const N: usize> Skip<In> for ArrayVec<T, N> {
+    #[inline]
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_flex::<In, T>(input, Some(N))
+    }
+}
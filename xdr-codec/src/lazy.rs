@@ -0,0 +1,169 @@
+//! Fields decoded on first use
+//!
+//! Unpacking a large structure decodes all of it, even if only a few fields are ever looked at.
+//! A `Lazy<T>` field instead keeps the encoded bytes of its value when it's unpacked, after
+//! skipping over them, and only decodes them the first time the value is asked for with `get()`.
+//! The decoded value is kept, so it's only decoded once. Packing a `Lazy<T>` writes the encoded
+//! bytes out again, whether or not they've been decoded.
+//!
+//! xdrgen generates `Lazy` fields for struct fields with a `/* @lazy */` comment before them.
+//!
+//! ```
+//! # use xdr_codec::lazy::Lazy;
+//! # use xdr_codec::{pack, skip_flex, Unpack};
+//! let mut buf = Vec::new();
+//! pack(&vec![1u32, 2, 3], &mut buf).unwrap();
+//!
+//! let mut input = &buf[..];
+//! let (lazy, sz) = Lazy::<Vec<u32>>::capture(
+//!     &mut input,
+//!     |input| skip_flex::<_, u32>(input, None),
+//!     |input| Unpack::unpack(input),
+//! )
+//! .unwrap();
+//! assert_eq!(sz, 16);
+//! assert_eq!(lazy.raw(), &buf[..]);
+//! assert!(!lazy.is_decoded());
+//! assert_eq!(lazy.get().unwrap(), &vec![1, 2, 3]);
+//! ```
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
+
+use error::*;
+
+use super::{Pack, Unpack};
+
+/// How a `Lazy` decodes its value from its encoded bytes.
+pub type Decode<T> = fn(&mut &[u8]) -> Result<(T, usize)>;
+
+/// A reader which keeps a copy of everything read through it.
+#[derive(Debug)]
+pub struct Capture<R> {
+    inner: R,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> Read for Capture<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A value kept encoded until it's first used.
+pub struct Lazy<T> {
+    raw: Vec<u8>,
+    value: OnceLock<T>,
+    decode: Decode<T>,
+}
+
+fn unpack_slice<T: for<'a> Unpack<&'a [u8]>>(input: &mut &[u8]) -> Result<(T, usize)> {
+    T::unpack(input)
+}
+
+impl<T> Lazy<T> {
+    /// Capture the encoding of a value from `input`, using `skip` to read past it, to be decoded
+    /// later with `decode`. Returns the value and the number of bytes it used.
+    pub fn capture<In: Read, S>(
+        input: &mut In,
+        skip: S,
+        decode: Decode<T>,
+    ) -> Result<(Lazy<T>, usize)>
+    where
+        S: FnOnce(&mut Capture<&mut In>) -> Result<usize>,
+    {
+        let mut capture = Capture {
+            inner: input,
+            buf: Vec::new(),
+        };
+        let sz = skip(&mut capture)?;
+        Ok((Lazy::from_raw(capture.buf, decode), sz))
+    }
+
+    /// A value from its encoding, to be decoded with `decode`.
+    pub fn from_raw(raw: Vec<u8>, decode: Decode<T>) -> Lazy<T> {
+        Lazy {
+            raw,
+            value: OnceLock::new(),
+            decode,
+        }
+    }
+
+    /// A value which has already been decoded, encoding it for when it's packed.
+    pub fn new(value: T) -> Result<Lazy<T>>
+    where
+        T: Pack<Vec<u8>> + for<'a> Unpack<&'a [u8]>,
+    {
+        let mut raw = Vec::new();
+        value.pack(&mut raw)?;
+        Ok(Lazy {
+            raw,
+            value: OnceLock::from(value),
+            decode: unpack_slice::<T>,
+        })
+    }
+
+    /// The encoded value.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Whether the value has been decoded yet.
+    pub fn is_decoded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// The value, decoding it if this is the first time it's been asked for.
+    pub fn get(&self) -> Result<&T> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let (value, _) = (self.decode)(&mut &self.raw[..])?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Unwrap into the value, decoding it if it hasn't been already.
+    pub fn into_inner(self) -> Result<T> {
+        match self.value.into_inner() {
+            Some(value) => Ok(value),
+            None => (self.decode)(&mut &self.raw[..]).map(|(value, _)| value),
+        }
+    }
+}
+
+impl<T: Clone> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Lazy {
+            raw: self.raw.clone(),
+            value: self.value.clone(),
+            decode: self.decode,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => write!(f, "Lazy(<{} bytes>)", self.raw.len()),
+        }
+    }
+}
+
+/// Values are equal if their encodings are.
+impl<T> PartialEq for Lazy<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for Lazy<T> {}
+
+impl<Out: Write, T> Pack<Out> for Lazy<T> {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        out.write_all(&self.raw)?;
+        Ok(self.raw.len())
+    }
+}
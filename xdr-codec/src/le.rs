@@ -0,0 +1,210 @@
+//! Little-endian XDR
+//!
+//! Some protocols use XDR's encoding rules with little-endian integers instead of big-endian.
+//! Rather than a separate set of types, this module converts between the two byte orders, using
+//! a type's `Descriptor` (see the `describe` module) to find the integers in its encoding. The
+//! same types, including ones generated by xdrgen with `--describe`, can then be used with
+//! either byte order: `le::pack()` packs a value as usual and converts the result, and
+//! `le::unpack()` converts its input before unpacking it.
+//!
+//! The byte order is a property of each call rather than of the types or the thread, so it's
+//! unaffected by which thread or task a value is packed on, and the `Pack` and `Unpack`
+//! implementations themselves only ever use big-endian byte order.
+//!
+//! Only the byte order changes. Values still take a multiple of 4 bytes, opaque data and strings
+//! are still padded, and their bytes are unchanged. `Quadruple` is treated as opaque data, and
+//! record marking headers are always big-endian.
+//!
+//! ```
+//! # use xdr_codec::describe::{Describe, Descriptor, Kind, Size, TypeDesc};
+//! # use xdr_codec::le;
+//! // typedef unsigned int list<>;
+//! struct List(Vec<u32>);
+//! # impl<Out: xdr_codec::Write> xdr_codec::Pack<Out> for List {
+//! #     fn pack(&self, out: &mut Out) -> xdr_codec::Result<usize> { self.0.pack(out) }
+//! # }
+//! # impl<In: xdr_codec::Read> xdr_codec::Unpack<In> for List {
+//! #     fn unpack(input: &mut In) -> xdr_codec::Result<(Self, usize)> {
+//! #         xdr_codec::Unpack::unpack(input).map(|(v, sz)| (List(v), sz))
+//! #     }
+//! # }
+//!
+//! impl Describe for List {
+//!     fn describe() -> Descriptor {
+//!         Descriptor {
+//!             name: "list",
+//!             kind: Kind::Newtype(TypeDesc::Array(Box::new(TypeDesc::UInt), Size::Flex(None))),
+//!         }
+//!     }
+//! }
+//!
+//! let mut buf = Vec::new();
+//! le::pack(&List(vec![1, 2]), &mut buf).unwrap();
+//! assert_eq!(buf, &[2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0]);
+//!
+//! let v: List = le::unpack(&mut &buf[..]).unwrap();
+//! assert_eq!(v.0, vec![1, 2]);
+//! ```
+use std::io::{Read, Write};
+
+use describe::{Describe, Descriptor, Kind, Size, TypeDesc};
+use error::*;
+
+use super::{splice_opaque_array, Pack, Unpack};
+
+/// Pack a value in little-endian byte order.
+pub fn pack<Out: Write, T: Describe + Pack<Vec<u8>>>(val: &T, out: &mut Out) -> Result<()> {
+    let mut buf = Vec::new();
+    val.pack(&mut buf)?;
+    to_little_endian(&T::describe(), &mut &buf[..], out).map(|_| ())
+}
+
+/// Unpack a value in little-endian byte order.
+pub fn unpack<In: Read, T: Describe + for<'a> Unpack<&'a [u8]>>(input: &mut In) -> Result<T> {
+    let mut buf = Vec::new();
+    from_little_endian(&T::describe(), input, &mut buf)?;
+    T::unpack(&mut &buf[..]).map(|(v, _)| v)
+}
+
+/// Convert a value of the type described by `desc` from the standard big-endian encoding in
+/// `input` to little-endian in `out`.
+///
+/// Returns the size of the value, which is the same in both byte orders. The input is checked
+/// as far as is needed to find its end: array lengths against their limits, and union
+/// discriminants against their cases.
+pub fn to_little_endian<In: Read, Out: Write>(
+    desc: &Descriptor,
+    input: &mut In,
+    out: &mut Out,
+) -> Result<usize> {
+    convert(desc, Order::Big, input, out)
+}
+
+/// Convert a value of the type described by `desc` from little-endian in `input` to the
+/// standard big-endian encoding in `out`.
+///
+/// This is the reverse of `to_little_endian()`.
+pub fn from_little_endian<In: Read, Out: Write>(
+    desc: &Descriptor,
+    input: &mut In,
+    out: &mut Out,
+) -> Result<usize> {
+    convert(desc, Order::Little, input, out)
+}
+
+// The byte order of the input; the output is always the other one
+#[derive(Clone, Copy)]
+enum Order {
+    Big,
+    Little,
+}
+
+// Copy a `size`-byte integer with its bytes reversed
+fn swap<In: Read, Out: Write>(size: usize, input: &mut In, out: &mut Out) -> Result<usize> {
+    let mut bytes = [0; 8];
+    let bytes = &mut bytes[..size];
+    input.read_exact(bytes)?;
+    bytes.reverse();
+    out.write_all(bytes)?;
+    Ok(size)
+}
+
+// Copy a 4-byte word with its bytes reversed, returning its value
+fn word<In: Read, Out: Write>(order: Order, input: &mut In, out: &mut Out) -> Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    let v = match order {
+        Order::Big => u32::from_be_bytes(bytes),
+        Order::Little => u32::from_le_bytes(bytes),
+    };
+    bytes.reverse();
+    out.write_all(&bytes)?;
+    Ok(v)
+}
+
+// Copy the length of a variable-sized array, checking it against its limit
+fn length<In: Read, Out: Write>(
+    order: Order,
+    max: Option<usize>,
+    input: &mut In,
+    out: &mut Out,
+) -> Result<usize> {
+    let len = word(order, input, out)? as usize;
+    match max {
+        Some(m) if len > m => bail!(ErrorKind::InvalidLen(m)),
+        _ => Ok(len),
+    }
+}
+
+fn convert<In: Read, Out: Write>(
+    desc: &Descriptor,
+    order: Order,
+    input: &mut In,
+    out: &mut Out,
+) -> Result<usize> {
+    match desc.kind {
+        Kind::Enum(_) => swap(4, input, out),
+
+        Kind::Struct(ref fields) => {
+            let mut sz = 0;
+            for field in fields {
+                sz += convert_type(&field.ty, order, input, out)?;
+            }
+            Ok(sz)
+        }
+
+        // Discriminants are all 4-byte integers: int, unsigned int, bool or an enum
+        Kind::Union { .. } => {
+            let disc = word(order, input, out)? as i32;
+            match desc.case(disc as i64) {
+                Some(case) => match case.field {
+                    Some(ref field) => Ok(4 + convert_type(&field.ty, order, input, out)?),
+                    None => Ok(4),
+                },
+                None => bail!(ErrorKind::InvalidCase(disc)),
+            }
+        }
+
+        Kind::Newtype(ref ty) => convert_type(ty, order, input, out),
+    }
+}
+
+fn convert_type<In: Read, Out: Write>(
+    ty: &TypeDesc,
+    order: Order,
+    input: &mut In,
+    out: &mut Out,
+) -> Result<usize> {
+    match *ty {
+        TypeDesc::Int | TypeDesc::UInt | TypeDesc::Float | TypeDesc::Bool => swap(4, input, out),
+        TypeDesc::Hyper | TypeDesc::UHyper | TypeDesc::Double => swap(8, input, out),
+        TypeDesc::Quadruple => splice_opaque_array(input, 16, out),
+
+        TypeDesc::Opaque(Size::Fixed(len)) | TypeDesc::String(Size::Fixed(len)) => {
+            splice_opaque_array(input, len, out)
+        }
+        TypeDesc::Opaque(Size::Flex(max)) | TypeDesc::String(Size::Flex(max)) => {
+            let len = length(order, max, input, out)?;
+            Ok(4 + splice_opaque_array(input, len, out)?)
+        }
+
+        TypeDesc::Array(ref elem, size) => {
+            let (len, mut sz) = match size {
+                Size::Fixed(len) => (len, 0),
+                Size::Flex(max) => (length(order, max, input, out)?, 4),
+            };
+            for _ in 0..len {
+                sz += convert_type(elem, order, input, out)?;
+            }
+            Ok(sz)
+        }
+
+        TypeDesc::Option(ref ty) => match word(order, input, out)? {
+            0 => Ok(4),
+            1 => Ok(4 + convert_type(ty, order, input, out)?),
+            v => bail!(ErrorKind::InvalidEnum(v as i32)),
+        },
+
+        TypeDesc::Named(_, describe) => convert(&describe(), order, input, out),
+    }
+}
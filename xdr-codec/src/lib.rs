@@ -17,23 +17,135 @@
 //! However, some protocols are mis-specified to use byte arrays (I'm looking at
 //! you, gluster), so the option to support the exists. You can enable byte codec
 //! with the `bytecodec` feature.
+//!
+//! Nothing in the codec depends on the operating system, so it also builds for
+//! `wasm32-unknown-unknown`, where it can decode payloads held in memory (for example with
+//! `SliceCursor`, or the record readers over a `&[u8]`). All the optional features build there
+//! too, apart from `zstd`, which needs a C compiler for the target.
 #![crate_type = "lib"]
 
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary as arbitrary_crate;
+#[cfg(feature = "arrayvec")]
+extern crate arrayvec;
 extern crate byteorder;
+#[cfg(feature = "bytes")]
+extern crate bytes;
 #[macro_use]
 extern crate error_chain;
-
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "framed")]
+extern crate crc32fast;
+#[cfg(feature = "hmac")]
+extern crate hmac;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(feature = "metrics")]
+extern crate metrics as metrics_facade;
+#[cfg(feature = "deflate")]
+extern crate flate2;
+#[cfg(feature = "futures")]
+extern crate futures_core;
+#[cfg(feature = "futures-io")]
+extern crate futures_io;
+#[cfg(feature = "futures")]
+extern crate futures_sink;
+#[cfg(feature = "hmac")]
+extern crate sha2;
+#[cfg(feature = "smallvec")]
+extern crate smallvec;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "uuid")]
+extern crate uuid;
+#[cfg(feature = "xxhash")]
+extern crate xxhash_rust;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
+use byteorder::WriteBytesExt;
 use std::borrow::{Borrow, Cow};
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::io;
 pub use std::io::{Read, Write};
+use std::num::{NonZeroI32, NonZeroI64, NonZeroU32, NonZeroU64, NonZeroUsize};
 use std::ops::Deref;
 
+#[macro_use]
+mod macros;
+
+pub mod analyze;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+pub mod budget;
+mod bounded;
+pub mod buffered;
+pub mod bulk;
+pub mod canonical;
+pub mod conformance;
+pub mod describe;
+pub mod header;
+pub mod hexdump;
+pub mod lazy;
+pub mod le;
+pub mod negotiate;
+mod optarray;
+pub mod packer;
+pub mod prelude;
+pub mod pretty;
 pub mod record;
+pub mod recording;
+mod status;
+pub mod value;
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+mod async_core;
+#[cfg(feature = "tokio")]
+pub mod async_record;
+#[cfg(feature = "futures-io")]
+pub mod futures_record;
+#[cfg(feature = "futures")]
+pub mod async_stream;
+pub mod slice;
+pub mod vectored;
+
+#[cfg(feature = "framed")]
+pub mod framed;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "log")]
+pub mod trace;
+
+#[cfg(any(feature = "deflate", feature = "zstd"))]
+pub mod compress;
+
+#[cfg(any(feature = "smallvec", feature = "arrayvec"))]
+pub mod inline;
+
+#[cfg(feature = "net")]
+pub mod net;
+
+#[cfg(feature = "timestamp")]
+pub mod timestamp;
+
+#[cfg(feature = "bytes")]
+pub mod bytes_buf;
 
 mod error;
 pub use error::*;
 
+pub use bounded::BoundedVec;
+pub use optarray::OptArray;
+pub use packer::SizeSink;
+pub use status::StatusUnion;
+
 #[cfg(test)]
 mod test;
 
@@ -43,11 +155,62 @@ static PADDING: [u8; 4] = [0; 4];
 ///
 /// Return slice of zero padding needed to bring `sz` up to a multiple of 4. If no padding is needed,
 /// it will be a zero-sized slice.
+///
+/// Only opaque data and strings are padded (the `*_opaque_*` and `*_string*` functions). Arrays of
+/// other types (`pack_array`, `pack_flex` and so on) are never padded, since every element is
+/// already a multiple of 4 bytes.
 #[inline]
 pub fn padding(sz: usize) -> &'static [u8] {
     &PADDING[..(4 - (sz % 4)) % 4]
 }
 
+/// Write the padding needed after `sz` bytes of opaque data.
+///
+/// `sz` can also include whatever was packed before the data (such as its length), as long as
+/// that's a multiple of 4. Returns the number of padding bytes written.
+#[inline]
+pub fn write_padding<Out: Write>(out: &mut Out, sz: usize) -> Result<usize> {
+    let p = padding(sz);
+    out.write_all(p)?;
+    Ok(p.len())
+}
+
+/// Read the padding after `sz` bytes of opaque data.
+///
+/// The counterpart of `write_padding()`; returns the number of padding bytes read. As elsewhere
+/// when unpacking, the padding bytes aren't checked to be zero.
+#[inline]
+pub fn read_padding<In: Read>(input: &mut In, sz: usize) -> Result<usize> {
+    let mut buf = [0; 3];
+    let p = padding(sz).len();
+    input.read_exact(&mut buf[..p])?;
+    Ok(p)
+}
+
+// Most bytes to preallocate for an array whose length came from the input
+const MAX_PREALLOC: usize = 64 * 1024;
+
+// Capacity to preallocate for `elems` elements of `T` read from the input. Lengths can be up to
+// 2^32-1, which is more than a 32-bit target (like wasm32) can allocate, so beyond a limit the
+// array grows as it's unpacked and a bogus length fails when the input runs out instead.
+#[inline]
+pub(crate) fn prealloc<T>(elems: usize) -> usize {
+    min(elems, MAX_PREALLOC / max(::std::mem::size_of::<T>(), 1))
+}
+
+// Read exactly `len` bytes onto the end of `out`. `read_to_end()` reads into the `Vec`'s spare
+// capacity without zero-filling it first, which is a significant cost for large opaque data. As
+// with other arrays, the capacity only grows as data arrives beyond the preallocation limit.
+fn read_vec<In: Read>(input: &mut In, len: usize, out: &mut Vec<u8>) -> Result<usize> {
+    out.reserve(prealloc::<u8>(len));
+
+    let got = input.take(len as u64).read_to_end(out)?;
+    if got != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "opaque data truncated").into());
+    }
+    Ok(len)
+}
+
 /// Wrapper for XDR opaque data.
 ///
 /// In XDR terms, "opaque data" is a plain array of bytes, packed as tightly as possible, and then
@@ -78,11 +241,164 @@ impl<'a> From<&'a [u8]> for Opaque<'a> {
     }
 }
 
+/// XDR string which may not be valid UTF-8.
+///
+/// XDR strings are just ASCII by definition, but in practice they contain whatever bytes the
+/// peer sent (such as Latin-1 filenames). Unpacking into a `String` fails on those with
+/// `InvalidUtf8`; `XdrString` keeps the raw bytes instead, and leaves it to the application to
+/// decide how to interpret them.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct XdrString(pub Vec<u8>);
+
+impl XdrString {
+    pub fn new<B: Into<Vec<u8>>>(bytes: B) -> XdrString {
+        XdrString(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Return the string if it's valid UTF-8.
+    pub fn to_str(&self) -> Option<&str> {
+        ::std::str::from_utf8(&self.0).ok()
+    }
+
+    /// Return the string, replacing invalid UTF-8 sequences with `U+FFFD`.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+
+    /// Convert into a `String`, failing with `InvalidUtf8` if it isn't valid UTF-8.
+    pub fn into_string(self) -> Result<String> {
+        String::from_utf8(self.0).map_err(Error::from)
+    }
+}
+
+impl Deref for XdrString {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for XdrString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\"", self.0.escape_ascii())
+    }
+}
+
+impl fmt::Display for XdrString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+impl From<Vec<u8>> for XdrString {
+    fn from(v: Vec<u8>) -> Self {
+        XdrString(v)
+    }
+}
+
+impl<'a> From<&'a [u8]> for XdrString {
+    fn from(v: &'a [u8]) -> Self {
+        XdrString(v.to_vec())
+    }
+}
+
+impl From<String> for XdrString {
+    fn from(v: String) -> Self {
+        XdrString(v.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for XdrString {
+    fn from(v: &'a str) -> Self {
+        XdrString(v.as_bytes().to_vec())
+    }
+}
+
+/// Quadruple-precision floating point value.
+///
+/// RFC4506 defines `quadruple` as a 128-bit IEEE floating point number, but Rust has no stable
+/// type for it. This carries the 16 bytes of the encoding as-is (big-endian, sign bit first) so
+/// that values can at least be passed through unchanged.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Quadruple(pub [u8; 16]);
+
+impl From<[u8; 16]> for Quadruple {
+    fn from(v: [u8; 16]) -> Self {
+        Quadruple(v)
+    }
+}
+
+impl From<Quadruple> for [u8; 16] {
+    fn from(v: Quadruple) -> Self {
+        v.0
+    }
+}
+
+/// 128-bit signed integer, encoded as a pair of hypers.
+///
+/// XDR has no 128-bit integer type, but some protocols extend it with one encoded as two
+/// 64-bit hypers: the high half first as a `hyper`, then the low half as an `unsigned hyper`.
+/// The result is the 16-byte big-endian two's complement encoding of the value.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct Hyper128(pub i128);
+
+/// 128-bit unsigned integer, encoded as a pair of unsigned hypers.
+///
+/// The high half is encoded first, so the result is the 16-byte big-endian encoding of the
+/// value.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub struct UHyper128(pub u128);
+
+impl From<i128> for Hyper128 {
+    fn from(v: i128) -> Self {
+        Hyper128(v)
+    }
+}
+
+impl From<Hyper128> for i128 {
+    fn from(v: Hyper128) -> Self {
+        v.0
+    }
+}
+
+impl From<u128> for UHyper128 {
+    fn from(v: u128) -> Self {
+        UHyper128(v)
+    }
+}
+
+impl From<UHyper128> for u128 {
+    fn from(v: UHyper128) -> Self {
+        v.0
+    }
+}
+
 /// Serialization (packing) helper.
 ///
 /// Helper to serialize any type implementing `Pack` into an implementation of `std::io::Write`.
+///
+/// Values are written a field at a time, so if `out` is an unbuffered stream wrap it in a
+/// `packer::PackBuffer` (or `std::io::BufWriter`) to avoid a system call per field.
 pub fn pack<Out: Write, T: Pack<Out>>(val: &T, out: &mut Out) -> Result<()> {
-    val.pack(out).map(|_| ())
+    pack_sized(val, out).map(|_| ())
+}
+
+/// Serialization (packing) helper which returns the number of bytes written.
+///
+/// As `pack()`, but returns the encoded size, for callers keeping track of offsets.
+pub fn pack_sized<Out: Write, T: Pack<Out>>(val: &T, out: &mut Out) -> Result<usize> {
+    let sz = val.pack(out)?;
+    #[cfg(feature = "metrics")]
+    metrics::packed(sz);
+    Ok(sz)
 }
 
 /// Pack a fixed-size array.
@@ -138,7 +454,8 @@ pub fn pack_opaque_array<Out: Write>(val: &[u8], sz: usize, out: &mut Out) -> Re
 
 /// Pack a dynamically sized array, with size limit check.
 ///
-/// This packs an array of packable objects, and also applies an optional size limit.
+/// This packs an array of packable objects, and also applies an optional size limit. Use
+/// `pack_opaque_flex` for bytes, which are packed tightly and padded.
 #[inline]
 pub fn pack_flex<Out: Write, T: Pack<Out>>(
     val: &[T],
@@ -168,12 +485,94 @@ pub fn pack_opaque_flex<Out: Write>(
     Opaque::borrowed(val).pack(out)
 }
 
+/// Pack a dynamically sized opaque array, streaming its contents from `reader`.
+///
+/// This packs `len` bytes read from `reader` as an opaque, copying them through a fixed-size
+/// buffer rather than holding them all in memory, so it's suitable for very large payloads.
+/// It fails with an `UnexpectedEof` IO error if `reader` has fewer than `len` bytes, but by then
+/// the length and some of the data will have been written.
+pub fn pack_opaque_from<R: Read, Out: Write>(
+    reader: &mut R,
+    len: usize,
+    out: &mut Out,
+) -> Result<usize> {
+    if len > u32::MAX as usize {
+        bail!(ErrorKind::InvalidLen(len));
+    }
+
+    let mut sz = (len as u32).pack(out)?;
+    let copied = io::copy(&mut reader.take(len as u64), out)?;
+    if copied != len as u64 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "opaque source too short").into());
+    }
+    sz += len;
+
+    Ok(sz + write_padding(out, len)?)
+}
+
+/// Copy a fixed-sized opaque array of `bytesz` bytes from `input` to `out`.
+///
+/// The bytes go straight from one to the other through `io::copy()`, without being unpacked into
+/// a `Vec` first, which lets proxies forward large payloads cheaply (and on Linux, between
+/// sockets and files, without copying them through userspace at all). The padding is read and
+/// written again as zeros. Returns the encoded size, which is the same on both sides. It fails
+/// with an `UnexpectedEof` IO error if `input` ends early, but by then some of the data will
+/// have been written.
+pub fn splice_opaque_array<In: Read, Out: Write>(
+    input: &mut In,
+    bytesz: usize,
+    out: &mut Out,
+) -> Result<usize> {
+    let copied = io::copy(&mut input.by_ref().take(bytesz as u64), out)?;
+    if copied != bytesz as u64 {
+        return Err(
+            io::Error::new(io::ErrorKind::UnexpectedEof, "input ended while splicing").into(),
+        );
+    }
+    read_padding(input, bytesz)?;
+
+    Ok(bytesz + write_padding(out, bytesz)?)
+}
+
+/// Copy a (perhaps) length-limited opaque array or string from `input` to `out`.
+///
+/// This is the same as unpacking it with `unpack_opaque_flex()` and packing it again with
+/// `pack_opaque_flex()`, but the data is copied as with `splice_opaque_array()`. The length is
+/// checked against `maxsz` before anything is written.
+pub fn splice_opaque_flex<In: Read, Out: Write>(
+    input: &mut In,
+    maxsz: Option<usize>,
+    out: &mut Out,
+) -> Result<usize> {
+    let (len, _) = u32::unpack(input)?;
+
+    if maxsz.is_some_and(|m| len as usize > m) {
+        bail!(ErrorKind::InvalidLen(maxsz.unwrap()));
+    }
+
+    let sz = len.pack(out)?;
+    Ok(sz + splice_opaque_array(input, len as usize, out)?)
+}
+
 /// Pack a string with size limit check.
 #[inline]
 pub fn pack_string<Out: Write>(val: &str, maxsz: Option<usize>, out: &mut Out) -> Result<usize> {
     pack_opaque_flex(val.as_bytes(), maxsz, out)
 }
 
+/// Convert `val` to the type `T` it's packed as, for values of a wider Rust type than their XDR
+/// type.
+///
+/// Fails with `ErrorKind::OutOfRange` if `val` doesn't fit in `T`.
+#[inline]
+pub fn narrow<T, V>(val: &V) -> Result<T>
+where
+    T: TryFrom<V>,
+    V: Copy + fmt::Display,
+{
+    T::try_from(*val).map_err(|_| ErrorKind::OutOfRange(val.to_string()).into())
+}
+
 /// Unpack a fixed-sized array
 ///
 /// Unpack a fixed-size array of elements. The results are placed in `array`, but the actual wire-size of
@@ -202,8 +601,35 @@ where
     unpack_array_with(input, array, arraysz, set, drop, defl)
 }
 
+/// Unpack a fixed-size array into a new `[T; N]`.
+///
+/// Unlike `unpack_array`, this doesn't need an existing array to fill in, so `T` needn't
+/// implement `Clone` or `Default`, and there's never a partially initialized array to clean up if
+/// unpacking fails part way through. The wire size of the array is `N`.
+pub fn unpack_array_fixed<In, T, const N: usize>(input: &mut In) -> Result<([T; N], usize)>
+where
+    In: Read,
+    T: Unpack<In>,
+{
+    let mut elems = Vec::with_capacity(N);
+    let mut sz = 0;
+
+    for _ in 0..N {
+        let (v, vsz) = T::unpack(input)?;
+        elems.push(v);
+        sz += vsz;
+    }
+
+    match <[T; N]>::try_from(elems) {
+        Ok(array) => Ok((array, sz)),
+        Err(_) => unreachable!("unpacked wrong number of elements"),
+    }
+}
+
 /// Specialized variant of `unpack_array` which initializes the element via a callback. This is primarily
 /// so that the array can be uninitialized, and we initialize it element at a time with `ptr::write()`.
+///
+/// `unpack_array_fixed` is a safe alternative for unpacking a whole `[T; N]`.
 #[inline]
 pub fn unpack_array_with<In, T>(
     input: &mut In,
@@ -269,40 +695,31 @@ where
 /// the array is `bytesz`. If the supplied `bytes` is too large, the remainer is filled in with 0x00;
 /// if it is too small, the excess elements are discarded.
 ///
-/// All the bytes in `bytes` will be initialized after a successful call.
+/// All the bytes in `bytes` will be initialized after a successful call. It fails with an
+/// `UnexpectedEof` IO error if the input ends before the whole array and its padding.
 pub fn unpack_opaque_array<In: Read>(
     input: &mut In,
     bytes: &mut [u8],
     bytesz: usize,
 ) -> Result<usize> {
     let sz = min(bytesz, bytes.len());
-    let mut rsz = 0;
-
-    while rsz < sz {
-        let r = input.read(&mut bytes[rsz..])?;
-        rsz += r;
-    }
+    input.read_exact(&mut bytes[..sz])?;
 
     // Fill in excess
-    if sz < bytes.len() {
-        for b in &mut bytes[sz..] {
-            *b = 0;
-        }
+    for b in &mut bytes[sz..] {
+        *b = 0;
     }
 
     // Mop up unused data on the wire and padding
     let p = padding(bytesz).len();
-    if bytes.len() < bytesz + p {
-        for _ in bytes.len()..(bytesz + p) {
-            let _ = input.read_u8()?;
-            rsz += 1;
-        }
-    }
+    skip_bytes(input, bytesz - sz + p)?;
 
-    Ok(rsz)
+    Ok(bytesz + p)
 }
 
 /// Unpack a (perhaps) length-limited array
+///
+/// This is the counterpart of `pack_flex`; use `unpack_opaque_flex` for bytes.
 pub fn unpack_flex<In: Read, T: Unpack<In>>(
     input: &mut In,
     maxsz: Option<usize>,
@@ -313,7 +730,8 @@ pub fn unpack_flex<In: Read, T: Unpack<In>>(
         bail!(ErrorKind::InvalidLen(maxsz.unwrap()));
     }
 
-    let mut out = Vec::with_capacity(elems);
+    budget::charge_elems::<T>(elems)?;
+    let mut out = Vec::with_capacity(prealloc::<T>(elems));
 
     for _ in 0..elems {
         let (e, esz) = Unpack::unpack(input)?;
@@ -321,12 +739,6 @@ pub fn unpack_flex<In: Read, T: Unpack<In>>(
         sz += esz;
     }
 
-    let p = padding(sz);
-    for _ in 0..p.len() {
-        let _ = input.read_u8()?;
-    }
-    sz += p.len();
-
     Ok((out, sz))
 }
 
@@ -343,19 +755,56 @@ pub fn unpack_opaque_flex<In: Read>(
         bail!(ErrorKind::InvalidLen(maxsz.unwrap()));
     }
 
-    let mut out = Vec::with_capacity(elems);
+    budget::charge(elems)?;
+    let mut out = Vec::new();
 
-    sz += input.take(elems as u64).read_to_end(&mut out)?;
+    sz += read_vec(input, elems, &mut out)?;
+    sz += read_padding(input, sz)?;
 
-    let p = padding(sz);
-    for _ in 0..p.len() {
-        let _ = input.read_u8()?;
-    }
-    sz += p.len();
+    Ok((out, sz))
+}
+
+/// Unpack a fixed-sized opaque array into a `Vec`
+///
+/// This is the same as `unpack_opaque_array`, but for an array whose size is only known at
+/// runtime. The bytes are read straight into the `Vec` rather than into a zero-filled buffer.
+pub fn unpack_opaque_vec<In: Read>(input: &mut In, len: usize) -> Result<(Vec<u8>, usize)> {
+    budget::charge(len)?;
+    let mut out = Vec::new();
+
+    let mut sz = read_vec(input, len, &mut out)?;
+    sz += read_padding(input, sz)?;
 
     Ok((out, sz))
 }
 
+/// Unpack a (perhaps) length-limited opaque array, streaming its contents to `writer`.
+///
+/// The counterpart to `pack_opaque_from`. The contents are copied through a fixed-size buffer
+/// rather than being held in memory. Returns the length of the opaque data and the number of
+/// bytes consumed from `input`.
+pub fn unpack_opaque_to<In: Read, W: Write>(
+    input: &mut In,
+    writer: &mut W,
+    maxsz: Option<usize>,
+) -> Result<(usize, usize)> {
+    let (len, mut sz) = usize::unpack(input)?;
+
+    if let Some(m) = maxsz {
+        if len > m {
+            bail!(ErrorKind::InvalidLen(m));
+        }
+    }
+
+    let copied = io::copy(&mut input.take(len as u64), writer)?;
+    if copied != len as u64 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "opaque data truncated").into());
+    }
+    sz += len;
+
+    Ok((len, sz + skip_bytes(input, padding(len).len())?))
+}
+
 /// Unpack (perhaps) length-limited string
 pub fn unpack_string<In: Read>(input: &mut In, maxsz: Option<usize>) -> Result<(String, usize)> {
     let (v, sz) = unpack_opaque_flex(input, maxsz)?;
@@ -363,6 +812,93 @@ pub fn unpack_string<In: Read>(input: &mut In, maxsz: Option<usize>) -> Result<(
     String::from_utf8(v).map_err(Error::from).map(|s| (s, sz))
 }
 
+/// Unpack (perhaps) length-limited string, allowing only printable ASCII
+///
+/// RFC4506 defines strings as ASCII, but `unpack_string` will accept anything which is valid
+/// UTF-8. This is stricter: any byte outside the printable ASCII range (`0x20` to `0x7e`),
+/// including control characters, fails with `InvalidStringChar`.
+pub fn unpack_string_strict<In: Read>(
+    input: &mut In,
+    maxsz: Option<usize>,
+) -> Result<(String, usize)> {
+    let (v, sz) = unpack_opaque_flex(input, maxsz)?;
+
+    if let Some(pos) = v.iter().position(|&c| !(0x20..=0x7e).contains(&c)) {
+        bail!(ErrorKind::InvalidStringChar(pos, v[pos]));
+    }
+
+    String::from_utf8(v).map_err(Error::from).map(|s| (s, sz))
+}
+
+/// Unpack (perhaps) length-limited string, without checking it's valid UTF-8
+pub fn unpack_xdrstring<In: Read>(
+    input: &mut In,
+    maxsz: Option<usize>,
+) -> Result<(XdrString, usize)> {
+    unpack_opaque_flex(input, maxsz).map(|(v, sz)| (XdrString(v), sz))
+}
+
+/// Skip `len` bytes of input.
+///
+/// Fails with an `UnexpectedEof` IO error if the input ends first.
+pub fn skip_bytes<In: Read>(input: &mut In, len: usize) -> Result<usize> {
+    let got = io::copy(&mut input.by_ref().take(len as u64), &mut io::sink())?;
+
+    if got != len as u64 {
+        return Err(
+            io::Error::new(io::ErrorKind::UnexpectedEof, "input ended while skipping").into(),
+        );
+    }
+    Ok(len)
+}
+
+/// Skip a fixed-sized opaque array of `bytesz` bytes, and its padding.
+pub fn skip_opaque_array<In: Read>(input: &mut In, bytesz: usize) -> Result<usize> {
+    match bytesz.checked_add(padding(bytesz).len()) {
+        Some(len) => skip_bytes(input, len),
+        None => bail!(ErrorKind::InvalidLen(bytesz)),
+    }
+}
+
+/// Skip a (perhaps) length-limited opaque array or string.
+///
+/// The length is still checked against `maxsz`, so this fails in the same cases as unpacking
+/// (apart from string encoding errors).
+pub fn skip_opaque_flex<In: Read>(input: &mut In, maxsz: Option<usize>) -> Result<usize> {
+    let (len, sz) = usize::unpack(input)?;
+
+    if let Some(m) = maxsz {
+        if len > m {
+            bail!(ErrorKind::InvalidLen(m));
+        }
+    }
+
+    Ok(sz + skip_opaque_array(input, len)?)
+}
+
+/// Skip a fixed-sized array of `arraysz` elements.
+pub fn skip_array<In: Read, T: Skip<In>>(input: &mut In, arraysz: usize) -> Result<usize> {
+    let mut sz = 0;
+
+    for _ in 0..arraysz {
+        sz += T::skip(input)?;
+    }
+    Ok(sz)
+}
+
+/// Skip a (perhaps) length-limited array.
+pub fn skip_flex<In: Read, T: Skip<In>>(input: &mut In, maxsz: Option<usize>) -> Result<usize> {
+    let (elems, sz) = usize::unpack(input)?;
+
+    if let Some(m) = maxsz {
+        if elems > m {
+            bail!(ErrorKind::InvalidLen(m));
+        }
+    }
+
+    Ok(sz + skip_array::<In, T>(input, elems)?)
+}
+
 /// Basic packing trait.
 ///
 /// This trait is used to implement XDR packing any Rust type into a
@@ -377,13 +913,35 @@ pub trait Pack<Out: Write> {
     fn pack(&self, out: &mut Out) -> Result<usize>;
 }
 
+/// Object-safe packing trait.
+///
+/// `Pack` is generic over the output stream, so each type gets a separate copy of its packing
+/// code for every `Write` implementation it's packed into. For large protocols this can add
+/// up to a lot of code and compile time. `PackDyn` always packs into a `&mut dyn Write`, so
+/// there's only ever one instantiation per type.
+///
+/// It's implemented for every type which implements `Pack` for all `Write` implementations
+/// (which includes all generated code). Because it's object-safe, it can also be used to pack
+/// heterogeneous collections via `&dyn PackDyn`.
+pub trait PackDyn {
+    fn pack_dyn(&self, out: &mut dyn Write) -> Result<usize>;
+}
+
+impl<T: ?Sized> PackDyn for T
+where
+    for<'a> T: Pack<&'a mut dyn Write>,
+{
+    #[inline]
+    fn pack_dyn(&self, mut out: &mut dyn Write) -> Result<usize> {
+        self.pack(&mut out)
+    }
+}
+
 #[cfg(feature = "bytecodec")]
 impl<Out: Write> Pack<Out> for u8 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_u32::<BigEndian>(*self as u32)
-            .map_err(Error::from)
-            .map(|_| 4)
+        (*self as u32).pack(out)
     }
 }
 
@@ -391,63 +949,80 @@ impl<Out: Write> Pack<Out> for u8 {
 impl<Out: Write> Pack<Out> for i8 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_i32::<BigEndian>(*self as i32)
-            .map_err(Error::from)
-            .map(|_| 4)
+        (*self as i32).pack(out)
     }
 }
 
 impl<Out: Write> Pack<Out> for u32 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_u32::<BigEndian>(*self)
-            .map_err(Error::from)
-            .map(|_| 4)
+        let bytes = self.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 4)
     }
 }
 
 impl<Out: Write> Pack<Out> for i32 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_i32::<BigEndian>(*self)
-            .map_err(Error::from)
-            .map(|_| 4)
+        let bytes = self.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 4)
     }
 }
 
 impl<Out: Write> Pack<Out> for u64 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_u64::<BigEndian>(*self)
-            .map_err(Error::from)
-            .map(|_| 8)
+        let bytes = self.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 8)
     }
 }
 
 impl<Out: Write> Pack<Out> for i64 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_i64::<BigEndian>(*self)
-            .map_err(Error::from)
-            .map(|_| 8)
+        let bytes = self.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 8)
     }
 }
 
 impl<Out: Write> Pack<Out> for f32 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_f32::<BigEndian>(*self)
-            .map_err(Error::from)
-            .map(|_| 4)
+        let bits = canonical::f32_bits(*self)?;
+        let bytes = bits.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 4)
     }
 }
 
 impl<Out: Write> Pack<Out> for f64 {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
-        out.write_f64::<BigEndian>(*self)
-            .map_err(Error::from)
-            .map(|_| 8)
+        let bits = canonical::f64_bits(*self)?;
+        let bytes = bits.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 8)
+    }
+}
+
+impl<Out: Write> Pack<Out> for Quadruple {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        out.write_all(&self.0).map_err(Error::from).map(|_| 16)
+    }
+}
+
+impl<Out: Write> Pack<Out> for Hyper128 {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        let bytes = self.0.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 16)
+    }
+}
+
+impl<Out: Write> Pack<Out> for UHyper128 {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        let bytes = self.0.to_be_bytes();
+        out.write_all(&bytes).map_err(Error::from).map(|_| 16)
     }
 }
 
@@ -458,6 +1033,14 @@ impl<Out: Write> Pack<Out> for bool {
     }
 }
 
+/// A `char` is packed as its Unicode scalar value in an `unsigned int`.
+impl<Out: Write> Pack<Out> for char {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        (*self as u32).pack(out)
+    }
+}
+
 impl<Out: Write> Pack<Out> for () {
     #[inline]
     fn pack(&self, _out: &mut Out) -> Result<usize> {
@@ -472,22 +1055,48 @@ impl<Out: Write> Pack<Out> for usize {
     }
 }
 
-impl<Out: Write, T: Pack<Out>> Pack<Out> for [T] {
-    fn pack(&self, out: &mut Out) -> Result<usize> {
-        let len = self.len();
-
-        let mut sz = len.pack(out)?;
-        for it in self {
-            sz += it.pack(out)?;
+// Non-zero integers are encoded the same way as the underlying integer type; unpacking fails
+// with `InvalidZero` if the encoded value is zero.
+macro_rules! nonzero_codec {
+    ($nz:ident, $prim:ty) => {
+        impl<Out: Write> Pack<Out> for $nz {
+            #[inline]
+            fn pack(&self, out: &mut Out) -> Result<usize> {
+                self.get().pack(out)
+            }
         }
 
-        let p = padding(sz);
-        if p.len() > 0 {
-            out.write_all(p)?;
-            sz += p.len();
+        impl<In: Read> Unpack<In> for $nz {
+            #[inline]
+            fn unpack(input: &mut In) -> Result<(Self, usize)> {
+                <$prim>::unpack(input).and_then(|(v, sz)| match $nz::new(v) {
+                    Some(v) => Ok((v, sz)),
+                    None => Err(ErrorKind::InvalidZero.into()),
+                })
+            }
         }
+    };
+}
 
-        Ok(sz)
+nonzero_codec!(NonZeroU32, u32);
+nonzero_codec!(NonZeroI32, i32);
+nonzero_codec!(NonZeroU64, u64);
+nonzero_codec!(NonZeroI64, i64);
+nonzero_codec!(NonZeroUsize, usize);
+
+/// A slice is packed as a variable-sized array. Unlike opaque data, there's no trailing padding:
+/// each element is a multiple of 4 bytes already.
+impl<Out: Write, T: Pack<Out>> Pack<Out> for [T] {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_seq(self.len(), self, out)
+    }
+}
+
+impl<Out: Write, T: Pack<Out>, const N: usize> Pack<Out> for [T; N] {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_array(&self[..], N, out, None)
     }
 }
 
@@ -502,6 +1111,54 @@ impl<Out: Write, T: Pack<Out>> Pack<Out> for Vec<T> {
     }
 }
 
+// Pack a sequence of elements as a variable-sized array
+fn pack_seq<'a, Out, T, I>(len: usize, elems: I, out: &mut Out) -> Result<usize>
+where
+    Out: Write,
+    T: 'a + Pack<Out>,
+    I: IntoIterator<Item = &'a T>,
+{
+    if len > u32::MAX as usize {
+        bail!(ErrorKind::InvalidLen(len));
+    }
+
+    let mut sz = len.pack(out)?;
+    for it in elems {
+        sz += it.pack(out)?;
+    }
+
+    Ok(sz)
+}
+
+/// A `VecDeque` is packed as a variable-sized array, from front to back.
+impl<Out: Write, T: Pack<Out>> Pack<Out> for VecDeque<T> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_seq(self.len(), self, out)
+    }
+}
+
+/// A `HashSet` is packed as a variable-sized array. The order of elements is unspecified, so
+/// use `BTreeSet` or `canonical::pack_canonical()` if the encoding must be deterministic.
+impl<Out: Write, T: Pack<Out> + Pack<Vec<u8>>, S> Pack<Out> for HashSet<T, S> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        if canonical::nan_policy().is_some() {
+            canonical::pack_sorted(self.len(), self, out)
+        } else {
+            pack_seq(self.len(), self, out)
+        }
+    }
+}
+
+/// A `BTreeSet` is packed as a variable-sized array, in sorted order.
+impl<Out: Write, T: Pack<Out>> Pack<Out> for BTreeSet<T> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_seq(self.len(), self, out)
+    }
+}
+
 impl<'a, Out: Write> Pack<Out> for Opaque<'a> {
     fn pack(&self, out: &mut Out) -> Result<usize> {
         let mut sz;
@@ -515,17 +1172,19 @@ impl<'a, Out: Write> Pack<Out> for Opaque<'a> {
 
         out.write_all(data)?;
         sz += data.len();
-
-        let p = padding(sz);
-        if p.len() > 0 {
-            out.write_all(p)?;
-            sz += p.len();
-        }
+        sz += write_padding(out, sz)?;
 
         Ok(sz)
     }
 }
 
+impl<Out: Write> Pack<Out> for XdrString {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_opaque_flex(&self.0, None, out)
+    }
+}
+
 impl<Out: Write> Pack<Out> for str {
     #[inline]
     fn pack(&self, out: &mut Out) -> Result<usize> {
@@ -570,7 +1229,18 @@ where
 /// to be unpacked, so its up to the calling envionment to clarify
 /// this. (Generally it falls out quite naturally.)
 pub fn unpack<In: Read, T: Unpack<In>>(input: &mut In) -> Result<T> {
-    T::unpack(input).map(|(v, _)| v)
+    unpack_sized(input).map(|(v, _)| v)
+}
+
+/// Deserialization (unpacking) helper which also returns the number of bytes consumed.
+///
+/// As `unpack()`, but returns the value along with the number of bytes read from `input`, for
+/// callers computing offsets into a larger buffer.
+pub fn unpack_sized<In: Read, T: Unpack<In>>(input: &mut In) -> Result<(T, usize)> {
+    let res = T::unpack(input);
+    #[cfg(feature = "metrics")]
+    metrics::unpacked(&res);
+    res
 }
 
 /// Basic unpacking trait
@@ -585,14 +1255,101 @@ pub trait Unpack<In: Read>: Sized {
     fn unpack(input: &mut In) -> Result<(Self, usize)>;
 }
 
+/// Types which can be both packed into `Out` and unpacked from `In`.
+///
+/// This is implemented for every such type, so it's just a shorthand for bounds in generic code:
+///
+/// ```
+/// # use xdr_codec::prelude::*;
+/// fn roundtrip<T: for<'a> Xdr<Vec<u8>, &'a [u8]>>(v: &T) -> T {
+///     let mut buf = Vec::new();
+///     pack(v, &mut buf).unwrap();
+///     unpack(&mut &buf[..]).unwrap()
+/// }
+///
+/// assert_eq!(roundtrip(&42u32), 42);
+/// ```
+pub trait Xdr<Out: Write, In: Read>: Pack<Out> + Unpack<In> {}
+
+impl<Out: Write, In: Read, T: Pack<Out> + Unpack<In>> Xdr<Out, In> for T {}
+
+/// Decode-time validation trait
+///
+/// `Unpack` only checks that the encoding is well-formed. Types with further invariants (value
+/// ranges, relationships between fields, etc) can implement `Validate` to check them as soon as
+/// they're decoded, rather than leaving it to the application.
+///
+/// Code generated by xdrgen with validation enabled calls `validate()` on each value after
+/// unpacking it, so every generated type needs an implementation (which can be empty to use
+/// the default). For other types, use `unpack_validated()` or the `Validated` wrapper.
+pub trait Validate {
+    /// Check the value's invariants, returning an error (typically `Error::validation()`) if
+    /// they don't hold.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Unpack a value and then validate it.
+pub fn unpack_validated<In: Read, T: Unpack<In> + Validate>(input: &mut In) -> Result<(T, usize)> {
+    let (v, sz) = T::unpack(input)?;
+    v.validate()?;
+    Ok((v, sz))
+}
+
+/// Wrapper which validates the inner value when it's unpacked.
+///
+/// This has the same encoding as `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Validated<T>(pub T);
+
+impl<T> Validated<T> {
+    /// Return the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Validated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<Out: Write, T: Pack<Out>> Pack<Out> for Validated<T> {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        self.0.pack(out)
+    }
+}
+
+impl<In: Read, T: Unpack<In> + Validate> Unpack<In> for Validated<T> {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        unpack_validated(input).map(|(v, sz)| (Validated(v), sz))
+    }
+}
+
+/// Skip over an encoded value without unpacking it.
+///
+/// This consumes exactly as much input as `Unpack` would, honouring lengths and padding, but
+/// without allocating or decoding the contents. It's useful for pulling a few fields out of a
+/// large structure. Length limits and discriminants are still checked, since they determine
+/// how much input there is to skip, but the contents of the value are not.
+///
+/// Code generated by xdrgen with skipping enabled implements this for every generated type.
+pub trait Skip<In: Read> {
+    /// Skip over a value, returning the number of bytes consumed.
+    fn skip(input: &mut In) -> Result<usize>;
+}
+
 #[cfg(feature = "bytecodec")]
 impl<In: Read> Unpack<In> for u8 {
     #[inline]
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_u32::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v as u8, 4))
+        u32::unpack(input).map(|(v, sz)| (v as u8, sz))
     }
 }
 
@@ -600,68 +1357,94 @@ impl<In: Read> Unpack<In> for u8 {
 impl<In: Read> Unpack<In> for i8 {
     #[inline]
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_i32::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v as i8, 4))
+        i32::unpack(input).map(|(v, sz)| (v as i8, sz))
     }
 }
 
 impl<In: Read> Unpack<In> for u32 {
     #[inline]
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_u32::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v, 4))
+        let mut bytes = [0; 4];
+        input.read_exact(&mut bytes)?;
+        let v = u32::from_be_bytes(bytes);
+        Ok((v, 4))
     }
 }
 
 impl<In: Read> Unpack<In> for i32 {
     #[inline]
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_i32::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v, 4))
+        let mut bytes = [0; 4];
+        input.read_exact(&mut bytes)?;
+        let v = i32::from_be_bytes(bytes);
+        Ok((v, 4))
     }
 }
 
 impl<In: Read> Unpack<In> for u64 {
     #[inline]
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_u64::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v, 8))
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        let v = u64::from_be_bytes(bytes);
+        Ok((v, 8))
     }
 }
 
 impl<In: Read> Unpack<In> for i64 {
     #[inline]
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_i64::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v, 8))
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        let v = i64::from_be_bytes(bytes);
+        Ok((v, 8))
     }
 }
 
 impl<In: Read> Unpack<In> for f32 {
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_f32::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v, 4))
+        let mut bytes = [0; 4];
+        input.read_exact(&mut bytes)?;
+        let v = f32::from_be_bytes(bytes);
+        Ok((v, 4))
     }
 }
 
 impl<In: Read> Unpack<In> for f64 {
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
-        input
-            .read_f64::<BigEndian>()
-            .map_err(Error::from)
-            .map(|v| (v, 8))
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        let v = f64::from_be_bytes(bytes);
+        Ok((v, 8))
+    }
+}
+
+impl<In: Read> Unpack<In> for Quadruple {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut v = [0; 16];
+        input.read_exact(&mut v)?;
+        Ok((Quadruple(v), 16))
+    }
+}
+
+impl<In: Read> Unpack<In> for Hyper128 {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut bytes = [0; 16];
+        input.read_exact(&mut bytes)?;
+        let v = i128::from_be_bytes(bytes);
+        Ok((Hyper128(v), 16))
+    }
+}
+
+impl<In: Read> Unpack<In> for UHyper128 {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut bytes = [0; 16];
+        input.read_exact(&mut bytes)?;
+        let v = u128::from_be_bytes(bytes);
+        Ok((UHyper128(v), 16))
     }
 }
 
@@ -676,6 +1459,17 @@ impl<In: Read> Unpack<In> for bool {
     }
 }
 
+/// Unpacking a `char` fails with `InvalidChar` if the value isn't a valid Unicode scalar value.
+impl<In: Read> Unpack<In> for char {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        u32::unpack(input).and_then(|(v, sz)| match std::char::from_u32(v) {
+            Some(c) => Ok((c, sz)),
+            None => Err(ErrorKind::InvalidChar(v).into()),
+        })
+    }
+}
+
 impl<In: Read> Unpack<In> for () {
     #[inline]
     fn unpack(_input: &mut In) -> Result<(Self, usize)> {
@@ -690,12 +1484,83 @@ impl<In: Read> Unpack<In> for usize {
     }
 }
 
+impl<In: Read, T: Unpack<In>, const N: usize> Unpack<In> for [T; N] {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        unpack_array_fixed(input)
+    }
+}
+
 impl<In: Read, T: Unpack<In>> Unpack<In> for Vec<T> {
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
         unpack_flex(input, None)
     }
 }
 
+// Unpack a variable-sized array, passing each element to `add`
+fn unpack_seq<In, T, F>(input: &mut In, mut add: F) -> Result<usize>
+where
+    In: Read,
+    T: Unpack<In>,
+    F: FnMut(T) -> Result<()>,
+{
+    let (elems, mut sz) = usize::unpack(input)?;
+
+    budget::charge_elems::<T>(elems)?;
+
+    for _ in 0..elems {
+        let (e, esz) = Unpack::unpack(input)?;
+        add(e)?;
+        sz += esz;
+    }
+
+    Ok(sz)
+}
+
+impl<In: Read, T: Unpack<In>> Unpack<In> for VecDeque<T> {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut out = VecDeque::new();
+        let sz = unpack_seq(input, |e| {
+            out.push_back(e);
+            Ok(())
+        })?;
+        Ok((out, sz))
+    }
+}
+
+/// Unpacking a set fails if the encoded array has duplicate elements.
+impl<In, T, S> Unpack<In> for HashSet<T, S>
+where
+    In: Read,
+    T: Unpack<In> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut out = HashSet::default();
+        let sz = unpack_seq(input, |e| {
+            if !out.insert(e) {
+                bail!("duplicate set element");
+            }
+            Ok(())
+        })?;
+        Ok((out, sz))
+    }
+}
+
+/// Unpacking a set fails if the encoded array has duplicate elements.
+impl<In: Read, T: Unpack<In> + Ord> Unpack<In> for BTreeSet<T> {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut out = BTreeSet::new();
+        let sz = unpack_seq(input, |e| {
+            if !out.insert(e) {
+                bail!("duplicate set element");
+            }
+            Ok(())
+        })?;
+        Ok((out, sz))
+    }
+}
+
 impl<In: Read> Unpack<In> for String {
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
         let (v, sz) = unpack_opaque_flex(input, None)?;
@@ -703,17 +1568,20 @@ impl<In: Read> Unpack<In> for String {
     }
 }
 
+impl<In: Read> Unpack<In> for XdrString {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        unpack_xdrstring(input, None)
+    }
+}
+
 impl<'a, In: Read> Unpack<In> for Opaque<'a> {
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
         let (len, mut sz) = usize::unpack(input)?;
+        budget::charge(len)?;
         let mut v = Vec::new();
-        sz += input.by_ref().take(len as u64).read_to_end(&mut v)?;
-
-        let p = padding(sz);
-        for _ in 0..p.len() {
-            let _ = input.read_u8()?;
-            sz += 1;
-        }
+        sz += read_vec(input, len, &mut v)?;
+        sz += read_padding(input, sz)?;
 
         Ok((Opaque(Cow::Owned(v)), sz))
     }
@@ -735,6 +1603,7 @@ impl<In: Read, T: Unpack<In>> Unpack<In> for Option<T> {
 
 impl<In: Read, T: Unpack<In>> Unpack<In> for Box<T> {
     fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        budget::charge_elems::<T>(1)?;
         let (b, sz) = Unpack::unpack(input)?;
         Ok((Box::new(b), sz))
     }
@@ -749,3 +1618,109 @@ where
         Ok((Cow::Owned(b), sz))
     }
 }
+
+macro_rules! skip_fixed {
+    ($($t:ty => $sz:expr),*) => {
+        $(
+            impl<In: Read> Skip<In> for $t {
+                #[inline]
+                fn skip(input: &mut In) -> Result<usize> {
+                    skip_bytes(input, $sz)
+                }
+            }
+        )*
+    };
+}
+
+skip_fixed!(u32 => 4, i32 => 4, u64 => 8, i64 => 8, f32 => 4, f64 => 8, Quadruple => 16, Hyper128 => 16, UHyper128 => 16, char => 4);
+
+#[cfg(feature = "bytecodec")]
+skip_fixed!(u8 => 4, i8 => 4);
+
+impl<In: Read> Skip<In> for bool {
+    #[inline]
+    fn skip(input: &mut In) -> Result<usize> {
+        bool::unpack(input).map(|(_, sz)| sz)
+    }
+}
+
+impl<In: Read> Skip<In> for () {
+    #[inline]
+    fn skip(_input: &mut In) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+impl<In: Read, T: Skip<In>, const N: usize> Skip<In> for [T; N] {
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_array::<In, T>(input, N)
+    }
+}
+
+impl<In: Read, T: Skip<In>> Skip<In> for Vec<T> {
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_flex::<In, T>(input, None)
+    }
+}
+
+impl<In: Read> Skip<In> for String {
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_opaque_flex(input, None)
+    }
+}
+
+impl<In: Read> Skip<In> for XdrString {
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_opaque_flex(input, None)
+    }
+}
+
+impl<'a, In: Read> Skip<In> for Opaque<'a> {
+    fn skip(input: &mut In) -> Result<usize> {
+        skip_opaque_flex(input, None)
+    }
+}
+
+impl<In: Read, T: Skip<In>> Skip<In> for Option<T> {
+    fn skip(input: &mut In) -> Result<usize> {
+        let (have, sz) = bool::unpack(input)?;
+        if have {
+            Ok(sz + T::skip(input)?)
+        } else {
+            Ok(sz)
+        }
+    }
+}
+
+impl<In: Read, T: Skip<In>> Skip<In> for Box<T> {
+    #[inline]
+    fn skip(input: &mut In) -> Result<usize> {
+        T::skip(input)
+    }
+}
+
+impl<In: Read, T: Skip<In>> Skip<In> for Validated<T> {
+    #[inline]
+    fn skip(input: &mut In) -> Result<usize> {
+        T::skip(input)
+    }
+}
+
+/// A UUID is packed as a 16 byte fixed-size opaque (`opaque uuid[16]`).
+#[cfg(feature = "uuid")]
+impl<Out: Write> Pack<Out> for uuid::Uuid {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_opaque_array(self.as_bytes(), 16, out)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl<In: Read> Unpack<In> for uuid::Uuid {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut bytes = [0; 16];
+        input.read_exact(&mut bytes)?;
+        Ok((uuid::Uuid::from_bytes(bytes), 16))
+    }
+}
@@ -0,0 +1,285 @@
+//! Macros for hand-written XDR types
+//!
+//! xdrgen generates `Pack` and `Unpack` for the enums and unions in a specification, but
+//! without it their discriminant handling is tedious to write by hand. `xdr_enum!` and
+//! `xdr_union!` define such a type along with its `Pack` and `Unpack` implementations.
+//! `xdr_struct!` does the same for structs, which may be generic, so that building blocks
+//! shared between protocols can be written once for any payload type.
+
+/// Define an XDR enum.
+///
+/// Each variant must be given its value explicitly. The enum packs as its value, and unpacking
+/// any other value fails with `ErrorKind::InvalidEnum`.
+///
+/// ```
+/// # #[macro_use] extern crate xdr_codec;
+/// # fn main() {
+/// xdr_enum! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub enum Color {
+///         Red = 0,
+///         Green = 1,
+///         Blue = 2,
+///     }
+/// }
+///
+/// let mut buf = Vec::new();
+/// xdr_codec::pack(&Color::Blue, &mut buf).unwrap();
+/// assert_eq!(buf, &[0, 0, 0, 2]);
+/// assert_eq!(xdr_codec::unpack::<_, Color>(&mut &buf[..]).unwrap(), Color::Blue);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! xdr_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$vmeta:meta])* $var:ident = $val:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$vmeta])* $var = $val),+
+        }
+
+        impl<Out: $crate::Write> $crate::Pack<Out> for $name {
+            #[inline]
+            fn pack(&self, out: &mut Out) -> $crate::Result<usize> {
+                let v: i32 = match *self {
+                    $($name::$var => $val,)+
+                };
+                $crate::Pack::pack(&v, out)
+            }
+        }
+
+        impl<In: $crate::Read> $crate::Unpack<In> for $name {
+            #[inline]
+            fn unpack(input: &mut In) -> $crate::Result<($name, usize)> {
+                let (v, sz): (i32, usize) = $crate::Unpack::unpack(input)?;
+                let e = match v {
+                    $(x if x == $val => $name::$var,)+
+                    e => return Err($crate::Error::invalidenum(e)),
+                };
+                Ok((e, sz))
+            }
+        }
+    };
+}
+
+/// Define an XDR discriminated union.
+///
+/// Each `case` gives a discriminant value and the variant it selects, which either has a single
+/// field for the arm's value, or none for a `void` arm. Every arm, including the last, ends with
+/// a comma.
+///
+/// An optional final `default` arm is selected by any other discriminant. Its variant is written
+/// with an `i32` first field, which holds the discriminant so that it can be packed again,
+/// followed by the arm's value type unless it's `void`. Without a `default` arm, unpacking any
+/// other discriminant fails with `ErrorKind::InvalidCase`.
+///
+/// ```
+/// # #[macro_use] extern crate xdr_codec;
+/// # use xdr_codec::XdrString;
+/// # fn main() {
+/// xdr_union! {
+///     #[derive(Debug, Clone, PartialEq)]
+///     pub enum Reply {
+///         case 0 => Ok(u32),
+///         case 1 => Retry,
+///         default => Error(i32, XdrString),
+///     }
+/// }
+///
+/// let mut buf = Vec::new();
+/// xdr_codec::pack(&Reply::Ok(7), &mut buf).unwrap();
+/// assert_eq!(buf, &[0, 0, 0, 0, 0, 0, 0, 7]);
+///
+/// let buf = [0, 0, 0, 9, 0, 0, 0, 2, b'n', b'o', 0, 0];
+/// assert_eq!(
+///     xdr_codec::unpack::<_, Reply>(&mut &buf[..]).unwrap(),
+///     Reply::Error(9, XdrString::new("no"))
+/// );
+/// # }
+/// ```
+#[macro_export]
+macro_rules! xdr_union {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($body:tt)*
+        }
+    ) => {
+        // The arms are processed one at a time, accumulating the variants and match arms for
+        // each impl. The names used across arms are passed along so they share hygiene.
+        xdr_union!(@arm [$(#[$meta])*] [$vis] $name (out input sz)
+                   [] [] []
+                   $($body)*);
+    };
+
+    // case with a value
+    (@arm $attrs:tt $vis:tt $name:ident ($out:ident $input:ident $sz:ident)
+     [$($vars:tt)*] [$($packs:tt)*] [$($unpacks:tt)*]
+     $(#[$vmeta:meta])* case $disc:expr => $var:ident($ty:ty), $($rest:tt)*) => {
+        xdr_union!(@arm $attrs $vis $name ($out $input $sz)
+                   [$($vars)* $(#[$vmeta])* $var($ty),]
+                   [$($packs)*
+                    $name::$var(ref val) =>
+                        $crate::Pack::pack(&(($disc) as i32), $out)? + $crate::Pack::pack(val, $out)?,]
+                   [$($unpacks)*
+                    x if x == ($disc) as i32 => {
+                        let (v, vsz) = $crate::Unpack::unpack($input)?;
+                        $sz += vsz;
+                        $name::$var(v)
+                    }]
+                   $($rest)*);
+    };
+
+    // void case
+    (@arm $attrs:tt $vis:tt $name:ident ($out:ident $input:ident $sz:ident)
+     [$($vars:tt)*] [$($packs:tt)*] [$($unpacks:tt)*]
+     $(#[$vmeta:meta])* case $disc:expr => $var:ident, $($rest:tt)*) => {
+        xdr_union!(@arm $attrs $vis $name ($out $input $sz)
+                   [$($vars)* $(#[$vmeta])* $var,]
+                   [$($packs)* $name::$var => $crate::Pack::pack(&(($disc) as i32), $out)?,]
+                   [$($unpacks)* x if x == ($disc) as i32 => $name::$var,]
+                   $($rest)*);
+    };
+
+    // default with a value
+    (@arm $attrs:tt $vis:tt $name:ident ($out:ident $input:ident $sz:ident)
+     [$($vars:tt)*] [$($packs:tt)*] [$($unpacks:tt)*]
+     $(#[$vmeta:meta])* default => $var:ident(i32, $ty:ty),) => {
+        xdr_union!(@emit $attrs $vis $name ($out $input $sz)
+                   [$($vars)* $(#[$vmeta])* $var(i32, $ty),]
+                   [$($packs)*
+                    $name::$var(d, ref val) =>
+                        $crate::Pack::pack(&d, $out)? + $crate::Pack::pack(val, $out)?,]
+                   [$($unpacks)*
+                    d => {
+                        let (v, vsz) = $crate::Unpack::unpack($input)?;
+                        $sz += vsz;
+                        $name::$var(d, v)
+                    }]);
+    };
+
+    // void default
+    (@arm $attrs:tt $vis:tt $name:ident ($out:ident $input:ident $sz:ident)
+     [$($vars:tt)*] [$($packs:tt)*] [$($unpacks:tt)*]
+     $(#[$vmeta:meta])* default => $var:ident(i32),) => {
+        xdr_union!(@emit $attrs $vis $name ($out $input $sz)
+                   [$($vars)* $(#[$vmeta])* $var(i32),]
+                   [$($packs)* $name::$var(d) => $crate::Pack::pack(&d, $out)?,]
+                   [$($unpacks)* d => $name::$var(d),]);
+    };
+
+    // no default
+    (@arm $attrs:tt $vis:tt $name:ident ($out:ident $input:ident $sz:ident)
+     [$($vars:tt)*] [$($packs:tt)*] [$($unpacks:tt)*]) => {
+        xdr_union!(@emit $attrs $vis $name ($out $input $sz)
+                   [$($vars)*]
+                   [$($packs)*]
+                   [$($unpacks)* d => return Err($crate::Error::invalidcase(d)),]);
+    };
+
+    (@emit [$($attrs:tt)*] [$($vis:tt)*] $name:ident ($out:ident $input:ident $sz:ident)
+     [$($vars:tt)*] [$($packs:tt)*] [$($unpacks:tt)*]) => {
+        $($attrs)*
+        $($vis)* enum $name {
+            $($vars)*
+        }
+
+        impl<Out: $crate::Write> $crate::Pack<Out> for $name {
+            fn pack(&self, $out: &mut Out) -> $crate::Result<usize> {
+                Ok(match *self {
+                    $($packs)*
+                })
+            }
+        }
+
+        impl<In: $crate::Read> $crate::Unpack<In> for $name {
+            fn unpack($input: &mut In) -> $crate::Result<($name, usize)> {
+                let mut $sz = 0;
+                let disc: i32 = {
+                    let (d, dsz) = $crate::Unpack::unpack($input)?;
+                    $sz += dsz;
+                    d
+                };
+                let v = match disc {
+                    $($unpacks)*
+                };
+                Ok((v, $sz))
+            }
+        }
+    };
+}
+
+/// Define an XDR struct.
+///
+/// The fields are packed in order. The struct may have type parameters, in which case the
+/// `Pack` and `Unpack` implementations require each field's type to implement the trait, rather
+/// than each parameter, so parameters which are only used inside a `Vec`, `Option` or `Box` work
+/// as expected. Type parameters can't have bounds of their own.
+///
+/// ```
+/// # #[macro_use] extern crate xdr_codec;
+/// # fn main() {
+/// xdr_struct! {
+///     #[derive(Debug, Clone, PartialEq)]
+///     pub struct Pair<T> {
+///         pub a: T,
+///         pub b: T,
+///     }
+/// }
+///
+/// let mut buf = Vec::new();
+/// xdr_codec::pack(&Pair { a: 1u32, b: 2u32 }, &mut buf).unwrap();
+/// assert_eq!(buf, &[0, 0, 0, 1, 0, 0, 0, 2]);
+/// assert_eq!(
+///     xdr_codec::unpack::<_, Pair<u32>>(&mut &buf[..]).unwrap(),
+///     Pair { a: 1, b: 2 }
+/// );
+/// # }
+/// ```
+#[macro_export]
+macro_rules! xdr_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident $(<$($gen:ident),+ $(,)?>)? {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident : $ty:ty),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name $(<$($gen),+>)? {
+            $($(#[$fmeta])* $fvis $field: $ty),+
+        }
+
+        impl<Out: $crate::Write $($(, $gen)+)?> $crate::Pack<Out> for $name $(<$($gen),+>)?
+        where
+            $($ty: $crate::Pack<Out>,)+
+        {
+            fn pack(&self, out: &mut Out) -> $crate::Result<usize> {
+                Ok(0 $(+ $crate::Pack::pack(&self.$field, out)?)+)
+            }
+        }
+
+        impl<In: $crate::Read $($(, $gen)+)?> $crate::Unpack<In> for $name $(<$($gen),+>)?
+        where
+            $($ty: $crate::Unpack<In>,)+
+        {
+            fn unpack(input: &mut In) -> $crate::Result<(Self, usize)> {
+                let mut sz = 0;
+                let v = $name {
+                    $($field: {
+                        let (v, fsz) = $crate::in_field(
+                            concat!(stringify!($name), ".", stringify!($field)),
+                            || $crate::Unpack::unpack(input),
+                        )?;
+                        sz += fsz;
+                        v
+                    },)+
+                };
+                Ok((v, sz))
+            }
+        }
+    };
+}
@@ -0,0 +1,89 @@
+//! Traffic metrics
+//!
+//! With the `metrics` feature, the crate reports what it encodes and decodes through the
+//! [`metrics`](https://docs.rs/metrics) facade, so it shows up in whatever exporter the
+//! application has installed. Nothing is recorded unless a recorder is installed.
+//!
+//! Values are only counted by the `pack()` and `unpack()` helpers, not by each nested
+//! `Pack::pack` or `Unpack::unpack` call, so each top-level value is counted once. Records are
+//! counted by the record marking readers and writers (including the async ones), and checksum
+//! failures by `FramedReader`.
+//!
+//! The metric names are below; `describe()` registers descriptions for them with the recorder.
+use metrics_facade::{counter, describe_counter, describe_histogram, histogram, Unit};
+
+use error::*;
+
+/// Counter of bytes packed by `pack()`.
+pub const BYTES_PACKED: &str = "xdr_bytes_packed_total";
+/// Counter of bytes unpacked by `unpack()`.
+pub const BYTES_UNPACKED: &str = "xdr_bytes_unpacked_total";
+/// Counter of `unpack()` failures, labelled by `kind` (see `error_kind()`).
+pub const DECODE_ERRORS: &str = "xdr_decode_errors_total";
+/// Counter of complete records written by `XdrRecordWriter`.
+pub const RECORDS_WRITTEN: &str = "xdr_records_written_total";
+/// Counter of records started by `XdrRecordReader`.
+pub const RECORDS_READ: &str = "xdr_records_read_total";
+/// Histogram of fragment sizes written by `XdrRecordWriter`.
+pub const FRAGMENT_BYTES: &str = "xdr_fragment_bytes";
+/// Counter of records rejected by `FramedReader` because of a bad checksum.
+pub const CHECKSUM_ERRORS: &str = "xdr_checksum_errors_total";
+
+/// Register descriptions of this crate's metrics with the installed recorder.
+pub fn describe() {
+    describe_counter!(BYTES_PACKED, Unit::Bytes, "Bytes of XDR packed");
+    describe_counter!(BYTES_UNPACKED, Unit::Bytes, "Bytes of XDR unpacked");
+    describe_counter!(DECODE_ERRORS, "XDR decode failures");
+    describe_counter!(RECORDS_WRITTEN, "Record-marked records written");
+    describe_counter!(RECORDS_READ, "Record-marked records read");
+    describe_histogram!(
+        FRAGMENT_BYTES,
+        Unit::Bytes,
+        "Size of record fragments written"
+    );
+    describe_counter!(CHECKSUM_ERRORS, "Framed records with a bad checksum");
+}
+
+/// The `kind` label used for an error in `xdr_decode_errors_total`.
+pub fn error_kind(err: &Error) -> &'static str {
+    match *err.root_kind() {
+        ErrorKind::IOError(_) => "io",
+        ErrorKind::InvalidUtf8(_) => "utf8",
+        ErrorKind::InvalidCase(_) => "case",
+        ErrorKind::InvalidEnum(_) => "enum",
+        ErrorKind::InvalidLen(_) => "len",
+        ErrorKind::InvalidChar(_) => "char",
+        ErrorKind::InvalidZero => "zero",
+        ErrorKind::InvalidStringChar(..) => "string_char",
+        ErrorKind::Validation(_) => "validation",
+        ErrorKind::BudgetExceeded(..) => "budget",
+        _ => "other",
+    }
+}
+
+pub(crate) fn packed(bytes: usize) {
+    counter!(BYTES_PACKED).increment(bytes as u64);
+}
+
+pub(crate) fn unpacked<T>(res: &Result<(T, usize)>) {
+    match *res {
+        Ok((_, bytes)) => counter!(BYTES_UNPACKED).increment(bytes as u64),
+        Err(ref err) => counter!(DECODE_ERRORS, "kind" => error_kind(err)).increment(1),
+    }
+}
+
+pub(crate) fn fragment_written(bytes: usize, eor: bool) {
+    histogram!(FRAGMENT_BYTES).record(bytes as f64);
+    if eor {
+        counter!(RECORDS_WRITTEN).increment(1);
+    }
+}
+
+pub(crate) fn record_read() {
+    counter!(RECORDS_READ).increment(1);
+}
+
+#[cfg(feature = "framed")]
+pub(crate) fn checksum_error() {
+    counter!(CHECKSUM_ERRORS).increment(1);
+}
@@ -0,0 +1,177 @@
+//! Protocol version negotiation
+//!
+//! When a protocol has several versions in use, a client and server need to agree on one before
+//! exchanging any messages. Each side has a list of the versions it supports, each with the
+//! fingerprint of the specification it was generated from (xdrgen's `SCHEMA_FINGERPRINT`, with
+//! `--schema-fingerprint`). The client sends its list as an offer; the server picks the highest
+//! version in both lists whose fingerprints match, and replies with it, or with its own list if
+//! there's none. A version whose fingerprints don't match was generated from a different revision
+//! of the specification, so it isn't used.
+//!
+//! The handshake is encoded as:
+//!
+//! ```text
+//! struct version { unsigned int version; opaque fingerprint[32]; };
+//! typedef version offer<>;
+//! union reply switch (bool accepted) {
+//! case TRUE: version chosen;
+//! case FALSE: offer supported;
+//! };
+//! ```
+//!
+//! `client()` and `server()` run the handshake over a pair of streams, flushing the output once
+//! their message is written. With an `XdrRecordWriter`, that sends the message as a fragment, so
+//! `end_record()` should be called afterwards to complete the record. `Offer::choose()` and
+//! `Reply` can be used to run the handshake some other way.
+//!
+//! ```
+//! # use xdr_codec::negotiate::{Offer, Version};
+//! let client = Offer::new(vec![Version::new(1, [1; 32]), Version::new(2, [2; 32])]);
+//! let server = Offer::new(vec![Version::new(2, [2; 32]), Version::new(3, [3; 32])]);
+//!
+//! assert_eq!(server.choose(&client), Some(Version::new(2, [2; 32])));
+//! ```
+use std::io::{Read, Write};
+
+use error::*;
+
+use super::{pack_opaque_array, Pack, Unpack};
+
+/// A version of a protocol, and the fingerprint of its specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Version {
+    /// The version number. Higher versions are preferred.
+    pub version: u32,
+    /// The fingerprint of the specification.
+    pub fingerprint: [u8; 32],
+}
+
+impl Version {
+    /// A version with its fingerprint.
+    pub fn new(version: u32, fingerprint: [u8; 32]) -> Version {
+        Version {
+            version,
+            fingerprint,
+        }
+    }
+}
+
+impl<Out: Write> Pack<Out> for Version {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        Ok(self.version.pack(out)? + pack_opaque_array(&self.fingerprint, 32, out)?)
+    }
+}
+
+impl<In: Read> Unpack<In> for Version {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (version, sz) = u32::unpack(input)?;
+        let mut fingerprint = [0; 32];
+        input.read_exact(&mut fingerprint)?;
+
+        Ok((Version::new(version, fingerprint), sz + 32))
+    }
+}
+
+/// The versions supported by one side of the handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Offer(pub Vec<Version>);
+
+impl Offer {
+    /// An offer of `versions`, in any order.
+    pub fn new(versions: Vec<Version>) -> Offer {
+        Offer(versions)
+    }
+
+    /// The highest version in both offers with the same fingerprint, if any.
+    pub fn choose(&self, theirs: &Offer) -> Option<Version> {
+        self.0
+            .iter()
+            .filter(|v| theirs.0.contains(v))
+            .max_by_key(|v| v.version)
+            .cloned()
+    }
+}
+
+impl<Out: Write> Pack<Out> for Offer {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        self.0.pack(out)
+    }
+}
+
+impl<In: Read> Unpack<In> for Offer {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        Vec::unpack(input).map(|(v, sz)| (Offer(v), sz))
+    }
+}
+
+/// The server's reply to an offer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// The version chosen.
+    Accepted(Version),
+    /// There's no version in common; these are the versions the server supports.
+    Rejected(Offer),
+}
+
+impl<Out: Write> Pack<Out> for Reply {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        match *self {
+            Reply::Accepted(ref v) => Ok(true.pack(out)? + v.pack(out)?),
+            Reply::Rejected(ref offer) => Ok(false.pack(out)? + offer.pack(out)?),
+        }
+    }
+}
+
+impl<In: Read> Unpack<In> for Reply {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (accepted, sz) = bool::unpack(input)?;
+        if accepted {
+            let (v, vsz) = Version::unpack(input)?;
+            Ok((Reply::Accepted(v), sz + vsz))
+        } else {
+            let (offer, osz) = Offer::unpack(input)?;
+            Ok((Reply::Rejected(offer), sz + osz))
+        }
+    }
+}
+
+/// Run the client side of the handshake: send `ours` to `output`, and read the server's reply
+/// from `input`.
+///
+/// Returns the version chosen, or fails with `ErrorKind::NoCommonVersion` if there isn't one.
+/// It also fails that way if the server chooses a version which wasn't offered.
+pub fn client<In: Read, Out: Write>(
+    input: &mut In,
+    output: &mut Out,
+    ours: &Offer,
+) -> Result<Version> {
+    ours.pack(output)?;
+    output.flush()?;
+
+    match Reply::unpack(input)?.0 {
+        Reply::Accepted(v) if ours.0.contains(&v) => Ok(v),
+        Reply::Accepted(_) | Reply::Rejected(_) => bail!(ErrorKind::NoCommonVersion),
+    }
+}
+
+/// Run the server side of the handshake: read the client's offer from `input`, and send the
+/// reply to `output`.
+///
+/// Returns the version chosen. If there isn't one, the client is sent the versions in `ours`,
+/// and this fails with `ErrorKind::NoCommonVersion`.
+pub fn server<In: Read, Out: Write>(
+    input: &mut In,
+    output: &mut Out,
+    ours: &Offer,
+) -> Result<Version> {
+    let (theirs, _) = Offer::unpack(input)?;
+
+    let chosen = ours.choose(&theirs);
+    match chosen {
+        Some(v) => Reply::Accepted(v).pack(output)?,
+        None => Reply::Rejected(ours.clone()).pack(output)?,
+    };
+    output.flush()?;
+
+    chosen.ok_or_else(|| ErrorKind::NoCommonVersion.into())
+}
@@ -0,0 +1,229 @@
+//! Network address codecs
+//!
+//! XDR has no standard representation for network addresses, so this module implements the
+//! conventional ones:
+//!
+//!  * `Ipv4Addr` is a 4 byte fixed opaque (`opaque addr[4]`), in network byte order
+//!  * `Ipv6Addr` is a 16 byte fixed opaque (`opaque addr[16]`)
+//!  * `IpAddr` is a discriminated union on the IP version:
+//!    `union switch (int version) { case 4: opaque v4[4]; case 6: opaque v6[16]; }`
+//!  * `SocketAddr` is a struct of the address and the port: `struct { IpAddr addr; unsigned int port; }`
+//!
+//! It also has helpers for the "universal address" string format used by rpcbind
+//! ([RFC5665](https://tools.ietf.org/html/rfc5665#section-5.2.3)), where the port is appended
+//! to the textual address as two extra dot-separated octets, and the `Netid` type for the
+//! network identifiers (`"tcp"`, `"udp6"` and so on) which say how to interpret them.
+//!
+//! This module is only available with the `net` feature.
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+
+use error::*;
+
+use super::{pack_opaque_array, unpack_opaque_array, Pack, Read, Unpack, Write};
+
+const IPV4: i32 = 4;
+const IPV6: i32 = 6;
+
+impl<Out: Write> Pack<Out> for Ipv4Addr {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_opaque_array(&self.octets(), 4, out)
+    }
+}
+
+impl<In: Read> Unpack<In> for Ipv4Addr {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut octets = [0; 4];
+        let sz = unpack_opaque_array(input, &mut octets, 4)?;
+        Ok((Ipv4Addr::from(octets), sz))
+    }
+}
+
+impl<Out: Write> Pack<Out> for Ipv6Addr {
+    #[inline]
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        pack_opaque_array(&self.octets(), 16, out)
+    }
+}
+
+impl<In: Read> Unpack<In> for Ipv6Addr {
+    #[inline]
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let mut octets = [0; 16];
+        let sz = unpack_opaque_array(input, &mut octets, 16)?;
+        Ok((Ipv6Addr::from(octets), sz))
+    }
+}
+
+impl<Out: Write> Pack<Out> for IpAddr {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        match *self {
+            IpAddr::V4(ref addr) => Ok(IPV4.pack(out)? + addr.pack(out)?),
+            IpAddr::V6(ref addr) => Ok(IPV6.pack(out)? + addr.pack(out)?),
+        }
+    }
+}
+
+impl<In: Read> Unpack<In> for IpAddr {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (version, sz) = i32::unpack(input)?;
+        match version {
+            IPV4 => Ipv4Addr::unpack(input).map(|(addr, asz)| (IpAddr::V4(addr), sz + asz)),
+            IPV6 => Ipv6Addr::unpack(input).map(|(addr, asz)| (IpAddr::V6(addr), sz + asz)),
+            v => Err(ErrorKind::InvalidCase(v).into()),
+        }
+    }
+}
+
+impl<Out: Write> Pack<Out> for SocketAddr {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        Ok(self.ip().pack(out)? + (self.port() as u32).pack(out)?)
+    }
+}
+
+impl<In: Read> Unpack<In> for SocketAddr {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (addr, asz) = IpAddr::unpack(input)?;
+        let (port, psz) = u32::unpack(input)?;
+
+        if port > u16::MAX as u32 {
+            bail!("invalid port number {}", port);
+        }
+
+        Ok((SocketAddr::new(addr, port as u16), asz + psz))
+    }
+}
+
+/// Format a socket address as a universal address.
+///
+/// For example, `192.168.1.2:2049` is formatted as `"192.168.1.2.8.1"`, and `[::1]:111` as
+/// `"::1.0.111"`.
+pub fn uaddr(addr: &SocketAddr) -> String {
+    format!("{}.{}.{}", addr.ip(), addr.port() >> 8, addr.port() & 0xff)
+}
+
+/// Parse a universal address into a socket address.
+pub fn parse_uaddr(uaddr: &str) -> Result<SocketAddr> {
+    let mut parts = uaddr.rsplitn(3, '.');
+
+    let (lo, hi, host) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(lo), Some(hi), Some(host)) => (lo, hi, host),
+        _ => bail!("invalid universal address '{}'", uaddr),
+    };
+
+    let port = match (hi.parse::<u8>(), lo.parse::<u8>()) {
+        (Ok(hi), Ok(lo)) => (hi as u16) << 8 | lo as u16,
+        _ => bail!("invalid port in universal address '{}'", uaddr),
+    };
+
+    match host.parse::<IpAddr>() {
+        Ok(ip) => Ok(SocketAddr::new(ip, port)),
+        Err(_) => bail!("invalid host in universal address '{}'", uaddr),
+    }
+}
+
+/// A network identifier, as used with universal addresses by rpcbind versions 3 and 4.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Netid {
+    /// TCP over IPv4 (`"tcp"`)
+    Tcp,
+    /// UDP over IPv4 (`"udp"`)
+    Udp,
+    /// TCP over IPv6 (`"tcp6"`)
+    Tcp6,
+    /// UDP over IPv6 (`"udp6"`)
+    Udp6,
+    /// A local (Unix domain) stream socket (`"local"`), whose universal address is a path
+    Local,
+}
+
+// IP protocol numbers, as registered with portmap version 2
+const IPPROTO_TCP: u32 = 6;
+const IPPROTO_UDP: u32 = 17;
+
+impl Netid {
+    /// The netid for a TCP (`stream`) or UDP socket at `addr`.
+    pub fn for_addr(addr: &SocketAddr, stream: bool) -> Netid {
+        match (addr.is_ipv6(), stream) {
+            (false, true) => Netid::Tcp,
+            (false, false) => Netid::Udp,
+            (true, true) => Netid::Tcp6,
+            (true, false) => Netid::Udp6,
+        }
+    }
+
+    /// The netid for a portmap version 2 protocol number, which only covers IPv4.
+    pub fn from_protocol(prot: u32) -> Option<Netid> {
+        match prot {
+            IPPROTO_TCP => Some(Netid::Tcp),
+            IPPROTO_UDP => Some(Netid::Udp),
+            _ => None,
+        }
+    }
+
+    /// The portmap version 2 protocol number, if this netid has one.
+    pub fn protocol(&self) -> Option<u32> {
+        match *self {
+            Netid::Tcp => Some(IPPROTO_TCP),
+            Netid::Udp => Some(IPPROTO_UDP),
+            _ => None,
+        }
+    }
+
+    /// The netid as a string.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Netid::Tcp => "tcp",
+            Netid::Udp => "udp",
+            Netid::Tcp6 => "tcp6",
+            Netid::Udp6 => "udp6",
+            Netid::Local => "local",
+        }
+    }
+
+    /// Whether this is a stream (rather than datagram) transport.
+    pub fn is_stream(&self) -> bool {
+        *self != Netid::Udp && *self != Netid::Udp6
+    }
+
+    /// Parse a universal address of this netid into a socket address.
+    ///
+    /// This fails if the address isn't of the netid's address family, including for all
+    /// `local` addresses, which are paths.
+    pub fn parse_uaddr(&self, uaddr: &str) -> Result<SocketAddr> {
+        if *self == Netid::Local {
+            bail!("local address '{}' isn't a socket address", uaddr);
+        }
+
+        let addr = parse_uaddr(uaddr)?;
+        let ipv6 = *self == Netid::Tcp6 || *self == Netid::Udp6;
+        if addr.is_ipv6() != ipv6 {
+            bail!("universal address '{}' doesn't match netid {}", uaddr, self);
+        }
+        Ok(addr)
+    }
+}
+
+impl fmt::Display for Netid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Netid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Netid> {
+        match s {
+            "tcp" => Ok(Netid::Tcp),
+            "udp" => Ok(Netid::Udp),
+            "tcp6" => Ok(Netid::Tcp6),
+            "udp6" => Ok(Netid::Udp6),
+            "local" | "unix" => Ok(Netid::Local),
+            _ => bail!("unknown netid '{}'", s),
+        }
+    }
+}
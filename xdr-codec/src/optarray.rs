@@ -0,0 +1,105 @@
+//! Optional values packed as arrays
+//!
+//! XDR's optional-data notation `T *x` packs an `Option<T>` as a boolean followed by the value,
+//! if any. Some protocols instead declare optional values as a variable-length array of at most
+//! one element, `T x<1>`, which packs as a length of 0 or 1 followed by the value. The encoding of
+//! a present value is the same apart from the leading word being a length rather than a boolean,
+//! but only `1` is valid either way.
+//!
+//! `OptArray<T>` wraps an `Option<T>` to pack it as an array, so it can be used with the same
+//! convenience as `Option<T>`:
+//!
+//! ```
+//! # use xdr_codec::{pack, OptArray};
+//! let mut buf = Vec::new();
+//! pack(&OptArray(Some(7u32)), &mut buf).unwrap();
+//! pack(&OptArray::<u32>(None), &mut buf).unwrap();
+//! assert_eq!(buf, &[0, 0, 0, 1, 0, 0, 0, 7, 0, 0, 0, 0]);
+//! ```
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+
+use error::*;
+
+use super::{Pack, Skip, Unpack};
+
+/// An `Option<T>` packed as an array of at most one element (`T x<1>`).
+///
+/// Unpacking fails with `ErrorKind::InvalidLen` if the array has more than one element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct OptArray<T>(pub Option<T>);
+
+impl<T> OptArray<T> {
+    /// Unwrap into the underlying `Option`.
+    pub fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<T> From<Option<T>> for OptArray<T> {
+    fn from(v: Option<T>) -> Self {
+        OptArray(v)
+    }
+}
+
+impl<T> From<OptArray<T>> for Option<T> {
+    fn from(v: OptArray<T>) -> Self {
+        v.0
+    }
+}
+
+impl<T> Deref for OptArray<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Option<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for OptArray<T> {
+    fn deref_mut(&mut self) -> &mut Option<T> {
+        &mut self.0
+    }
+}
+
+// The number of elements, checking there's at most one
+fn unpack_len<In: Read>(input: &mut In) -> Result<(bool, usize)> {
+    let (len, sz) = u32::unpack(input)?;
+    match len {
+        0 => Ok((false, sz)),
+        1 => Ok((true, sz)),
+        _ => bail!(ErrorKind::InvalidLen(1)),
+    }
+}
+
+impl<Out: Write, T: Pack<Out>> Pack<Out> for OptArray<T> {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        match self.0 {
+            None => 0u32.pack(out),
+            Some(ref v) => Ok(1u32.pack(out)? + v.pack(out)?),
+        }
+    }
+}
+
+impl<In: Read, T: Unpack<In>> Unpack<In> for OptArray<T> {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (have, sz) = unpack_len(input)?;
+        if have {
+            let (v, vsz) = T::unpack(input)?;
+            Ok((OptArray(Some(v)), sz + vsz))
+        } else {
+            Ok((OptArray(None), sz))
+        }
+    }
+}
+
+impl<In: Read, T: Skip<In>> Skip<In> for OptArray<T> {
+    fn skip(input: &mut In) -> Result<usize> {
+        let (have, sz) = unpack_len(input)?;
+        if have {
+            Ok(sz + T::skip(input)?)
+        } else {
+            Ok(sz)
+        }
+    }
+}
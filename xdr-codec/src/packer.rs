@@ -0,0 +1,326 @@
+//! Encode buffers
+//!
+//! Packing each message into a fresh `Vec` costs an allocation (and usually several, as it
+//! grows) per message, which can dominate the cost of encoding small messages. `Packer` keeps a
+//! single buffer which is cleared and reused for each value, so once it has grown to fit the
+//! largest message, encoding doesn't allocate at all.
+//!
+//! ```
+//! # use xdr_codec::packer::Packer;
+//! let mut packer = Packer::new();
+//!
+//! for i in 0..3u32 {
+//!     let bytes = packer.pack_reuse(&i).unwrap();
+//!     assert_eq!(bytes, &[0, 0, 0, i as u8]);
+//! }
+//! ```
+//!
+//! Packing writes each field separately, often only 4 bytes at a time, so packing straight into
+//! an unbuffered `File` or `TcpStream` costs a system call per field. `PackBuffer` coalesces these
+//! small writes, passing them on to the underlying stream in large chunks.
+//!
+//! `SizeSink` discards everything written to it, only counting the bytes, so it finds the encoded
+//! size of a value (say, for a record header) without building the encoding. `TeeWrite` packs
+//! into two writers at once, such as an output stream and a hasher, so a value's exact encoding
+//! can be checksummed or hashed as it's written.
+use std::io::{self, IoSlice, Write};
+
+use error::*;
+
+use super::Pack;
+
+// Default PackBuffer size
+const PACKBUF: usize = 8 * 1024;
+
+/// Packs values into a reusable buffer.
+#[derive(Debug, Default, Clone)]
+pub struct Packer {
+    buf: Vec<u8>,
+}
+
+impl Packer {
+    /// Create a new `Packer` with an empty buffer.
+    pub fn new() -> Packer {
+        Default::default()
+    }
+
+    /// Create a new `Packer` with a buffer preallocated to `cap` bytes.
+    pub fn with_capacity(cap: usize) -> Packer {
+        Packer {
+            buf: Vec::with_capacity(cap),
+        }
+    }
+
+    /// Pack `val`, returning its encoding.
+    ///
+    /// The encoding is only valid until the next call, which will overwrite it. If packing fails
+    /// the buffer is left empty.
+    pub fn pack_reuse<T: Pack<Vec<u8>> + ?Sized>(&mut self, val: &T) -> Result<&[u8]> {
+        self.buf.clear();
+
+        if let Err(err) = val.pack(&mut self.buf) {
+            self.buf.clear();
+            return Err(err);
+        }
+        #[cfg(feature = "metrics")]
+        super::metrics::packed(self.buf.len());
+
+        Ok(&self.buf)
+    }
+
+    /// Current capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Shrink the buffer to at most `cap` bytes, to release memory after packing an unusually
+    /// large value.
+    pub fn shrink_to(&mut self, cap: usize) {
+        self.buf.shrink_to(cap)
+    }
+
+    /// Unwrap this `Packer`, returning the buffer holding the most recent encoding.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Write buffer for packing into unbuffered streams.
+///
+/// This collects small writes into an internal buffer, and only writes to the underlying stream
+/// when the buffer is full, or on `flush()`. Writes at least as large as the buffer go straight
+/// through. It's similar to `std::io::BufWriter`, but `into_inner()` returns XDR errors.
+///
+/// Any buffered data is written when the `PackBuffer` is dropped, but errors are ignored then, so
+/// call `flush()` or `into_inner()` to check for them.
+///
+/// ```
+/// # use xdr_codec::packer::PackBuffer;
+/// # use xdr_codec::pack;
+/// let mut out = PackBuffer::new(Vec::new());
+///
+/// pack(&1u32, &mut out).unwrap();
+/// pack(&2u32, &mut out).unwrap();
+/// assert!(out.get_ref().is_empty());
+///
+/// assert_eq!(out.into_inner().unwrap(), &[0, 0, 0, 1, 0, 0, 0, 2]);
+/// ```
+#[derive(Debug)]
+pub struct PackBuffer<W: Write> {
+    buf: Vec<u8>,      // pending output
+    bufsz: usize,      // flush once buffer reaches this size
+    writer: Option<W>, // None once taken by into_inner
+}
+
+impl<W: Write> PackBuffer<W> {
+    /// Create a new `PackBuffer` wrapped around `w`, using a default buffer size (8k).
+    pub fn new(w: W) -> PackBuffer<W> {
+        PackBuffer::with_capacity(PACKBUF, w)
+    }
+
+    /// Create a new `PackBuffer` with a buffer of `bufsz` bytes. Panics if the size is zero.
+    pub fn with_capacity(bufsz: usize, w: W) -> PackBuffer<W> {
+        if bufsz == 0 {
+            panic!("bufsz must be non-zero")
+        }
+        PackBuffer {
+            buf: Vec::with_capacity(bufsz),
+            bufsz,
+            writer: Some(w),
+        }
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            let writer = self.writer.as_mut().expect("writer taken");
+            writer.write_all(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Data buffered but not yet written.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Reference to the underlying stream.
+    pub fn get_ref(&self) -> &W {
+        self.writer.as_ref().expect("writer taken")
+    }
+
+    /// Mutable reference to the underlying stream. Writing to it directly will reorder output
+    /// relative to anything still buffered.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.as_mut().expect("writer taken")
+    }
+
+    /// Write out any buffered data, and return the underlying stream.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush_buf()?;
+        Ok(self.writer.take().expect("writer taken"))
+    }
+}
+
+impl<W: Write> Write for PackBuffer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.bufsz {
+            self.flush_buf()?;
+        }
+
+        if buf.len() >= self.bufsz {
+            self.get_mut().write(buf)
+        } else {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for PackBuffer<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.flush_buf();
+        }
+    }
+}
+
+/// Write sink which discards data, counting its length.
+///
+/// Packing into a `SizeSink` gives the size of a value's encoding without allocating a buffer
+/// for it.
+///
+/// ```
+/// # use xdr_codec::{pack, SizeSink, XdrString};
+/// let mut sink = SizeSink::new();
+/// pack(&XdrString::new("hello"), &mut sink).unwrap();
+/// pack(&1u32, &mut sink).unwrap();
+/// assert_eq!(sink.len(), 16);
+///
+/// assert_eq!(SizeSink::size_of(&[1u32, 2][..]).unwrap(), 12);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SizeSink {
+    len: usize,
+}
+
+impl SizeSink {
+    /// Create a new `SizeSink` with a zero length.
+    pub fn new() -> SizeSink {
+        Default::default()
+    }
+
+    /// The size of `val`'s encoding.
+    pub fn size_of<T: Pack<SizeSink> + ?Sized>(val: &T) -> Result<usize> {
+        let mut sink = SizeSink::new();
+        val.pack(&mut sink)?;
+        Ok(sink.len)
+    }
+
+    /// Total bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reset the length to zero.
+    pub fn reset(&mut self) {
+        self.len = 0
+    }
+}
+
+impl Write for SizeSink {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.len += buf.len();
+        Ok(())
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        let len = bufs.iter().map(|b| b.len()).sum();
+        self.len += len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer which copies everything written to it into two writers.
+///
+/// Data is written to the first writer, and whatever it accepts is then written in full to the
+/// second. This allows, for example, computing a digest of a value's exact encoding while packing
+/// it to its destination, using any hasher which implements `Write`.
+///
+/// ```
+/// # use xdr_codec::packer::TeeWrite;
+/// # use xdr_codec::pack;
+/// let mut out = TeeWrite::new(Vec::new(), Vec::new());
+/// pack(&7u32, &mut out).unwrap();
+///
+/// let (a, b) = out.into_inner();
+/// assert_eq!(a, &[0, 0, 0, 7]);
+/// assert_eq!(a, b);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TeeWrite<W1, W2> {
+    first: W1,
+    second: W2,
+}
+
+impl<W1: Write, W2: Write> TeeWrite<W1, W2> {
+    /// Create a new `TeeWrite` copying to `first` and `second`.
+    pub fn new(first: W1, second: W2) -> TeeWrite<W1, W2> {
+        TeeWrite { first, second }
+    }
+
+    /// References to the underlying writers.
+    pub fn get_ref(&self) -> (&W1, &W2) {
+        (&self.first, &self.second)
+    }
+
+    /// Mutable references to the underlying writers. Writing to either directly means they no
+    /// longer see the same data.
+    pub fn get_mut(&mut self) -> (&mut W1, &mut W2) {
+        (&mut self.first, &mut self.second)
+    }
+
+    /// Unwrap this `TeeWrite`, returning the underlying writers.
+    pub fn into_inner(self) -> (W1, W2) {
+        (self.first, self.second)
+    }
+}
+
+impl<W1: Write, W2: Write> Write for TeeWrite<W1, W2> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.first.write(buf)?;
+        self.second.write_all(&buf[..len])?;
+        Ok(len)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
@@ -0,0 +1,11 @@
+//! Commonly used items
+//!
+//! ```
+//! use xdr_codec::prelude::*;
+//! ```
+//!
+//! brings the codec traits, the `pack()` and `unpack()` helpers, and the record-marking stream
+//! types into scope. It doesn't include `Error` or `Result`, to avoid clashing with other
+//! crates' types of the same names.
+pub use super::{pack, pack_sized, unpack, unpack_sized, Pack, Unpack, Xdr};
+pub use record::{XdrRecordReader, XdrRecordWriter};
@@ -0,0 +1,336 @@
+//! Pretty-printing of decoded values
+//!
+//! Derived `Debug` output for large messages is hard to read: opaque data comes out as long lists
+//! of decimal bytes, enums in unions as generated variant names, and huge arrays in full. Using
+//! a type's `Descriptor` (see the `describe` module), `print()` instead formats values with their
+//! XDR field and enum names, opaque data as truncated hex, and only the first few elements of
+//! long arrays.
+//!
+//! Values are printed from their encoding, so `Printer::print_bytes()` can also print encoded
+//! messages directly, given a descriptor for them.
+//!
+//! ```
+//! use xdr_codec::describe::{Describe, Descriptor, Field, Kind, Size, TypeDesc};
+//! use xdr_codec::{pack_opaque_flex, Pack, Result, Write};
+//!
+//! struct Blob {
+//!     id: u32,
+//!     data: Vec<u8>,
+//! }
+//!
+//! impl Describe for Blob {
+//!     fn describe() -> Descriptor {
+//!         Descriptor {
+//!             name: "blob",
+//!             kind: Kind::Struct(vec![
+//!                 Field { name: "id", ty: TypeDesc::UInt },
+//!                 Field { name: "data", ty: TypeDesc::Opaque(Size::Flex(None)) },
+//!             ]),
+//!         }
+//!     }
+//! }
+//!
+//! impl<Out: Write> Pack<Out> for Blob {
+//!     fn pack(&self, out: &mut Out) -> Result<usize> {
+//!         Ok(self.id.pack(out)? + pack_opaque_flex(&self.data, None, out)?)
+//!     }
+//! }
+//!
+//! let blob = Blob { id: 7, data: vec![0xde, 0xad, 0xbe, 0xef] };
+//! assert_eq!(
+//!     xdr_codec::pretty::print(&blob),
+//!     "blob {\n    id: 7,\n    data: 0xdeadbeef,\n}"
+//! );
+//! ```
+use std::fmt::Write as FmtWrite;
+
+use describe::{Describe, Descriptor, Kind, Size, TypeDesc};
+use error::*;
+
+use super::{pack, unpack_opaque_flex, unpack_opaque_vec, Pack, Unpack};
+
+const INDENT: usize = 4;
+
+/// Print a value with the default `Printer` settings.
+///
+/// If the value can't be packed, the error is printed instead.
+pub fn print<T: Describe + Pack<Vec<u8>>>(val: &T) -> String {
+    Printer::new()
+        .print(val)
+        .unwrap_or_else(|e| format!("<{}>", e))
+}
+
+/// Pretty-printer for values of described types.
+///
+/// Output is laid out like Rust's alternate (`{:#?}`) `Debug` format, with structs and unions
+/// split over several lines. Unions are printed like structs, with their selector and the
+/// field of the selected arm, if any.
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    max_elems: usize,
+    max_bytes: usize,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Printer {
+            max_elems: 16,
+            max_bytes: 32,
+        }
+    }
+}
+
+impl Printer {
+    /// Create a printer which shows up to 16 array elements and 32 bytes of opaque data or
+    /// strings.
+    pub fn new() -> Printer {
+        Default::default()
+    }
+
+    /// Set the number of array elements shown. The number left out is shown instead of the
+    /// rest.
+    pub fn max_elems(mut self, max_elems: usize) -> Printer {
+        self.max_elems = max_elems;
+        self
+    }
+
+    /// Set the number of bytes of opaque data or strings shown. The total length is shown if
+    /// there are more.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Printer {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Print a value.
+    pub fn print<T: Describe + Pack<Vec<u8>>>(&self, val: &T) -> Result<String> {
+        let mut buf = Vec::new();
+        pack(val, &mut buf)?;
+        self.print_bytes(&T::describe(), &buf)
+    }
+
+    /// Print an encoded value of the type described by `desc`.
+    ///
+    /// This fails if `bytes` isn't a valid encoding, but anything after the value is ignored.
+    pub fn print_bytes(&self, desc: &Descriptor, bytes: &[u8]) -> Result<String> {
+        let mut out = String::new();
+        self.named(desc, &mut &bytes[..], 0, &mut out)?;
+        Ok(out)
+    }
+
+    fn named(
+        &self,
+        desc: &Descriptor,
+        input: &mut &[u8],
+        indent: usize,
+        out: &mut String,
+    ) -> Result<()> {
+        match desc.kind {
+            Kind::Enum(_) => {
+                let (v, _) = i32::unpack(input)?;
+                match desc.enum_name(v as i64) {
+                    Some(name) => out.push_str(name),
+                    None => {
+                        let _ = write!(out, "{}", v);
+                    }
+                }
+            }
+
+            Kind::Struct(ref fields) => {
+                let _ = writeln!(out, "{} {{", desc.name);
+                for field in fields {
+                    self.field(field.name, &field.ty, input, indent + INDENT, out)?;
+                }
+                let _ = write!(out, "{:1$}}}", "", indent);
+            }
+
+            Kind::Union { ref selector, .. } => {
+                let _ = writeln!(out, "{} {{", desc.name);
+
+                // Peek at the discriminant to find the case, then print it as the selector
+                let (disc, _) = i32::unpack(&mut &input[..])?;
+                self.field(selector.name, &selector.ty, input, indent + INDENT, out)?;
+
+                match desc.case(disc as i64) {
+                    Some(case) => {
+                        if let Some(ref field) = case.field {
+                            self.field(field.name, &field.ty, input, indent + INDENT, out)?;
+                        }
+                    }
+                    None => bail!(ErrorKind::InvalidCase(disc)),
+                }
+                let _ = write!(out, "{:1$}}}", "", indent);
+            }
+
+            Kind::Newtype(ref ty) => {
+                let _ = write!(out, "{}(", desc.name);
+                self.value(ty, input, indent, out)?;
+                out.push(')');
+            }
+        }
+        Ok(())
+    }
+
+    fn field(
+        &self,
+        name: &str,
+        ty: &TypeDesc,
+        input: &mut &[u8],
+        indent: usize,
+        out: &mut String,
+    ) -> Result<()> {
+        let _ = write!(out, "{:1$}{2}: ", "", indent, name);
+        self.value(ty, input, indent, out)?;
+        out.push_str(",\n");
+        Ok(())
+    }
+
+    fn value(
+        &self,
+        ty: &TypeDesc,
+        input: &mut &[u8],
+        indent: usize,
+        out: &mut String,
+    ) -> Result<()> {
+        match *ty {
+            TypeDesc::Int => {
+                let _ = write!(out, "{}", i32::unpack(input)?.0);
+            }
+            TypeDesc::UInt => {
+                let _ = write!(out, "{}", u32::unpack(input)?.0);
+            }
+            TypeDesc::Hyper => {
+                let _ = write!(out, "{}", i64::unpack(input)?.0);
+            }
+            TypeDesc::UHyper => {
+                let _ = write!(out, "{}", u64::unpack(input)?.0);
+            }
+            TypeDesc::Float => {
+                let _ = write!(out, "{:?}", f32::unpack(input)?.0);
+            }
+            TypeDesc::Double => {
+                let _ = write!(out, "{:?}", f64::unpack(input)?.0);
+            }
+            TypeDesc::Bool => {
+                let _ = write!(out, "{}", bool::unpack(input)?.0);
+            }
+            TypeDesc::Quadruple => {
+                let bytes = self.opaque(Size::Fixed(16), input)?;
+                self.hex(&bytes, out);
+            }
+
+            TypeDesc::Opaque(size) => {
+                let bytes = self.opaque(size, input)?;
+                self.hex(&bytes, out);
+            }
+
+            TypeDesc::String(size) => {
+                let bytes = self.opaque(size, input)?;
+                let shown = &bytes[..bytes.len().min(self.max_bytes)];
+                let _ = write!(out, "{:?}", String::from_utf8_lossy(shown));
+                if shown.len() < bytes.len() {
+                    let _ = write!(out, "…({} bytes)", bytes.len());
+                }
+            }
+
+            TypeDesc::Array(ref elem, size) => {
+                let len = match size {
+                    Size::Fixed(len) => len,
+                    Size::Flex(max) => {
+                        let (len, _) = usize::unpack(input)?;
+                        if let Some(m) = max {
+                            if len > m {
+                                bail!(ErrorKind::InvalidLen(m));
+                            }
+                        }
+                        len
+                    }
+                };
+                self.array(elem, len, input, indent, out)?;
+            }
+
+            TypeDesc::Option(ref ty) => {
+                if bool::unpack(input)?.0 {
+                    out.push_str("Some(");
+                    self.value(ty, input, indent, out)?;
+                    out.push(')');
+                } else {
+                    out.push_str("None");
+                }
+            }
+
+            TypeDesc::Named(_, describe) => self.named(&describe(), input, indent, out)?,
+        }
+        Ok(())
+    }
+
+    fn array(
+        &self,
+        elem: &TypeDesc,
+        len: usize,
+        input: &mut &[u8],
+        indent: usize,
+        out: &mut String,
+    ) -> Result<()> {
+        // Elements which print on one line are printed on a single line together
+        let inline = match *elem {
+            TypeDesc::Named(_, describe) => matches!(describe().kind, Kind::Enum(_)),
+            TypeDesc::Array(..) | TypeDesc::Option(_) => false,
+            _ => true,
+        };
+
+        out.push('[');
+        let mut skipped = String::new();
+        for i in 0..len {
+            // Elements past the limit still need to be consumed
+            if i >= self.max_elems {
+                skipped.clear();
+                self.value(elem, input, indent + INDENT, &mut skipped)?;
+                continue;
+            }
+
+            if inline {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+            } else {
+                let _ = write!(out, "\n{:1$}", "", indent + INDENT);
+            }
+            self.value(elem, input, indent + INDENT, out)?;
+            if !inline {
+                out.push(',');
+            }
+        }
+
+        if len > self.max_elems {
+            let more = len - self.max_elems;
+            if inline {
+                let _ = write!(out, ", …({} more)", more);
+            } else {
+                let _ = write!(out, "\n{:1$}…({2} more)", "", indent + INDENT, more);
+            }
+        }
+        if !inline && len > 0 {
+            let _ = write!(out, "\n{:1$}", "", indent);
+        }
+        out.push(']');
+        Ok(())
+    }
+
+    fn opaque(&self, size: Size, input: &mut &[u8]) -> Result<Vec<u8>> {
+        match size {
+            Size::Fixed(len) => unpack_opaque_vec(input, len).map(|(v, _)| v),
+            Size::Flex(max) => unpack_opaque_flex(input, max).map(|(v, _)| v),
+        }
+    }
+
+    fn hex(&self, bytes: &[u8], out: &mut String) {
+        let shown = &bytes[..bytes.len().min(self.max_bytes)];
+        out.push_str("0x");
+        for b in shown {
+            let _ = write!(out, "{:02x}", b);
+        }
+        if shown.len() < bytes.len() {
+            let _ = write!(out, "…({} bytes)", bytes.len());
+        }
+    }
+}
@@ -13,18 +13,67 @@
 //! There's no magic number or other way to determine whether a stream
 //! is using record marking; both ends must agree.
 use std::cmp::min;
+use std::error;
+use std::fmt;
 use std::io::{self, BufRead, Read, Write};
 
-use error::*;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
-use super::{pack, unpack, Error};
+use buffered;
 
-const LAST_REC: u32 = 1u32 << 31;
+#[cfg(feature = "log")]
+use log::trace;
 
-fn mapioerr(xdrerr: Error) -> io::Error {
-    match xdrerr {
-        Error(ErrorKind::IOError(ioerr), _) => ioerr,
-        other => io::Error::new(io::ErrorKind::Other, other),
+#[cfg(feature = "log")]
+use hexdump::HexDump;
+
+pub(crate) const LAST_REC: u32 = 1u32 << 31;
+
+/// Information about a record fragment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentInfo {
+    /// Length of the fragment's data, not including the header.
+    pub len: usize,
+    /// This is the last fragment of the record.
+    pub eor: bool,
+    /// Offset of the fragment's data within its record.
+    pub record_offset: usize,
+    /// Offset of the fragment's header within the underlying stream.
+    pub stream_offset: u64,
+}
+
+/// How a record reader handles empty fragments and records.
+///
+/// Zero-length fragments are legal, and `XdrRecordWriter` sends one as the final fragment of a
+/// record if there was nothing left in its buffer when the record was ended. It will also send an
+/// empty record if asked to. However some broken peers also send empty non-final fragments, which
+/// can be a problem for code that expects every fragment to make progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyFragments {
+    /// Accept empty fragments and records as-is. Empty records are returned as such.
+    #[default]
+    Accept,
+    /// Silently skip empty non-final fragments and empty records, so they're never seen.
+    Coalesce,
+    /// Fail with an `InvalidData` error on an empty non-final fragment or an empty record.
+    Reject,
+}
+
+impl EmptyFragments {
+    // Check a fragment header against the policy; returns true if the fragment should be skipped.
+    // `record_offset` is the amount of record data before this fragment.
+    pub(crate) fn skip(self, len: usize, eor: bool, record_offset: usize) -> io::Result<bool> {
+        let what = match (len, eor, record_offset) {
+            (0, false, _) => "empty record fragment",
+            (0, true, 0) => "empty record",
+            _ => return Ok(false),
+        };
+
+        match self {
+            EmptyFragments::Accept => Ok(false),
+            EmptyFragments::Coalesce => Ok(true),
+            EmptyFragments::Reject => Err(io::Error::new(io::ErrorKind::InvalidData, what)),
+        }
     }
 }
 
@@ -40,6 +89,10 @@ pub struct XdrRecordReader<R: BufRead> {
     consumed: usize, // bytes consumed
     eor: bool,       // is last record
 
+    frag: Option<FragmentInfo>, // current fragment
+    stream_pos: u64,            // bytes read from reader
+    empty: EmptyFragments,      // empty fragment policy
+
     reader: R, // reader
 }
 
@@ -51,6 +104,9 @@ impl<R: BufRead> XdrRecordReader<R> {
             size: 0,
             consumed: 0,
             eor: false,
+            frag: None,
+            stream_pos: 0,
+            empty: EmptyFragments::default(),
             reader: rd,
         }
     }
@@ -59,21 +115,105 @@ impl<R: BufRead> XdrRecordReader<R> {
     fn nextrec(&mut self) -> io::Result<bool> {
         assert_eq!(self.consumed, self.size);
 
-        let rechdr: u32 = match unpack(&mut self.reader) {
-            Ok(v) => v,
-            Err(Error(ErrorKind::IOError(ref err), _))
-                if err.kind() == io::ErrorKind::UnexpectedEof =>
+        loop {
+            let rechdr = match self.reader.read_u32::<BigEndian>() {
+                Ok(v) => v,
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(true),
+                Err(e) => return Err(e),
+            };
+
+            let record_offset = match self.frag {
+                Some(ref frag) if !frag.eor => frag.record_offset + frag.len,
+                _ => 0,
+            };
+            let stream_offset = self.stream_pos;
+            let size = (rechdr & !LAST_REC) as usize;
+            let eor = (rechdr & LAST_REC) != 0;
+
+            self.stream_pos += 4;
+
+            #[cfg(feature = "log")]
+            trace!(
+                "read fragment at {}: {} bytes{}",
+                stream_offset,
+                size,
+                if eor { ", end of record" } else { "" }
+            );
+
+            if self.empty.skip(size, eor, record_offset)? {
+                continue;
+            }
+
+            #[cfg(feature = "metrics")]
             {
-                return Ok(true)
+                if record_offset == 0 {
+                    ::metrics::record_read();
+                }
             }
-            Err(e) => return Err(mapioerr(e)),
-        };
 
-        self.size = (rechdr & !LAST_REC) as usize;
-        self.consumed = 0;
-        self.eor = (rechdr & LAST_REC) != 0;
+            self.size = size;
+            self.consumed = 0;
+            self.eor = eor;
+
+            self.frag = Some(FragmentInfo {
+                len: size,
+                eor,
+                record_offset,
+                stream_offset,
+            });
 
-        Ok(false)
+            return Ok(false);
+        }
+    }
+
+    /// Set how empty fragments and records are handled. The default is
+    /// `EmptyFragments::Accept`.
+    pub fn set_empty_fragments(&mut self, policy: EmptyFragments) {
+        self.empty = policy;
+    }
+
+    // Read the rest of the current record, or all of the next one if at a record boundary, and
+    // append it to `buf`. Fails if the record is longer than `limit`. Returns false on EOF at a
+    // record boundary.
+    #[cfg(any(feature = "framed", feature = "deflate", feature = "zstd"))]
+    pub(crate) fn read_record_into(&mut self, buf: &mut Vec<u8>, limit: usize) -> io::Result<bool> {
+        let start = buf.len();
+
+        loop {
+            if self.totremains() == 0 {
+                let midrec = match self.frag {
+                    Some(ref frag) => !frag.eor,
+                    None => false,
+                };
+                if self.nextrec()? {
+                    if midrec {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "unterminated record",
+                        ));
+                    }
+                    return Ok(false);
+                }
+            }
+
+            let remains = self.totremains();
+            let eor = self.eor;
+
+            if buf.len() - start + remains > limit {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "record too large",
+                ));
+            }
+
+            if self.by_ref().take(remains as u64).read_to_end(buf)? != remains {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "short record"));
+            }
+
+            if eor {
+                return Ok(true);
+            }
+        }
     }
 
     fn totremains(&self) -> usize {
@@ -84,6 +224,37 @@ impl<R: BufRead> XdrRecordReader<R> {
     pub fn eor(&self) -> bool {
         self.eor
     }
+
+    /// Information about the current fragment, or `None` if no fragment header has been read
+    /// yet.
+    pub fn fragment(&self) -> Option<FragmentInfo> {
+        self.frag
+    }
+
+    /// Number of bytes of the current fragment which haven't been read yet.
+    pub fn fragment_remaining(&self) -> usize {
+        self.totremains()
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly will corrupt the record stream unless it's at a fragment
+    /// boundary.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap this `XdrRecordReader`, returning the underlying reader.
+    ///
+    /// Any unread part of the current fragment is left in the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
 }
 
 impl<R: BufRead> Read for XdrRecordReader<R> {
@@ -98,6 +269,10 @@ impl<R: BufRead> Read for XdrRecordReader<R> {
         self.consume(nread);
         Ok(nread)
     }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        buffered::read_exact(self, buf)
+    }
 }
 
 impl<R: BufRead> BufRead for XdrRecordReader<R> {
@@ -116,6 +291,7 @@ impl<R: BufRead> BufRead for XdrRecordReader<R> {
     fn consume(&mut self, sz: usize) {
         assert!(sz <= self.totremains());
         self.consumed += sz;
+        self.stream_pos += sz as u64;
         self.reader.consume(sz);
     }
 }
@@ -185,12 +361,19 @@ const WRBUF: usize = 65536;
 
 /// Write records into a bytestream.
 ///
-/// Flushes the current buffer as end of record when destroyed.
+/// Data written is accumulated into record fragments, which are sent once the buffer fills or
+/// on `flush()`. Neither of these ends the record; call `end_record()` to send the final
+/// fragment. Anything written after that starts a new record, so any number of records can be
+/// sent over the same stream.
+///
+/// Flushes the current buffer as end of record when destroyed, but any error is lost at that
+/// point. Call `end_record()` explicitly to see it. A writer which has never been written to
+/// sends an empty record; one whose last record has already been ended sends nothing more.
 pub struct XdrRecordWriter<W: Write> {
-    buf: Vec<u8>, // accumulated record fragment
-    bufsz: usize, // max fragment size
-    eor: bool,    // last fragment was eor
-    writer: W,    // writer we're passing on to
+    buf: Vec<u8>,      // accumulated record fragment
+    bufsz: usize,      // max fragment size
+    eor: bool,         // last fragment was eor
+    writer: Option<W>, // writer we're passing on to (None once taken by into_inner)
 }
 
 impl<W: Write> XdrRecordWriter<W> {
@@ -210,7 +393,7 @@ impl<W: Write> XdrRecordWriter<W> {
             buf: Vec::with_capacity(bufsz),
             bufsz: bufsz,
             eor: false,
-            writer: w,
+            writer: Some(w),
         }
     }
 
@@ -223,19 +406,139 @@ impl<W: Write> XdrRecordWriter<W> {
 
         let rechdr = self.buf.len() as u32 | (if eor { LAST_REC } else { 0 });
 
-        pack(&rechdr, &mut self.writer).map_err(mapioerr)?;
-        let _ = self.writer.write_all(&self.buf).map(|_| ())?;
+        #[cfg(feature = "log")]
+        trace!(
+            "write fragment: {} bytes{}\n{}",
+            self.buf.len(),
+            if eor { ", end of record" } else { "" },
+            HexDump::new(&self.buf)
+        );
+
+        let writer = self.writer.as_mut().expect("writer taken");
+        writer.write_u32::<BigEndian>(rechdr)?;
+        let _ = writer.write_all(&self.buf).map(|_| ())?;
+        #[cfg(feature = "metrics")]
+        ::metrics::fragment_written(self.buf.len(), eor);
         self.buf.truncate(0);
 
         self.eor = eor;
-        self.writer.flush()
+        writer.flush()
+    }
+
+    /// End the current record.
+    ///
+    /// This sends any buffered data as the final fragment of the record, and flushes the
+    /// underlying stream. It doesn't close the stream. If nothing has been written since the
+    /// previous record was ended, this sends an empty record.
+    pub fn end_record(&mut self) -> io::Result<()> {
+        self.flush_eor(true)
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.writer.as_ref().expect("writer taken")
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly will corrupt the record stream unless it's at a record boundary.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.as_mut().expect("writer taken")
+    }
+
+    /// Unwrap this `XdrRecordWriter`, returning the underlying writer.
+    ///
+    /// As when it's dropped, any unterminated record is ended first, so a writer which has never
+    /// been written to sends an empty record. If ending the record fails, the error is returned
+    /// along with this writer, so the underlying writer isn't lost.
+    pub fn into_inner(mut self) -> Result<W, IntoInnerError<XdrRecordWriter<W>>> {
+        match self.finish() {
+            Ok(()) => Ok(self.writer.take().expect("writer taken")),
+            Err(err) => Err(IntoInnerError(self, err)),
+        }
+    }
+
+    // Start out at a record boundary, so nothing is sent when finishing unless something has
+    // been written. For wrappers which manage records themselves.
+    #[cfg(any(feature = "framed", feature = "deflate", feature = "zstd"))]
+    pub(crate) fn at_boundary(mut self) -> Self {
+        self.eor = true;
+        self
+    }
+
+    /// Unwrap this `XdrRecordWriter` without ending the current record, returning the underlying
+    /// writer and any buffered data which hasn't been sent.
+    pub fn into_parts(mut self) -> (W, Vec<u8>) {
+        let buf = ::std::mem::take(&mut self.buf);
+        (self.writer.take().expect("writer taken"), buf)
+    }
+
+    // End the current record if it hasn't been already
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() || !self.eor {
+            self.flush_eor(true)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The error from `XdrRecordWriter::into_inner()`, with the writer it failed to unwrap.
+///
+/// Like `std::io::IntoInnerError`, this allows the writer to be recovered, for example with
+/// `XdrRecordWriter::into_parts()`, after the final fragment of its record couldn't be sent.
+pub struct IntoInnerError<W>(W, io::Error);
+
+impl<W> IntoInnerError<W> {
+    /// The error which stopped the writer being unwrapped.
+    pub fn error(&self) -> &io::Error {
+        &self.1
+    }
+
+    /// The writer which couldn't be unwrapped.
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+
+    /// The error, dropping the writer.
+    pub fn into_error(self) -> io::Error {
+        self.1
+    }
+
+    /// The error and the writer.
+    pub fn into_parts(self) -> (io::Error, W) {
+        (self.1, self.0)
+    }
+}
+
+impl<W> fmt::Debug for IntoInnerError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.1.fmt(f)
+    }
+}
+
+impl<W> fmt::Display for IntoInnerError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.1.fmt(f)
+    }
+}
+
+impl<W> error::Error for IntoInnerError<W> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.1)
+    }
+}
+
+impl<W> From<IntoInnerError<W>> for io::Error {
+    fn from(err: IntoInnerError<W>) -> io::Error {
+        err.1
     }
 }
 
 impl<W: Write> Drop for XdrRecordWriter<W> {
     fn drop(&mut self) {
-        if self.buf.len() > 0 || !self.eor {
-            let _ = self.flush_eor(true);
+        if self.writer.is_some() {
+            let _ = self.finish();
         }
     }
 }
@@ -0,0 +1,223 @@
+//! Annotated encodings for tests
+//!
+//! Golden-byte tests of encoders are much easier to write, and to debug when they fail, if you
+//! can see which bytes came from which field. `RecordingWriter` captures everything packed into
+//! it, along with named spans marking which part of the output each field produced. Spans nest,
+//! so a struct's span can contain spans for each of its members. `pretty()` then prints the
+//! encoding laid out by span:
+//!
+//! ```
+//! # use xdr_codec::recording::RecordingWriter;
+//! # use xdr_codec::XdrString;
+//! let mut rec = RecordingWriter::new();
+//!
+//! rec.field("id", &7u32).unwrap();
+//! rec.begin("entry");
+//! rec.field("name", &XdrString::new("hello")).unwrap();
+//! rec.field("flag", &true).unwrap();
+//! rec.end();
+//!
+//! assert_eq!(
+//!     format!("{}", rec.pretty()),
+//!     "0000..0004 id: 00 00 00 07\n\
+//!      0004..0014 entry\n\
+//!      0004..0010   name: 00 00 00 05  68 65 6c 6c  6f 00 00 00\n\
+//!      0010..0014   flag: 00 00 00 01\n"
+//! );
+//! ```
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use error::*;
+use hexdump::HexDump;
+
+use super::Pack;
+
+// Fields larger than this are shown as a hex dump rather than inline
+const INLINE: usize = 16;
+
+/// A named range of the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// Name given to `begin()` or `field()`.
+    pub name: String,
+    /// Byte range of the output it covers.
+    pub range: Range<usize>,
+    /// Nesting depth, 0 for the outermost spans.
+    pub depth: usize,
+}
+
+/// Writer which records the output along with named spans.
+///
+/// Spans are started with `begin()` and ended with `end()`, or `field()` packs a value within a
+/// span of its own. Data written outside any span is recorded but not annotated.
+#[derive(Debug, Default)]
+pub struct RecordingWriter {
+    buf: Vec<u8>,
+    spans: Vec<Span>, // in order of starting
+    open: Vec<usize>, // indices of unended spans
+}
+
+impl RecordingWriter {
+    /// Create a new, empty `RecordingWriter`.
+    pub fn new() -> RecordingWriter {
+        Default::default()
+    }
+
+    /// Start a new span, nested within any currently open span.
+    pub fn begin<S: Into<String>>(&mut self, name: S) {
+        let pos = self.buf.len();
+
+        self.open.push(self.spans.len());
+        self.spans.push(Span {
+            name: name.into(),
+            range: pos..pos,
+            depth: self.open.len() - 1,
+        });
+    }
+
+    /// End the most recently started span. Panics if there's no open span.
+    pub fn end(&mut self) {
+        let idx = self.open.pop().expect("no open span");
+        self.spans[idx].range.end = self.buf.len();
+    }
+
+    /// Pack a value within a span named `name`.
+    ///
+    /// The span is ended even if packing fails.
+    pub fn field<S: Into<String>, T: Pack<Self> + ?Sized>(
+        &mut self,
+        name: S,
+        val: &T,
+    ) -> Result<usize> {
+        self.begin(name);
+        let res = val.pack(self);
+        self.end();
+        res
+    }
+
+    /// The recorded output.
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The recorded spans, in the order they were started. Unended spans have an empty range.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Bytes covered by a span.
+    pub fn span_bytes(&self, span: &Span) -> &[u8] {
+        &self.buf[span.range.clone()]
+    }
+
+    /// Return a `Display` adapter which prints the output laid out by span.
+    ///
+    /// Each span is printed with its range and name, indented by depth. Spans without nested
+    /// spans also show their bytes, inline if short or as a hex dump otherwise. Output which
+    /// isn't covered by any outermost span is shown as `-`.
+    pub fn pretty(&self) -> Pretty<'_> {
+        Pretty(self)
+    }
+
+    /// Unwrap this `RecordingWriter`, returning the recorded output.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Write for RecordingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Pretty-printer for a `RecordingWriter`; see `RecordingWriter::pretty()`.
+#[derive(Debug)]
+pub struct Pretty<'a>(&'a RecordingWriter);
+
+fn units(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    for (i, unit) in data.chunks(4).enumerate() {
+        if i > 0 {
+            write!(f, "  ")?;
+        }
+        for (j, b) in unit.iter().enumerate() {
+            if j > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+    }
+    Ok(())
+}
+
+impl<'a> Pretty<'a> {
+    fn bytes(&self, f: &mut fmt::Formatter, range: Range<usize>, depth: usize) -> fmt::Result {
+        let data = &self.0.buf[range.clone()];
+
+        if data.is_empty() {
+            writeln!(f)
+        } else if data.len() <= INLINE {
+            write!(f, " ")?;
+            units(f, data)?;
+            writeln!(f)
+        } else {
+            writeln!(f)?;
+            for line in HexDump::with_base(data, range.start).to_string().lines() {
+                writeln!(f, "{:1$}{2}", "", 11 + 2 * depth, line)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn gap(&self, f: &mut fmt::Formatter, range: Range<usize>) -> fmt::Result {
+        if range.start < range.end {
+            write!(f, "{:04x}..{:04x} -:", range.start, range.end)?;
+            self.bytes(f, range, 0)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Pretty<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spans = &self.0.spans;
+        let mut pos = 0;
+
+        for (i, span) in spans.iter().enumerate() {
+            if span.depth == 0 {
+                self.gap(f, pos..span.range.start)?;
+                pos = span.range.end;
+            }
+
+            write!(
+                f,
+                "{:04x}..{:04x} {:3$}{4}",
+                span.range.start,
+                span.range.end,
+                "",
+                2 * span.depth,
+                span.name
+            )?;
+
+            let leaf = match spans.get(i + 1) {
+                Some(next) => next.depth <= span.depth,
+                None => true,
+            };
+            if leaf {
+                write!(f, ":")?;
+                self.bytes(f, span.range.clone(), span.depth)?;
+            } else {
+                writeln!(f)?;
+            }
+        }
+
+        self.gap(f, pos..self.0.buf.len())
+    }
+}
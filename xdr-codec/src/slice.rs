@@ -0,0 +1,232 @@
+//! Random access decoding from memory
+//!
+//! Unpacking normally streams from a `Read`, so getting to a value in the middle of a large
+//! archive means decoding (or at least reading) everything before it. When the whole archive is
+//! in memory - for example, a memory-mapped file - `SliceCursor` can jump straight to any offset,
+//! skip over values, and split off sub-slices to decode independently. Since it implements `Read`,
+//! anything implementing `Unpack` can be unpacked from it, and opaque data can be borrowed
+//! directly from the underlying slice rather than copied.
+//!
+//! Types implementing `UnpackRef` borrow their opaque and string data from the slice when
+//! unpacked with `unpack_ref()`. xdrgen generates these as borrowed variants of structs with
+//! `Config::borrowed()`.
+use std::cmp::min;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::str;
+
+use error::*;
+
+use super::{padding, Unpack};
+
+fn eof() -> Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "offset past end of slice").into()
+}
+
+/// Cursor over a byte slice for random-access unpacking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SliceCursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SliceCursor<'a> {
+    /// Create a new cursor at the start of `data`.
+    pub fn new(data: &'a [u8]) -> SliceCursor<'a> {
+        SliceCursor { data, pos: 0 }
+    }
+
+    /// Current offset from the start of the slice.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of bytes remaining after the current position.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// The whole underlying slice.
+    pub fn get_ref(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The part of the slice after the current position.
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    /// Move to an absolute offset. Fails if it's past the end of the slice.
+    pub fn seek_to(&mut self, pos: usize) -> Result<()> {
+        if pos > self.data.len() {
+            return Err(eof());
+        }
+        self.pos = pos;
+        Ok(())
+    }
+
+    /// Skip `n` bytes. Fails if that would go past the end of the slice.
+    pub fn skip(&mut self, n: usize) -> Result<()> {
+        if n > self.remaining() {
+            return Err(eof());
+        }
+        self.pos += n;
+        Ok(())
+    }
+
+    /// Return a new cursor over `len` bytes starting at absolute offset `pos`, without moving
+    /// this one.
+    pub fn sub(&self, pos: usize, len: usize) -> Result<SliceCursor<'a>> {
+        match pos.checked_add(len) {
+            Some(end) if end <= self.data.len() => Ok(SliceCursor::new(&self.data[pos..end])),
+            _ => Err(eof()),
+        }
+    }
+
+    /// Split off the next `len` bytes as a new cursor, and skip past them.
+    pub fn split(&mut self, len: usize) -> Result<SliceCursor<'a>> {
+        let sub = self.sub(self.pos, len)?;
+        self.pos += len;
+        Ok(sub)
+    }
+
+    /// Unpack a value at the current position.
+    pub fn unpack<T: Unpack<Self>>(&mut self) -> Result<T> {
+        T::unpack(self).map(|(v, _)| v)
+    }
+
+    /// Unpack a value at absolute offset `pos`, without moving this cursor.
+    pub fn unpack_at<T: Unpack<Self>>(&self, pos: usize) -> Result<T> {
+        let mut cur = *self;
+        cur.seek_to(pos)?;
+        cur.unpack()
+    }
+
+    /// Unpack a fixed-size opaque of `len` bytes, borrowing it from the underlying slice.
+    pub fn opaque_array(&mut self, len: usize) -> Result<&'a [u8]> {
+        let padded = match len.checked_add(padding(len).len()) {
+            Some(padded) if padded <= self.remaining() => padded,
+            _ => return Err(eof()),
+        };
+
+        let data = &self.data[self.pos..self.pos + len];
+        self.pos += padded;
+        Ok(data)
+    }
+
+    /// Unpack a (perhaps) length-limited opaque, borrowing it from the underlying slice.
+    pub fn opaque_flex(&mut self, maxsz: Option<usize>) -> Result<&'a [u8]> {
+        let start = self.pos;
+        let len: u32 = self.unpack()?;
+        let len = len as usize;
+
+        if let Some(m) = maxsz {
+            if len > m {
+                self.pos = start;
+                bail!(ErrorKind::InvalidLen(m));
+            }
+        }
+
+        let res = self.opaque_array(len);
+        if res.is_err() {
+            self.pos = start;
+        }
+        res
+    }
+
+    /// Unpack a (perhaps) length-limited string, borrowing it from the underlying slice.
+    ///
+    /// Fails with `InvalidStringChar` at the first byte which isn't valid UTF-8.
+    pub fn string(&mut self, maxsz: Option<usize>) -> Result<&'a str> {
+        let start = self.pos;
+        let data = self.opaque_flex(maxsz)?;
+
+        str::from_utf8(data).map_err(|e| {
+            self.pos = start;
+            let pos = e.valid_up_to();
+            ErrorKind::InvalidStringChar(pos, data[pos]).into()
+        })
+    }
+
+    /// Unpack a (perhaps) length-limited string, borrowing it from the underlying slice and
+    /// allowing only printable ASCII, like `unpack_string_strict()`.
+    pub fn string_strict(&mut self, maxsz: Option<usize>) -> Result<&'a str> {
+        let start = self.pos;
+        let data = self.opaque_flex(maxsz)?;
+
+        if let Some(pos) = data.iter().position(|&c| !(0x20..=0x7e).contains(&c)) {
+            self.pos = start;
+            bail!(ErrorKind::InvalidStringChar(pos, data[pos]));
+        }
+        // Printable ASCII is always valid UTF-8
+        Ok(str::from_utf8(data).unwrap())
+    }
+
+    /// Unpack a value at the current position, borrowing from the underlying slice.
+    pub fn unpack_ref<T: UnpackRef<'a>>(&mut self) -> Result<T> {
+        T::unpack_ref(self)
+    }
+}
+
+/// Values which can borrow from the slice they're unpacked from.
+///
+/// The encoding is the same as for the corresponding owned type, but opaque and string data
+/// refers to the slice rather than being copied out of it.
+pub trait UnpackRef<'a>: Sized {
+    /// Unpack a value from `cur`.
+    fn unpack_ref(cur: &mut SliceCursor<'a>) -> Result<Self>;
+}
+
+impl<'a> Read for SliceCursor<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = min(buf.len(), self.remaining());
+
+        buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if buf.len() > self.remaining() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ));
+        }
+
+        buf.copy_from_slice(&self.data[self.pos..self.pos + buf.len()]);
+        self.pos += buf.len();
+        Ok(())
+    }
+}
+
+impl<'a> BufRead for SliceCursor<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining_slice())
+    }
+
+    fn consume(&mut self, sz: usize) {
+        assert!(sz <= self.remaining());
+        self.pos += sz;
+    }
+}
+
+impl<'a> Seek for SliceCursor<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, off) = match pos {
+            SeekFrom::Start(off) => (0, off as i64),
+            SeekFrom::Current(off) => (self.pos as i64, off),
+            SeekFrom::End(off) => (self.data.len() as i64, off),
+        };
+
+        match base.checked_add(off) {
+            Some(p) if p >= 0 && p as u64 <= self.data.len() as u64 => {
+                self.pos = p as usize;
+                Ok(self.pos as u64)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek outside slice",
+            )),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Unions switched on a status
+//!
+//! Many protocols return results as a union switched on a status, where zero means success and
+//! anything else is an error:
+//!
+//! ```text
+//! union read_res switch (status s) {
+//! case OK:
+//!     read_ok resok;
+//! default:
+//!     read_fail resfail;
+//! };
+//! ```
+//!
+//! `StatusUnion<T, E>` packs and unpacks any union of this shape as a `Result`. The error keeps
+//! the status alongside the `default` arm's value, so it can be packed again.
+//!
+//! ```
+//! # use xdr_codec::{pack, unpack, StatusUnion};
+//! let mut buf = Vec::new();
+//! pack(&StatusUnion::<u32, ()>::ok(7), &mut buf).unwrap();
+//! pack(&StatusUnion::<u32, ()>::err(5, ()), &mut buf).unwrap();
+//! assert_eq!(buf, &[0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 5]);
+//!
+//! let mut input = &buf[..];
+//! let res: StatusUnion<u32, ()> = unpack(&mut input).unwrap();
+//! assert_eq!(res.into_result(), Ok(7));
+//! let res: StatusUnion<u32, ()> = unpack(&mut input).unwrap();
+//! assert_eq!(res.into_result(), Err((5, ())));
+//! ```
+use std::io::{Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::result;
+
+use error::*;
+
+use super::{Pack, Skip, Unpack};
+
+/// A union of `T` for a zero status, or `E` for any other.
+///
+/// The status is packed as an `int`; unsigned or enum statuses have the same representation. An
+/// error with a zero status can't be packed, and fails with `ErrorKind::InvalidCase(0)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusUnion<T, E>(pub result::Result<T, (i32, E)>);
+
+impl<T, E> StatusUnion<T, E> {
+    /// A successful result.
+    pub fn ok(v: T) -> Self {
+        StatusUnion(Ok(v))
+    }
+
+    /// A failure with a (non-zero) status.
+    pub fn err(status: i32, e: E) -> Self {
+        StatusUnion(Err((status, e)))
+    }
+
+    /// The status: zero on success.
+    pub fn status(&self) -> i32 {
+        match self.0 {
+            Ok(_) => 0,
+            Err((status, _)) => status,
+        }
+    }
+
+    /// Unwrap into the underlying `Result`.
+    pub fn into_result(self) -> result::Result<T, (i32, E)> {
+        self.0
+    }
+}
+
+impl<T, E> From<result::Result<T, (i32, E)>> for StatusUnion<T, E> {
+    fn from(v: result::Result<T, (i32, E)>) -> Self {
+        StatusUnion(v)
+    }
+}
+
+impl<T, E> From<StatusUnion<T, E>> for result::Result<T, (i32, E)> {
+    fn from(v: StatusUnion<T, E>) -> Self {
+        v.0
+    }
+}
+
+impl<T, E> Deref for StatusUnion<T, E> {
+    type Target = result::Result<T, (i32, E)>;
+
+    fn deref(&self) -> &result::Result<T, (i32, E)> {
+        &self.0
+    }
+}
+
+impl<T, E> DerefMut for StatusUnion<T, E> {
+    fn deref_mut(&mut self) -> &mut result::Result<T, (i32, E)> {
+        &mut self.0
+    }
+}
+
+impl<Out: Write, T: Pack<Out>, E: Pack<Out>> Pack<Out> for StatusUnion<T, E> {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        match self.0 {
+            Ok(ref v) => Ok(0i32.pack(out)? + v.pack(out)?),
+            Err((0, _)) => bail!(ErrorKind::InvalidCase(0)),
+            Err((status, ref e)) => Ok(status.pack(out)? + e.pack(out)?),
+        }
+    }
+}
+
+impl<In: Read, T: Unpack<In>, E: Unpack<In>> Unpack<In> for StatusUnion<T, E> {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (status, sz) = i32::unpack(input)?;
+        if status == 0 {
+            let (v, vsz) = T::unpack(input)?;
+            Ok((StatusUnion(Ok(v)), sz + vsz))
+        } else {
+            let (e, esz) = E::unpack(input)?;
+            Ok((StatusUnion(Err((status, e))), sz + esz))
+        }
+    }
+}
+
+impl<In: Read, T: Skip<In>, E: Skip<In>> Skip<In> for StatusUnion<T, E> {
+    fn skip(input: &mut In) -> Result<usize> {
+        let (status, sz) = i32::unpack(input)?;
+        if status == 0 {
+            Ok(sz + T::skip(input)?)
+        } else {
+            Ok(sz + E::skip(input)?)
+        }
+    }
+}
@@ -1,10 +1,15 @@
 // Don't rustfmt in here to avoid trashing vec![] formatting
 #![cfg_attr(rustfmt, rustfmt_skip)]
 
-use std::io::Cursor;
-use super::{Error, ErrorKind, Pack, Unpack, Opaque,
-            pack_flex, pack_opaque_flex, pack_string, pack_array, pack_opaque_array,
-            unpack_array, unpack_opaque_array, unpack_string, unpack_flex, unpack_opaque_flex};
+use std::io::{Cursor, Write};
+use std::num::{NonZeroI64, NonZeroU32};
+use super::hexdump::HexDump;
+use super::{BoundedVec, OptArray, StatusUnion, Error, ErrorKind, Pack, PackDyn, Unpack, Opaque, Quadruple, Hyper128, UHyper128, Skip, Validate, Validated, XdrString, unpack_validated, unpack_xdrstring, unpack_string_strict,
+            pack_sized, unpack_sized, pack_flex, pack_opaque_flex, pack_string, pack_array, pack_opaque_array,
+            unpack_array, unpack_array_fixed, unpack_opaque_array, unpack_string, unpack_flex, unpack_opaque_flex,
+            skip_opaque_array, skip_opaque_flex, skip_flex, pack_opaque_from, unpack_opaque_to, unpack_opaque_vec,
+            splice_opaque_array, splice_opaque_flex,
+            padding, read_padding, write_padding, narrow};
 
 
 #[cfg(feature = "bytecodec")]
@@ -615,6 +620,49 @@ fn basic_array() {
     }
 }
 
+#[test]
+fn fixed_array() {
+    {
+        let a = [XdrString::new("a"), XdrString::new("bcde")];
+        let mut out = Vec::new();
+
+        assert_eq!(a.pack(&mut out).unwrap(), 8 + 8);
+        assert_eq!(out, vec![0x00, 0x00, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00,
+                             0x00, 0x00, 0x00, 0x04, 0x62, 0x63, 0x64, 0x65]);
+
+        let (b, bsz) = <[XdrString; 2]>::unpack(&mut &out[..]).expect("unpack failed");
+        assert_eq!(bsz, 16);
+        assert_eq!(a, b);
+
+        assert_eq!(<[XdrString; 2]>::skip(&mut &out[..]).unwrap(), 16);
+    }
+
+    {
+        let a = [[1u32, 2], [3, 4], [5, 6]];
+        let mut out = Vec::new();
+
+        assert_eq!(a.pack(&mut out).unwrap(), 6*4);
+
+        let (b, bsz) = unpack_array_fixed::<_, [u32; 2], 3>(&mut &out[..]).expect("unpack failed");
+        assert_eq!(bsz, 6*4);
+        assert_eq!(a, b);
+    }
+
+    {
+        // Empty
+        let (b, bsz) = <[XdrString; 0]>::unpack(&mut &[][..]).expect("unpack failed");
+        assert_eq!(bsz, 0);
+        assert!(b.is_empty());
+    }
+
+    {
+        // Truncated second element
+        let v = vec![0x00, 0x00, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x04, 0x62, 0x63];
+        assert!(<[XdrString; 2]>::unpack(&mut &v[..]).is_err());
+    }
+}
+
 #[test]
 fn basic_opaque_array() {
     {
@@ -708,6 +756,25 @@ fn basic_opaque_array() {
         assert_eq!(bsz, 4);
         assert_eq!(vec![0x11, 0x22, 0x33, 0x00], b);
     }
+
+    {
+        // A larger buffer still reads the padding, and nothing after it
+        let mut input = Cursor::new(vec![0x11, 0x22, 0x33, 0x44, 0x55, 0x00, 0x00, 0x00, 0x99]);
+        let mut b = [0xffu8; 7];
+        assert_eq!(unpack_opaque_array(&mut input, &mut b[..], 5).unwrap(), 8);
+        assert_eq!(b, [0x11, 0x22, 0x33, 0x44, 0x55, 0x00, 0x00]);
+        assert_eq!(input.position(), 8);
+    }
+
+    // Truncated data or padding is an error, not a hang
+    for len in 0..8 {
+        let v = vec![0x11, 0x22, 0x33, 0x44, 0x55, 0x00, 0x00, 0x00];
+        let mut b = [0u8; 5];
+        match unpack_opaque_array(&mut Cursor::new(&v[..len]), &mut b[..], 5) {
+            Err(Error(ErrorKind::IOError(ref e), _)) if e.kind() == ::std::io::ErrorKind::UnexpectedEof => (),
+            res => panic!("bad result {:?} for {} bytes", res, len),
+        }
+    }
 }
 
 #[test]
@@ -737,3 +804,656 @@ fn basic_option() {
         res => panic!("bad result {:?}", res),
     }
 }
+
+#[test]
+fn basic_dyn() {
+    let mut out = Vec::new();
+    let foo = Opaque::borrowed(b"foo");
+    let vals: Vec<&dyn PackDyn> = vec![&1u32, &-2i64, &foo, &Some(true)];
+
+    let mut sz = 0;
+    for v in vals {
+        sz += v.pack_dyn(&mut out).unwrap();
+    }
+
+    assert_eq!(sz, 28);
+    assert_eq!(out, vec![0x00, 0x00, 0x00, 0x01,
+                         0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+                         0x00, 0x00, 0x00, 0x03,  0x66, 0x6f, 0x6f, 0x00,
+                         0x00, 0x00, 0x00, 0x01,  0x00, 0x00, 0x00, 0x01,]);
+
+    let mut dynout: Box<dyn Write> = Box::new(Cursor::new(Vec::new()));
+    assert_eq!(vec![1u32, 2, 3].pack_dyn(&mut dynout).unwrap(), 16);
+}
+
+#[test]
+fn basic_char() {
+    let mut out = Cursor::new(Vec::new());
+
+    assert_eq!('a'.pack(&mut out).unwrap(), 4);
+    assert_eq!('\u{1f980}'.pack(&mut out).unwrap(), 4);
+
+    let v = out.into_inner();
+
+    assert_eq!(v, vec![0x00, 0x00, 0x00, 0x61,
+                       0x00, 0x01, 0xf9, 0x80,]);
+
+    let mut input = Cursor::new(v);
+    assert_eq!(Unpack::unpack(&mut input).unwrap(), ('a', 4));
+    assert_eq!(Unpack::unpack(&mut input).unwrap(), ('\u{1f980}', 4));
+
+    for &bad in &[0xd800u32, 0x110000] {
+        let mut input = Cursor::new(Vec::new());
+        bad.pack(&mut input).unwrap();
+        input.set_position(0);
+
+        match char::unpack(&mut input) {
+            Err(Error(ErrorKind::InvalidChar(v), _)) => assert_eq!(v, bad),
+            res => panic!("bad result {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn basic_nonzero() {
+    let mut out = Cursor::new(Vec::new());
+
+    assert_eq!(NonZeroU32::new(0x11223344).unwrap().pack(&mut out).unwrap(), 4);
+    assert_eq!(NonZeroI64::new(-2).unwrap().pack(&mut out).unwrap(), 8);
+    assert_eq!(0u32.pack(&mut out).unwrap(), 4);
+
+    let v = out.into_inner();
+
+    assert_eq!(v, vec![0x11, 0x22, 0x33, 0x44,
+                       0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+                       0x00, 0x00, 0x00, 0x00,]);
+
+    let mut input = Cursor::new(v);
+    assert_eq!(Unpack::unpack(&mut input).unwrap(), (NonZeroU32::new(0x11223344).unwrap(), 4));
+    assert_eq!(Unpack::unpack(&mut input).unwrap(), (NonZeroI64::new(-2).unwrap(), 8));
+
+    match NonZeroU32::unpack(&mut input) {
+        Err(Error(ErrorKind::InvalidZero, _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn basic_uuid() {
+    use uuid::Uuid;
+
+    let mut out = Cursor::new(Vec::new());
+    let id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+
+    assert_eq!(id.pack(&mut out).unwrap(), 16);
+
+    let v = out.into_inner();
+    assert_eq!(v, vec![0x67, 0xe5, 0x50, 0x44,  0x10, 0xb1, 0x42, 0x6f,
+                       0x92, 0x47, 0xbb, 0x68,  0x0e, 0x5f, 0xe0, 0xc8,]);
+
+    let mut input = Cursor::new(v.clone());
+    assert_eq!(Uuid::unpack(&mut input).unwrap(), (id, 16));
+
+    // Truncated
+    let mut input = Cursor::new(v[..15].to_vec());
+    assert!(Uuid::unpack(&mut input).is_err());
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn basic_chrono() {
+    use chrono::{DateTime, Utc};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let t = DateTime::<Utc>::from_timestamp(-2, 500_000_000).unwrap();
+    let mut out = Cursor::new(Vec::new());
+
+    assert_eq!(t.pack(&mut out).unwrap(), 12);
+
+    let v = out.into_inner();
+    assert_eq!(v, vec![0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xfe,  0x1d, 0xcd, 0x65, 0x00]);
+
+    // Same encoding as SystemTime
+    let mut st = Vec::new();
+    (UNIX_EPOCH - Duration::from_millis(1500)).pack(&mut st).unwrap();
+    assert_eq!(v, st);
+
+    let mut input = Cursor::new(v.clone());
+    assert_eq!(DateTime::<Utc>::unpack(&mut input).unwrap(), (t, 12));
+
+    // A negative TimeDelta has the same encoding, relative to zero
+    let d = chrono::TimeDelta::milliseconds(-1500);
+    let mut out = Vec::new();
+    assert_eq!(d.pack(&mut out).unwrap(), 12);
+    assert_eq!(out, v);
+    assert_eq!(chrono::TimeDelta::unpack(&mut Cursor::new(v)).unwrap(), (d, 12));
+}
+
+#[test]
+fn basic_collections() {
+    use std::collections::{BTreeSet, HashSet, VecDeque};
+
+    let mut dq = VecDeque::new();
+    dq.push_back(2u32);
+    dq.push_back(3);
+    dq.push_front(1);
+
+    let mut out = Cursor::new(Vec::new());
+    assert_eq!(dq.pack(&mut out).unwrap(), 16);
+    let v = out.into_inner();
+    assert_eq!(v, vec![0, 0, 0, 3,  0, 0, 0, 1,  0, 0, 0, 2,  0, 0, 0, 3]);
+
+    let mut input = Cursor::new(v.clone());
+    assert_eq!(VecDeque::<u32>::unpack(&mut input).unwrap(), (dq, 16));
+
+    // BTreeSet packs in sorted order
+    let bs: BTreeSet<u32> = vec![3, 1, 2].into_iter().collect();
+    let mut out = Cursor::new(Vec::new());
+    assert_eq!(bs.pack(&mut out).unwrap(), 16);
+    assert_eq!(out.into_inner(), vec![0, 0, 0, 3,  0, 0, 0, 1,  0, 0, 0, 2,  0, 0, 0, 3]);
+
+    let mut input = Cursor::new(v.clone());
+    assert_eq!(BTreeSet::<u32>::unpack(&mut input).unwrap(), (bs.clone(), 16));
+
+    let hs: HashSet<u32> = bs.iter().cloned().collect();
+    let mut out = Cursor::new(Vec::new());
+    assert_eq!(hs.pack(&mut out).unwrap(), 16);
+    let mut input = Cursor::new(out.into_inner());
+    assert_eq!(HashSet::<u32>::unpack(&mut input).unwrap(), (hs, 16));
+
+    // Duplicates are rejected
+    let dup = vec![0, 0, 0, 2,  0, 0, 0, 1,  0, 0, 0, 1];
+    let mut input = Cursor::new(dup.clone());
+    assert!(BTreeSet::<u32>::unpack(&mut input).is_err());
+    let mut input = Cursor::new(dup);
+    assert!(HashSet::<u32>::unpack(&mut input).is_err());
+}
+
+#[test]
+fn array_no_padding() {
+    // Not a valid XDR type, but shows that element arrays get no trailing padding, unlike opaques
+    #[derive(Debug, PartialEq)]
+    struct Short(u16);
+
+    impl<Out: Write> Pack<Out> for Short {
+        fn pack(&self, out: &mut Out) -> ::Result<usize> {
+            out.write_all(&self.0.to_be_bytes())?;
+            Ok(2)
+        }
+    }
+
+    impl<In: ::std::io::Read> Unpack<In> for Short {
+        fn unpack(input: &mut In) -> ::Result<(Self, usize)> {
+            let mut b = [0; 2];
+            input.read_exact(&mut b)?;
+            Ok((Short(u16::from_be_bytes(b)), 2))
+        }
+    }
+
+    let v = vec![Short(0x0102), Short(0x0304), Short(0x0506)];
+    let mut out = Vec::new();
+    assert_eq!(pack_flex(&v, None, &mut out).unwrap(), 4 + 6);
+    assert_eq!(out, vec![0, 0, 0, 3,  1, 2,  3, 4,  5, 6]);
+
+    out.extend_from_slice(&[0xff, 0xff]);
+    let mut input = Cursor::new(out);
+    assert_eq!(unpack_flex::<_, Short>(&mut input, None).unwrap(), (v, 10));
+    assert_eq!(input.position(), 10);
+
+    let mut out = Vec::new();
+    assert_eq!(pack_opaque_flex(&[1, 2, 3, 4, 5, 6], None, &mut out).unwrap(), 4 + 8);
+}
+
+#[test]
+fn basic_boundedvec() {
+    use std::convert::TryFrom;
+
+    let mut v = BoundedVec::<u32, 3>::new();
+    v.push(1).unwrap();
+    v.push(2).unwrap();
+    v[1] = 5;
+
+    let mut out = Vec::new();
+    assert_eq!(v.pack(&mut out).unwrap(), 12);
+    assert_eq!(out, vec![0, 0, 0, 2,  0, 0, 0, 1,  0, 0, 0, 5]);
+
+    let mut input = Cursor::new(out);
+    assert_eq!(BoundedVec::<u32, 3>::unpack(&mut input).unwrap(), (v.clone(), 12));
+    input.set_position(0);
+    assert_eq!(BoundedVec::<u32, 3>::skip(&mut input).unwrap(), 12);
+    input.set_position(0);
+    assert!(BoundedVec::<u32, 1>::unpack(&mut input).is_err());
+    input.set_position(0);
+    assert!(BoundedVec::<u32, 1>::skip(&mut input).is_err());
+
+    v.push(3).unwrap();
+    match v.push(4) {
+        Err(Error(ErrorKind::InvalidLen(3), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+    assert_eq!(v.len(), 3);
+
+    assert!(BoundedVec::<u32, 2>::try_from(vec![1, 2, 3]).is_err());
+    assert_eq!(Vec::from(BoundedVec::<u32, 2>::try_from(vec![1, 2]).unwrap()), vec![1, 2]);
+}
+
+#[test]
+fn basic_optarray() {
+    let mut out = Vec::new();
+    assert_eq!(OptArray(Some(5u32)).pack(&mut out).unwrap(), 8);
+    assert_eq!(OptArray::<u32>(None).pack(&mut out).unwrap(), 4);
+    assert_eq!(out, vec![0, 0, 0, 1,  0, 0, 0, 5,  0, 0, 0, 0]);
+
+    let mut input = Cursor::new(out);
+    assert_eq!(OptArray::<u32>::unpack(&mut input).unwrap(), (OptArray(Some(5)), 8));
+    assert_eq!(OptArray::<u32>::unpack(&mut input).unwrap(), (OptArray(None), 4));
+    input.set_position(0);
+    assert_eq!(OptArray::<u32>::skip(&mut input).unwrap(), 8);
+    assert_eq!(OptArray::<u32>::skip(&mut input).unwrap(), 4);
+
+    // Same as the pointer encoding when present
+    let mut ptr = Vec::new();
+    Some(5u32).pack(&mut ptr).unwrap();
+    assert_eq!(&ptr[..], &input.get_ref()[..8]);
+
+    let mut input = Cursor::new(vec![0, 0, 0, 2,  0, 0, 0, 1,  0, 0, 0, 2]);
+    match OptArray::<u32>::unpack(&mut input) {
+        Err(Error(ErrorKind::InvalidLen(1), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+    input.set_position(0);
+    assert!(OptArray::<u32>::skip(&mut input).is_err());
+
+    let v: Option<u32> = OptArray::from(Some(3)).into();
+    assert_eq!(v, Some(3));
+    assert!(OptArray(Some(3)).is_some());
+}
+
+#[test]
+fn basic_statusunion() {
+    let mut out = Vec::new();
+    assert_eq!(StatusUnion::<u32, i64>::ok(5).pack(&mut out).unwrap(), 8);
+    assert_eq!(StatusUnion::<u32, i64>::err(-2, 7).pack(&mut out).unwrap(), 12);
+    assert_eq!(StatusUnion::<u32, ()>::err(3, ()).pack(&mut out).unwrap(), 4);
+    assert_eq!(out, vec![0, 0, 0, 0,  0, 0, 0, 5,
+                         255, 255, 255, 254,  0, 0, 0, 0, 0, 0, 0, 7,
+                         0, 0, 0, 3]);
+
+    let mut input = Cursor::new(out);
+    assert_eq!(StatusUnion::<u32, i64>::unpack(&mut input).unwrap(), (StatusUnion::ok(5), 8));
+    assert_eq!(StatusUnion::<u32, i64>::unpack(&mut input).unwrap(), (StatusUnion::err(-2, 7), 12));
+    let (v, _) = StatusUnion::<u32, ()>::unpack(&mut input).unwrap();
+    assert_eq!(v.status(), 3);
+    assert_eq!(v.into_result(), Err((3, ())));
+    input.set_position(0);
+    assert_eq!(StatusUnion::<u32, i64>::skip(&mut input).unwrap(), 8);
+    assert_eq!(StatusUnion::<u32, i64>::skip(&mut input).unwrap(), 12);
+    assert_eq!(StatusUnion::<u32, ()>::skip(&mut input).unwrap(), 4);
+
+    match StatusUnion::<u32, ()>::err(0, ()).pack(&mut Vec::new()) {
+        Err(Error(ErrorKind::InvalidCase(0), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    let v: StatusUnion<u32, ()> = Ok(1).into();
+    assert!(v.is_ok());
+    assert_eq!(v.status(), 0);
+}
+
+#[test]
+fn error_context() {
+    use super::{in_field, ResultContext};
+
+    let err = Error::invalidlen(3).with_field("inner.v").context("unpacking outer");
+    match *err.kind() {
+        ErrorKind::Context(ref msg) => assert_eq!(msg, "unpacking outer"),
+        ref kind => panic!("bad kind {:?}", kind),
+    }
+    match *err.root_kind() {
+        ErrorKind::InvalidLen(3) => (),
+        ref kind => panic!("bad kind {:?}", kind),
+    }
+    assert_eq!(err.chained().to_string(), "unpacking outer: inner.v: invalid array len: '3'");
+
+    // Without context
+    let err = Error::invalidenum(-1);
+    match *err.root_kind() {
+        ErrorKind::InvalidEnum(-1) => (),
+        ref kind => panic!("bad kind {:?}", kind),
+    }
+    assert_eq!(err.chained().to_string(), "invalid enum value: '-1'");
+
+    let mut input = Cursor::new(vec![0, 0, 0, 1,  0, 0]);
+    assert_eq!(in_field("a", || u32::unpack(&mut input)).unwrap(), (1, 4));
+    let err = in_field("b", || u32::unpack(&mut input)).unwrap_err();
+    match *err.root_kind() {
+        ErrorKind::IOError(_) => (),
+        ref kind => panic!("bad kind {:?}", kind),
+    }
+    assert!(err.chained().to_string().starts_with("b: "));
+
+    assert_eq!(Ok::<_, Error>(1).context("x").unwrap(), 1);
+}
+
+#[test]
+fn basic_sized() {
+    let mut out = Vec::new();
+
+    assert_eq!(pack_sized(&XdrString::new("abcde"), &mut out).unwrap(), 12);
+    assert_eq!(pack_sized(&7u64, &mut out).unwrap(), 8);
+
+    let mut input = &out[..];
+    assert_eq!(unpack_sized::<_, XdrString>(&mut input).unwrap(), (XdrString::new("abcde"), 12));
+    assert_eq!(unpack_sized::<_, u64>(&mut input).unwrap(), (7, 8));
+    assert!(unpack_sized::<_, u32>(&mut input).is_err());
+}
+
+#[test]
+fn basic_validate() {
+    #[derive(Debug, PartialEq)]
+    struct Even(u32);
+
+    impl<In: ::std::io::Read> Unpack<In> for Even {
+        fn unpack(input: &mut In) -> ::Result<(Self, usize)> {
+            u32::unpack(input).map(|(v, sz)| (Even(v), sz))
+        }
+    }
+
+    impl Validate for Even {
+        fn validate(&self) -> ::Result<()> {
+            if self.0 % 2 == 0 { Ok(()) } else { Err(Error::validation(format!("{} is odd", self.0))) }
+        }
+    }
+
+    let mut input = Cursor::new(vec![0, 0, 0, 2,  0, 0, 0, 3]);
+    assert_eq!(unpack_validated::<_, Even>(&mut input).unwrap(), (Even(2), 4));
+    match unpack_validated::<_, Even>(&mut input) {
+        Err(Error(ErrorKind::Validation(ref msg), _)) => assert_eq!(msg, "3 is odd"),
+        res => panic!("bad result {:?}", res),
+    }
+
+    let mut input = Cursor::new(vec![0, 0, 0, 2,  0, 0, 0, 4,  0, 0, 0, 2,  0, 0, 0, 5]);
+    let (v, sz) = Vec::<Validated<Even>>::unpack(&mut input).unwrap();
+    assert_eq!(sz, 12);
+    assert_eq!(*v[0], Even(4));
+    assert!(Vec::<Validated<Even>>::unpack(&mut input).is_err());
+}
+
+#[test]
+fn basic_xdrstring() {
+    // Latin-1 "café"
+    let latin1 = XdrString::new(&b"caf\xe9"[..]);
+    let mut out = Cursor::new(Vec::new());
+
+    assert_eq!(latin1.pack(&mut out).unwrap(), 8);
+
+    let v = out.into_inner();
+    assert_eq!(v, vec![0, 0, 0, 4,  0x63, 0x61, 0x66, 0xe9]);
+
+    let mut input = Cursor::new(v.clone());
+    assert!(String::unpack(&mut input).is_err());
+
+    let mut input = Cursor::new(v.clone());
+    let (s, sz) = XdrString::unpack(&mut input).unwrap();
+    assert_eq!(sz, 8);
+    assert_eq!(s, latin1);
+    assert_eq!(s.to_str(), None);
+    assert_eq!(s.to_string_lossy(), "caf\u{fffd}");
+    assert_eq!(format!("{:?}", s), "\"caf\\xe9\"");
+    assert!(s.into_string().is_err());
+
+    let mut input = Cursor::new(v);
+    assert!(unpack_xdrstring(&mut input, Some(3)).is_err());
+
+    let s = XdrString::from("hello");
+    assert_eq!(s.to_str(), Some("hello"));
+    assert_eq!(s.into_string().unwrap(), "hello");
+}
+
+#[test]
+fn strict_string() {
+    let mut input = Cursor::new(vec![0, 0, 0, 5,  0x68, 0x65, 0x6c, 0x6c,  0x6f, 0, 0, 0]);
+    assert_eq!(unpack_string_strict(&mut input, None).unwrap(), (String::from("hello"), 12));
+
+    // Control character
+    let mut input = Cursor::new(vec![0, 0, 0, 3,  0x61, 0x0a, 0x62, 0]);
+    match unpack_string_strict(&mut input, None) {
+        Err(Error(ErrorKind::InvalidStringChar(1, 0x0a), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    // Valid UTF-8, but not ASCII
+    let mut input = Cursor::new(vec![0, 0, 0, 2,  0xc3, 0xa9, 0, 0]);
+    assert!(unpack_string(&mut input, None).is_ok());
+    let mut input = Cursor::new(vec![0, 0, 0, 2,  0xc3, 0xa9, 0, 0]);
+    match unpack_string_strict(&mut input, None) {
+        Err(Error(ErrorKind::InvalidStringChar(0, 0xc3), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    let mut input = Cursor::new(vec![0, 0, 0, 5,  0x68, 0x65, 0x6c, 0x6c,  0x6f, 0, 0, 0]);
+    assert!(unpack_string_strict(&mut input, Some(4)).is_err());
+}
+
+#[test]
+fn basic_hyper128() {
+    let mut out = Cursor::new(Vec::new());
+
+    assert_eq!(Hyper128(-2).pack(&mut out).unwrap(), 16);
+    assert_eq!(Hyper128(0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10).pack(&mut out).unwrap(), 16);
+    assert_eq!(UHyper128(u128::max_value() - 1).pack(&mut out).unwrap(), 16);
+    assert_eq!(UHyper128(1 << 64).pack(&mut out).unwrap(), 16);
+
+    let v = out.into_inner();
+    assert_eq!(v, vec![0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xfe,
+                       0x01, 0x02, 0x03, 0x04,  0x05, 0x06, 0x07, 0x08,  0x09, 0x0a, 0x0b, 0x0c,  0x0d, 0x0e, 0x0f, 0x10,
+                       0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xff,  0xff, 0xff, 0xff, 0xfe,
+                       0x00, 0x00, 0x00, 0x00,  0x00, 0x00, 0x00, 0x01,  0x00, 0x00, 0x00, 0x00,  0x00, 0x00, 0x00, 0x00]);
+
+    let mut input = Cursor::new(v);
+    assert_eq!(Hyper128::unpack(&mut input).unwrap(), (Hyper128(-2), 16));
+    assert_eq!(Hyper128::unpack(&mut input).unwrap(), (Hyper128(0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10), 16));
+    assert_eq!(UHyper128::unpack(&mut input).unwrap(), (UHyper128(u128::max_value() - 1), 16));
+    assert_eq!(UHyper128::skip(&mut input).unwrap(), 16);
+
+    let mut input = Cursor::new(vec![0, 0, 0, 0,  0, 0, 0, 1,  0, 0, 0, 0]);
+    assert!(Hyper128::unpack(&mut input).is_err());
+}
+
+#[test]
+fn basic_quadruple() {
+    // 1.0
+    let one = Quadruple([0x3f, 0xff, 0, 0,  0, 0, 0, 0,  0, 0, 0, 0,  0, 0, 0, 0]);
+    let mut out = Cursor::new(Vec::new());
+
+    assert_eq!(one.pack(&mut out).unwrap(), 16);
+
+    let v = out.into_inner();
+    assert_eq!(v, one.0.to_vec());
+
+    let mut input = Cursor::new(v);
+    assert_eq!(Quadruple::unpack(&mut input).unwrap(), (one, 16));
+
+    let mut input = Cursor::new(vec![0x3f, 0xff, 0, 0]);
+    assert!(Quadruple::unpack(&mut input).is_err());
+}
+
+#[test]
+fn basic_skip() {
+    let mut out = Cursor::new(Vec::new());
+    let mut sz = 0;
+
+    sz += 1u32.pack(&mut out).unwrap();
+    sz += (-2i64).pack(&mut out).unwrap();
+    sz += "hello".pack(&mut out).unwrap();
+    sz += Some(vec![1u32, 2]).pack(&mut out).unwrap();
+    sz += None::<u32>.pack(&mut out).unwrap();
+    sz += Quadruple([0; 16]).pack(&mut out).unwrap();
+    sz += pack_opaque_array(&[1, 2, 3], 3, &mut out).unwrap();
+    assert_eq!(sz, 4 + 8 + 12 + 16 + 4 + 16 + 4);
+    99u32.pack(&mut out).unwrap();
+    let v = out.into_inner();
+
+    let mut input = Cursor::new(v.clone());
+    let skipped = u32::skip(&mut input).unwrap() + i64::skip(&mut input).unwrap() +
+        String::skip(&mut input).unwrap() + Option::<Vec<u32>>::skip(&mut input).unwrap() +
+        Option::<u32>::skip(&mut input).unwrap() + Quadruple::skip(&mut input).unwrap() +
+        skip_opaque_array(&mut input, 3).unwrap();
+    assert_eq!(skipped, sz);
+    assert_eq!(u32::unpack(&mut input).unwrap(), (99, 4));
+
+    // Lengths are still checked
+    let mut input = Cursor::new(v[12..].to_vec());
+    assert!(skip_opaque_flex(&mut input, Some(4)).is_err());
+    let mut input = Cursor::new(v[28..].to_vec());
+    assert!(skip_flex::<_, u32>(&mut input, Some(1)).is_err());
+
+    // Short input
+    let mut input = Cursor::new(v[..22].to_vec());
+    assert_eq!(u32::skip(&mut input).unwrap(), 4);
+    assert_eq!(i64::skip(&mut input).unwrap(), 8);
+    assert!(String::skip(&mut input).is_err());
+}
+
+#[test]
+fn opaque_streaming() {
+    let data: Vec<u8> = (0..100u8).collect();
+
+    let mut out = Cursor::new(Vec::new());
+    assert_eq!(pack_opaque_from(&mut &data[..], 99, &mut out).unwrap(), 104);
+    let v = out.into_inner();
+
+    let mut expected = Cursor::new(Vec::new());
+    assert_eq!(pack_opaque_flex(&data[..99], None, &mut expected).unwrap(), 104);
+    assert_eq!(v, expected.into_inner());
+
+    let mut input = Cursor::new(v.clone());
+    let mut body = Vec::new();
+    assert_eq!(unpack_opaque_to(&mut input, &mut body, Some(100)).unwrap(), (99, 104));
+    assert_eq!(&body[..], &data[..99]);
+    assert_eq!(input.position(), 104);
+
+    let mut input = Cursor::new(v.clone());
+    match unpack_opaque_to(&mut input, &mut Vec::new(), Some(98)) {
+        Err(Error(ErrorKind::InvalidLen(98), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    // Short source and short input
+    assert!(pack_opaque_from(&mut &data[..10], 11, &mut Cursor::new(Vec::new())).is_err());
+    let mut input = Cursor::new(v[..50].to_vec());
+    assert!(unpack_opaque_to(&mut input, &mut Vec::new(), None).is_err());
+}
+
+#[test]
+fn opaque_splice() {
+    let data: Vec<u8> = (0..100u8).collect();
+    let mut v = Vec::new();
+    pack_opaque_flex(&data[..99], None, &mut v).unwrap();
+    7u32.pack(&mut v).unwrap();
+
+    let mut input = Cursor::new(v.clone());
+    let mut out = Vec::new();
+    assert_eq!(splice_opaque_flex(&mut input, Some(100), &mut out).unwrap(), 104);
+    assert_eq!(&out[..], &v[..104]);
+    assert_eq!(u32::unpack(&mut input).unwrap(), (7, 4));
+
+    // Padding is written out as zeros whatever it was on input
+    let mut dirty = v.clone();
+    dirty[103] = 0xff;
+    let mut out = Vec::new();
+    assert_eq!(splice_opaque_array(&mut &dirty[4..], 99, &mut out).unwrap(), 100);
+    assert_eq!(&out[..], &v[4..104]);
+
+    let mut out = Vec::new();
+    match splice_opaque_flex(&mut Cursor::new(v.clone()), Some(98), &mut out) {
+        Err(Error(ErrorKind::InvalidLen(98), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+    assert!(out.is_empty());
+
+    // Short input
+    assert!(splice_opaque_flex(&mut Cursor::new(v[..50].to_vec()), None, &mut Vec::new()).is_err());
+    assert!(splice_opaque_array(&mut &v[4..102], 99, &mut Vec::new()).is_err());
+}
+
+#[test]
+fn opaque_vec() {
+    // Larger than the preallocation limit, so the Vec grows as it's read
+    let data: Vec<u8> = (0..100_003u32).map(|i| i as u8).collect();
+
+    let mut out = Cursor::new(Vec::new());
+    assert_eq!(pack_opaque_array(&data, data.len(), &mut out).unwrap(), 100_004);
+    let v = out.into_inner();
+
+    let mut input = Cursor::new(v.clone());
+    assert_eq!(unpack_opaque_vec(&mut input, data.len()).unwrap(), (data.clone(), 100_004));
+    assert_eq!(input.position(), 100_004);
+
+    let mut out = Cursor::new(Vec::new());
+    pack_opaque_flex(&data, None, &mut out).unwrap();
+    let v = out.into_inner();
+    assert_eq!(unpack_opaque_flex(&mut Cursor::new(v.clone()), None).unwrap(), (data.clone(), 100_008));
+    assert_eq!(Opaque::unpack(&mut Cursor::new(v.clone())).unwrap(), (Opaque::owned(data.clone()), 100_008));
+
+    // Truncated data fails, even when it ends on a 4-byte boundary
+    assert!(unpack_opaque_vec(&mut Cursor::new(&v[4..50_004]), data.len()).is_err());
+    assert!(unpack_opaque_flex(&mut Cursor::new(&v[..50_004]), None).is_err());
+    assert!(Opaque::unpack(&mut Cursor::new(&v[..50_004])).is_err());
+}
+
+#[test]
+fn hexdump() {
+    assert_eq!(format!("{}", HexDump::new(&[])), "");
+
+    let mut data: Vec<u8> = (0x41..0x51).collect();
+    data.extend(&[0x20, 0x7f, 0xff]);
+    assert_eq!(format!("{}", HexDump::with_base(&data, 0x100)),
+               "00000100  41 42 43 44  45 46 47 48  49 4a 4b 4c  4d 4e 4f 50 |ABCDEFGHIJKLMNOP|\n\
+                00000110  20 7f ff                                           | ..|");
+}
+
+#[test]
+fn huge_lengths() {
+    // Lengths beyond what a 32-bit target can allocate fail when the input runs out
+    let input = vec![0xff, 0xff, 0xff, 0xfd, 1, 2, 3, 4];
+
+    assert!(unpack_flex::<_, u32>(&mut Cursor::new(input.clone()), None).is_err());
+    assert!(Vec::<u64>::unpack(&mut Cursor::new(input.clone())).is_err());
+    assert!(skip_opaque_flex(&mut Cursor::new(input.clone()), None).is_err());
+    assert!(skip_opaque_array(&mut Cursor::new(input), usize::MAX).is_err());
+}
+
+#[test]
+fn padding_helpers() {
+    for sz in 0..8 {
+        assert_eq!((sz + padding(sz).len()) % 4, 0);
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"abcde");
+    assert_eq!(write_padding(&mut out, 5).unwrap(), 3);
+    assert_eq!(write_padding(&mut out, 8).unwrap(), 0);
+    assert_eq!(out, b"abcde\0\0\0");
+
+    let mut input = Cursor::new(vec![0, 0, 0, 1]);
+    assert_eq!(read_padding(&mut input, 4).unwrap(), 0);
+    assert_eq!(read_padding(&mut input, 1).unwrap(), 3);
+    assert_eq!(input.position(), 3);
+    // Short input
+    assert!(read_padding(&mut input, 2).is_err());
+}
+
+#[test]
+fn narrowing() {
+    assert_eq!(narrow::<i32, i64>(&-5).unwrap(), -5i32);
+    assert_eq!(narrow::<u32, u64>(&0xffff_ffff).unwrap(), 0xffff_ffffu32);
+    assert_eq!(narrow::<u32, i64>(&7).unwrap(), 7u32);
+
+    match narrow::<i32, i64>(&(1 << 31)) {
+        Err(Error(ErrorKind::OutOfRange(ref v), _)) => assert_eq!(v, "2147483648"),
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert!(narrow::<u32, i64>(&-1).is_err());
+}
@@ -0,0 +1,216 @@
+//! Timestamp codecs
+//!
+//! XDR has no standard time type, but most protocols settle on a seconds/nanoseconds pair
+//! relative to the Unix epoch:
+//!
+//! ```text
+//! struct timestamp {
+//!     hyper seconds;
+//!     unsigned int nseconds;
+//! };
+//! ```
+//!
+//! `SystemTime` (and `chrono::DateTime<Utc>` with the `chrono` feature) is packed in this layout.
+//! Times before the epoch have negative `seconds`, but `nseconds` is always a positive offset
+//! from that, and must be less than 1,000,000,000.
+//!
+//! Some older protocols use microseconds instead (like `struct timeval`); `Timeval` wraps a
+//! `SystemTime` to pack it that way.
+//!
+//! Durations (timeouts, leases and so on) use the same layout, with `seconds` counting from
+//! zero instead of the epoch. `Duration` is packed this way, as is `chrono::TimeDelta` with the
+//! `chrono` feature. Negative durations follow the timestamp convention: `seconds` is rounded
+//! down, and `nseconds` is still positive, so -1.5s is packed as `{ -2, 500000000 }`. A
+//! `Duration` can't be negative, so unpacking one fails on a negative `seconds`.
+//!
+//! This module is only available with the `timestamp` feature.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use error::*;
+
+use super::{Pack, Read, Unpack, Write};
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+const MICROS_PER_SEC: u32 = 1_000_000;
+
+// Split a time into seconds and fractional nanoseconds since the epoch
+fn to_parts(time: &SystemTime) -> Result<(i64, u32)> {
+    let (secs, nsecs) = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i128, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            if d.subsec_nanos() == 0 {
+                (-(d.as_secs() as i128), 0)
+            } else {
+                (-(d.as_secs() as i128) - 1, NANOS_PER_SEC - d.subsec_nanos())
+            }
+        }
+    };
+
+    if secs < i64::MIN as i128 || secs > i64::MAX as i128 {
+        bail!("timestamp out of range");
+    }
+
+    Ok((secs as i64, nsecs))
+}
+
+fn from_parts(secs: i64, nsecs: u32) -> Result<SystemTime> {
+    if nsecs >= NANOS_PER_SEC {
+        bail!("invalid timestamp nanoseconds {}", nsecs);
+    }
+
+    let t = if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::new(secs as u64, nsecs))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+            .and_then(|t| t.checked_add(Duration::new(0, nsecs)))
+    };
+
+    match t {
+        Some(t) => Ok(t),
+        None => bail!("timestamp out of range"),
+    }
+}
+
+impl<Out: Write> Pack<Out> for SystemTime {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        let (secs, nsecs) = to_parts(self)?;
+        Ok(secs.pack(out)? + nsecs.pack(out)?)
+    }
+}
+
+impl<In: Read> Unpack<In> for SystemTime {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (secs, ssz) = i64::unpack(input)?;
+        let (nsecs, nsz) = u32::unpack(input)?;
+
+        Ok((from_parts(secs, nsecs)?, ssz + nsz))
+    }
+}
+
+impl<Out: Write> Pack<Out> for Duration {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        if self.as_secs() > i64::MAX as u64 {
+            bail!("duration out of range");
+        }
+        Ok((self.as_secs() as i64).pack(out)? + self.subsec_nanos().pack(out)?)
+    }
+}
+
+impl<In: Read> Unpack<In> for Duration {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (secs, ssz) = i64::unpack(input)?;
+        let (nsecs, nsz) = u32::unpack(input)?;
+
+        if nsecs >= NANOS_PER_SEC {
+            bail!("invalid duration nanoseconds {}", nsecs);
+        }
+        if secs < 0 {
+            bail!("negative duration");
+        }
+
+        Ok((Duration::new(secs as u64, nsecs), ssz + nsz))
+    }
+}
+
+/// Timestamp with microsecond resolution.
+///
+/// Packed as `struct { hyper seconds; unsigned int useconds; }`. Packing truncates any
+/// sub-microsecond part of the time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timeval(pub SystemTime);
+
+impl From<SystemTime> for Timeval {
+    fn from(time: SystemTime) -> Self {
+        Timeval(time)
+    }
+}
+
+impl From<Timeval> for SystemTime {
+    fn from(tv: Timeval) -> Self {
+        tv.0
+    }
+}
+
+impl<Out: Write> Pack<Out> for Timeval {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        let (secs, nsecs) = to_parts(&self.0)?;
+        Ok(secs.pack(out)? + (nsecs / 1000).pack(out)?)
+    }
+}
+
+impl<In: Read> Unpack<In> for Timeval {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (secs, ssz) = i64::unpack(input)?;
+        let (usecs, usz) = u32::unpack(input)?;
+
+        if usecs >= MICROS_PER_SEC {
+            bail!("invalid timestamp microseconds {}", usecs);
+        }
+
+        Ok((Timeval(from_parts(secs, usecs * 1000)?), ssz + usz))
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use chrono::{DateTime, TimeDelta, Utc};
+    use std::cmp::min;
+
+    use error::*;
+
+    use super::super::{Pack, Read, Unpack, Write};
+
+    impl<Out: Write> Pack<Out> for DateTime<Utc> {
+        fn pack(&self, out: &mut Out) -> Result<usize> {
+            // chrono represents a leap second as nanoseconds past 1,000,000,000; clamp it to
+            // the end of the previous second
+            let nsecs = min(self.timestamp_subsec_nanos(), super::NANOS_PER_SEC - 1);
+            Ok(self.timestamp().pack(out)? + nsecs.pack(out)?)
+        }
+    }
+
+    impl<In: Read> Unpack<In> for DateTime<Utc> {
+        fn unpack(input: &mut In) -> Result<(Self, usize)> {
+            let (secs, ssz) = i64::unpack(input)?;
+            let (nsecs, nsz) = u32::unpack(input)?;
+
+            if nsecs >= super::NANOS_PER_SEC {
+                bail!("invalid timestamp nanoseconds {}", nsecs);
+            }
+
+            match DateTime::from_timestamp(secs, nsecs) {
+                Some(t) => Ok((t, ssz + nsz)),
+                None => bail!("timestamp out of range"),
+            }
+        }
+    }
+
+    impl<Out: Write> Pack<Out> for TimeDelta {
+        fn pack(&self, out: &mut Out) -> Result<usize> {
+            // subsec_nanos() has the same sign as the whole delta; make it a positive offset
+            let (secs, nsecs) = match self.subsec_nanos() {
+                n if n < 0 => (self.num_seconds() - 1, n + super::NANOS_PER_SEC as i32),
+                n => (self.num_seconds(), n),
+            };
+            Ok(secs.pack(out)? + (nsecs as u32).pack(out)?)
+        }
+    }
+
+    impl<In: Read> Unpack<In> for TimeDelta {
+        fn unpack(input: &mut In) -> Result<(Self, usize)> {
+            let (secs, ssz) = i64::unpack(input)?;
+            let (nsecs, nsz) = u32::unpack(input)?;
+
+            if nsecs >= super::NANOS_PER_SEC {
+                bail!("invalid duration nanoseconds {}", nsecs);
+            }
+
+            match TimeDelta::new(secs, nsecs) {
+                Some(d) => Ok((d, ssz + nsz)),
+                None => bail!("duration out of range"),
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Logging of encoded values for wire debugging
+//!
+//! When an XDR peer (often a C implementation using rpcgen) disagrees about an encoding, the
+//! quickest way to find out why is to look at the bytes. `Traced` wraps a stream, and its `pack`
+//! and `unpack` methods log each value's type name and a hex dump of its encoding through the
+//! [`log`](https://docs.rs/log) crate at trace level. Use `tracing`'s `log` compatibility to
+//! collect them with `tracing`.
+//!
+//! Bytes are only captured while trace logging is enabled for this module, so it costs little to
+//! leave in place. The record marking reader and writer also log each fragment header (and the
+//! writer, each fragment's contents) at trace level under the `xdr_codec::record` target.
+//!
+//! This module is only available with the `log` feature.
+use std::any::type_name;
+use std::io::{self, Read, Write};
+
+use log::{log_enabled, trace, Level};
+
+use error::*;
+use hexdump::HexDump;
+
+use super::{Pack, Unpack};
+
+/// Stream wrapper which logs the encoding of each value packed or unpacked through it.
+///
+/// Data read or written directly through `Read` or `Write` isn't logged, but it's still counted
+/// in the offsets shown for later values.
+#[derive(Debug)]
+pub struct Traced<S> {
+    inner: S,
+    pos: usize,               // bytes passed through
+    capture: Option<Vec<u8>>, // bytes of the current value, if logging it
+}
+
+impl<S> Traced<S> {
+    /// Wrap a stream.
+    pub fn new(inner: S) -> Traced<S> {
+        Traced {
+            inner,
+            pos: 0,
+            capture: None,
+        }
+    }
+
+    /// Number of bytes read or written so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap this `Traced`, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    // Run `f` capturing the bytes it moves, then log them along with its result
+    fn traced<F, R>(&mut self, op: &str, name: &str, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Self) -> Result<R>,
+    {
+        if !log_enabled!(Level::Trace) {
+            return f(self);
+        }
+
+        let start = self.pos;
+        self.capture = Some(Vec::new());
+        let res = f(self);
+        let bytes = self.capture.take().unwrap_or_default();
+
+        match res {
+            Ok(_) => trace!(
+                "{} {}: {} bytes at {}\n{}",
+                op,
+                name,
+                bytes.len(),
+                start,
+                HexDump::with_base(&bytes, start)
+            ),
+            Err(ref err) => trace!(
+                "{} {} failed after {} bytes at {}: {}\n{}",
+                op,
+                name,
+                bytes.len(),
+                start,
+                err,
+                HexDump::with_base(&bytes, start)
+            ),
+        }
+
+        res
+    }
+
+    fn moved(&mut self, data: &[u8]) {
+        self.pos += data.len();
+        if let Some(ref mut capture) = self.capture {
+            capture.extend_from_slice(data);
+        }
+    }
+}
+
+impl<S: Write> Traced<S> {
+    /// Pack a value, logging its encoding.
+    pub fn pack<T: Pack<Self>>(&mut self, val: &T) -> Result<usize> {
+        self.traced("pack", type_name::<T>(), |out| val.pack(out))
+    }
+}
+
+impl<S: Read> Traced<S> {
+    /// Unpack a value, logging its encoding.
+    pub fn unpack<T: Unpack<Self>>(&mut self) -> Result<T> {
+        self.traced("unpack", type_name::<T>(), |input| T::unpack(input))
+            .map(|(v, _)| v)
+    }
+}
+
+impl<S: Read> Read for Traced<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let sz = self.inner.read(buf)?;
+        self.moved(&buf[..sz]);
+        Ok(sz)
+    }
+}
+
+impl<S: Write> Write for Traced<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let sz = self.inner.write(buf)?;
+        self.moved(&buf[..sz]);
+        Ok(sz)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,389 @@
+//! Dynamically typed values
+//!
+//! A `Value` holds a decoded XDR value of any described type (see the `describe` module), so
+//! generic code can inspect messages without knowing their Rust types. `unpack()` decodes one
+//! from its encoding and a `Descriptor`; `to_value()` converts a value of a described type.
+//!
+//! Parts of a value can be found by path with `Value::get()`. A path is a list of struct field
+//! names separated by `.`, each optionally followed by array indexes like `[2]`. The name of a
+//! union's selector gives the discriminant, and the name of its arm gives the arm's value if
+//! that arm is selected. Optional values are looked through if they're present.
+//!
+//! ```
+//! use xdr_codec::describe::{Descriptor, Field, Kind, Size, TypeDesc};
+//! use xdr_codec::value::{self, Value};
+//!
+//! let desc = Descriptor {
+//!     name: "point",
+//!     kind: Kind::Struct(vec![
+//!         Field { name: "name", ty: TypeDesc::String(Size::Flex(None)) },
+//!         Field { name: "xy", ty: TypeDesc::Array(Box::new(TypeDesc::Int), Size::Fixed(2)) },
+//!     ]),
+//! };
+//!
+//! let buf = [0u8, 0, 0, 1, b'p', 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 4];
+//! let (val, _) = value::unpack(&desc, &mut &buf[..]).unwrap();
+//!
+//! assert_eq!(val.get("name").and_then(Value::as_str), Some("p"));
+//! assert_eq!(val.get("xy[1]"), Some(&Value::Int(4)));
+//! assert_eq!(val.get("xy[2]"), None);
+//! ```
+use std::io::Read;
+
+use describe::{Describe, Descriptor, Kind, Size, TypeDesc};
+use error::*;
+
+use super::{
+    budget, pack, prealloc, unpack_opaque_flex, unpack_opaque_vec, unpack_string, Pack, Unpack,
+};
+
+/// A decoded value.
+///
+/// Named newtypes are represented by the value they wrap.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    UInt(u32),
+    Hyper(i64),
+    UHyper(u64),
+    Float(f32),
+    Double(f64),
+    Quadruple([u8; 16]),
+    Bool(bool),
+    Opaque(Vec<u8>),
+    String(String),
+    Array(Vec<Value>),
+    Option(Option<Box<Value>>),
+    /// An enum value, with its name if it's one of the enum's values.
+    Enum(i32, Option<&'static str>),
+    /// A struct's fields, in order.
+    Struct(Vec<(&'static str, Value)>),
+    /// A union's selector, the name of the selected case's variant, and its arm, unless it's
+    /// `void`.
+    Union {
+        selector: (&'static str, Box<Value>),
+        variant: &'static str,
+        arm: Option<(&'static str, Box<Value>)>,
+    },
+}
+
+impl Value {
+    /// Find a part of this value by path, or `None` if there is no such part.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut cur = self;
+
+        for seg in path.split('.') {
+            let (name, mut idx) = match seg.find('[') {
+                Some(pos) => seg.split_at(pos),
+                None => (seg, ""),
+            };
+
+            if !name.is_empty() {
+                cur = cur.field(name)?;
+            }
+
+            while !idx.is_empty() {
+                if !idx.starts_with('[') {
+                    return None;
+                }
+                let end = idx.find(']')?;
+                cur = cur.index(idx[1..end].parse().ok()?)?;
+                idx = &idx[end + 1..];
+            }
+        }
+
+        Some(cur)
+    }
+
+    /// Find a struct field, or a union's selector or arm, by name.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match *self {
+            Value::Option(Some(ref val)) => val.field(name),
+            Value::Struct(ref fields) => fields.iter().find(|f| f.0 == name).map(|f| &f.1),
+            Value::Union {
+                ref selector,
+                ref arm,
+                ..
+            } => {
+                if selector.0 == name {
+                    Some(&selector.1)
+                } else {
+                    arm.as_ref().filter(|a| a.0 == name).map(|a| &*a.1)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Find an array element by index.
+    pub fn index(&self, idx: usize) -> Option<&Value> {
+        match *self {
+            Value::Option(Some(ref val)) => val.index(idx),
+            Value::Array(ref elems) => elems.get(idx),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer, enum or union discriminant, if it fits in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(v) | Value::Enum(v, _) => Some(v as i64),
+            Value::UInt(v) => Some(v as i64),
+            Value::Hyper(v) => Some(v),
+            Value::UHyper(v) if v <= i64::MAX as u64 => Some(v as i64),
+            _ => None,
+        }
+    }
+
+    /// The contents of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The contents of opaque data.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            Value::Opaque(ref b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Format this value as JSON.
+    ///
+    /// Structs and unions become objects, with a union's selector and arm as its members.
+    /// Enums are their names, or numbers if they have none. Opaque data is a hex string, and
+    /// floating-point values which JSON can't represent are `null`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        match *self {
+            Value::Int(v) => out.push_str(&v.to_string()),
+            Value::UInt(v) => out.push_str(&v.to_string()),
+            Value::Hyper(v) => out.push_str(&v.to_string()),
+            Value::UHyper(v) => out.push_str(&v.to_string()),
+            Value::Float(v) if v.is_finite() => out.push_str(&format!("{:?}", v)),
+            Value::Double(v) if v.is_finite() => out.push_str(&format!("{:?}", v)),
+            Value::Float(_) | Value::Double(_) | Value::Option(None) => out.push_str("null"),
+            Value::Bool(v) => out.push_str(if v { "true" } else { "false" }),
+            Value::Quadruple(ref b) => json_hex(b, out),
+            Value::Opaque(ref b) => json_hex(b, out),
+            Value::String(ref s) => json_str(s, out),
+            Value::Enum(_, Some(name)) => json_str(name, out),
+            Value::Enum(v, None) => out.push_str(&v.to_string()),
+            Value::Option(Some(ref v)) => v.write_json(out),
+
+            Value::Array(ref elems) => {
+                out.push('[');
+                for (i, e) in elems.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    e.write_json(out);
+                }
+                out.push(']');
+            }
+
+            Value::Struct(ref fields) => {
+                out.push('{');
+                for (i, f) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    json_str(f.0, out);
+                    out.push(':');
+                    f.1.write_json(out);
+                }
+                out.push('}');
+            }
+
+            Value::Union {
+                ref selector,
+                ref arm,
+                ..
+            } => {
+                out.push('{');
+                json_str(selector.0, out);
+                out.push(':');
+                selector.1.write_json(out);
+                if let Some(ref arm) = *arm {
+                    out.push(',');
+                    json_str(arm.0, out);
+                    out.push(':');
+                    arm.1.write_json(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn json_hex(bytes: &[u8], out: &mut String) {
+    out.push('"');
+    for b in bytes {
+        out.push_str(&format!("{:02x}", b));
+    }
+    out.push('"');
+}
+
+fn json_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Convert a value of a described type to a `Value`.
+pub fn to_value<T: Describe + Pack<Vec<u8>>>(val: &T) -> Result<Value> {
+    let mut buf = Vec::new();
+    pack(val, &mut buf)?;
+    unpack(&T::describe(), &mut &buf[..]).map(|(v, _)| v)
+}
+
+/// Unpack a value of the type described by `desc`.
+///
+/// Returns the value and the number of bytes consumed. Arrays are charged to the current
+/// budget, if any.
+pub fn unpack<In: Read>(desc: &Descriptor, input: &mut In) -> Result<(Value, usize)> {
+    match desc.kind {
+        Kind::Enum(_) => {
+            let (v, sz) = i32::unpack(input)?;
+            Ok((Value::Enum(v, desc.enum_name(v as i64)), sz))
+        }
+
+        Kind::Struct(ref fields) => {
+            let mut vals = Vec::with_capacity(fields.len());
+            let mut sz = 0;
+            for field in fields {
+                let (v, fsz) = unpack_type(&field.ty, input)?;
+                vals.push((field.name, v));
+                sz += fsz;
+            }
+            Ok((Value::Struct(vals), sz))
+        }
+
+        Kind::Union { ref selector, .. } => {
+            let (sel, mut sz) = unpack_type(&selector.ty, input)?;
+            let disc = match sel {
+                Value::Int(v) | Value::Enum(v, _) => v,
+                Value::UInt(v) => v as i32,
+                Value::Bool(v) => v as i32,
+                _ => bail!(ErrorKind::Validation(format!(
+                    "union {} has a non-integer selector",
+                    desc.name
+                ))),
+            };
+
+            let case = match desc.case(disc as i64) {
+                Some(case) => case,
+                None => bail!(ErrorKind::InvalidCase(disc)),
+            };
+            let arm = match case.field {
+                Some(ref field) => {
+                    let (v, asz) = unpack_type(&field.ty, input)?;
+                    sz += asz;
+                    Some((field.name, Box::new(v)))
+                }
+                None => None,
+            };
+
+            Ok((
+                Value::Union {
+                    selector: (selector.name, Box::new(sel)),
+                    variant: case.variant,
+                    arm,
+                },
+                sz,
+            ))
+        }
+
+        Kind::Newtype(ref ty) => unpack_type(ty, input),
+    }
+}
+
+fn unpack_type<In: Read>(ty: &TypeDesc, input: &mut In) -> Result<(Value, usize)> {
+    match *ty {
+        TypeDesc::Int => i32::unpack(input).map(|(v, sz)| (Value::Int(v), sz)),
+        TypeDesc::UInt => u32::unpack(input).map(|(v, sz)| (Value::UInt(v), sz)),
+        TypeDesc::Hyper => i64::unpack(input).map(|(v, sz)| (Value::Hyper(v), sz)),
+        TypeDesc::UHyper => u64::unpack(input).map(|(v, sz)| (Value::UHyper(v), sz)),
+        TypeDesc::Float => f32::unpack(input).map(|(v, sz)| (Value::Float(v), sz)),
+        TypeDesc::Double => f64::unpack(input).map(|(v, sz)| (Value::Double(v), sz)),
+        TypeDesc::Bool => bool::unpack(input).map(|(v, sz)| (Value::Bool(v), sz)),
+        TypeDesc::Quadruple => {
+            let mut bytes = [0; 16];
+            input.read_exact(&mut bytes)?;
+            Ok((Value::Quadruple(bytes), 16))
+        }
+
+        TypeDesc::Opaque(Size::Fixed(len)) => {
+            unpack_opaque_vec(input, len).map(|(v, sz)| (Value::Opaque(v), sz))
+        }
+        TypeDesc::Opaque(Size::Flex(max)) => {
+            unpack_opaque_flex(input, max).map(|(v, sz)| (Value::Opaque(v), sz))
+        }
+
+        TypeDesc::String(Size::Fixed(len)) => {
+            let (bytes, sz) = unpack_opaque_vec(input, len)?;
+            String::from_utf8(bytes)
+                .map_err(Error::from)
+                .map(|s| (Value::String(s), sz))
+        }
+        TypeDesc::String(Size::Flex(max)) => {
+            unpack_string(input, max).map(|(v, sz)| (Value::String(v), sz))
+        }
+
+        TypeDesc::Array(ref elem, size) => {
+            let (len, mut sz) = match size {
+                Size::Fixed(len) => (len, 0),
+                Size::Flex(max) => {
+                    let (len, sz) = usize::unpack(input)?;
+                    if let Some(m) = max {
+                        if len > m {
+                            bail!(ErrorKind::InvalidLen(m));
+                        }
+                    }
+                    (len, sz)
+                }
+            };
+
+            budget::charge_elems::<Value>(len)?;
+            let mut elems = Vec::with_capacity(prealloc::<Value>(len));
+            for _ in 0..len {
+                let (v, esz) = unpack_type(elem, input)?;
+                elems.push(v);
+                sz += esz;
+            }
+            Ok((Value::Array(elems), sz))
+        }
+
+        TypeDesc::Option(ref ty) => {
+            let (present, mut sz) = bool::unpack(input)?;
+            let val = if present {
+                let (v, vsz) = unpack_type(ty, input)?;
+                sz += vsz;
+                Some(Box::new(v))
+            } else {
+                None
+            };
+            Ok((Value::Option(val), sz))
+        }
+
+        TypeDesc::Named(_, describe) => unpack(&describe(), input),
+    }
+}
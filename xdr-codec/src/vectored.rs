@@ -0,0 +1,161 @@
+//! Vectored output
+//!
+//! Messages made up of a small header followed by large opaque bodies are expensive to encode
+//! into a single buffer, since the bodies have to be copied just to be written out again.
+//! `IoVecWriter` instead builds up a list of buffers: encoded fields are accumulated into an
+//! internal buffer, while opaque payloads (and their padding) are borrowed in place. The whole
+//! lot is then submitted with `Write::write_vectored`.
+use std::cmp::min;
+use std::io::{self, IoSlice, Write};
+use std::ops::Range;
+
+use error::*;
+
+use super::{padding, Pack};
+
+#[derive(Debug)]
+enum Segment<'a> {
+    Owned(Range<usize>),
+    Borrowed(&'a [u8]),
+}
+
+/// Gather XDR encoded data for vectored output.
+///
+/// Anything written via `Write` - typically by packing values with `Pack` - is appended to an
+/// internal buffer. Opaque data added with `opaque()` or `opaque_array()` is referenced rather
+/// than copied. Once complete, the data can be written out with `write_to()`.
+#[derive(Debug, Default)]
+pub struct IoVecWriter<'a> {
+    buf: Vec<u8>,           // encoded fields
+    segs: Vec<Segment<'a>>, // output in order
+    len: usize,             // total length
+}
+
+impl<'a> IoVecWriter<'a> {
+    /// Create a new, empty `IoVecWriter`.
+    pub fn new() -> IoVecWriter<'a> {
+        Default::default()
+    }
+
+    fn borrowed(&mut self, data: &'a [u8]) {
+        if !data.is_empty() {
+            self.segs.push(Segment::Borrowed(data));
+            self.len += data.len();
+        }
+    }
+
+    /// Add a dynamically sized opaque array, with size limit check.
+    ///
+    /// The length is encoded into the internal buffer, but `data` itself is borrowed. Returns the
+    /// encoded size, including the length and padding.
+    pub fn opaque(&mut self, data: &'a [u8], maxsz: Option<usize>) -> Result<usize> {
+        if let Some(m) = maxsz {
+            if data.len() > m {
+                bail!(ErrorKind::InvalidLen(m));
+            }
+        }
+        if data.len() > u32::MAX as usize {
+            bail!(ErrorKind::InvalidLen(data.len()));
+        }
+
+        let sz = data.len().pack(self)?;
+        self.borrowed(data);
+        self.borrowed(padding(data.len()));
+
+        Ok(sz + data.len() + padding(data.len()).len())
+    }
+
+    /// Add a fixed-size opaque array.
+    ///
+    /// As with `pack_opaque_array`, if `data` is longer than `sz` it is truncated, and if it's
+    /// shorter it is padded with `0x00`.
+    pub fn opaque_array(&mut self, data: &'a [u8], sz: usize) -> Result<usize> {
+        let data = &data[..min(sz, data.len())];
+
+        self.borrowed(data);
+        for _ in data.len()..sz {
+            self.write_all(&[0])?;
+        }
+        self.borrowed(padding(sz));
+
+        Ok(sz + padding(sz).len())
+    }
+
+    /// Total number of bytes gathered so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if nothing has been gathered.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the gathered data as a list of `IoSlice`s, in order.
+    pub fn io_slices(&self) -> Vec<IoSlice<'_>> {
+        self.segs
+            .iter()
+            .map(|seg| match *seg {
+                Segment::Owned(ref range) => IoSlice::new(&self.buf[range.clone()]),
+                Segment::Borrowed(data) => IoSlice::new(data),
+            })
+            .collect()
+    }
+
+    /// Write all the gathered data to `out` with `write_vectored`.
+    ///
+    /// Short writes are retried until everything has been written. Returns the number of bytes
+    /// written.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> Result<usize> {
+        let mut slices = self.io_slices();
+        let mut bufs = &mut slices[..];
+        let mut written = 0;
+
+        while !bufs.is_empty() {
+            match out.write_vectored(bufs) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    )
+                    .into())
+                }
+                Ok(n) => {
+                    written += n;
+                    IoSlice::advance_slices(&mut bufs, n);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+impl<'a> Write for IoVecWriter<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        let start = self.buf.len();
+        self.buf.extend_from_slice(data);
+        self.len += data.len();
+
+        // Coalesce with the previous segment if it's also in the buffer
+        if let Some(&mut Segment::Owned(ref mut range)) = self.segs.last_mut() {
+            if range.end == start {
+                range.end = self.buf.len();
+                return Ok(data.len());
+            }
+        }
+        self.segs.push(Segment::Owned(start..self.buf.len()));
+
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
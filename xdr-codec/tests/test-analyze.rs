@@ -0,0 +1,166 @@
+extern crate xdr_codec;
+
+use std::io::Write;
+
+use xdr_codec::analyze::{Analyzer, Body, Procedure, Schema};
+use xdr_codec::describe::{Descriptor, Field, Kind, Size, TypeDesc};
+use xdr_codec::record::XdrRecordWriter;
+use xdr_codec::value::Value;
+use xdr_codec::{pack, pack_string};
+
+fn args() -> Descriptor {
+    Descriptor {
+        name: "args",
+        kind: Kind::Struct(vec![Field {
+            name: "name",
+            ty: TypeDesc::String(Size::Flex(Some(8))),
+        }]),
+    }
+}
+
+fn results() -> Descriptor {
+    Descriptor {
+        name: "results",
+        kind: Kind::Newtype(TypeDesc::Array(Box::new(TypeDesc::Int), Size::Flex(None))),
+    }
+}
+
+fn schema() -> Schema {
+    Schema::new()
+        .procedure(
+            100,
+            1,
+            0,
+            Procedure {
+                name: "NULL",
+                args: None,
+                results: None,
+            },
+        )
+        .procedure(
+            100,
+            1,
+            1,
+            Procedure {
+                name: "LOOKUP",
+                args: Some(args()),
+                results: Some(results()),
+            },
+        )
+}
+
+fn words(ws: &[u32]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for w in ws {
+        pack(w, &mut buf).unwrap();
+    }
+    buf
+}
+
+fn call(xid: u32, proc_: u32) -> Vec<u8> {
+    // CALL, rpcvers 2, prog 100, vers 1, AUTH_SYS cred with a 4 byte body, AUTH_NONE verf
+    words(&[xid, 0, 2, 100, 1, proc_, 1, 4, 0xabcd, 0, 0])
+}
+
+fn reply(xid: u32) -> Vec<u8> {
+    // REPLY, MSG_ACCEPTED, AUTH_NONE verf, SUCCESS
+    words(&[xid, 1, 0, 0, 0, 0])
+}
+
+#[test]
+fn analyze_call_reply() {
+    let mut an = Analyzer::new(schema());
+
+    let mut rec = call(1, 1);
+    pack_string("foo", None, &mut rec).unwrap();
+    let msg = an.decode(&rec).unwrap();
+    assert_eq!(msg.xid, 1);
+    assert_eq!(msg.procedure, Some("LOOKUP"));
+    assert_eq!(
+        msg.header.get("body.cbody.cred.flavor"),
+        Some(&Value::Enum(1, Some("AUTH_SYS")))
+    );
+    match msg.body {
+        Body::Value(ref v) => assert_eq!(v.get("name").and_then(Value::as_str), Some("foo")),
+        ref b => panic!("unexpected {:?}", b),
+    }
+
+    let mut rec = reply(1);
+    pack(&vec![3i32, 4], &mut rec).unwrap();
+    let msg = an.decode(&rec).unwrap();
+    assert_eq!(msg.procedure, Some("LOOKUP"));
+    assert_eq!(
+        msg.body,
+        Body::Value(Value::Array(vec![Value::Int(3), Value::Int(4)]))
+    );
+
+    // The call has been answered
+    let msg = an.decode(&rec).unwrap();
+    assert_eq!(msg.procedure, None);
+    assert_eq!(msg.body, Body::Unknown(rec[24..].to_vec()));
+
+    // Void arguments
+    let msg = an.decode(&call(2, 0)).unwrap();
+    assert_eq!(msg.procedure, Some("NULL"));
+    assert_eq!(msg.body, Body::None);
+
+    // Unknown procedure
+    let rec = words(&[3, 0, 2, 100, 1, 9, 0, 0, 0, 0, 42]);
+    assert_eq!(an.decode(&rec).unwrap().body, Body::Unknown(words(&[42])));
+
+    // Invalid arguments
+    let mut rec = call(4, 1);
+    pack_string("too long!", None, &mut rec).unwrap();
+    match an.decode(&rec).unwrap().body {
+        Body::Invalid(ref raw, _) => assert_eq!(raw.len(), 16),
+        ref b => panic!("unexpected {:?}", b),
+    }
+}
+
+#[test]
+fn analyze_rejected() {
+    let mut an = Analyzer::new(schema());
+    an.decode(&call(5, 1)).unwrap();
+
+    // REPLY, MSG_DENIED, AUTH_ERROR, AUTH_TOOWEAK
+    let msg = an.decode(&words(&[5, 1, 1, 1, 5])).unwrap();
+    assert_eq!(msg.procedure, Some("LOOKUP"));
+    assert_eq!(msg.body, Body::None);
+    assert_eq!(
+        msg.header.get("body.rbody.rreply.auth_stat"),
+        Some(&Value::Enum(5, Some("AUTH_TOOWEAK")))
+    );
+
+    // Not an RPC message
+    assert!(an.decode(&words(&[6, 2])).is_err());
+    assert!(an.decode(&[0, 0]).is_err());
+}
+
+#[test]
+fn analyze_stream() {
+    let mut stream = Vec::new();
+    {
+        let mut wr = XdrRecordWriter::new(&mut stream);
+        for rec in &[call(7, 0), words(&[7, 2]), reply(7)] {
+            wr.write_all(rec).unwrap();
+            wr.end_record().unwrap();
+        }
+    }
+
+    let mut out = Vec::new();
+    let count = Analyzer::new(schema())
+        .analyze(&stream[..], &mut out)
+        .unwrap();
+    assert_eq!(count, 3);
+
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{"xid":7,"procedure":"NULL","header":{"xid":7,"body":{"mtype":"CALL","cbody":{"rpcvers":2,"prog":100,"vers":1,"proc":0,"cred":{"flavor":"AUTH_SYS","body":"0000abcd"},"verf":{"flavor":"AUTH_NONE","body":""}}}},"body":null}"#,
+            r#"{"error":"invalid union case: '2'","record":"0000000700000002"}"#,
+            r#"{"xid":7,"procedure":"NULL","header":{"xid":7,"body":{"mtype":"REPLY","rbody":{"stat":"MSG_ACCEPTED","areply":{"verf":{"flavor":"AUTH_NONE","body":""},"reply_data":{"stat":"SUCCESS"}}}}},"body":null}"#,
+        ]
+    );
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "arbitrary")]
+
+extern crate xdr_codec;
+
+use xdr_codec::arbitrary::{self, Arbitrary, Unstructured};
+use xdr_codec::describe::{Case, Descriptor, EnumValue, Field, Kind, Size, TypeDesc};
+use xdr_codec::value::Value;
+use xdr_codec::{pack, unpack, unpack_string_strict, Opaque, Pack, Quadruple, XdrString};
+
+fn data() -> Vec<u8> {
+    (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect()
+}
+
+#[test]
+fn arbitrary_bounds() {
+    let data = data();
+    let mut u = Unstructured::new(&data);
+
+    for _ in 0..50 {
+        assert!(arbitrary::flex::<u32>(&mut u, Some(3)).unwrap().len() <= 3);
+        assert!(arbitrary::opaque_flex(&mut u, Some(5)).unwrap().len() <= 5);
+        assert!(arbitrary::string(&mut u, Some(7)).unwrap().len() <= 7);
+        assert!(
+            arbitrary::xdrstring(&mut u, Some(2))
+                .unwrap()
+                .as_bytes()
+                .len()
+                <= 2
+        );
+
+        let s = arbitrary::ascii_string(&mut u, Some(9)).unwrap();
+        assert!(s.len() <= 9);
+        let mut buf = Vec::new();
+        s.as_str().pack(&mut buf).unwrap();
+        assert_eq!(unpack_string_strict(&mut &buf[..], Some(9)).unwrap().0, s);
+    }
+
+    // Exhausted input still generates (empty) values
+    let mut u = Unstructured::new(&[]);
+    assert_eq!(arbitrary::flex::<u64>(&mut u, None).unwrap(), vec![]);
+    assert_eq!(arbitrary::string(&mut u, Some(1)).unwrap(), "");
+}
+
+#[test]
+fn arbitrary_types() {
+    let data = data();
+    let mut u = Unstructured::new(&data);
+
+    for _ in 0..20 {
+        let s = XdrString::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        pack(&s, &mut buf).unwrap();
+        assert_eq!(unpack::<_, XdrString>(&mut &buf[..]).unwrap(), s);
+
+        let q = Quadruple::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        pack(&q, &mut buf).unwrap();
+        assert_eq!(unpack::<_, Quadruple>(&mut &buf[..]).unwrap(), q);
+    }
+
+    // Opaques borrow from the input
+    let mut u = Unstructured::new(&data);
+    let o = Opaque::arbitrary(&mut u).unwrap();
+    assert!(data.windows(o.len()).any(|w| w == &o[..]));
+}
+
+// enum color { RED = 1, GREEN = 2 };
+fn color() -> Descriptor {
+    Descriptor {
+        name: "color",
+        kind: Kind::Enum(vec![
+            EnumValue {
+                name: "RED",
+                value: 1,
+            },
+            EnumValue {
+                name: "GREEN",
+                value: 2,
+            },
+        ]),
+    }
+}
+
+// union shade switch (color c) { case RED: opaque data<3>; default: void; };
+// struct msg { shade s; string tag[4]; int vals<2>; };
+fn msg() -> Descriptor {
+    fn shade() -> Descriptor {
+        Descriptor {
+            name: "shade",
+            kind: Kind::Union {
+                selector: Field {
+                    name: "c",
+                    ty: TypeDesc::Named("color", color),
+                },
+                cases: vec![
+                    Case {
+                        value: Some(1),
+                        variant: "RED",
+                        field: Some(Field {
+                            name: "data",
+                            ty: TypeDesc::Opaque(Size::Flex(Some(3))),
+                        }),
+                    },
+                    Case {
+                        value: None,
+                        variant: "default",
+                        field: None,
+                    },
+                ],
+            },
+        }
+    }
+
+    Descriptor {
+        name: "msg",
+        kind: Kind::Struct(vec![
+            Field {
+                name: "s",
+                ty: TypeDesc::Named("shade", shade),
+            },
+            Field {
+                name: "tag",
+                ty: TypeDesc::String(Size::Fixed(4)),
+            },
+            Field {
+                name: "vals",
+                ty: TypeDesc::Array(Box::new(TypeDesc::Int), Size::Flex(Some(2))),
+            },
+        ]),
+    }
+}
+
+#[test]
+fn arbitrary_value() {
+    let data = data();
+    let mut u = Unstructured::new(&data);
+
+    // Described values respect their types
+    for _ in 0..50 {
+        let v = arbitrary::value(&mut u, &msg()).unwrap();
+
+        match (v.get("s.c"), v.get("s.data")) {
+            (Some(&Value::Enum(1, Some("RED"))), Some(&Value::Opaque(ref d))) => {
+                assert!(d.len() <= 3)
+            }
+            (Some(&Value::Enum(c, name)), None) => {
+                assert!(c != 1);
+                assert_eq!(name, color().enum_name(c as i64));
+            }
+            res => panic!("bad union {:?}", res),
+        }
+        assert_eq!(v.get("tag").and_then(Value::as_str).map(str::len), Some(4));
+        match v.get("vals") {
+            Some(&Value::Array(ref vals)) => assert!(vals.len() <= 2),
+            res => panic!("bad array {:?}", res),
+        }
+    }
+
+    // Free values can have any shape
+    for _ in 0..50 {
+        let v = Value::arbitrary(&mut u).unwrap();
+        assert!(!v.to_json().is_empty());
+    }
+}
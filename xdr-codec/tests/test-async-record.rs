@@ -0,0 +1,287 @@
+#![cfg(feature = "tokio")]
+extern crate tokio;
+extern crate xdr_codec;
+
+use std::future::{poll_fn, Future};
+use std::io::{self, Cursor};
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use xdr_codec::async_record::{AsyncXdrRecordReader, AsyncXdrRecordWriter};
+use xdr_codec::record::EmptyFragments;
+
+// Minimal executor; none of the IO here needs real wakeups.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+            return v;
+        }
+    }
+}
+
+fn write_all<W: AsyncWrite + Unpin>(w: &mut W, mut data: &[u8]) -> io::Result<()> {
+    block_on(poll_fn(|cx| {
+        while !data.is_empty() {
+            let n = match Pin::new(&mut *w).poll_write(cx, data) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => return Poll::Pending,
+            };
+            data = &data[n..];
+        }
+        Poll::Ready(Ok(()))
+    }))
+}
+
+fn flush<W: AsyncWrite + Unpin>(w: &mut W) -> io::Result<()> {
+    block_on(poll_fn(|cx| Pin::new(&mut *w).poll_flush(cx)))
+}
+
+fn shutdown<W: AsyncWrite + Unpin>(w: &mut W) -> io::Result<()> {
+    block_on(poll_fn(|cx| Pin::new(&mut *w).poll_shutdown(cx)))
+}
+
+fn read<R: AsyncRead + Unpin>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    block_on(poll_fn(|cx| {
+        let mut rb = ReadBuf::new(buf);
+        match Pin::new(&mut *r).poll_read(cx, &mut rb) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(rb.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }))
+}
+
+// Reader/writer which alternates between Pending and transferring a single byte.
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+    ready: bool,
+}
+
+impl Trickle {
+    fn new(data: Vec<u8>) -> Trickle {
+        Trickle {
+            data,
+            pos: 0,
+            ready: false,
+        }
+    }
+
+    fn tick(&mut self, cx: &mut Context) -> bool {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+        }
+        self.ready
+    }
+}
+
+impl AsyncWrite for Trickle {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if !this.tick(cx) {
+            return Poll::Pending;
+        }
+        this.data.push(buf[0]);
+        Poll::Ready(Ok(1))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        if !self.get_mut().tick(cx) {
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let n = {
+            let data = match self.as_mut().poll_fill_buf(cx) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => return Poll::Pending,
+            };
+            buf.put_slice(data);
+            data.len()
+        };
+        self.consume(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncBufRead for Trickle {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if !this.tick(cx) {
+            return Poll::Pending;
+        }
+        let end = std::cmp::min(this.pos + 1, this.data.len());
+        Poll::Ready(Ok(&this.data[this.pos..end]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().pos += amt;
+    }
+}
+
+#[test]
+fn async_recread() {
+    let inbuf = vec![0, 0, 0, 5, 0, 1, 2, 3, 4, 128, 0, 0, 5, 5, 6, 7, 8, 9];
+    let mut recread = AsyncXdrRecordReader::new(Cursor::new(inbuf));
+    let mut buf = vec![0; 10];
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 5);
+    assert_eq!(buf, vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]);
+    assert!(!recread.eor());
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 5);
+    assert_eq!(buf, vec![5, 6, 7, 8, 9, 0, 0, 0, 0, 0]);
+    assert!(recread.eor());
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 0);
+}
+
+#[test]
+fn async_read_records() {
+    let inbuf = vec![
+        0, 0, 0, 5, 0, 1, 2, 3, 4, 128, 0, 0, 5, 5, 6, 7, 8, 9, 128, 0, 0, 1, 99, 0, 0, 0, 0, 128,
+        0, 0, 0,
+    ];
+    let expected = vec![vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9], vec![99], vec![]];
+
+    let mut recread = AsyncXdrRecordReader::new(Cursor::new(inbuf.clone()));
+    let mut got = Vec::new();
+    while let Some(rec) = block_on(recread.read_record()).unwrap() {
+        got.push(rec);
+    }
+    assert_eq!(got, expected);
+
+    let mut recread = AsyncXdrRecordReader::new(Trickle::new(inbuf));
+    let mut got = Vec::new();
+    while let Some(rec) = block_on(recread.read_record()).unwrap() {
+        got.push(rec);
+    }
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn async_read_unterminated() {
+    let inbuf = vec![0, 0, 0, 2, 1, 2];
+    let mut recread = AsyncXdrRecordReader::new(Cursor::new(inbuf));
+
+    let err = block_on(recread.read_record()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let inbuf = vec![128, 0, 0, 4, 1, 2];
+    let mut recread = AsyncXdrRecordReader::new(Cursor::new(inbuf));
+
+    let err = block_on(recread.read_record()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn async_largerec() {
+    let expected = vec![
+        0, 0, 0, 3, 104, 101, 108, 128, 0, 0, 2, 108, 111, 0, 0, 0, 2, 97, 98, 128, 0, 0, 0,
+    ];
+
+    let mut out = Vec::new();
+    {
+        let mut xw = AsyncXdrRecordWriter::with_buffer(&mut out, 3);
+
+        write_all(&mut xw, b"hello").unwrap();
+        block_on(xw.end_record()).unwrap();
+        write_all(&mut xw, b"ab").unwrap();
+        flush(&mut xw).unwrap();
+        shutdown(&mut xw).unwrap();
+    }
+    assert_eq!(out, expected);
+
+    let mut out = Trickle::new(Vec::new());
+    {
+        let mut xw = AsyncXdrRecordWriter::with_buffer(&mut out, 3);
+
+        write_all(&mut xw, b"hello").unwrap();
+        block_on(xw.end_record()).unwrap();
+        write_all(&mut xw, b"ab").unwrap();
+        flush(&mut xw).unwrap();
+        shutdown(&mut xw).unwrap();
+    }
+    assert_eq!(out.data, expected);
+}
+
+#[test]
+fn async_shutdown_terminated() {
+    // Nothing more to send once the record has been ended
+    let mut out = Vec::new();
+    {
+        let mut xw = AsyncXdrRecordWriter::new(&mut out);
+
+        write_all(&mut xw, b"hi").unwrap();
+        block_on(xw.end_record()).unwrap();
+        shutdown(&mut xw).unwrap();
+    }
+    assert_eq!(out, vec![128, 0, 0, 2, 104, 105]);
+}
+
+#[test]
+fn async_into_inner() {
+    let mut xw = AsyncXdrRecordWriter::new(Vec::new());
+
+    write_all(&mut xw, b"hi").unwrap();
+    block_on(xw.end_record()).unwrap();
+    assert_eq!(xw.get_ref(), &vec![128, 0, 0, 2, 104, 105]);
+    xw.get_mut().clear();
+    assert!(xw.into_inner().is_empty());
+
+    let inbuf = vec![128, 0, 0, 2, 1, 2, 3, 4];
+    let mut recread = AsyncXdrRecordReader::new(Cursor::new(inbuf));
+    let mut buf = [0; 10];
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 2);
+    assert_eq!(recread.get_ref().position(), 6);
+    assert_eq!(recread.into_inner().position(), 6);
+}
+
+#[test]
+fn async_empty_fragments() {
+    let inbuf = vec![
+        0, 0, 0, 2, 1, 2, 0, 0, 0, 0, 128, 0, 0, 1, 3, 128, 0, 0, 0, 0, 0, 0, 1, 4, 128, 0, 0, 0,
+    ];
+
+    let records = |policy| {
+        let mut recread = AsyncXdrRecordReader::new(Trickle::new(inbuf.clone()));
+        recread.set_empty_fragments(policy);
+        let mut got = Vec::new();
+        while let Some(rec) = block_on(recread.read_record())? {
+            got.push(rec);
+        }
+        Ok::<_, io::Error>(got)
+    };
+
+    assert_eq!(
+        records(EmptyFragments::Accept).unwrap(),
+        vec![vec![1, 2, 3], vec![], vec![4]]
+    );
+    assert_eq!(
+        records(EmptyFragments::Coalesce).unwrap(),
+        vec![vec![1, 2, 3], vec![4]]
+    );
+    assert_eq!(
+        records(EmptyFragments::Reject).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "futures")]
+extern crate futures_core;
+extern crate futures_sink;
+extern crate tokio;
+extern crate xdr_codec;
+
+use std::future::{poll_fn, Future};
+use std::io::{self, Cursor};
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::AsyncWrite;
+
+use xdr_codec::async_record::{AsyncXdrRecordReader, AsyncXdrRecordWriter};
+use xdr_codec::async_stream::{RecordSink, RecordStream};
+
+// Minimal executor; none of the IO here needs real wakeups.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+            return v;
+        }
+    }
+}
+
+fn send<S: Sink<T> + Unpin, T>(sink: &mut S, item: T) -> Result<(), S::Error> {
+    block_on(poll_fn(|cx| Pin::new(&mut *sink).poll_ready(cx)))?;
+    Pin::new(&mut *sink).start_send(item)
+}
+
+fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    block_on(poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)))
+}
+
+// Writer which is blocked until opened.
+struct Gate {
+    data: Vec<u8>,
+    open: bool,
+}
+
+impl AsyncWrite for Gate {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if !this.open {
+            return Poll::Pending;
+        }
+        this.data.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn stream_sink_roundtrip() {
+    let mut sink = RecordSink::new(AsyncXdrRecordWriter::new(Vec::new()));
+    send(&mut sink, vec![1u32, 2]).unwrap();
+    send(&mut sink, vec![3u32]).unwrap();
+    // A record with a different type
+    send(&mut sink, 4u32).unwrap();
+    block_on(poll_fn(|cx| {
+        Sink::<u32>::poll_close(Pin::new(&mut sink), cx)
+    }))
+    .unwrap();
+
+    let buf = sink.into_inner().into_inner();
+    assert_eq!(buf.len(), 16 + 12 + 8);
+
+    let mut stream: RecordStream<_, Vec<u32>> =
+        RecordStream::new(AsyncXdrRecordReader::new(Cursor::new(buf)));
+    assert_eq!(next(&mut stream).unwrap().unwrap(), vec![1, 2]);
+    assert_eq!(next(&mut stream).unwrap().unwrap(), vec![3]);
+    // Undecodable records don't end the stream
+    assert!(next(&mut stream).unwrap().is_err());
+    assert!(next(&mut stream).is_none());
+    assert!(next(&mut stream).is_none());
+}
+
+#[test]
+fn stream_trailing() {
+    let buf = vec![
+        0x80, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0, 2, 0x80, 0, 0, 4, 0, 0, 0, 3,
+    ];
+
+    let mut stream: RecordStream<_, u32> =
+        RecordStream::new(AsyncXdrRecordReader::new(Cursor::new(buf)));
+    assert!(next(&mut stream).unwrap().is_err());
+    assert_eq!(next(&mut stream).unwrap().unwrap(), 3);
+    assert!(next(&mut stream).is_none());
+
+    // IO errors end the stream
+    let buf = vec![0x80, 0, 0, 8, 0, 0, 0, 1];
+    let mut stream: RecordStream<_, u32> =
+        RecordStream::new(AsyncXdrRecordReader::new(Cursor::new(buf)));
+    assert!(next(&mut stream).unwrap().is_err());
+    assert!(next(&mut stream).is_none());
+}
+
+#[test]
+fn sink_backpressure() {
+    let gate = Gate {
+        data: Vec::new(),
+        open: false,
+    };
+    let mut sink = RecordSink::new(AsyncXdrRecordWriter::new(gate));
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let mut sink = Pin::new(&mut sink);
+    assert!(Sink::<u32>::poll_ready(sink.as_mut(), &mut cx).is_ready());
+    sink.as_mut().start_send(1u32).unwrap();
+
+    // Not ready for another value until the first is written
+    assert!(Sink::<u32>::poll_ready(sink.as_mut(), &mut cx).is_pending());
+    assert!(Sink::<u32>::poll_ready(sink.as_mut(), &mut cx).is_pending());
+
+    sink.as_mut().get_mut().get_mut().get_mut().open = true;
+    match Sink::<u32>::poll_ready(sink.as_mut(), &mut cx) {
+        Poll::Ready(Ok(())) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    let buf = &sink.get_ref().get_ref().data;
+    assert_eq!(buf, &[0x80, 0, 0, 4, 0, 0, 0, 1]);
+}
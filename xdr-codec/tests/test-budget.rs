@@ -0,0 +1,99 @@
+extern crate xdr_codec;
+
+use std::io::Cursor;
+use std::thread;
+
+use xdr_codec::budget::{remaining, unpack_with_budget, with_budget, Budgeted};
+use xdr_codec::{pack, Error, ErrorKind, XdrString};
+
+#[test]
+fn budget_nested() {
+    let v = vec![vec![String::from("abcd"); 3]; 2];
+    let xv: Vec<Vec<XdrString>> = v
+        .iter()
+        .map(|inner| inner.iter().map(|s| XdrString::from(s.as_str())).collect())
+        .collect();
+    let mut buf = Vec::new();
+    pack(&xv, &mut buf).unwrap();
+
+    // Outer Vec, 2 inner Vecs, 6 strings
+    let need = 2 * 24 + 2 * 3 * 24 + 6 * 4;
+
+    let (got, _): (Vec<Vec<String>>, _) = unpack_with_budget(&mut &buf[..], need).unwrap();
+    assert_eq!(got, v);
+
+    match unpack_with_budget::<_, Vec<Vec<String>>>(&mut &buf[..], need - 1) {
+        Err(Error(ErrorKind::BudgetExceeded(4, 3), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    assert_eq!(remaining(), None);
+}
+
+#[test]
+fn budget_hostile_len() {
+    // Claims 4G elements, but there's no data
+    let buf = [0xffu8, 0xff, 0xff, 0xff];
+
+    match unpack_with_budget::<_, Vec<u64>>(&mut &buf[..], 1 << 20) {
+        Err(Error(ErrorKind::BudgetExceeded(..), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+}
+
+#[test]
+fn budget_scopes() {
+    with_budget(100, || {
+        assert_eq!(remaining(), Some(100));
+
+        let buf = [0u8, 0, 0, 8, 1, 2, 3, 4, 5, 6, 7, 8];
+        with_budget(1000, || {
+            assert_eq!(remaining(), Some(100));
+            let _: Vec<u8> = xdr_codec::unpack_opaque_flex(&mut &buf[..], None)
+                .unwrap()
+                .0;
+            assert_eq!(remaining(), Some(92));
+        });
+        assert_eq!(remaining(), Some(92));
+
+        let _ = ::std::panic::catch_unwind(|| with_budget(10, || panic!("oops")));
+        assert_eq!(remaining(), Some(92));
+    });
+    assert_eq!(remaining(), None);
+}
+
+#[test]
+fn budgeted() {
+    let mut buf = Vec::new();
+    pack(&vec![1u32, 2], &mut buf).unwrap();
+    pack(&vec![3u32], &mut buf).unwrap();
+    pack(&vec![4u32], &mut buf).unwrap();
+
+    // The budget goes with the input, to another thread and from one value to the next
+    let mut input = Budgeted::new(Cursor::new(buf), 12);
+    let mut input = thread::spawn(move || {
+        let (v, _): (Vec<u32>, _) = input.unpack().unwrap();
+        assert_eq!(v, vec![1, 2]);
+        input
+    })
+    .join()
+    .unwrap();
+    assert_eq!(input.remaining(), 4);
+    assert_eq!(remaining(), None);
+
+    let (v, _): (Vec<u32>, _) = input.unpack().unwrap();
+    assert_eq!(v, vec![3]);
+    match input.unpack::<Vec<u32>>() {
+        Err(Error(ErrorKind::BudgetExceeded(4, 0), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    // An enclosing scope limits it too, and is charged for it
+    let mut input = Budgeted::new(&[0u8, 0, 0, 1, 0, 0, 0, 5][..], 100);
+    with_budget(10, || {
+        let (v, _): (Vec<u32>, _) = input.unpack().unwrap();
+        assert_eq!(v, vec![5]);
+        assert_eq!(remaining(), Some(6));
+    });
+    assert_eq!(input.remaining(), 96);
+}
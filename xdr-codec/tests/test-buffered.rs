@@ -0,0 +1,126 @@
+#[macro_use]
+extern crate xdr_codec;
+
+use std::io::{self, BufRead, Read};
+
+use xdr_codec::buffered::{self, BufInput};
+use xdr_codec::record::XdrRecordReader;
+use xdr_codec::slice::SliceCursor;
+use xdr_codec::{unpack, Pack, XdrString};
+
+xdr_struct! {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Msg {
+        a: u32,
+        b: i64,
+        c: Vec<u32>,
+        d: XdrString,
+    }
+}
+
+// BufRead which hands out its data a few bytes at a time, and can't be read directly
+struct Chunked {
+    data: Vec<u8>,
+    pos: usize,
+    chunk: usize,
+}
+
+impl Read for Chunked {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        panic!("read() called")
+    }
+}
+
+impl BufRead for Chunked {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let end = std::cmp::min(self.pos + self.chunk, self.data.len());
+        Ok(&self.data[self.pos..end])
+    }
+
+    fn consume(&mut self, sz: usize) {
+        self.pos += sz;
+    }
+}
+
+fn chunked(data: Vec<u8>, chunk: usize) -> Chunked {
+    Chunked {
+        data,
+        pos: 0,
+        chunk,
+    }
+}
+
+fn packed() -> (Vec<u8>, Msg) {
+    let v = Msg {
+        a: 1,
+        b: -2,
+        c: vec![3, 4, 5],
+        d: XdrString::new("hello"),
+    };
+    let mut buf = Vec::new();
+    v.pack(&mut buf).unwrap();
+    (buf, v)
+}
+
+#[test]
+fn bufinput() {
+    let (buf, v) = packed();
+
+    // Values straddle the chunks for everything but 4
+    for chunk in 1..10 {
+        let mut input = BufInput::new(chunked(buf.clone(), chunk));
+        assert_eq!(unpack::<_, Msg>(&mut input).unwrap(), v);
+        assert!(input.into_inner().fill_buf().unwrap().is_empty());
+    }
+
+    let mut input = BufInput::new(chunked(buf[..10].to_vec(), 3));
+    let err = unpack::<_, Msg>(&mut input).unwrap_err();
+    match *err.root_kind() {
+        xdr_codec::ErrorKind::IOError(ref e) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+        ref e => panic!("unexpected error {:?}", e),
+    }
+}
+
+#[test]
+fn read_exact() {
+    let mut input = chunked(vec![1, 2, 3, 4, 5, 6, 7], 2);
+    let mut buf = [0; 5];
+    buffered::read_exact(&mut input, &mut buf).unwrap();
+    assert_eq!(buf, [1, 2, 3, 4, 5]);
+
+    let err = buffered::read_exact(&mut input, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(input.pos, 7);
+}
+
+#[test]
+fn record_fragments() {
+    let (buf, v) = packed();
+
+    // Split into fragments which break up values
+    let mut rec = Vec::new();
+    let mut chunks = buf.chunks(3).peekable();
+    while let Some(chunk) = chunks.next() {
+        let last = if chunks.peek().is_none() { 1 << 31 } else { 0 };
+        (last | chunk.len() as u32).pack(&mut rec).unwrap();
+        rec.extend_from_slice(chunk);
+    }
+
+    let mut input = XdrRecordReader::new(&rec[..]);
+    assert_eq!(unpack::<_, Msg>(&mut input).unwrap(), v);
+    assert!(input.eor());
+}
+
+#[test]
+fn slice_cursor() {
+    let (buf, v) = packed();
+
+    let mut input = SliceCursor::new(&buf);
+    assert_eq!(unpack::<_, Msg>(&mut input).unwrap(), v);
+    assert_eq!(input.remaining(), 0);
+
+    // A failed read doesn't move the cursor
+    let mut input = SliceCursor::new(&buf[..6]);
+    assert!(unpack::<_, Msg>(&mut input).is_err());
+    assert_eq!(input.position(), 4);
+}
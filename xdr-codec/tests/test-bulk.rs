@@ -0,0 +1,137 @@
+extern crate xdr_codec;
+
+use xdr_codec::bulk::{self, Bulk};
+use xdr_codec::canonical::{self, NanPolicy};
+use xdr_codec::{budget, pack, pack_flex, unpack_flex, Error, ErrorKind, Pack};
+
+// Bulk packing must give exactly the same encoding as packing element by element
+fn roundtrip<T>(val: Vec<T>)
+where
+    T: Bulk + Pack<Vec<u8>> + xdr_codec::Unpack<&'static [u8]> + PartialEq + std::fmt::Debug,
+{
+    let mut expected = Vec::new();
+    let esz = pack_flex(&val, None, &mut expected).unwrap();
+
+    let mut buf = Vec::new();
+    let sz = bulk::pack_flex(&val, None, &mut buf).unwrap();
+    assert_eq!(sz, esz);
+    assert_eq!(buf, expected);
+
+    let mut input = &buf[..];
+    let (v, usz) = bulk::unpack_flex::<_, T>(&mut input, None).unwrap();
+    assert_eq!(v, val);
+    assert_eq!(usz, sz);
+    assert!(input.is_empty());
+}
+
+#[test]
+fn bulk_roundtrip() {
+    for &n in &[0, 1, 5, 1023, 1024, 1025, 3000] {
+        roundtrip(
+            (0..n)
+                .map(|i| (i as u32).wrapping_mul(0x01010101))
+                .collect::<Vec<u32>>(),
+        );
+        roundtrip((0..n).map(|i| -(i as i32) * 7).collect::<Vec<i32>>());
+        roundtrip(
+            (0..n)
+                .map(|i| (i as u64) << 40 | i as u64)
+                .collect::<Vec<u64>>(),
+        );
+        roundtrip((0..n).map(|i| -(i as i64) << 33).collect::<Vec<i64>>());
+        roundtrip((0..n).map(|i| i as f32 / 3.0).collect::<Vec<f32>>());
+        roundtrip((0..n).map(|i| -(i as f64) / 7.0).collect::<Vec<f64>>());
+    }
+}
+
+#[test]
+fn bulk_slice() {
+    let mut buf = Vec::new();
+    assert_eq!(bulk::pack_i64_slice(&[-1, 2], &mut buf).unwrap(), 16);
+    assert_eq!(
+        buf,
+        vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 2]
+    );
+    assert_eq!(
+        bulk::unpack_i64_vec(2, &mut &buf[..]).unwrap(),
+        (vec![-1, 2], 16)
+    );
+
+    let mut buf = Vec::new();
+    assert_eq!(bulk::pack_f32_slice(&[1.0], &mut buf).unwrap(), 4);
+    assert_eq!(buf, vec![0x3f, 0x80, 0, 0]);
+}
+
+#[test]
+fn bulk_canonical() {
+    let val = vec![1.0f64, f64::from_bits(0xfff0_0000_0000_0001)];
+
+    let mut expected = Vec::new();
+    canonical::with_canonical(NanPolicy::Canonicalize, || pack(&val, &mut expected)).unwrap();
+    let mut buf = Vec::new();
+    canonical::with_canonical(NanPolicy::Canonicalize, || {
+        bulk::pack_flex(&val, None, &mut buf)
+    })
+    .unwrap();
+    assert_eq!(buf, expected);
+    assert_eq!(&buf[12..], &[0x7f, 0xf8, 0, 0, 0, 0, 0, 0]);
+
+    let mut buf = Vec::new();
+    let res =
+        canonical::with_canonical(NanPolicy::Reject, || bulk::pack_flex(&val, None, &mut buf));
+    match res {
+        Err(Error(ErrorKind::NonCanonical(_), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn bulk_maxsz() {
+    let val = vec![1u32, 2, 3];
+
+    let mut buf = Vec::new();
+    match bulk::pack_flex(&val, Some(2), &mut buf) {
+        Err(Error(ErrorKind::InvalidLen(2), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert!(buf.is_empty());
+
+    bulk::pack_flex(&val, Some(3), &mut buf).unwrap();
+    match bulk::unpack_flex::<_, u32>(&mut &buf[..], Some(2)) {
+        Err(Error(ErrorKind::InvalidLen(2), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    let (v, _) = unpack_flex::<_, u32>(&mut &buf[..], Some(3)).unwrap();
+    assert_eq!(v, val);
+}
+
+#[test]
+fn bulk_truncated() {
+    let val: Vec<u64> = (0..1000).collect();
+    let mut buf = Vec::new();
+    bulk::pack_flex(&val, None, &mut buf).unwrap();
+
+    buf.truncate(buf.len() - 1);
+    assert!(bulk::unpack_flex::<_, u64>(&mut &buf[..], None).is_err());
+
+    // A huge length with no data fails without allocating it all up front
+    let buf = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 1];
+    assert!(bulk::unpack_flex::<_, u32>(&mut &buf[..], None).is_err());
+}
+
+#[test]
+fn bulk_budget() {
+    let val: Vec<u32> = (0..100).collect();
+    let mut buf = Vec::new();
+    bulk::pack_flex(&val, None, &mut buf).unwrap();
+
+    let res = budget::with_budget(100, || bulk::unpack_flex::<_, u32>(&mut &buf[..], None));
+    match res {
+        Err(Error(ErrorKind::BudgetExceeded(..), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    let (v, _) =
+        budget::with_budget(1000, || bulk::unpack_flex::<_, u32>(&mut &buf[..], None)).unwrap();
+    assert_eq!(v, val);
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "bytes")]
+
+extern crate xdr_codec;
+
+use xdr_codec::bytes_buf::BytesPacker;
+use xdr_codec::record::XdrRecordReader;
+use xdr_codec::{pack, unpack, Error, ErrorKind, StatusUnion, XdrString};
+
+#[test]
+fn bytes_pack() {
+    let mut packer = BytesPacker::with_capacity(64);
+
+    let s = XdrString::new("hello");
+    let mut expect = Vec::new();
+    pack(&s, &mut expect).unwrap();
+
+    let a = packer.pack(&s).unwrap();
+    let b = packer.pack(&[1u32, 2][..]).unwrap();
+    assert_eq!(&a[..], &expect[..]);
+    assert_eq!(&b[..], &[0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2]);
+
+    // Both share the packer's allocation
+    assert_eq!(b.as_ptr(), a[a.len()..].as_ptr());
+    assert_eq!(packer.capacity(), 64 - a.len() - b.len());
+
+    // Once they're dropped, the space is reused
+    let ptr = a.as_ptr();
+    drop(a);
+    drop(b);
+    packer.reserve(64);
+    assert_eq!(packer.pack(&1u32).unwrap().as_ptr(), ptr);
+}
+
+#[test]
+fn bytes_pack_fail() {
+    let mut packer = BytesPacker::new();
+
+    // Fails after packing the length and first element
+    let bad = vec![StatusUnion::<u32, ()>::ok(1), StatusUnion::err(0, ())];
+    match packer.pack(&bad) {
+        Err(Error(ErrorKind::InvalidCase(0), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    match packer.pack_record(&bad) {
+        Err(Error(ErrorKind::InvalidCase(0), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    assert_eq!(&packer.pack(&7u32).unwrap()[..], &[0, 0, 0, 7]);
+}
+
+#[test]
+fn bytes_pack_record() {
+    let mut packer = BytesPacker::new();
+
+    let mut stream = Vec::new();
+    for i in 0..3u32 {
+        let rec = packer.pack_record(&vec![i; i as usize]).unwrap();
+        assert_eq!(rec.len(), 4 + 4 + 4 * i as usize);
+        stream.extend_from_slice(&rec);
+    }
+
+    let mut rd = XdrRecordReader::new(&stream[..]);
+    for i in 0..3u32 {
+        assert_eq!(unpack::<_, Vec<u32>>(&mut rd).unwrap(), vec![i; i as usize]);
+        assert!(rd.eor());
+    }
+}
@@ -0,0 +1,56 @@
+extern crate xdr_codec;
+
+use std::collections::HashSet;
+
+use xdr_codec::canonical::{nan_policy, pack_canonical, with_canonical, NanPolicy};
+use xdr_codec::{pack, ErrorKind};
+
+#[test]
+fn canonical_set() {
+    let set: HashSet<Vec<u32>> = vec![vec![1, 2], vec![7], vec![], vec![5]]
+        .into_iter()
+        .collect();
+
+    let mut buf = Vec::new();
+    pack_canonical(&set, &mut buf).unwrap();
+
+    // Sorted by encoding, so shorter arrays come first
+    let mut expect = Vec::new();
+    pack(&vec![vec![], vec![5u32], vec![7], vec![1, 2]], &mut expect).unwrap();
+    assert_eq!(buf, expect);
+
+    // Nested sets are sorted too
+    let nested: Vec<HashSet<u32>> = vec![vec![2, 1].into_iter().collect()];
+    let mut buf = Vec::new();
+    pack_canonical(&nested, &mut buf).unwrap();
+    assert_eq!(buf, &[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2]);
+}
+
+#[test]
+fn canonical_nan() {
+    let nan = f64::from_bits(0x7ff8_0000_dead_beef);
+
+    let mut buf = Vec::new();
+    pack(&nan, &mut buf).unwrap();
+    assert_eq!(buf, &[0x7f, 0xf8, 0, 0, 0xde, 0xad, 0xbe, 0xef]);
+
+    let mut buf = Vec::new();
+    pack_canonical(&nan, &mut buf).unwrap();
+    assert_eq!(buf, &[0x7f, 0xf8, 0, 0, 0, 0, 0, 0]);
+
+    // Other values are unchanged
+    let mut buf = Vec::new();
+    pack_canonical(&vec![-0.0f32, 1.5], &mut buf).unwrap();
+    assert_eq!(buf, &[0, 0, 0, 2, 0x80, 0, 0, 0, 0x3f, 0xc0, 0, 0]);
+
+    with_canonical(NanPolicy::Reject, || {
+        assert_eq!(nan_policy(), Some(NanPolicy::Reject));
+        match pack(&nan, &mut Vec::new()) {
+            Err(xdr_codec::Error(ErrorKind::NonCanonical(_), _)) => (),
+            res => panic!("unexpected {:?}", res),
+        }
+        assert!(pack(&(-::std::f32::NAN), &mut Vec::new()).is_err());
+        assert!(pack(&::std::f32::NAN, &mut Vec::new()).is_ok());
+    });
+    assert_eq!(nan_policy(), None);
+}
@@ -0,0 +1,82 @@
+#![cfg(any(feature = "deflate", feature = "zstd"))]
+extern crate xdr_codec;
+
+use std::io::{self, Cursor, Read, Write};
+
+use xdr_codec::compress::{CompressedRecordReader, CompressedRecordWriter, Compression};
+use xdr_codec::record::XdrRecordReader;
+
+fn roundtrip(compression: Compression) {
+    let big = vec![0x5a; 10000];
+
+    let mut cw = CompressedRecordWriter::new(Vec::new(), compression);
+    cw.write_all(&big).unwrap();
+    cw.end_record().unwrap();
+    cw.end_record().unwrap();
+    assert_eq!(cw.pack_record(&vec![1u32, 2, 3]).unwrap(), 16);
+    write!(cw, "unterminated").unwrap();
+
+    let buf = cw.into_inner().unwrap();
+
+    // Each record is compressed separately
+    let raw: Vec<_> = XdrRecordReader::new(Cursor::new(buf.clone()))
+        .into_iter()
+        .map(|r| r.expect("IO error"))
+        .collect();
+    assert_eq!(raw.len(), 4);
+    assert!(raw[0].len() < 1000);
+
+    let mut cr = CompressedRecordReader::new(Cursor::new(buf.clone()), compression);
+    assert_eq!(cr.read_record().unwrap(), Some(big));
+    assert_eq!(cr.read_record().unwrap(), Some(vec![]));
+    assert_eq!(cr.unpack_record::<Vec<u32>>().unwrap(), Some(vec![1, 2, 3]));
+
+    assert!(cr.next_record().unwrap());
+    let mut s = String::new();
+    cr.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "unterminated");
+
+    assert_eq!(cr.read_record().unwrap(), None);
+
+    // Decompressed size is limited
+    let mut cr = CompressedRecordReader::new(Cursor::new(buf), compression);
+    cr.set_max_record(9999);
+    assert_eq!(
+        cr.read_record().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
+
+fn unused(compression: Compression) {
+    let mut buf = Vec::new();
+    {
+        let _ = CompressedRecordWriter::new(&mut buf, compression);
+    }
+    assert!(buf.is_empty());
+
+    let cw = CompressedRecordWriter::new(Vec::new(), compression);
+    assert!(cw.into_inner().unwrap().is_empty());
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn deflate() {
+    roundtrip(Compression::Deflate(6));
+    unused(Compression::Deflate(6));
+
+    // Not deflate data
+    let buf = vec![128, 0, 0, 4, 0xff, 0xff, 0xff, 0xff];
+    let mut cr = CompressedRecordReader::new(Cursor::new(buf), Compression::Deflate(6));
+    assert!(cr.read_record().is_err());
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd() {
+    roundtrip(Compression::Zstd(3));
+    unused(Compression::Zstd(3));
+
+    let buf = vec![128, 0, 0, 4, 0xff, 0xff, 0xff, 0xff];
+    let mut cr = CompressedRecordReader::new(Cursor::new(buf), Compression::Zstd(3));
+    assert!(cr.read_record().is_err());
+}
@@ -0,0 +1,22 @@
+extern crate xdr_codec;
+
+use xdr_codec::conformance::{self, Vector};
+
+#[test]
+fn conformance_vectors() {
+    conformance::check_all().unwrap();
+}
+
+#[test]
+fn conformance_vector_sizes() {
+    fn aligned<T>(vectors: &[Vector<T>]) -> bool {
+        vectors.iter().all(|v| v.encoded.len() % 4 == 0)
+    }
+
+    assert!(aligned(conformance::INTS));
+    assert!(aligned(conformance::FIXED_OPAQUES));
+    assert!(aligned(conformance::OPAQUES));
+    assert!(aligned(conformance::STRINGS));
+    assert!(aligned(conformance::STRUCTS));
+    assert!(aligned(conformance::UNIONS));
+}
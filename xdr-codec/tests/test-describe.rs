@@ -0,0 +1,116 @@
+extern crate xdr_codec;
+
+use xdr_codec::describe::{Case, Describe, Descriptor, EnumValue, Field, Kind, Size, TypeDesc};
+
+// struct list { int v; list *next; };
+struct List;
+
+impl Describe for List {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "list",
+            kind: Kind::Struct(vec![
+                Field {
+                    name: "v",
+                    ty: TypeDesc::Int,
+                },
+                Field {
+                    name: "next",
+                    ty: TypeDesc::Option(Box::new(TypeDesc::Named("list", List::describe))),
+                },
+            ]),
+        }
+    }
+}
+
+// enum color { RED = 1, GREEN = 2 };
+struct Color;
+
+impl Describe for Color {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "color",
+            kind: Kind::Enum(vec![
+                EnumValue {
+                    name: "RED",
+                    value: 1,
+                },
+                EnumValue {
+                    name: "GREEN",
+                    value: 2,
+                },
+            ]),
+        }
+    }
+}
+
+// union reply switch (int status) { case 0: opaque data<16>; case 1: void; default: void; };
+struct Reply;
+
+impl Describe for Reply {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "reply",
+            kind: Kind::Union {
+                selector: Field {
+                    name: "status",
+                    ty: TypeDesc::Int,
+                },
+                cases: vec![
+                    Case {
+                        value: Some(0),
+                        variant: "Const0",
+                        field: Some(Field {
+                            name: "data",
+                            ty: TypeDesc::Opaque(Size::Flex(Some(16))),
+                        }),
+                    },
+                    Case {
+                        value: Some(1),
+                        variant: "Const1",
+                        field: None,
+                    },
+                    Case {
+                        value: None,
+                        variant: "default",
+                        field: None,
+                    },
+                ],
+            },
+        }
+    }
+}
+
+#[test]
+fn describe_struct() {
+    let desc = List::describe();
+    assert_eq!(desc.name, "list");
+    assert!(desc.field("v").is_some());
+    assert!(desc.field("w").is_none());
+
+    // Recursive types are described on demand
+    let next = desc.field("next").unwrap();
+    match next.ty {
+        TypeDesc::Option(ref ty) => assert_eq!(ty.descriptor().unwrap().name, "list"),
+        ref ty => panic!("unexpected {:?}", ty),
+    }
+    assert!(TypeDesc::Int.descriptor().is_none());
+}
+
+#[test]
+fn describe_enum() {
+    let desc = Color::describe();
+    assert_eq!(desc.enum_name(2), Some("GREEN"));
+    assert_eq!(desc.enum_name(3), None);
+    assert!(desc.case(1).is_none());
+}
+
+#[test]
+fn describe_union() {
+    let desc = Reply::describe();
+    assert_eq!(desc.case(0).unwrap().variant, "Const0");
+    assert!(desc.case(1).unwrap().field.is_none());
+    assert_eq!(desc.case(7).unwrap().variant, "default");
+    assert_eq!(desc.case(7).unwrap().value, None);
+    assert!(desc.field("status").is_none());
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "framed")]
+// Don't rustfmt in here to avoid trashing vec![] formatting
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+extern crate xdr_codec;
+
+use std::io::{self, Cursor, Read, Write};
+
+use xdr_codec::framed::{Checksum, FramedReader, FramedWriter};
+
+#[test]
+fn framed_crc32() {
+    let mut fw = FramedWriter::new(Vec::new(), Checksum::Crc32);
+
+    assert_eq!(write!(fw, "hello").unwrap(), ());
+    fw.end_record().unwrap();
+    assert_eq!(fw.pack_record(&0x1234u32).unwrap(), 4);
+
+    let buf = fw.into_inner().unwrap();
+    assert_eq!(buf, vec![128, 0, 0, 9, 104, 101, 108, 108, 111, 0x36, 0x10, 0xa6, 0x86,
+                         128, 0, 0, 8, 0, 0, 0x12, 0x34, 0x78, 0x04, 0x5b, 0x7a]);
+
+    let mut fr = FramedReader::new(Cursor::new(buf), Checksum::Crc32);
+    let mut rec = Vec::new();
+
+    assert!(fr.next_record().unwrap());
+    assert_eq!(fr.read_to_end(&mut rec).unwrap(), 5);
+    assert_eq!(rec, b"hello");
+    assert_eq!(fr.unpack_record::<u32>().unwrap(), Some(0x1234));
+    assert_eq!(fr.read_record().unwrap(), None);
+}
+
+#[test]
+fn framed_drop() {
+    let mut buf = Vec::new();
+    {
+        let _ = FramedWriter::new(&mut buf, Checksum::Crc32);
+    }
+    // Nothing is sent for an unused writer
+    assert_eq!(buf, vec![]);
+
+    {
+        let mut fw = FramedWriter::new(&mut buf, Checksum::Crc32);
+        assert_eq!(write!(fw, "hello").unwrap(), ());
+    }
+    let mut fr = FramedReader::new(Cursor::new(buf), Checksum::Crc32);
+    assert_eq!(fr.read_record().unwrap(), Some(b"hello".to_vec()));
+    assert_eq!(fr.read_record().unwrap(), None);
+}
+
+#[test]
+fn framed_corrupt() {
+    let good = vec![128, 0, 0, 9, 104, 101, 108, 108, 111, 0x36, 0x10, 0xa6, 0x86];
+
+    let read = |buf: Vec<u8>, max| {
+        let mut fr = FramedReader::new(Cursor::new(buf), Checksum::Crc32);
+        fr.set_max_record(max);
+        fr.read_record()
+    };
+
+    assert_eq!(read(good.clone(), 5).unwrap(), Some(b"hello".to_vec()));
+    assert_eq!(read(good.clone(), 4).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut bad = good.clone();
+    bad[5] ^= 1;
+    assert_eq!(read(bad, 5).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let short = vec![128, 0, 0, 2, 1, 2];
+    assert_eq!(read(short, 5).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let unterminated = good[..10].to_vec();
+    assert_eq!(read(unterminated, 5).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn framed_trailing() {
+    let mut fw = FramedWriter::new(Vec::new(), Checksum::Crc32);
+    fw.pack_record(&vec![1u32, 2]).unwrap();
+
+    let buf = fw.into_inner().unwrap();
+    let mut fr = FramedReader::new(Cursor::new(buf), Checksum::Crc32);
+    assert!(fr.unpack_record::<u32>().is_err());
+}
+
+#[cfg(feature = "xxhash")]
+#[test]
+fn framed_xxh64() {
+    let mut fw = FramedWriter::new(Vec::new(), Checksum::Xxh64);
+    assert_eq!(write!(fw, "hello").unwrap(), ());
+
+    let buf = fw.into_inner().unwrap();
+    assert_eq!(buf.len(), 4 + 5 + 8);
+
+    let mut fr = FramedReader::new(Cursor::new(buf.clone()), Checksum::Xxh64);
+    assert_eq!(fr.read_record().unwrap(), Some(b"hello".to_vec()));
+
+    let mut fr = FramedReader::new(Cursor::new(buf), Checksum::Crc32);
+    assert_eq!(fr.read_record().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[cfg(feature = "hmac")]
+#[test]
+fn framed_hmac() {
+    use xdr_codec::framed::HmacSha256;
+
+    // RFC 4231 test case 2
+    let mut fw = FramedWriter::new(Vec::new(), HmacSha256::new(b"Jefe"));
+    assert_eq!(write!(fw, "what do ya want for nothing?").unwrap(), ());
+
+    let buf = fw.into_inner().unwrap();
+    assert_eq!(buf[..4], [128, 0, 0, 28 + 32]);
+    assert_eq!(buf[32..], [0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26,
+                           0x08, 0x95, 0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83,
+                           0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec, 0x38, 0x43]);
+
+    let mut fr = FramedReader::new(Cursor::new(buf.clone()), HmacSha256::new(b"Jefe"));
+    assert_eq!(fr.read_record().unwrap(), Some(b"what do ya want for nothing?".to_vec()));
+
+    // Wrong key
+    let mut fr = FramedReader::new(Cursor::new(buf.clone()), HmacSha256::new(b"Jeff"));
+    assert_eq!(fr.read_record().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    // Tampered payload
+    let mut bad = buf;
+    bad[4] ^= 1;
+    let mut fr = FramedReader::new(Cursor::new(bad), HmacSha256::new(b"Jefe"));
+    assert_eq!(fr.read_record().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    // The key is kept out of debug output
+    assert_eq!(format!("{:?}", HmacSha256::new(b"Jefe")), "HmacSha256");
+}
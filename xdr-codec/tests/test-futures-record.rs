@@ -0,0 +1,216 @@
+#![cfg(feature = "futures-io")]
+extern crate futures_io;
+extern crate xdr_codec;
+
+use std::future::{poll_fn, Future};
+use std::io;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+use xdr_codec::futures_record::{FuturesXdrRecordReader, FuturesXdrRecordWriter};
+use xdr_codec::record::EmptyFragments;
+
+// Minimal executor; none of the IO here needs real wakeups.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+            return v;
+        }
+    }
+}
+
+fn write_all<W: AsyncWrite + Unpin>(w: &mut W, mut data: &[u8]) -> io::Result<()> {
+    block_on(poll_fn(|cx| {
+        while !data.is_empty() {
+            let n = match Pin::new(&mut *w).poll_write(cx, data) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => return Poll::Pending,
+            };
+            data = &data[n..];
+        }
+        Poll::Ready(Ok(()))
+    }))
+}
+
+fn close<W: AsyncWrite + Unpin>(w: &mut W) -> io::Result<()> {
+    block_on(poll_fn(|cx| Pin::new(&mut *w).poll_close(cx)))
+}
+
+fn read<R: AsyncRead + Unpin>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    block_on(poll_fn(|cx| Pin::new(&mut *r).poll_read(cx, buf)))
+}
+
+// Reader/writer which alternates between Pending and transferring a single byte.
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+    ready: bool,
+}
+
+impl Trickle {
+    fn new(data: Vec<u8>) -> Trickle {
+        Trickle {
+            data,
+            pos: 0,
+            ready: false,
+        }
+    }
+
+    fn tick(&mut self, cx: &mut Context) -> bool {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+        }
+        self.ready
+    }
+}
+
+impl AsyncWrite for Trickle {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if !this.tick(cx) {
+            return Poll::Pending;
+        }
+        this.data.push(buf[0]);
+        Poll::Ready(Ok(1))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        if !self.get_mut().tick(cx) {
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = {
+            let data = match self.as_mut().poll_fill_buf(cx) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => return Poll::Pending,
+            };
+            buf[..data.len()].copy_from_slice(data);
+            data.len()
+        };
+        self.consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncBufRead for Trickle {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if !this.tick(cx) {
+            return Poll::Pending;
+        }
+        let end = std::cmp::min(this.pos + 1, this.data.len());
+        Poll::Ready(Ok(&this.data[this.pos..end]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().pos += amt;
+    }
+}
+
+#[test]
+fn futures_recread() {
+    let inbuf = vec![0, 0, 0, 5, 0, 1, 2, 3, 4, 128, 0, 0, 5, 5, 6, 7, 8, 9];
+    let mut recread = FuturesXdrRecordReader::new(&inbuf[..]);
+    let mut buf = vec![0; 10];
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 5);
+    assert_eq!(buf, vec![0, 1, 2, 3, 4, 0, 0, 0, 0, 0]);
+    assert!(!recread.eor());
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 5);
+    assert_eq!(buf, vec![5, 6, 7, 8, 9, 0, 0, 0, 0, 0]);
+    assert!(recread.eor());
+
+    assert_eq!(read(&mut recread, &mut buf).unwrap(), 0);
+    assert!(recread.into_inner().is_empty());
+}
+
+#[test]
+fn futures_read_records() {
+    let inbuf = vec![
+        0, 0, 0, 5, 0, 1, 2, 3, 4, 128, 0, 0, 5, 5, 6, 7, 8, 9, 128, 0, 0, 1, 99, 0, 0, 0, 0, 128,
+        0, 0, 0,
+    ];
+    let expected = vec![vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9], vec![99], vec![]];
+
+    let mut recread = FuturesXdrRecordReader::new(&inbuf[..]);
+    let mut got = Vec::new();
+    while let Some(rec) = block_on(recread.read_record()).unwrap() {
+        got.push(rec);
+    }
+    assert_eq!(got, expected);
+
+    let mut recread = FuturesXdrRecordReader::new(Trickle::new(inbuf));
+    let mut got = Vec::new();
+    while let Some(rec) = block_on(recread.read_record()).unwrap() {
+        got.push(rec);
+    }
+    assert_eq!(got, expected);
+
+    let inbuf = vec![0, 0, 0, 2, 1, 2];
+    let mut recread = FuturesXdrRecordReader::new(&inbuf[..]);
+    let err = block_on(recread.read_record()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn futures_empty_fragments() {
+    let inbuf = vec![0, 0, 0, 0, 128, 0, 0, 1, 3, 128, 0, 0, 0];
+
+    let mut recread = FuturesXdrRecordReader::new(&inbuf[..]);
+    recread.set_empty_fragments(EmptyFragments::Coalesce);
+    assert_eq!(block_on(recread.read_record()).unwrap(), Some(vec![3]));
+    assert_eq!(block_on(recread.read_record()).unwrap(), None);
+}
+
+#[test]
+fn futures_largerec() {
+    let expected = vec![
+        0, 0, 0, 3, 104, 101, 108, 128, 0, 0, 2, 108, 111, 0, 0, 0, 2, 97, 98, 128, 0, 0, 0,
+    ];
+
+    let mut xw = FuturesXdrRecordWriter::with_buffer(Vec::new(), 3);
+    write_all(&mut xw, b"hello").unwrap();
+    block_on(xw.end_record()).unwrap();
+    write_all(&mut xw, b"ab").unwrap();
+    block_on(poll_fn(|cx| Pin::new(&mut xw).poll_flush(cx))).unwrap();
+    close(&mut xw).unwrap();
+    assert_eq!(xw.into_inner(), expected);
+
+    let mut xw = FuturesXdrRecordWriter::with_buffer(Trickle::new(Vec::new()), 3);
+    write_all(&mut xw, b"hello").unwrap();
+    block_on(xw.end_record()).unwrap();
+    write_all(&mut xw, b"ab").unwrap();
+    close(&mut xw).unwrap();
+    // Closing sends the unflushed data as the last fragment
+    assert_eq!(
+        xw.get_ref().data,
+        vec![0, 0, 0, 3, 104, 101, 108, 128, 0, 0, 2, 108, 111, 128, 0, 0, 2, 97, 98]
+    );
+
+    // Nothing more to send once the record has been ended
+    let mut xw = FuturesXdrRecordWriter::new(Vec::new());
+    write_all(&mut xw, b"hi").unwrap();
+    block_on(xw.end_record()).unwrap();
+    close(&mut xw).unwrap();
+    assert_eq!(xw.get_ref(), &vec![128, 0, 0, 2, 104, 105]);
+}
@@ -0,0 +1,81 @@
+extern crate xdr_codec;
+
+use xdr_codec::header::{unpack_header, UnpackHeader};
+use xdr_codec::{pack, unpack, Read, Result, Unpack};
+
+#[derive(Debug, PartialEq)]
+struct Header {
+    xid: u32,
+    proc_: u32,
+}
+
+impl<In: Read> Unpack<In> for Header {
+    fn unpack(input: &mut In) -> Result<(Header, usize)> {
+        let (xid, xsz) = Unpack::unpack(input)?;
+        let (proc_, psz) = Unpack::unpack(input)?;
+        Ok((Header { xid, proc_ }, xsz + psz))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Call {
+    xid: u32,
+    proc_: u32,
+    args: Vec<u8>,
+}
+
+impl<In: Read> Unpack<In> for Call {
+    fn unpack(input: &mut In) -> Result<(Call, usize)> {
+        let (hdr, hsz) = Call::unpack_header(input)?;
+        let (call, sz) = Call::unpack_rest(hdr, input)?;
+        Ok((call, hsz + sz))
+    }
+}
+
+impl<In: Read> UnpackHeader<In> for Call {
+    type Header = Header;
+
+    fn unpack_rest(hdr: Header, input: &mut In) -> Result<(Call, usize)> {
+        let (args, sz) = xdr_codec::unpack_opaque_flex(input, None)?;
+        Ok((
+            Call {
+                xid: hdr.xid,
+                proc_: hdr.proc_,
+                args,
+            },
+            sz,
+        ))
+    }
+}
+
+fn call() -> Vec<u8> {
+    let mut buf = Vec::new();
+    pack(&9u32, &mut buf).unwrap();
+    pack(&3u32, &mut buf).unwrap();
+    pack(&xdr_codec::Opaque::borrowed(b"hello"), &mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn header_then_rest() {
+    let buf = call();
+
+    let (hdr, rest) = unpack_header::<Call>(&buf).unwrap();
+    assert_eq!(hdr, Header { xid: 9, proc_: 3 });
+    assert_eq!(rest, &buf[8..]);
+
+    let mut input = rest;
+    let (call, sz) = Call::unpack_rest(hdr, &mut input).unwrap();
+    assert_eq!(sz, 12);
+    assert!(input.is_empty());
+    assert_eq!(call, unpack::<_, Call>(&mut &buf[..]).unwrap());
+    assert_eq!(call.args, b"hello");
+}
+
+#[test]
+fn header_short() {
+    let buf = call();
+    assert!(unpack_header::<Call>(&buf[..6]).is_err());
+    // The header doesn't need the rest to be there
+    assert!(unpack_header::<Call>(&buf[..8]).is_ok());
+}
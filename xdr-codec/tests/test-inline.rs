@@ -0,0 +1,74 @@
+#![cfg(any(feature = "smallvec", feature = "arrayvec"))]
+
+extern crate xdr_codec;
+
+#[cfg(feature = "arrayvec")]
+use xdr_codec::inline::ArrayVec;
+#[cfg(feature = "smallvec")]
+use xdr_codec::inline::{unpack_smallvec, SmallVec};
+use xdr_codec::{pack, unpack, ErrorKind, Skip};
+
+#[cfg(feature = "smallvec")]
+#[test]
+fn smallvec_roundtrip() {
+    let v: SmallVec<[u32; 2]> = SmallVec::from_slice(&[1, 2, 3]);
+    let mut buf = Vec::new();
+    pack(&v, &mut buf).unwrap();
+
+    let mut expect = Vec::new();
+    pack(&vec![1u32, 2, 3], &mut expect).unwrap();
+    assert_eq!(buf, expect);
+
+    // Spills to the heap past the inline capacity
+    let u: SmallVec<[u32; 2]> = unpack(&mut &buf[..]).unwrap();
+    assert_eq!(u, v);
+    assert!(u.spilled());
+
+    let u: SmallVec<[u32; 4]> = unpack(&mut &buf[..]).unwrap();
+    assert_eq!(&u[..], &[1, 2, 3]);
+    assert!(!u.spilled());
+
+    assert_eq!(SmallVec::<[u32; 4]>::skip(&mut &buf[..]).unwrap(), buf.len());
+
+    match unpack_smallvec::<_, u32, 4>(&mut &buf[..], Some(2)) {
+        Err(xdr_codec::Error(ErrorKind::InvalidLen(2), _)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+}
+
+#[cfg(feature = "smallvec")]
+#[test]
+fn smallvec_budget() {
+    use xdr_codec::budget::unpack_with_budget;
+
+    let mut buf = Vec::new();
+    pack(&vec![1u32, 2, 3], &mut buf).unwrap();
+
+    // Inline elements aren't charged
+    assert!(unpack_with_budget::<_, SmallVec<[u32; 4]>>(&mut &buf[..], 0).is_ok());
+    assert!(unpack_with_budget::<_, SmallVec<[u32; 2]>>(&mut &buf[..], 11).is_err());
+    assert!(unpack_with_budget::<_, SmallVec<[u32; 2]>>(&mut &buf[..], 12).is_ok());
+}
+
+#[cfg(feature = "arrayvec")]
+#[test]
+fn arrayvec_roundtrip() {
+    let mut v = ArrayVec::<u32, 3>::new();
+    v.push(1);
+    v.push(2);
+
+    let mut buf = Vec::new();
+    pack(&v, &mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2]);
+
+    assert_eq!(unpack::<_, ArrayVec<u32, 3>>(&mut &buf[..]).unwrap(), v);
+    assert_eq!(unpack::<_, ArrayVec<u32, 2>>(&mut &buf[..]).unwrap(), v[..]);
+    assert_eq!(ArrayVec::<u32, 2>::skip(&mut &buf[..]).unwrap(), buf.len());
+
+    // The capacity is also the limit
+    match unpack::<_, ArrayVec<u32, 1>>(&mut &buf[..]) {
+        Err(xdr_codec::Error(ErrorKind::InvalidLen(1), _)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+    assert!(ArrayVec::<u32, 1>::skip(&mut &buf[..]).is_err());
+}
@@ -0,0 +1,67 @@
+extern crate xdr_codec;
+
+use std::io::Cursor;
+
+use xdr_codec::lazy::Lazy;
+use xdr_codec::{pack, skip_opaque_flex, unpack_opaque_flex, Pack, Unpack};
+
+fn opaque(input: &mut &[u8]) -> xdr_codec::Result<(Vec<u8>, usize)> {
+    unpack_opaque_flex(input, Some(8))
+}
+
+#[test]
+fn lazy_capture() {
+    let mut buf = Vec::new();
+    pack(&xdr_codec::Opaque::borrowed(b"hello"), &mut buf).unwrap();
+    pack(&7u32, &mut buf).unwrap();
+
+    // Captured from a stream, not just a slice
+    let mut input = Cursor::new(buf.clone());
+    let (lazy, sz) =
+        Lazy::capture(&mut input, |input| skip_opaque_flex(input, Some(8)), opaque).unwrap();
+    assert_eq!(sz, 12);
+    assert_eq!(lazy.raw(), &buf[..12]);
+    assert_eq!(u32::unpack(&mut input).unwrap(), (7, 4));
+
+    assert_eq!(format!("{:?}", lazy), "Lazy(<12 bytes>)");
+    let copy = lazy.clone();
+    assert_eq!(lazy.get().unwrap(), b"hello");
+    assert_eq!(format!("{:?}", lazy), "Lazy([104, 101, 108, 108, 111])");
+    assert!(!copy.is_decoded());
+    assert_eq!(copy, lazy);
+    assert_eq!(copy.into_inner().unwrap(), b"hello");
+
+    let mut out = Vec::new();
+    assert_eq!(lazy.pack(&mut out).unwrap(), 12);
+    assert_eq!(out, &buf[..12]);
+}
+
+#[test]
+fn lazy_limits() {
+    let mut buf = Vec::new();
+    pack(&xdr_codec::Opaque::borrowed(b"too long!"), &mut buf).unwrap();
+
+    // Skipping checks the length, so it fails straight away
+    let res = Lazy::capture(
+        &mut &buf[..],
+        |input| skip_opaque_flex(input, Some(8)),
+        opaque,
+    );
+    assert!(res.is_err());
+
+    // Decoding it later does too
+    let lazy = Lazy::from_raw(buf, opaque);
+    assert!(lazy.get().is_err());
+    assert!(!lazy.is_decoded());
+}
+
+#[test]
+fn lazy_new() {
+    let lazy = Lazy::new(vec![1u32, 2]).unwrap();
+    assert!(lazy.is_decoded());
+    assert_eq!(lazy.raw(), &[0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2]);
+    assert_eq!(
+        lazy,
+        Lazy::from_raw(lazy.raw().to_vec(), |input| Unpack::unpack(input))
+    );
+}
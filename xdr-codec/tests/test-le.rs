@@ -0,0 +1,233 @@
+// Don't rustfmt in here to avoid trashing vec![] formatting
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+extern crate xdr_codec;
+
+use std::thread;
+
+use xdr_codec::describe::{Case, Describe, Descriptor, EnumValue, Field, Kind, Size, TypeDesc};
+use xdr_codec::le;
+use xdr_codec::record::{XdrRecordReader, XdrRecordWriter};
+use xdr_codec::{pack, unpack, Error, ErrorKind, Pack, Quadruple, Read, Result, Unpack, Write,
+                XdrString};
+
+fn field(name: &'static str, ty: TypeDesc) -> Field {
+    Field { name, ty }
+}
+
+// enum color { RED = 1, GREEN = 2 };
+fn color() -> Descriptor {
+    Descriptor {
+        name: "color",
+        kind: Kind::Enum(vec![
+            EnumValue { name: "RED", value: 1 },
+            EnumValue { name: "GREEN", value: 2 },
+        ]),
+    }
+}
+
+// union shade switch (color c) { case RED: hyper h; case GREEN: void; };
+fn shade() -> Descriptor {
+    Descriptor {
+        name: "shade",
+        kind: Kind::Union {
+            selector: field("c", TypeDesc::Named("color", color)),
+            cases: vec![
+                Case { value: Some(1), variant: "RED", field: Some(field("h", TypeDesc::Hyper)) },
+                Case { value: Some(2), variant: "GREEN", field: None },
+            ],
+        },
+    }
+}
+
+// struct all { unsigned a; int b; unsigned hyper c; bool d; float e; double f;
+//              quadruple q; opaque fix[3]; string s<8>; int *o; unsigned v<>;
+//              shade sh[2]; color col; };
+fn all() -> Descriptor {
+    Descriptor {
+        name: "all",
+        kind: Kind::Struct(vec![
+            field("a", TypeDesc::UInt),
+            field("b", TypeDesc::Int),
+            field("c", TypeDesc::UHyper),
+            field("d", TypeDesc::Bool),
+            field("e", TypeDesc::Float),
+            field("f", TypeDesc::Double),
+            field("q", TypeDesc::Quadruple),
+            field("fix", TypeDesc::Opaque(Size::Fixed(3))),
+            field("s", TypeDesc::String(Size::Flex(Some(8)))),
+            field("o", TypeDesc::Option(Box::new(TypeDesc::Int))),
+            field("v", TypeDesc::Array(Box::new(TypeDesc::UInt), Size::Flex(None))),
+            field("sh", TypeDesc::Array(Box::new(TypeDesc::Named("shade", shade)), Size::Fixed(2))),
+            field("col", TypeDesc::Named("color", color)),
+        ]),
+    }
+}
+
+const BIG: &[u8] = &[
+    1, 2, 3, 4,
+    0xff, 0xff, 0xff, 0xfe,
+    1, 2, 3, 4, 5, 6, 7, 8,
+    0, 0, 0, 1,
+    0x3f, 0x80, 0, 0,
+    0x3f, 0xf0, 0, 0, 0, 0, 0, 0,
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+    b'x', b'y', b'z', 0,
+    0, 0, 0, 5, b'a', b'b', b'c', b'd', b'e', 0, 0, 0,
+    0, 0, 0, 1, 0, 0, 0, 7,
+    0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2,
+    0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 9,
+    0, 0, 0, 2,
+    0, 0, 0, 2,
+];
+
+// Integers are reversed; opaque bytes, strings and padding are unchanged
+const LITTLE: &[u8] = &[
+    4, 3, 2, 1,
+    0xfe, 0xff, 0xff, 0xff,
+    8, 7, 6, 5, 4, 3, 2, 1,
+    1, 0, 0, 0,
+    0, 0, 0x80, 0x3f,
+    0, 0, 0, 0, 0, 0, 0xf0, 0x3f,
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+    b'x', b'y', b'z', 0,
+    5, 0, 0, 0, b'a', b'b', b'c', b'd', b'e', 0, 0, 0,
+    1, 0, 0, 0, 7, 0, 0, 0,
+    2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0,
+    1, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0,
+    2, 0, 0, 0,
+    2, 0, 0, 0,
+];
+
+#[test]
+fn le_convert() {
+    let mut out = Vec::new();
+    assert_eq!(le::to_little_endian(&all(), &mut &BIG[..], &mut out).unwrap(), BIG.len());
+    assert_eq!(out, LITTLE);
+
+    let mut out = Vec::new();
+    assert_eq!(le::from_little_endian(&all(), &mut &LITTLE[..], &mut out).unwrap(), LITTLE.len());
+    assert_eq!(out, BIG);
+}
+
+#[test]
+fn le_invalid() {
+    let ty = |ty| Descriptor { name: "t", kind: Kind::Newtype(ty) };
+
+    // Lengths are checked against their limits, in the input's byte order
+    let desc = ty(TypeDesc::Opaque(Size::Flex(Some(4))));
+    match le::from_little_endian(&desc, &mut &[5u8, 0, 0, 0, 1, 2, 3, 4, 5, 0, 0, 0][..], &mut Vec::new()) {
+        Err(Error(ErrorKind::InvalidLen(4), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+    assert!(le::to_little_endian(&desc, &mut &[0u8, 0, 0, 4, 1, 2, 3, 4][..], &mut Vec::new()).is_ok());
+
+    match le::to_little_endian(&shade(), &mut &[0u8, 0, 0, 3][..], &mut Vec::new()) {
+        Err(Error(ErrorKind::InvalidCase(3), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    let desc = ty(TypeDesc::Option(Box::new(TypeDesc::Int)));
+    match le::from_little_endian(&desc, &mut &[2u8, 0, 0, 0][..], &mut Vec::new()) {
+        Err(Error(ErrorKind::InvalidEnum(2), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    // Truncated
+    assert!(le::to_little_endian(&all(), &mut &BIG[..BIG.len() - 1], &mut Vec::new()).is_err());
+}
+
+// typedef unsigned list<>; with a string name alongside
+#[derive(Debug, PartialEq)]
+struct Named {
+    name: XdrString,
+    list: Vec<u32>,
+}
+
+impl Describe for Named {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "named",
+            kind: Kind::Struct(vec![
+                field("name", TypeDesc::String(Size::Flex(None))),
+                field("list", TypeDesc::Array(Box::new(TypeDesc::UInt), Size::Flex(None))),
+            ]),
+        }
+    }
+}
+
+impl<Out: Write> Pack<Out> for Named {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        Ok(self.name.pack(out)? + self.list.pack(out)?)
+    }
+}
+
+impl<In: Read> Unpack<In> for Named {
+    fn unpack(input: &mut In) -> Result<(Self, usize)> {
+        let (name, nsz) = XdrString::unpack(input)?;
+        let (list, lsz) = Vec::unpack(input)?;
+        Ok((Named { name, list }, nsz + lsz))
+    }
+}
+
+#[test]
+fn le_pack() {
+    let val = Named { name: XdrString::new("abcde"), list: vec![1, 2] };
+
+    let mut buf = Vec::new();
+    le::pack(&val, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        vec![
+            5, 0, 0, 0, b'a', b'b', b'c', b'd', b'e', 0, 0, 0,
+            2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0,
+        ]
+    );
+    assert_eq!(le::unpack::<_, Named>(&mut &buf[..]).unwrap(), val);
+
+    // The byte order only applies to the call, wherever the value is packed
+    let le = thread::spawn(move || {
+        let mut other = Vec::new();
+        le::pack(&Named { name: XdrString::new("abcde"), list: vec![1, 2] }, &mut other).unwrap();
+        other
+    })
+    .join()
+    .unwrap();
+    assert_eq!(le, buf);
+
+    let mut be = Vec::new();
+    pack(&val, &mut be).unwrap();
+    assert_eq!(&be[12..16], &[0, 0, 0, 2]);
+    assert_eq!(unpack::<_, Named>(&mut &be[..]).unwrap(), val);
+}
+
+#[test]
+fn le_record() {
+    let mut buf = Vec::new();
+    {
+        let mut w = XdrRecordWriter::new(&mut buf);
+        le::pack(&Named { name: XdrString::new(""), list: vec![1] }, &mut w).unwrap();
+        w.flush_eor(true).unwrap();
+    }
+
+    // The record header stays big-endian
+    assert_eq!(buf, vec![0x80, 0, 0, 12,  0, 0, 0, 0,  1, 0, 0, 0,  1, 0, 0, 0]);
+
+    let mut r = XdrRecordReader::new(&buf[..]);
+    assert_eq!(
+        le::unpack::<_, Named>(&mut r).unwrap(),
+        Named { name: XdrString::new(""), list: vec![1] }
+    );
+}
+
+#[test]
+fn le_quadruple() {
+    // Quadruples are opaque, so unchanged
+    let desc = Descriptor { name: "q", kind: Kind::Newtype(TypeDesc::Quadruple) };
+    let mut buf = Vec::new();
+    pack(&Quadruple([1; 16]), &mut buf).unwrap();
+
+    let mut out = Vec::new();
+    le::to_little_endian(&desc, &mut &buf[..], &mut out).unwrap();
+    assert_eq!(out, buf);
+}
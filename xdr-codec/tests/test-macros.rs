@@ -0,0 +1,158 @@
+#[macro_use]
+extern crate xdr_codec;
+
+use xdr_codec::{pack, unpack, Error, ErrorKind, Pack, Unpack};
+
+xdr_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Kind {
+        A = 1,
+        B = 2,
+        /// Negative values are fine
+        C = -1,
+    }
+}
+
+xdr_union! {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Shape {
+        case 1 => Circle(u32),
+        case Kind::B as i32 => Square(Kind),
+        /// Void arm
+        case 3 => Empty,
+    }
+}
+
+xdr_union! {
+    #[derive(Debug, PartialEq)]
+    pub enum Maybe {
+        case 0 => Nothing,
+        default => Other(i32, u64),
+    }
+}
+
+xdr_union! {
+    #[derive(Debug, PartialEq)]
+    enum Flag {
+        case 0 => Off,
+        default => On(i32),
+    }
+}
+
+xdr_struct! {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Pair<T> {
+        a: T,
+        b: T,
+    }
+}
+
+// Private field type, and parameters only used inside other types
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Id(u32);
+
+impl<Out: xdr_codec::Write> Pack<Out> for Id {
+    fn pack(&self, out: &mut Out) -> xdr_codec::Result<usize> {
+        self.0.pack(out)
+    }
+}
+
+impl<In: xdr_codec::Read> Unpack<In> for Id {
+    fn unpack(input: &mut In) -> xdr_codec::Result<(Id, usize)> {
+        u32::unpack(input).map(|(v, sz)| (Id(v), sz))
+    }
+}
+
+xdr_struct! {
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Envelope<H, P> {
+        id: Id,
+        /// Documented field
+        pub header: Option<H>,
+        pub payload: Vec<P>,
+    }
+}
+
+fn roundtrip<T>(v: &T) -> Vec<u8>
+where
+    T: Pack<Vec<u8>> + for<'a> Unpack<&'a [u8]> + PartialEq + std::fmt::Debug,
+{
+    let mut buf = Vec::new();
+    pack(v, &mut buf).unwrap();
+    assert_eq!(unpack::<_, T>(&mut &buf[..]).unwrap(), *v);
+    buf
+}
+
+#[test]
+fn macro_enum() {
+    assert_eq!(roundtrip(&Kind::A), vec![0, 0, 0, 1]);
+    assert_eq!(roundtrip(&Kind::C), vec![0xff, 0xff, 0xff, 0xff]);
+
+    match unpack::<_, Kind>(&mut &[0, 0, 0, 3][..]) {
+        Err(Error(ErrorKind::InvalidEnum(3), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+}
+
+#[test]
+fn macro_union() {
+    assert_eq!(roundtrip(&Shape::Circle(5)), vec![0, 0, 0, 1, 0, 0, 0, 5]);
+    assert_eq!(
+        roundtrip(&Shape::Square(Kind::A)),
+        vec![0, 0, 0, 2, 0, 0, 0, 1]
+    );
+    assert_eq!(roundtrip(&Shape::Empty), vec![0, 0, 0, 3]);
+
+    match unpack::<_, Shape>(&mut &[0, 0, 0, 4][..]) {
+        Err(Error(ErrorKind::InvalidCase(4), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+
+    assert_eq!(roundtrip(&Maybe::Nothing), vec![0, 0, 0, 0]);
+    assert_eq!(
+        roundtrip(&Maybe::Other(7, 1)),
+        vec![0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 1]
+    );
+    assert_eq!(roundtrip(&Flag::On(-2)), vec![0xff, 0xff, 0xff, 0xfe]);
+    assert_eq!(roundtrip(&Flag::Off), vec![0, 0, 0, 0]);
+
+    // Truncated value
+    assert!(unpack::<_, Shape>(&mut &[0, 0, 0, 1, 0, 0][..]).is_err());
+}
+
+#[test]
+fn macro_struct() {
+    assert_eq!(
+        roundtrip(&Pair { a: 1u32, b: 2 }),
+        vec![0, 0, 0, 1, 0, 0, 0, 2]
+    );
+    assert_eq!(
+        roundtrip(&Pair {
+            a: Kind::A,
+            b: Kind::C
+        }),
+        vec![0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff]
+    );
+    assert_eq!(
+        roundtrip(&Pair {
+            a: Pair { a: 1u64, b: 2 },
+            b: Pair { a: 3, b: 4 }
+        })
+        .len(),
+        32
+    );
+
+    let env = Envelope {
+        id: Id(9),
+        header: Some(Shape::Empty),
+        payload: vec![Pair { a: true, b: false }],
+    };
+    assert_eq!(
+        roundtrip(&env),
+        vec![0, 0, 0, 9, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0]
+    );
+
+    // Errors name the field
+    let err = unpack::<_, Pair<Kind>>(&mut &[0, 0, 0, 1, 0, 0, 0, 7][..]).unwrap_err();
+    assert!(err.to_string().contains("Pair.b"), "{}", err);
+}
@@ -0,0 +1,160 @@
+#![cfg(feature = "metrics")]
+
+extern crate metrics;
+extern crate xdr_codec;
+
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use metrics::{
+    Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+use xdr_codec::record::{XdrRecordReader, XdrRecordWriter};
+use xdr_codec::{pack, unpack, Error, ErrorKind};
+
+#[derive(Default)]
+struct Samples(Mutex<Vec<f64>>);
+
+impl HistogramFn for Samples {
+    fn record(&self, value: f64) {
+        self.0.lock().unwrap().push(value);
+    }
+}
+
+// Keep everything registered, keyed by name{label=value,...}
+#[derive(Default)]
+struct TestRecorder {
+    counters: Mutex<HashMap<String, Arc<AtomicU64>>>,
+    histograms: Mutex<HashMap<String, Arc<Samples>>>,
+}
+
+fn keystr(key: &Key) -> String {
+    let labels: Vec<_> = key
+        .labels()
+        .map(|l| format!("{}={}", l.key(), l.value()))
+        .collect();
+
+    if labels.is_empty() {
+        key.name().to_string()
+    } else {
+        format!("{}{{{}}}", key.name(), labels.join(","))
+    }
+}
+
+impl TestRecorder {
+    fn counter(&self, key: &str) -> u64 {
+        self.counters
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|c| c.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    fn histogram(&self, key: &str) -> Vec<f64> {
+        self.histograms
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|h| h.0.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Recorder for TestRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata) -> Counter {
+        let c = self
+            .counters
+            .lock()
+            .unwrap()
+            .entry(keystr(key))
+            .or_default()
+            .clone();
+        Counter::from_arc(c)
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata) -> Histogram {
+        let h = self
+            .histograms
+            .lock()
+            .unwrap()
+            .entry(keystr(key))
+            .or_default()
+            .clone();
+        Histogram::from_arc(h)
+    }
+}
+
+#[test]
+fn metrics_values() {
+    let rec = TestRecorder::default();
+
+    metrics::with_local_recorder(&rec, || {
+        xdr_codec::metrics::describe();
+
+        let mut buf = Vec::new();
+        pack(&vec![1u32, 2, 3], &mut buf).unwrap();
+        pack(&7u64, &mut buf).unwrap();
+
+        let mut cur = Cursor::new(buf);
+        assert_eq!(unpack::<_, Vec<u32>>(&mut cur).unwrap(), vec![1, 2, 3]);
+        assert!(unpack::<_, bool>(&mut Cursor::new(vec![0, 0, 0, 2])).is_err());
+        assert!(unpack::<_, u64>(&mut Cursor::new(vec![0, 0])).is_err());
+    });
+
+    assert_eq!(rec.counter("xdr_bytes_packed_total"), 24);
+    assert_eq!(rec.counter("xdr_bytes_unpacked_total"), 16);
+    assert_eq!(rec.counter("xdr_decode_errors_total{kind=enum}"), 1);
+    assert_eq!(rec.counter("xdr_decode_errors_total{kind=io}"), 1);
+}
+
+#[test]
+fn metrics_records() {
+    let rec = TestRecorder::default();
+
+    metrics::with_local_recorder(&rec, || {
+        let mut w = XdrRecordWriter::with_buffer(Vec::new(), 4);
+        w.write_all(b"abcdef").unwrap();
+        w.end_record().unwrap();
+        w.write_all(b"gh").unwrap();
+        w.end_record().unwrap();
+        let buf = w.into_inner().unwrap();
+
+        let r = XdrRecordReader::new(Cursor::new(buf));
+        let mut recs = Vec::new();
+        for record in r {
+            recs.push(record.unwrap());
+        }
+        assert_eq!(recs, vec![b"abcdef".to_vec(), b"gh".to_vec()]);
+    });
+
+    assert_eq!(rec.counter("xdr_records_written_total"), 2);
+    assert_eq!(rec.counter("xdr_records_read_total"), 2);
+    assert_eq!(rec.histogram("xdr_fragment_bytes"), vec![4.0, 2.0, 2.0]);
+    // Record headers aren't values
+    assert_eq!(rec.counter("xdr_bytes_packed_total"), 0);
+    assert_eq!(rec.counter("xdr_decode_errors_total{kind=io}"), 0);
+}
+
+#[test]
+fn metrics_error_kind() {
+    assert_eq!(xdr_codec::metrics::error_kind(&Error::invalidlen(3)), "len");
+    assert_eq!(
+        xdr_codec::metrics::error_kind(&Error::invalidenum(3).with_field("foo.a")),
+        "enum"
+    );
+    assert_eq!(
+        xdr_codec::metrics::error_kind(&ErrorKind::Msg("x".into()).into()),
+        "other"
+    );
+}
@@ -0,0 +1,148 @@
+extern crate xdr_codec;
+
+use std::io::BufReader;
+use std::thread;
+
+use xdr_codec::negotiate::{self, Offer, Reply, Version};
+use xdr_codec::record::{XdrRecordReader, XdrRecordWriter};
+use xdr_codec::{pack, unpack, Error, ErrorKind};
+
+fn offer(versions: &[(u32, u8)]) -> Offer {
+    Offer::new(
+        versions
+            .iter()
+            .map(|&(v, f)| Version::new(v, [f; 32]))
+            .collect(),
+    )
+}
+
+#[test]
+fn negotiate_choose() {
+    let ours = offer(&[(1, 1), (2, 2), (3, 3)]);
+
+    assert_eq!(
+        ours.choose(&offer(&[(3, 3), (1, 1)])),
+        Some(Version::new(3, [3; 32]))
+    );
+    assert_eq!(
+        ours.choose(&offer(&[(2, 2), (4, 4)])),
+        Some(Version::new(2, [2; 32]))
+    );
+    // Same version number, but a different specification
+    assert_eq!(
+        ours.choose(&offer(&[(1, 1), (3, 9)])),
+        Some(Version::new(1, [1; 32]))
+    );
+    assert_eq!(ours.choose(&offer(&[(3, 9)])), None);
+    assert_eq!(ours.choose(&Offer::default()), None);
+}
+
+#[test]
+fn negotiate_encoding() {
+    let mut buf = Vec::new();
+    pack(&Reply::Accepted(Version::new(2, [0xaa; 32])), &mut buf).unwrap();
+    assert_eq!(buf.len(), 4 + 4 + 32);
+    assert_eq!(&buf[..8], &[0, 0, 0, 1, 0, 0, 0, 2]);
+    assert_eq!(&buf[8..], &[0xaa; 32][..]);
+
+    let reply = Reply::Rejected(offer(&[(1, 1), (2, 2)]));
+    let mut buf = Vec::new();
+    pack(&reply, &mut buf).unwrap();
+    assert_eq!(&buf[..8], &[0, 0, 0, 0, 0, 0, 0, 2]);
+    assert_eq!(unpack::<_, Reply>(&mut &buf[..]).unwrap(), reply);
+}
+
+#[test]
+fn negotiate_exchange() {
+    let client = offer(&[(1, 1), (2, 2)]);
+    let server = offer(&[(2, 2), (3, 3)]);
+
+    let mut request = Vec::new();
+    let mut reply = Vec::new();
+    pack(&client, &mut request).unwrap();
+
+    let v = negotiate::server(&mut &request[..], &mut reply, &server).unwrap();
+    assert_eq!(v, Version::new(2, [2; 32]));
+
+    let mut sent = Vec::new();
+    let v = negotiate::client(&mut &reply[..], &mut sent, &client).unwrap();
+    assert_eq!(v, Version::new(2, [2; 32]));
+    assert_eq!(sent, request);
+}
+
+#[test]
+fn negotiate_no_common() {
+    let client = offer(&[(1, 1)]);
+    let server = offer(&[(1, 9), (2, 2)]);
+
+    let mut request = Vec::new();
+    pack(&client, &mut request).unwrap();
+
+    let mut reply = Vec::new();
+    match negotiate::server(&mut &request[..], &mut reply, &server) {
+        Err(Error(ErrorKind::NoCommonVersion, _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    // The client is told what the server supports
+    assert_eq!(
+        unpack::<_, Reply>(&mut &reply[..]).unwrap(),
+        Reply::Rejected(server)
+    );
+
+    match negotiate::client(&mut &reply[..], &mut Vec::new(), &client) {
+        Err(Error(ErrorKind::NoCommonVersion, _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn negotiate_not_offered() {
+    // A server choosing something the client didn't offer is rejected
+    let mut reply = Vec::new();
+    pack(&Reply::Accepted(Version::new(3, [3; 32])), &mut reply).unwrap();
+
+    match negotiate::client(&mut &reply[..], &mut Vec::new(), &offer(&[(1, 1)])) {
+        Err(Error(ErrorKind::NoCommonVersion, _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn negotiate_truncated() {
+    // A peer which stops part way through its offer is an error, not a hang
+    let mut request = Vec::new();
+    pack(&offer(&[(1, 1), (2, 2)]), &mut request).unwrap();
+
+    for len in &[2, 4, 8, 20, 40, request.len() - 1] {
+        let mut reply = Vec::new();
+        let res = negotiate::server(&mut &request[..*len], &mut reply, &offer(&[(2, 2)]));
+        assert!(res.is_err(), "{} bytes accepted", len);
+        assert!(reply.is_empty());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn negotiate_records() {
+    use std::os::unix::net::UnixStream;
+
+    let (cs, ss) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut rd = XdrRecordReader::new(BufReader::new(ss.try_clone().unwrap()));
+        let mut wr = XdrRecordWriter::new(ss);
+        let v = negotiate::server(&mut rd, &mut wr, &offer(&[(1, 1), (2, 2), (3, 3)])).unwrap();
+        wr.end_record().unwrap();
+        // Keep the stream open until the client has finished its record
+        (v, rd)
+    });
+
+    let mut rd = XdrRecordReader::new(BufReader::new(cs.try_clone().unwrap()));
+    let mut wr = XdrRecordWriter::new(cs);
+    let ours = offer(&[(2, 2), (3, 3), (4, 4)]);
+    let v = negotiate::client(&mut rd, &mut wr, &ours).unwrap();
+    wr.end_record().unwrap();
+
+    assert_eq!(v, Version::new(3, [3; 32]));
+    assert_eq!(server.join().unwrap().0, v);
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "net")]
+// Don't rustfmt in here to avoid trashing vec![] formatting
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+extern crate xdr_codec;
+
+use std::io::Cursor;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use xdr_codec::net::{parse_uaddr, uaddr, Netid};
+use xdr_codec::{Error, ErrorKind, Pack, Unpack};
+
+#[test]
+fn ipaddr() {
+    let mut out = Cursor::new(Vec::new());
+
+    let v4 = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+
+    assert_eq!(v4.pack(&mut out).unwrap(), 8);
+    assert_eq!(v6.pack(&mut out).unwrap(), 20);
+
+    let v = out.into_inner();
+    assert_eq!(v, vec![0, 0, 0, 4,  192, 168, 1, 2,
+                       0, 0, 0, 6,  0, 0, 0, 0,  0, 0, 0, 0,  0, 0, 0, 0,  0, 0, 0, 1]);
+
+    let mut input = Cursor::new(v);
+    assert_eq!(IpAddr::unpack(&mut input).unwrap(), (v4, 8));
+    assert_eq!(IpAddr::unpack(&mut input).unwrap(), (v6, 20));
+
+    let mut input = Cursor::new(vec![0, 0, 0, 5,  1, 2, 3, 4]);
+    match IpAddr::unpack(&mut input) {
+        Err(Error(ErrorKind::InvalidCase(5), _)) => (),
+        res => panic!("bad result {:?}", res),
+    }
+}
+
+#[test]
+fn truncated() {
+    // Short input is an error rather than waiting forever for more
+    assert!(Ipv4Addr::unpack(&mut &[192u8, 168][..]).is_err());
+    assert!(Ipv6Addr::unpack(&mut &[0u8; 15][..]).is_err());
+    assert!(IpAddr::unpack(&mut &[0u8, 0, 0, 4,  10, 0][..]).is_err());
+    assert!(IpAddr::unpack(&mut &[0u8, 0, 0, 6,  0, 0, 0, 0][..]).is_err());
+    assert!(SocketAddr::unpack(&mut &[0u8, 0, 0, 4,  10, 0, 0][..]).is_err());
+    assert!(SocketAddr::unpack(&mut &[0u8, 0, 0, 4,  10, 0, 0, 1,  0, 0][..]).is_err());
+}
+
+#[test]
+fn socketaddr() {
+    let mut out = Cursor::new(Vec::new());
+    let addr: SocketAddr = "10.0.0.1:2049".parse().unwrap();
+
+    assert_eq!(addr.pack(&mut out).unwrap(), 12);
+
+    let v = out.into_inner();
+    assert_eq!(v, vec![0, 0, 0, 4,  10, 0, 0, 1,  0, 0, 0x08, 0x01]);
+
+    let mut input = Cursor::new(v);
+    assert_eq!(SocketAddr::unpack(&mut input).unwrap(), (addr, 12));
+
+    let mut input = Cursor::new(vec![0, 0, 0, 4,  10, 0, 0, 1,  0, 1, 0, 0]);
+    assert!(SocketAddr::unpack(&mut input).is_err());
+}
+
+#[test]
+fn universal_address() {
+    let cases = vec![
+        ("192.168.1.2:2049", "192.168.1.2.8.1"),
+        ("[::1]:111", "::1.0.111"),
+        ("[fe80::1:2]:65535", "fe80::1:2.255.255"),
+        ("0.0.0.0:0", "0.0.0.0.0.0"),
+    ];
+
+    for (sa, ua) in cases {
+        let sa: SocketAddr = sa.parse().unwrap();
+        assert_eq!(uaddr(&sa), ua);
+        assert_eq!(parse_uaddr(ua).unwrap(), sa);
+    }
+
+    for bad in &["", "1.2.3.4", "1.2.3.4.5", "1.2.3.4.256.1", "foo.1.2", "::1.x.1"] {
+        assert!(parse_uaddr(bad).is_err(), "{} parsed", bad);
+    }
+}
+
+#[test]
+fn netid() {
+    let cases = vec![
+        (Netid::Tcp, "tcp", "10.0.0.1:2049", true, Some(6)),
+        (Netid::Udp, "udp", "10.0.0.1:111", false, Some(17)),
+        (Netid::Tcp6, "tcp6", "[::1]:2049", true, None),
+        (Netid::Udp6, "udp6", "[fe80::1]:111", false, None),
+    ];
+
+    for (netid, s, sa, stream, prot) in cases {
+        let sa: SocketAddr = sa.parse().unwrap();
+        assert_eq!(netid.to_string(), s);
+        assert_eq!(s.parse::<Netid>().unwrap(), netid);
+        assert_eq!(Netid::for_addr(&sa, stream), netid);
+        assert_eq!(netid.is_stream(), stream);
+        assert_eq!(netid.protocol(), prot);
+        assert_eq!(netid.parse_uaddr(&uaddr(&sa)).unwrap(), sa);
+    }
+
+    assert_eq!("local".parse::<Netid>().unwrap(), Netid::Local);
+    assert_eq!("unix".parse::<Netid>().unwrap(), Netid::Local);
+    assert_eq!(Netid::Local.to_string(), "local");
+    assert!(Netid::Local.is_stream());
+    assert!("sctp".parse::<Netid>().is_err());
+
+    assert_eq!(Netid::from_protocol(6), Some(Netid::Tcp));
+    assert_eq!(Netid::from_protocol(17), Some(Netid::Udp));
+    assert_eq!(Netid::from_protocol(1), None);
+
+    // Addresses must be of the netid's family
+    assert!(Netid::Tcp.parse_uaddr("::1.0.111").is_err());
+    assert!(Netid::Udp6.parse_uaddr("127.0.0.1.0.111").is_err());
+    assert!(Netid::Local.parse_uaddr("/var/run/rpcbind.sock").is_err());
+}
@@ -0,0 +1,182 @@
+extern crate xdr_codec;
+
+use std::io::Write;
+
+use xdr_codec::packer::{PackBuffer, Packer, TeeWrite};
+use xdr_codec::{pack, ErrorKind, Opaque, Pack, Result, SizeSink, XdrString};
+
+#[test]
+fn packer_reuse() {
+    let mut packer = Packer::with_capacity(8);
+
+    let s = XdrString::new("hello");
+    let mut expect = Vec::new();
+    pack(&s, &mut expect).unwrap();
+    assert_eq!(packer.pack_reuse(&s).unwrap(), &expect[..]);
+
+    // Buffer is reused, not appended to
+    let cap = packer.capacity();
+    assert_eq!(packer.pack_reuse(&1u32).unwrap(), &[0, 0, 0, 1]);
+    assert_eq!(packer.capacity(), cap);
+
+    assert_eq!(
+        packer.pack_reuse(&[1u32, 2][..]).unwrap(),
+        &[0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2]
+    );
+
+    let big = vec![0; 1000];
+    assert_eq!(
+        packer.pack_reuse(&Opaque::borrowed(&big)).unwrap().len(),
+        1004
+    );
+    packer.pack_reuse(&2u32).unwrap();
+    packer.shrink_to(16);
+    assert!(packer.capacity() < 1004);
+    assert_eq!(packer.into_inner(), vec![0, 0, 0, 2]);
+}
+
+#[test]
+fn packer_error() {
+    struct Fail;
+
+    impl<Out: Write> Pack<Out> for Fail {
+        fn pack(&self, out: &mut Out) -> Result<usize> {
+            1u32.pack(out)?;
+            Err(ErrorKind::InvalidLen(1).into())
+        }
+    }
+
+    let mut packer = Packer::new();
+    assert!(packer.pack_reuse(&Fail).is_err());
+    assert!(packer.into_inner().is_empty());
+}
+
+// Writer which counts calls to write
+#[derive(Default)]
+struct Counting {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl Write for Counting {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writes += 1;
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn packbuffer_coalesce() {
+    let mut out = PackBuffer::with_capacity(16, Counting::default());
+
+    for i in 0..10u32 {
+        i.pack(&mut out).unwrap();
+    }
+    assert_eq!(out.get_ref().writes, 2);
+    assert_eq!(out.buffer().len(), 8);
+
+    // Large writes go straight through, after what's buffered
+    let big = vec![7; 20];
+    Opaque::borrowed(&big).pack(&mut out).unwrap();
+    assert_eq!(out.buffer().len(), 0);
+
+    out.flush().unwrap();
+    let writes = out.get_ref().writes;
+    1u32.pack(&mut out).unwrap();
+    let inner = out.into_inner().unwrap();
+    assert_eq!(inner.writes, writes + 1);
+
+    let mut expect = Vec::new();
+    for i in 0..10u32 {
+        i.pack(&mut expect).unwrap();
+    }
+    Opaque::borrowed(&big).pack(&mut expect).unwrap();
+    1u32.pack(&mut expect).unwrap();
+    assert_eq!(inner.data, expect);
+}
+
+#[test]
+fn packbuffer_drop() {
+    let mut data = Vec::new();
+    {
+        let mut out = PackBuffer::new(&mut data);
+        XdrString::new("abc").pack(&mut out).unwrap();
+    }
+    assert_eq!(data, vec![0, 0, 0, 3, 0x61, 0x62, 0x63, 0]);
+}
+
+#[test]
+fn size_sink() {
+    let vals = (
+        XdrString::new("hello"),
+        vec![1u64, 2, 3],
+        Some(Opaque::borrowed(&[1, 2, 3, 4, 5])),
+    );
+
+    let mut expect = Vec::new();
+    let mut sink = SizeSink::new();
+    assert!(sink.is_empty());
+
+    let sz = vals.0.pack(&mut expect).unwrap()
+        + vals.1.pack(&mut expect).unwrap()
+        + vals.2.pack(&mut expect).unwrap();
+    let ssz = vals.0.pack(&mut sink).unwrap()
+        + vals.1.pack(&mut sink).unwrap()
+        + vals.2.pack(&mut sink).unwrap();
+    assert_eq!(ssz, sz);
+    assert_eq!(sink.len(), expect.len());
+
+    assert_eq!(SizeSink::size_of(&vals.1).unwrap(), 28);
+    assert_eq!(SizeSink::size_of(&vals.2).unwrap(), 16);
+
+    sink.reset();
+    assert_eq!(sink, SizeSink::new());
+    sink.write_all(&[0; 10]).unwrap();
+    assert_eq!(sink.len(), 10);
+}
+
+// Accepts at most 3 bytes per write
+#[derive(Default)]
+struct Short(Vec<u8>);
+
+impl Write for Short {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = std::cmp::min(buf.len(), 3);
+        self.0.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn tee_write() {
+    let val = (XdrString::new("hello"), vec![1u32, 2, 3]);
+    let mut expect = Vec::new();
+    val.0.pack(&mut expect).unwrap();
+    val.1.pack(&mut expect).unwrap();
+
+    let mut out = TeeWrite::new(Vec::new(), SizeSink::new());
+    val.0.pack(&mut out).unwrap();
+    val.1.pack(&mut out).unwrap();
+    assert_eq!(out.get_ref().1.len(), expect.len());
+    let (data, _) = out.into_inner();
+    assert_eq!(data, expect);
+
+    // The second writer gets exactly what the first accepted
+    let mut out = TeeWrite::new(Short::default(), Vec::new());
+    assert_eq!(out.write(&expect).unwrap(), 3);
+    assert_eq!(out.get_ref().1, &expect[..3]);
+    out.write_all(&expect[3..]).unwrap();
+    out.flush().unwrap();
+    let (first, second) = out.into_inner();
+    assert_eq!(first.0, expect);
+    assert_eq!(second, expect);
+}
@@ -0,0 +1,35 @@
+extern crate xdr_codec;
+
+use std::io::Cursor;
+
+use xdr_codec::prelude::*;
+use xdr_codec::XdrString;
+
+// Generic code needs only the one bound for both directions
+fn roundtrip<T>(v: &T) -> (T, usize)
+where
+    T: Xdr<XdrRecordWriter<Vec<u8>>, XdrRecordReader<Cursor<Vec<u8>>>>,
+{
+    let mut w = XdrRecordWriter::new(Vec::new());
+    let sz = pack_sized(v, &mut w).unwrap();
+    w.flush_eor(true).unwrap();
+
+    let mut r = XdrRecordReader::new(Cursor::new(w.into_inner().unwrap()));
+    let (v, usz) = unpack_sized(&mut r).unwrap();
+    assert_eq!(sz, usz);
+    (v, usz)
+}
+
+#[test]
+fn prelude_roundtrip() {
+    assert_eq!(roundtrip(&1u32), (1, 4));
+    assert_eq!(roundtrip(&vec![1u64, 2]), (vec![1, 2], 20));
+    assert_eq!(
+        roundtrip(&XdrString::new("abc")),
+        (XdrString::new("abc"), 8)
+    );
+
+    let mut buf = Vec::new();
+    true.pack(&mut buf).unwrap();
+    assert!(unpack::<_, bool>(&mut &buf[..]).unwrap());
+}
@@ -0,0 +1,216 @@
+extern crate xdr_codec;
+
+use xdr_codec::describe::{Case, Describe, Descriptor, EnumValue, Field, Kind, Size, TypeDesc};
+use xdr_codec::pretty::{print, Printer};
+use xdr_codec::{pack, pack_opaque_flex, pack_string, ErrorKind, Pack, Result, Write};
+
+// enum color { RED = 1, GREEN = 2 };
+struct Color;
+
+impl Describe for Color {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "color",
+            kind: Kind::Enum(vec![
+                EnumValue {
+                    name: "RED",
+                    value: 1,
+                },
+                EnumValue {
+                    name: "GREEN",
+                    value: 2,
+                },
+            ]),
+        }
+    }
+}
+
+// union reply switch (int status) { case 0: opaque data<>; default: void; };
+struct Reply;
+
+impl Describe for Reply {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "reply",
+            kind: Kind::Union {
+                selector: Field {
+                    name: "status",
+                    ty: TypeDesc::Int,
+                },
+                cases: vec![
+                    Case {
+                        value: Some(0),
+                        variant: "Const0",
+                        field: Some(Field {
+                            name: "data",
+                            ty: TypeDesc::Opaque(Size::Flex(None)),
+                        }),
+                    },
+                    Case {
+                        value: None,
+                        variant: "default",
+                        field: None,
+                    },
+                ],
+            },
+        }
+    }
+}
+
+// struct msg { string name<>; color colors<>; reply *reply; int values<>; };
+struct Msg {
+    name: String,
+    colors: Vec<i32>,
+    reply: Option<Vec<u8>>,
+    values: Vec<i32>,
+}
+
+impl Describe for Msg {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "msg",
+            kind: Kind::Struct(vec![
+                Field {
+                    name: "name",
+                    ty: TypeDesc::String(Size::Flex(None)),
+                },
+                Field {
+                    name: "colors",
+                    ty: TypeDesc::Array(
+                        Box::new(TypeDesc::Named("color", Color::describe)),
+                        Size::Flex(None),
+                    ),
+                },
+                Field {
+                    name: "reply",
+                    ty: TypeDesc::Option(Box::new(TypeDesc::Named("reply", Reply::describe))),
+                },
+                Field {
+                    name: "values",
+                    ty: TypeDesc::Array(Box::new(TypeDesc::Int), Size::Flex(Some(8))),
+                },
+            ]),
+        }
+    }
+}
+
+impl<Out: Write> Pack<Out> for Msg {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        let mut sz = pack_string(&self.name, None, out)?;
+        sz += self.colors.pack(out)?;
+        sz += match self.reply {
+            Some(ref data) => {
+                true.pack(out)? + 0i32.pack(out)? + pack_opaque_flex(data, None, out)?
+            }
+            None => false.pack(out)?,
+        };
+        Ok(sz + self.values.pack(out)?)
+    }
+}
+
+#[test]
+fn pretty_struct() {
+    let msg = Msg {
+        name: "hello".into(),
+        colors: vec![2, 1, 5],
+        reply: Some(vec![1, 2, 0xff]),
+        values: vec![1, -2],
+    };
+    assert_eq!(
+        print(&msg),
+        "msg {\n    name: \"hello\",\n    colors: [GREEN, RED, 5],\n    reply: Some(reply {\n        status: 0,\n        data: 0x0102ff,\n    }),\n    values: [1, -2],\n}"
+    );
+
+    let msg = Msg {
+        name: String::new(),
+        colors: vec![],
+        reply: None,
+        values: vec![],
+    };
+    assert_eq!(
+        print(&msg),
+        "msg {\n    name: \"\",\n    colors: [],\n    reply: None,\n    values: [],\n}"
+    );
+}
+
+#[test]
+fn pretty_truncate() {
+    let msg = Msg {
+        name: "abcdefgh".into(),
+        colors: vec![1; 5],
+        reply: Some(vec![0xaa; 6]),
+        values: vec![7; 4],
+    };
+    let printer = Printer::new().max_elems(2).max_bytes(4);
+    assert_eq!(
+        printer.print(&msg).unwrap(),
+        "msg {\n    name: \"abcd\"…(8 bytes),\n    colors: [RED, RED, …(3 more)],\n    reply: Some(reply {\n        status: 0,\n        data: 0xaaaaaaaa…(6 bytes),\n    }),\n    values: [7, 7, …(2 more)],\n}"
+    );
+}
+
+#[test]
+fn pretty_nested_array() {
+    let desc = Descriptor {
+        name: "table",
+        kind: Kind::Newtype(TypeDesc::Array(
+            Box::new(TypeDesc::Array(Box::new(TypeDesc::UInt), Size::Fixed(2))),
+            Size::Flex(None),
+        )),
+    };
+
+    let mut buf = Vec::new();
+    pack(&vec![[1u32, 2], [3, 4], [5, 6]], &mut buf).unwrap();
+
+    let printer = Printer::new().max_elems(2);
+    assert_eq!(
+        printer.print_bytes(&desc, &buf).unwrap(),
+        "table([\n    [1, 2],\n    [3, 4],\n    …(1 more)\n])"
+    );
+}
+
+#[test]
+fn pretty_errors() {
+    let printer = Printer::new();
+
+    // Unknown union case without a default
+    let desc = Descriptor {
+        name: "u",
+        kind: Kind::Union {
+            selector: Field {
+                name: "tag",
+                ty: TypeDesc::Int,
+            },
+            cases: vec![Case {
+                value: Some(1),
+                variant: "Const1",
+                field: None,
+            }],
+        },
+    };
+    assert_eq!(
+        printer.print_bytes(&desc, &[0, 0, 0, 1]).unwrap(),
+        "u {\n    tag: 1,\n}"
+    );
+    match printer.print_bytes(&desc, &[0, 0, 0, 2]) {
+        Err(xdr_codec::Error(ErrorKind::InvalidCase(2), _)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    // Over-long array
+    let mut buf = Vec::new();
+    pack(&vec![0i32; 9], &mut buf).unwrap();
+    let desc = Descriptor {
+        name: "a",
+        kind: Kind::Newtype(TypeDesc::Array(
+            Box::new(TypeDesc::Int),
+            Size::Flex(Some(8)),
+        )),
+    };
+    match printer.print_bytes(&desc, &buf) {
+        Err(xdr_codec::Error(ErrorKind::InvalidLen(8), _)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    // Truncated input
+    assert!(printer.print_bytes(&desc, &buf[..8]).is_err());
+}
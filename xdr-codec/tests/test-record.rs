@@ -3,9 +3,9 @@
 
 extern crate xdr_codec;
 
-use std::io::{Cursor, Read, Write};
+use std::io::{self, Cursor, Read, Write};
 
-use xdr_codec::record::{XdrRecordReader, XdrRecordWriter};
+use xdr_codec::record::{EmptyFragments, FragmentInfo, XdrRecordReader, XdrRecordWriter};
 
 #[test]
 fn recread_full() {
@@ -174,3 +174,186 @@ fn largerec_flush() {
         ]
     )
 }
+
+#[test]
+fn multirec() {
+    let mut buf = Vec::new();
+
+    {
+        let mut xw = XdrRecordWriter::with_buffer(&mut buf, 4);
+
+        assert_eq!(write!(xw, "hello").unwrap(), ());
+        xw.end_record().unwrap();
+        assert_eq!(write!(xw, "abc").unwrap(), ());
+        xw.end_record().unwrap();
+        xw.end_record().unwrap();
+    }
+
+    // Nothing more is written on drop
+    assert_eq!(buf, vec![0, 0, 0, 4, 104, 101, 108, 108,
+                         128, 0, 0, 1, 111,
+                         128, 0, 0, 3, 97, 98, 99,
+                         128, 0, 0, 0]);
+
+    let recread = XdrRecordReader::new(Cursor::new(buf));
+    let got: Vec<_> = recread.into_iter().map(|r| r.expect("IO error")).collect();
+
+    assert_eq!(got, vec![b"hello".to_vec(), b"abc".to_vec(), vec![]]);
+}
+
+#[test]
+fn recread_fraginfo() {
+    let inbuf = vec![0, 0, 0, 5, 0, 1, 2, 3, 4,
+                     128, 0, 0, 3, 5, 6, 7,
+                     128, 0, 0, 1, 99];
+    let cur = Cursor::new(inbuf);
+
+    let mut recread = XdrRecordReader::new(cur);
+    let mut buf = vec![0; 2];
+
+    assert_eq!(recread.fragment(), None);
+
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 2);
+    assert_eq!(recread.fragment(),
+               Some(FragmentInfo { len: 5, eor: false, record_offset: 0, stream_offset: 0 }));
+    assert_eq!(recread.fragment_remaining(), 3);
+
+    let mut buf = vec![0; 10];
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 3);
+    assert_eq!(recread.fragment_remaining(), 0);
+
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 3);
+    assert_eq!(recread.fragment(),
+               Some(FragmentInfo { len: 3, eor: true, record_offset: 5, stream_offset: 9 }));
+
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 1);
+    assert_eq!(recread.fragment(),
+               Some(FragmentInfo { len: 1, eor: true, record_offset: 0, stream_offset: 16 }));
+}
+
+#[test]
+fn into_inner() {
+    let mut xw = XdrRecordWriter::with_buffer(Vec::new(), 10);
+
+    assert_eq!(write!(xw, "hel").unwrap(), ());
+    xw.flush().unwrap();
+    assert_eq!(xw.get_ref(), &vec![0, 0, 0, 3, 104, 101, 108]);
+    assert_eq!(write!(xw, "lo").unwrap(), ());
+
+    // Unterminated record is ended
+    let buf = xw.into_inner().unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 3, 104, 101, 108, 128, 0, 0, 2, 108, 111]);
+
+    let mut xw = XdrRecordWriter::new(buf);
+    xw.get_mut().clear();
+    assert_eq!(write!(xw, "x").unwrap(), ());
+    xw.end_record().unwrap();
+    assert_eq!(xw.into_inner().unwrap(), vec![128, 0, 0, 1, 120]);
+
+    let inbuf = vec![128, 0, 0, 2, 1, 2, 3, 4];
+    let mut recread = XdrRecordReader::new(Cursor::new(inbuf));
+    let mut buf = [0; 10];
+
+    assert_eq!(recread.read(&mut buf).unwrap(), 2);
+    assert_eq!(recread.get_ref().position(), 6);
+
+    let mut cur = recread.into_inner();
+    assert_eq!(cur.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], &[3, 4]);
+}
+
+// Fails after `limit` bytes
+#[derive(Debug)]
+struct Limited {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for Limited {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.limit {
+            return Err(io::Error::new(io::ErrorKind::Other, "full"));
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn into_inner_boundary() {
+    // A writer which hasn't been written to sends an empty record
+    let xw = XdrRecordWriter::new(Vec::new());
+    assert_eq!(xw.into_inner().unwrap(), vec![128, 0, 0, 0]);
+
+    {
+        let mut buf = Vec::new();
+        drop(XdrRecordWriter::new(&mut buf));
+        assert_eq!(buf, vec![128, 0, 0, 0]);
+    }
+
+    // Nothing more is sent at the end of a record
+    let mut xw = XdrRecordWriter::new(Vec::new());
+    write!(xw, "x").unwrap();
+    xw.end_record().unwrap();
+    assert_eq!(xw.into_inner().unwrap(), vec![128, 0, 0, 1, 120]);
+}
+
+#[test]
+fn into_inner_error() {
+    let mut xw = XdrRecordWriter::new(Limited { buf: Vec::new(), limit: 4 });
+    write!(xw, "hello").unwrap();
+
+    // The writer comes back with the error, still holding the unsent data
+    let err = xw.into_inner().unwrap_err();
+    assert_eq!(err.error().kind(), io::ErrorKind::Other);
+    let (inner, unsent) = err.into_inner().into_parts();
+    assert_eq!(unsent, b"hello");
+    assert_eq!(inner.buf, vec![128, 0, 0, 5]);
+}
+
+#[test]
+fn recread_empty_fragments() {
+    // empty non-final fragment, empty record, record with empty final fragment
+    let inbuf = vec![0, 0, 0, 2, 1, 2,
+                     0, 0, 0, 0,
+                     128, 0, 0, 1, 3,
+                     128, 0, 0, 0,
+                     0, 0, 0, 1, 4,
+                     128, 0, 0, 0];
+
+    let records = |policy| {
+        let mut recread = XdrRecordReader::new(Cursor::new(inbuf.clone()));
+        recread.set_empty_fragments(policy);
+        recread.into_iter().collect::<io::Result<Vec<_>>>()
+    };
+
+    assert_eq!(records(EmptyFragments::Accept).unwrap(),
+               vec![vec![1, 2, 3], vec![], vec![4]]);
+    assert_eq!(records(EmptyFragments::Coalesce).unwrap(),
+               vec![vec![1, 2, 3], vec![4]]);
+    assert_eq!(records(EmptyFragments::Reject).unwrap_err().kind(),
+               io::ErrorKind::InvalidData);
+
+    // Empty final fragments are fine
+    let mut recread = XdrRecordReader::new(Cursor::new(vec![0, 0, 0, 1, 4, 128, 0, 0, 0]));
+    recread.set_empty_fragments(EmptyFragments::Reject);
+    let got: Vec<_> = recread.into_iter().map(|r| r.expect("IO error")).collect();
+    assert_eq!(got, vec![vec![4]]);
+
+    // Skipped fragments don't affect fragment info
+    let mut recread = XdrRecordReader::new(Cursor::new(inbuf.clone()));
+    recread.set_empty_fragments(EmptyFragments::Coalesce);
+    let mut buf = vec![0; 10];
+
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 2);
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 1);
+    assert_eq!(recread.fragment(),
+               Some(FragmentInfo { len: 1, eor: true, record_offset: 2, stream_offset: 10 }));
+    assert_eq!(recread.read(&mut buf[..]).unwrap(), 1);
+    assert_eq!(recread.fragment(),
+               Some(FragmentInfo { len: 1, eor: false, record_offset: 0, stream_offset: 19 }));
+}
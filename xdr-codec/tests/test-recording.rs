@@ -0,0 +1,77 @@
+// Don't rustfmt in here to avoid trashing vec![] formatting
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+extern crate xdr_codec;
+
+use std::io::Write;
+
+use xdr_codec::recording::{RecordingWriter, Span};
+use xdr_codec::{pack_array, Opaque};
+
+#[test]
+fn recording_spans() {
+    let mut rec = RecordingWriter::new();
+
+    rec.write_all(&[0xde, 0xad, 0xbe, 0xef]).unwrap();
+    rec.begin("outer");
+    rec.field("a", &1u32).unwrap();
+    rec.begin("inner");
+    rec.field("b", &2u64).unwrap();
+    rec.end();
+    rec.end();
+    assert!(rec.field("c", &Opaque::borrowed(&[1, 2, 3])).is_ok());
+
+    assert_eq!(rec.spans(), &[
+        Span { name: "outer".into(), range: 4..16, depth: 0 },
+        Span { name: "a".into(), range: 4..8, depth: 1 },
+        Span { name: "inner".into(), range: 8..16, depth: 1 },
+        Span { name: "b".into(), range: 8..16, depth: 2 },
+        Span { name: "c".into(), range: 16..24, depth: 0 },
+    ]);
+    assert_eq!(rec.span_bytes(&rec.spans()[3]), &[0, 0, 0, 0, 0, 0, 0, 2]);
+
+    rec.write_all(&[9]).unwrap();
+
+    assert_eq!(format!("{}", rec.pretty()),
+               "0000..0004 -: de ad be ef\n\
+                0004..0010 outer\n\
+                0004..0008   a: 00 00 00 01\n\
+                0008..0010   inner\n\
+                0008..0010     b: 00 00 00 00  00 00 00 02\n\
+                0010..0018 c: 00 00 00 03  01 02 03 00\n\
+                0018..0019 -: 09\n");
+    assert_eq!(rec.into_inner().len(), 25);
+}
+
+#[test]
+fn recording_large() {
+    let mut rec = RecordingWriter::new();
+
+    rec.begin("s");
+    rec.field("data", &Opaque::borrowed(b"0123456789abcdefghij")).unwrap();
+    rec.field("empty", &Opaque::borrowed(&[])).unwrap();
+    rec.begin("none");
+    rec.end();
+    rec.end();
+
+    assert_eq!(format!("{}", rec.pretty()),
+               "0000..001c s\n\
+                0000..0018   data:\n             \
+                00000000  00 00 00 14  30 31 32 33  34 35 36 37  38 39 61 62 |....0123456789ab|\n             \
+                00000010  63 64 65 66  67 68 69 6a                           |cdefghij|\n\
+                0018..001c   empty: 00 00 00 00\n\
+                001c..001c   none:\n");
+}
+
+#[test]
+fn recording_error() {
+    let mut rec = RecordingWriter::new();
+
+    // Array too short with no default fills what it can, then fails
+    rec.begin("arr");
+    assert!(pack_array(&[1u32], 2, &mut rec, None).is_err());
+    rec.end();
+    assert!(rec.field("x", &[1u32, 2][..]).is_ok());
+
+    assert_eq!(rec.spans()[1], Span { name: "x".into(), range: rec.spans()[0].range.end..rec.bytes().len(), depth: 0 });
+}
@@ -0,0 +1,116 @@
+extern crate xdr_codec;
+
+use std::io::{Seek, SeekFrom};
+
+use xdr_codec::pack;
+use xdr_codec::slice::{SliceCursor, UnpackRef};
+use xdr_codec::{Error, ErrorKind, Opaque, Pack, Result};
+
+#[test]
+fn slice_random_access() {
+    let mut buf = Vec::new();
+    pack(&1u32, &mut buf).unwrap();
+    pack(&Opaque::borrowed(b"hello"), &mut buf).unwrap();
+    pack(&2u64, &mut buf).unwrap();
+    "tail".pack(&mut buf).unwrap();
+    assert_eq!(buf.len(), 4 + 12 + 8 + 8);
+
+    let mut cur = SliceCursor::new(&buf);
+    assert_eq!(cur.unpack::<u32>().unwrap(), 1);
+    assert_eq!(cur.opaque_flex(Some(5)).unwrap(), b"hello");
+    assert_eq!(cur.position(), 16);
+
+    assert_eq!(cur.unpack_at::<u64>(16).unwrap(), 2);
+    assert_eq!(cur.position(), 16);
+
+    cur.skip(8).unwrap();
+    assert_eq!(cur.unpack::<String>().unwrap(), "tail");
+    assert_eq!(cur.remaining(), 0);
+    assert!(cur.skip(1).is_err());
+    assert!(cur.unpack::<u32>().is_err());
+
+    cur.seek_to(4).unwrap();
+    assert!(cur.opaque_flex(Some(4)).is_err());
+    assert_eq!(cur.position(), 4);
+    assert!(cur.seek_to(buf.len() + 1).is_err());
+
+    let mut sub = cur.split(12).unwrap();
+    assert_eq!(cur.position(), 16);
+    assert_eq!(sub.unpack::<String>().unwrap(), "hello");
+    assert_eq!(sub.remaining(), 0);
+
+    let mut sub = cur.sub(24, 8).unwrap();
+    assert_eq!(sub.unpack::<String>().unwrap(), "tail");
+    assert!(cur.sub(24, 9).is_err());
+
+    assert_eq!(cur.seek(SeekFrom::End(-8)).unwrap(), 24);
+    assert_eq!(cur.seek(SeekFrom::Current(-4)).unwrap(), 20);
+    assert!(cur.seek(SeekFrom::Current(-21)).is_err());
+    assert_eq!(cur.opaque_array(4).unwrap(), &[0, 0, 0, 2]);
+
+    // Lengths which overflow with their padding
+    assert!(cur.opaque_array(usize::MAX).is_err());
+    assert_eq!(cur.position(), 24);
+    let mut cur = SliceCursor::new(&[0xff, 0xff, 0xff, 0xff, 1, 2, 3, 4]);
+    assert!(cur.opaque_flex(None).is_err());
+    assert_eq!(cur.position(), 0);
+}
+
+#[test]
+fn slice_strings() {
+    let mut buf = Vec::new();
+    "héllo".pack(&mut buf).unwrap();
+    Opaque::borrowed(b"a\xffb").pack(&mut buf).unwrap();
+    "tab\t".pack(&mut buf).unwrap();
+
+    let mut cur = SliceCursor::new(&buf);
+    let s = cur.string(None).unwrap();
+    assert_eq!(s, "héllo");
+    assert_eq!(s.as_ptr(), buf[4..].as_ptr());
+    assert!(cur.string_strict(None).is_err());
+
+    let pos = cur.position();
+    match cur.string(None) {
+        Err(Error(ErrorKind::InvalidStringChar(1, 0xff), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert_eq!(cur.position(), pos);
+    cur.opaque_flex(None).unwrap();
+
+    match cur.string_strict(None) {
+        Err(Error(ErrorKind::InvalidStringChar(3, b'\t'), _)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    assert_eq!(cur.string(Some(4)).unwrap(), "tab\t");
+}
+
+#[test]
+fn slice_unpack_ref() {
+    #[derive(Debug, PartialEq)]
+    struct Named<'a> {
+        id: u32,
+        name: &'a str,
+    }
+
+    impl<'a> UnpackRef<'a> for Named<'a> {
+        fn unpack_ref(cur: &mut SliceCursor<'a>) -> Result<Self> {
+            Ok(Named {
+                id: cur.unpack()?,
+                name: cur.string(None)?,
+            })
+        }
+    }
+
+    let mut buf = Vec::new();
+    pack(&7u32, &mut buf).unwrap();
+    "seven".pack(&mut buf).unwrap();
+
+    let mut cur = SliceCursor::new(&buf);
+    assert_eq!(
+        cur.unpack_ref::<Named>().unwrap(),
+        Named {
+            id: 7,
+            name: "seven"
+        }
+    );
+}
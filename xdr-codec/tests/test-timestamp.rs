@@ -0,0 +1,84 @@
+#![cfg(feature = "timestamp")]
+extern crate xdr_codec;
+
+use std::io::Cursor;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use xdr_codec::timestamp::Timeval;
+use xdr_codec::{Pack, Unpack};
+
+fn roundtrip<T>(v: T, expected: &[u8])
+where
+    T: Pack<Vec<u8>> + Unpack<Cursor<Vec<u8>>> + PartialEq + std::fmt::Debug,
+{
+    let mut out = Vec::new();
+    assert_eq!(v.pack(&mut out).unwrap(), expected.len());
+    assert_eq!(out, expected);
+
+    let mut input = Cursor::new(out);
+    assert_eq!(T::unpack(&mut input).unwrap(), (v, expected.len()));
+}
+
+#[test]
+fn systemtime() {
+    roundtrip(UNIX_EPOCH, &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    roundtrip(
+        UNIX_EPOCH + Duration::new(0x1_0000_0002, 500),
+        &[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0x01, 0xf4],
+    );
+    // 1.5s before the epoch is -2s + 0.5s
+    roundtrip(
+        UNIX_EPOCH - Duration::from_millis(1500),
+        &[
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0x1d, 0xcd, 0x65, 0x00,
+        ],
+    );
+    roundtrip(
+        UNIX_EPOCH - Duration::from_secs(3),
+        &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfd, 0, 0, 0, 0],
+    );
+}
+
+#[test]
+fn systemtime_bad_nsecs() {
+    let mut input = Cursor::new(vec![0, 0, 0, 0, 0, 0, 0, 1, 0x3b, 0x9a, 0xca, 0x00]);
+    assert!(SystemTime::unpack(&mut input).is_err());
+}
+
+#[test]
+fn timeval() {
+    roundtrip(
+        Timeval(UNIX_EPOCH + Duration::new(10, 250_000)),
+        &[0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0xfa],
+    );
+
+    // sub-microsecond part is dropped
+    let mut out = Vec::new();
+    Timeval(UNIX_EPOCH + Duration::new(10, 250_999))
+        .pack(&mut out)
+        .unwrap();
+    assert_eq!(out, vec![0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0xfa]);
+
+    let mut input = Cursor::new(vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0x0f, 0x42, 0x40]);
+    assert!(Timeval::unpack(&mut input).is_err());
+}
+
+#[test]
+fn duration() {
+    roundtrip(Duration::ZERO, &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    roundtrip(
+        Duration::new(90, 500),
+        &[0, 0, 0, 0, 0, 0, 0, 90, 0, 0, 0x01, 0xf4],
+    );
+
+    // Too long to pack
+    assert!(Duration::MAX.pack(&mut Vec::new()).is_err());
+
+    // Negative durations and bad nanoseconds can't be unpacked
+    let mut input = Cursor::new(vec![
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0x1d, 0xcd, 0x65, 0x00,
+    ]);
+    assert!(Duration::unpack(&mut input).is_err());
+    let mut input = Cursor::new(vec![0, 0, 0, 0, 0, 0, 0, 1, 0x3b, 0x9a, 0xca, 0x00]);
+    assert!(Duration::unpack(&mut input).is_err());
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "log")]
+
+extern crate log;
+extern crate xdr_codec;
+
+use std::io::{Cursor, Write};
+use std::sync::Mutex;
+
+use log::{LevelFilter, Log, Metadata, Record};
+use xdr_codec::record::XdrRecordWriter;
+use xdr_codec::trace::Traced;
+use xdr_codec::XdrString;
+
+struct Capture(Mutex<Vec<String>>);
+
+impl Log for Capture {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}: {}", record.target(), record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Capture = Capture(Mutex::new(Vec::new()));
+
+// Everything's in one test, as the logger is global
+#[test]
+fn trace_values() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    let mut out = Traced::new(Vec::new());
+    assert_eq!(out.pack(&1u32).unwrap(), 4);
+    assert_eq!(out.pack(&XdrString::new("hello")).unwrap(), 12);
+    assert_eq!(out.position(), 16);
+
+    let mut input = Traced::new(Cursor::new(out.into_inner()));
+    assert_eq!(input.unpack::<u32>().unwrap(), 1);
+    assert!(input.unpack::<bool>().is_err());
+
+    let mut rec = XdrRecordWriter::new(Vec::new());
+    rec.write_all(b"abc").unwrap();
+    rec.end_record().unwrap();
+
+    let logs = LOGGER.0.lock().unwrap().clone();
+    assert_eq!(
+        logs,
+        vec![
+            "xdr_codec::trace: pack u32: 4 bytes at 0\n\
+             00000000  00 00 00 01                                        |....|",
+            "xdr_codec::trace: pack xdr_codec::XdrString: 12 bytes at 4\n\
+             00000004  00 00 00 05  68 65 6c 6c  6f 00 00 00              |....hello...|",
+            "xdr_codec::trace: unpack u32: 4 bytes at 0\n\
+             00000000  00 00 00 01                                        |....|",
+            "xdr_codec::trace: unpack bool failed after 4 bytes at 4: invalid enum value: '5'\n\
+             00000004  00 00 00 05                                        |....|",
+            "xdr_codec::record: write fragment: 3 bytes, end of record\n\
+             00000000  61 62 63                                           |abc|",
+        ]
+    );
+}
@@ -0,0 +1,210 @@
+extern crate xdr_codec;
+
+use xdr_codec::budget::with_budget;
+use xdr_codec::describe::{Case, Describe, Descriptor, EnumValue, Field, Kind, Size, TypeDesc};
+use xdr_codec::value::{self, to_value, Value};
+use xdr_codec::{pack, pack_opaque_flex, ErrorKind, Pack, Result, Write};
+
+// enum color { RED = 1, GREEN = 2 };
+struct Color;
+
+impl Describe for Color {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "color",
+            kind: Kind::Enum(vec![
+                EnumValue {
+                    name: "RED",
+                    value: 1,
+                },
+                EnumValue {
+                    name: "GREEN",
+                    value: 2,
+                },
+            ]),
+        }
+    }
+}
+
+// union reply switch (color status) { case RED: opaque data<>; default: void; };
+struct Reply;
+
+impl Describe for Reply {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "reply",
+            kind: Kind::Union {
+                selector: Field {
+                    name: "status",
+                    ty: TypeDesc::Named("color", Color::describe),
+                },
+                cases: vec![
+                    Case {
+                        value: Some(1),
+                        variant: "RED",
+                        field: Some(Field {
+                            name: "data",
+                            ty: TypeDesc::Opaque(Size::Flex(None)),
+                        }),
+                    },
+                    Case {
+                        value: None,
+                        variant: "default",
+                        field: None,
+                    },
+                ],
+            },
+        }
+    }
+}
+
+// struct msg { unsigned xid; reply *reply; int values<>; };
+struct Msg {
+    xid: u32,
+    reply: Option<(i32, Vec<u8>)>,
+    values: Vec<i32>,
+}
+
+impl Describe for Msg {
+    fn describe() -> Descriptor {
+        Descriptor {
+            name: "msg",
+            kind: Kind::Struct(vec![
+                Field {
+                    name: "xid",
+                    ty: TypeDesc::UInt,
+                },
+                Field {
+                    name: "reply",
+                    ty: TypeDesc::Option(Box::new(TypeDesc::Named("reply", Reply::describe))),
+                },
+                Field {
+                    name: "values",
+                    ty: TypeDesc::Array(Box::new(TypeDesc::Int), Size::Flex(None)),
+                },
+            ]),
+        }
+    }
+}
+
+impl<Out: Write> Pack<Out> for Msg {
+    fn pack(&self, out: &mut Out) -> Result<usize> {
+        let mut sz = self.xid.pack(out)?;
+        sz += match self.reply {
+            Some((1, ref data)) => {
+                true.pack(out)? + 1i32.pack(out)? + pack_opaque_flex(data, None, out)?
+            }
+            Some((status, _)) => true.pack(out)? + status.pack(out)?,
+            None => false.pack(out)?,
+        };
+        Ok(sz + self.values.pack(out)?)
+    }
+}
+
+#[test]
+fn value_paths() {
+    let msg = Msg {
+        xid: 42,
+        reply: Some((1, vec![1, 2, 3])),
+        values: vec![5, 6, 7],
+    };
+    let val = to_value(&msg).unwrap();
+
+    assert_eq!(val.get(""), Some(&val));
+    assert_eq!(val.get("xid").and_then(Value::as_i64), Some(42));
+    assert_eq!(val.get("reply.status"), Some(&Value::Enum(1, Some("RED"))));
+    assert_eq!(
+        val.get("reply.data").and_then(Value::as_bytes),
+        Some(&[1u8, 2, 3][..])
+    );
+    assert_eq!(val.get("values[2]"), Some(&Value::Int(7)));
+
+    assert_eq!(val.get("values[3]"), None);
+    assert_eq!(val.get("values[x]"), None);
+    assert_eq!(val.get("values[1"), None);
+    assert_eq!(val.get("values[1]x"), None);
+    assert_eq!(val.get("xid[0]"), None);
+    assert_eq!(val.get("reply.missing"), None);
+
+    match *val.get("reply").unwrap() {
+        Value::Option(Some(ref reply)) => match **reply {
+            Value::Union { variant, .. } => assert_eq!(variant, "RED"),
+            ref v => panic!("unexpected {:?}", v),
+        },
+        ref v => panic!("unexpected {:?}", v),
+    }
+
+    // Arms which aren't selected aren't there
+    let msg = Msg {
+        xid: 1,
+        reply: Some((7, vec![])),
+        values: vec![],
+    };
+    let val = to_value(&msg).unwrap();
+    assert_eq!(val.get("reply.status"), Some(&Value::Enum(7, None)));
+    assert_eq!(val.get("reply.data"), None);
+
+    // Nor are absent optional values
+    let msg = Msg {
+        xid: 1,
+        reply: None,
+        values: vec![],
+    };
+    let val = to_value(&msg).unwrap();
+    assert_eq!(val.get("reply"), Some(&Value::Option(None)));
+    assert_eq!(val.get("reply.status"), None);
+}
+
+#[test]
+fn value_unpack() {
+    let desc = Descriptor {
+        name: "grid",
+        kind: Kind::Newtype(TypeDesc::Array(
+            Box::new(TypeDesc::Array(Box::new(TypeDesc::UInt), Size::Fixed(2))),
+            Size::Flex(Some(4)),
+        )),
+    };
+
+    let mut buf = Vec::new();
+    pack(&vec![[1u32, 2], [3, 4]], &mut buf).unwrap();
+
+    let (val, sz) = value::unpack(&desc, &mut &buf[..]).unwrap();
+    assert_eq!(sz, buf.len());
+    assert_eq!(val.get("[1][0]"), Some(&Value::UInt(3)));
+
+    // Arrays are charged to the budget
+    assert!(with_budget(16, || value::unpack(&desc, &mut &buf[..])).is_err());
+
+    let mut buf = Vec::new();
+    pack(&vec![[0u32, 0]; 5], &mut buf).unwrap();
+    match value::unpack(&desc, &mut &buf[..]) {
+        Err(xdr_codec::Error(ErrorKind::InvalidLen(4), _)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    // Truncated captures are an error
+    let desc = Descriptor {
+        name: "quad",
+        kind: Kind::Newtype(TypeDesc::Quadruple),
+    };
+    let buf = [0x3f; 16];
+    let (val, sz) = value::unpack(&desc, &mut &buf[..]).unwrap();
+    assert_eq!((val, sz), (Value::Quadruple([0x3f; 16]), 16));
+    assert!(value::unpack(&desc, &mut &buf[..10]).is_err());
+}
+
+#[test]
+fn value_json() {
+    let val = Value::Struct(vec![
+        ("s", Value::String("a\"b\\\n\u{1}é".to_string())),
+        ("f", Value::Double(1.5)),
+        ("nan", Value::Float(::std::f32::NAN)),
+        ("o", Value::Option(Some(Box::new(Value::Bool(true))))),
+        ("e", Value::Enum(3, None)),
+        ("b", Value::Opaque(vec![0, 0xff])),
+    ]);
+    assert_eq!(
+        val.to_json(),
+        r#"{"s":"a\"b\\\n\u0001é","f":1.5,"nan":null,"o":true,"e":3,"b":"00ff"}"#
+    );
+}
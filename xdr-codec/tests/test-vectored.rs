@@ -0,0 +1,91 @@
+extern crate xdr_codec;
+
+use std::io::{self, IoSlice, Write};
+
+use xdr_codec::vectored::IoVecWriter;
+use xdr_codec::{pack_opaque_array, pack_opaque_flex, Pack};
+
+// Writer which accepts at most `limit` bytes per call, to exercise short writes
+struct Dribble {
+    data: Vec<u8>,
+    limit: usize,
+    calls: usize,
+}
+
+impl Write for Dribble {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.calls += 1;
+        let mut n = 0;
+        for buf in bufs {
+            let take = std::cmp::min(buf.len(), self.limit - n);
+            self.data.extend_from_slice(&buf[..take]);
+            n += take;
+            if n == self.limit {
+                break;
+            }
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn vectored_matches_pack() {
+    let payload: Vec<u8> = (0..37).collect();
+    let fixed = [1u8, 2, 3];
+
+    let mut expected = Vec::new();
+    1234u32.pack(&mut expected).unwrap();
+    pack_opaque_flex(&payload, None, &mut expected).unwrap();
+    (-1i32).pack(&mut expected).unwrap();
+    pack_opaque_array(&fixed, 6, &mut expected).unwrap();
+
+    let mut iov = IoVecWriter::new();
+    assert!(iov.is_empty());
+    assert_eq!(1234u32.pack(&mut iov).unwrap(), 4);
+    assert_eq!(iov.opaque(&payload, None).unwrap(), 44);
+    assert_eq!((-1i32).pack(&mut iov).unwrap(), 4);
+    assert_eq!(iov.opaque_array(&fixed, 6).unwrap(), 8);
+    assert_eq!(iov.len(), expected.len());
+
+    let mut out = Vec::new();
+    assert_eq!(iov.write_to(&mut out).unwrap(), expected.len());
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn vectored_short_writes() {
+    let payload = vec![0xaau8; 100];
+
+    let mut iov = IoVecWriter::new();
+    7u32.pack(&mut iov).unwrap();
+    iov.opaque(&payload, Some(100)).unwrap();
+    8u32.pack(&mut iov).unwrap();
+
+    let mut out = Dribble {
+        data: Vec::new(),
+        limit: 7,
+        calls: 0,
+    };
+    assert_eq!(iov.write_to(&mut out).unwrap(), 112);
+    assert_eq!(out.data.len(), 112);
+    assert_eq!(out.calls, 16);
+    assert_eq!(&out.data[..8], &[0, 0, 0, 7, 0, 0, 0, 100]);
+    assert_eq!(&out.data[108..], &[0, 0, 0, 8]);
+}
+
+#[test]
+fn vectored_limit() {
+    let payload = vec![0u8; 10];
+    let mut iov = IoVecWriter::new();
+
+    assert!(iov.opaque(&payload, Some(9)).is_err());
+    assert!(iov.is_empty());
+}
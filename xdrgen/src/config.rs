@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Code generation options
+///
+/// The defaults match `generate()` and `compile()`. Options are set builder-style, and the
+/// result passed to `generate_with()` or `compile_with()`:
+///
+/// ```ignore
+/// let config = xdrgen::Config::new().validate(true);
+/// xdrgen::compile_with("src/simple.x", &config).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub(crate) validate: bool,
+    pub(crate) xdr_strings: bool,
+    pub(crate) strict_strings: bool,
+    pub(crate) skip: bool,
+    pub(crate) arbitrary: bool,
+    pub(crate) describe: bool,
+    pub(crate) bounded_vecs: bool,
+    pub(crate) inline_vecs: Option<usize>,
+    pub(crate) opt_arrays: bool,
+    pub(crate) status_unions: bool,
+    pub(crate) source_locations: bool,
+    pub(crate) schema_fingerprint: bool,
+    pub(crate) bitmasks: bool,
+    pub(crate) borrowed: bool,
+    pub(crate) overrides: BTreeMap<String, (String, Option<String>)>,
+    pub(crate) int_type: Option<String>,
+    pub(crate) uint_type: Option<String>,
+    pub(crate) newtypes: BTreeSet<String>,
+    pub(crate) prefixes: Vec<(String, Option<String>)>,
+    pub(crate) keep_prefix_collisions: bool,
+    pub(crate) skip_malformed: bool,
+}
+
+impl Config {
+    /// Create a new configuration with the default options.
+    pub fn new() -> Config {
+        Default::default()
+    }
+
+    /// Call `xdr_codec::Validate::validate()` on every generated type once it has been unpacked.
+    ///
+    /// When enabled, each type in the specification must implement `Validate`.
+    pub fn validate(mut self, validate: bool) -> Config {
+        self.validate = validate;
+        self
+    }
+
+    /// Represent `string<>` as `xdr_codec::XdrString` rather than `String`.
+    ///
+    /// This preserves strings which aren't valid UTF-8, rather than failing to unpack them.
+    pub fn xdr_strings(mut self, xdr_strings: bool) -> Config {
+        self.xdr_strings = xdr_strings;
+        self
+    }
+
+    /// Reject `string<>` values which contain anything other than printable ASCII when
+    /// unpacking.
+    ///
+    /// Strict strings are always valid UTF-8, so this takes precedence over `xdr_strings`.
+    pub fn strict_strings(mut self, strict_strings: bool) -> Config {
+        self.strict_strings = strict_strings;
+        self
+    }
+
+    /// Implement `xdr_codec::Skip` for every generated type, so values can be skipped over
+    /// without unpacking them.
+    pub fn skip(mut self, skip: bool) -> Config {
+        self.skip = skip;
+        self
+    }
+
+    /// Implement `arbitrary::Arbitrary` for every generated type, for property testing and
+    /// fuzzing. Generated values respect the specification's size limits, so they can always be
+    /// packed.
+    ///
+    /// The generated code uses `xdr_codec::arbitrary`, so needs xdr-codec's `arbitrary` feature.
+    pub fn arbitrary(mut self, arbitrary: bool) -> Config {
+        self.arbitrary = arbitrary;
+        self
+    }
+
+    /// Implement `xdr_codec::describe::Describe` for every generated type, so its structure can
+    /// be inspected at runtime.
+    ///
+    /// Types used by the specification but not defined in it must also implement `Describe`.
+    pub fn describe(mut self, describe: bool) -> Config {
+        self.describe = describe;
+        self
+    }
+
+    /// Represent bounded arrays of elements, such as `int v<10>`, as `xdr_codec::BoundedVec`
+    /// rather than `Vec`, so the limit is part of the type and is checked whenever the length
+    /// changes. Opaques, strings and unbounded arrays are unaffected.
+    pub fn bounded_vecs(mut self, bounded_vecs: bool) -> Config {
+        self.bounded_vecs = bounded_vecs;
+        self
+    }
+
+    /// Represent bounded arrays of elements with a limit of at most `max`, such as `int v<4>`, as
+    /// `xdr_codec::inline::ArrayVec`, which stores them inline rather than allocating. This
+    /// takes precedence over `bounded_vecs` for those arrays.
+    ///
+    /// The generated code needs xdr-codec's `arrayvec` feature.
+    pub fn inline_vecs(mut self, max: Option<usize>) -> Config {
+        self.inline_vecs = max;
+        self
+    }
+
+    /// Represent arrays of at most one element, such as `int v<1>`, as `xdr_codec::OptArray`
+    /// rather than a `Vec`. Some protocols use these for optional values instead of `int *v`, so
+    /// this gives them the same `Option` representation. This takes precedence over
+    /// `bounded_vecs` and `inline_vecs` for those arrays.
+    pub fn opt_arrays(mut self, opt_arrays: bool) -> Config {
+        self.opt_arrays = opt_arrays;
+        self
+    }
+
+    /// Represent unions switched on a status, with a case for zero and a `default`, as
+    /// `xdr_codec::StatusUnion`, which is a `Result` of the zero case's value or the status and
+    /// `default` value. For example, `union res switch (stat s) { case OK: int v; default: void;
+    /// }` becomes `StatusUnion<i32, ()>`. The status must be an `int`, `unsigned int` or enum.
+    ///
+    /// These unions can't be described, so this can't be combined with `describe`.
+    pub fn status_unions(mut self, status_unions: bool) -> Config {
+        self.status_unions = status_unions;
+        self
+    }
+
+    /// Document each generated constant and type with where it's defined in the specification,
+    /// such as `Defined at simple.x:12`, so generated code can be traced back to it.
+    pub fn source_locations(mut self, source_locations: bool) -> Config {
+        self.source_locations = source_locations;
+        self
+    }
+
+    /// Generate `pub const SCHEMA_FINGERPRINT: [u8; 32]`, a hash of the specification (see
+    /// `schema_fingerprint()`), so that programs can check they were generated from the same
+    /// revision of a protocol, for example when connecting.
+    pub fn schema_fingerprint(mut self, schema_fingerprint: bool) -> Config {
+        self.schema_fingerprint = schema_fingerprint;
+        self
+    }
+
+    /// Also generate a flags type for each group of constants which looks like a bit mask: at
+    /// least two distinct powers of two sharing a prefix, such as `MODE_READ = 1` and
+    /// `MODE_WRITE = 2`. The type is named for the prefix (`MODE`), has each flag as an
+    /// associated constant (`MODE::READ`), and packs and unpacks as an `unsigned int`. The
+    /// constants themselves are still generated as well.
+    pub fn bitmasks(mut self, bitmasks: bool) -> Config {
+        self.bitmasks = bitmasks;
+        self
+    }
+
+    /// Also generate a borrowed variant `FooRef<'a>` of each struct `Foo` with opaque or string
+    /// fields. Those fields are `&'a [u8]` or `&'a str` referring to the input, and it's unpacked
+    /// without copying them with `xdr_codec::slice::SliceCursor::unpack_ref()`. `into_owned()`
+    /// converts it to a `Foo`, and it can also be made from a `&Foo`.
+    pub fn borrowed(mut self, borrowed: bool) -> Config {
+        self.borrowed = borrowed;
+        self
+    }
+
+    /// Use the existing Rust type `rust_type`, such as `uuid::Uuid`, wherever the XDR type `name`
+    /// is used, rather than the type generated for it. The generated type is still defined, so it
+    /// can be used for conversions.
+    ///
+    /// `rust_type` must implement `Pack` and `Unpack`, unless `shim` is given. That's the path of
+    /// a module with functions to pack and unpack it, typically by converting to and from the
+    /// generated type:
+    ///
+    /// ```ignore
+    /// pub fn pack<Out: xdr_codec::Write>(val: &SystemTime, out: &mut Out) -> xdr_codec::Result<usize>;
+    /// pub fn unpack<In: xdr_codec::Read>(input: &mut In) -> xdr_codec::Result<(SystemTime, usize)>;
+    /// ```
+    ///
+    /// A type with a shim can't be used in arrays or optional values. `rust_type` must also
+    /// implement whatever traits are derived by the generated types which use it.
+    pub fn override_type<S: Into<String>>(
+        mut self,
+        name: S,
+        rust_type: S,
+        shim: Option<S>,
+    ) -> Config {
+        self.overrides
+            .insert(name.into(), (rust_type.into(), shim.map(Into::into)));
+        self
+    }
+
+    /// Use the Rust integer type `rust_type` for XDR `int`s rather than `i32`, for code which
+    /// does its arithmetic with wider types, such as `i64`, and so would otherwise need casts.
+    /// Values are still packed as `int`s, failing with `xdr_codec::ErrorKind::OutOfRange` if
+    /// they don't fit. `rust_type` must be `i32`, `i64` or `i128`.
+    ///
+    /// Arrays of them are always `Vec`s, rather than the types chosen by `bounded_vecs()`,
+    /// `inline_vecs()` or `opt_arrays()`, and unions with them in an arm aren't status unions.
+    pub fn int_type<S: Into<String>>(mut self, rust_type: S) -> Config {
+        self.int_type = Some(rust_type.into());
+        self
+    }
+
+    /// Use the Rust integer type `rust_type` for XDR `unsigned int`s rather than `u32`, as with
+    /// `int_type()`. `rust_type` must be `u32`, `u64`, `u128`, `i64` or `i128`.
+    pub fn uint_type<S: Into<String>>(mut self, rust_type: S) -> Config {
+        self.uint_type = Some(rust_type.into());
+        self
+    }
+
+    /// Generate the typedef `name` of an integer type as a newtype wrapping the integer, rather
+    /// than an alias for it, so that values of different typedefs can't be mixed up. It packs
+    /// and unpacks as the integer, and converts to and from it with `From`.
+    pub fn newtype<S: Into<String>>(mut self, name: S) -> Config {
+        self.newtypes.insert(name.into());
+        self
+    }
+
+    /// Strip `prefix` from the names of the constants and types which start with it, as C
+    /// specifications often prefix every name, such as `nfs3_status`. With `module`, they're
+    /// generated in `pub mod module` (so `nfs3_status` becomes `nfs3::status`), and otherwise
+    /// alongside everything else. May be given more than once; the longest matching prefix is
+    /// stripped.
+    ///
+    /// Other options naming types, such as `override_type()`, still use the names in the
+    /// specification.
+    pub fn strip_prefix<S: Into<String>>(mut self, prefix: S, module: Option<S>) -> Config {
+        self.prefixes.push((prefix.into(), module.map(Into::into)));
+        self
+    }
+
+    /// When stripping a prefix would give a definition the same name as another, leave its
+    /// prefix in place rather than failing.
+    pub fn keep_prefix_collisions(mut self, keep: bool) -> Config {
+        self.keep_prefix_collisions = keep;
+        self
+    }
+
+    /// Skip definitions which can't be parsed, generating code for the rest, rather than failing.
+    /// Each skipped region runs up to the next `;` which isn't inside braces; `malformed()` lists
+    /// them, and they're logged as warnings, so a large specification can be fixed up a piece at
+    /// a time.
+    pub fn skip_malformed(mut self, skip: bool) -> Config {
+        self.skip_malformed = skip;
+        self
+    }
+
+    // Whether strings are represented as `XdrString`
+    pub(crate) fn use_xdrstring(&self) -> bool {
+        self.xdr_strings && !self.strict_strings
+    }
+}
@@ -0,0 +1,162 @@
+// Include graphs
+//
+// Specifications split across several files refer to each other with C preprocessor `#include`
+// directives, which the parser otherwise ignores. Here they're followed from a root file to find
+// every file it depends on, so that each can be generated as its own module which uses the
+// modules of the files it includes.
+//
+// Includes are resolved relative to the including file, whether they're written `"file.x"` or
+// `<file.x>`. Each file is only visited once, so include guards aren't needed, and files may
+// include each other. `%#include` lines are passed through to rpcgen's C output, so they aren't
+// followed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use xdr::{Error, Result};
+
+use spec::quote_ident;
+use Config;
+
+// A file in an include graph
+pub struct Source {
+    // The file's path: the root as given, and each include joined to the including file's
+    // directory
+    pub path: PathBuf,
+    pub text: String,
+    // Indexes of the files it includes directly
+    pub includes: Vec<usize>,
+}
+
+// The file names in the include directives in `text`
+fn directives(text: &str) -> Vec<&str> {
+    text.lines()
+        .filter_map(|line| {
+            let rest = line.trim_start().strip_prefix('#')?;
+            let rest = rest.trim_start().strip_prefix("include")?.trim_start();
+            let close = match rest.chars().next()? {
+                '"' => '"',
+                '<' => '>',
+                _ => return None,
+            };
+            rest[1..].split_once(close).map(|(name, _)| name)
+        })
+        .collect()
+}
+
+struct Graph {
+    sources: Vec<Source>,
+    // Index of each file, by canonical path
+    index: HashMap<PathBuf, usize>,
+}
+
+impl Graph {
+    fn visit(&mut self, path: PathBuf) -> Result<usize> {
+        let canonical = fs::canonicalize(&path)
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))?;
+        if let Some(&idx) = self.index.get(&canonical) {
+            return Ok(idx);
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| Error::from(format!("{}: {}", path.display(), e)))?;
+
+        let idx = self.sources.len();
+        self.index.insert(canonical, idx);
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let names: Vec<_> = directives(&text).into_iter().map(String::from).collect();
+        self.sources.push(Source {
+            path,
+            text,
+            includes: Vec::new(),
+        });
+
+        let mut includes = Vec::new();
+        for name in names {
+            let inc = dir.join(&name);
+            if !inc.is_file() {
+                return Err(Error::from(format!(
+                    "{}: can't find included file {}",
+                    self.sources[idx].path.display(),
+                    name
+                )));
+            }
+            includes.push(self.visit(inc)?);
+        }
+        self.sources[idx].includes = includes;
+
+        Ok(idx)
+    }
+}
+
+// `root` followed by every file it includes, directly or indirectly
+pub fn graph(root: &Path) -> Result<Vec<Source>> {
+    let mut graph = Graph {
+        sources: Vec::new(),
+        index: HashMap::new(),
+    };
+    graph.visit(root.to_path_buf())?;
+    Ok(graph.sources)
+}
+
+// Indexes of the files `graph[idx]` includes, directly or indirectly, in graph order
+pub fn transitive(graph: &[Source], idx: usize) -> Vec<usize> {
+    let mut seen = vec![false; graph.len()];
+    let mut pending = graph[idx].includes.clone();
+    while let Some(i) = pending.pop() {
+        if !seen[i] {
+            seen[i] = true;
+            pending.extend(&graph[i].includes);
+        }
+    }
+    (0..graph.len()).filter(|&i| seen[i] && i != idx).collect()
+}
+
+// The name of the module generated for `path`: its stem, made into an identifier
+pub fn module(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => quote_ident(name).as_ref().to_string(),
+        _ => format!("_{}", name),
+    }
+}
+
+// A hash of everything the code generated for `graph[idx]` depends on: the text of the file and
+// of those it includes, the options, and the version of xdrgen
+pub fn inputs_hash(graph: &[Source], idx: usize, config: &Config) -> String {
+    let mut hash = Sha256::new();
+    let mut add = |bytes: &[u8]| {
+        hash.update((bytes.len() as u64).to_be_bytes());
+        hash.update(bytes);
+    };
+
+    add(env!("CARGO_PKG_VERSION").as_bytes());
+    add(format!("{:?}", config).as_bytes());
+    for i in Some(idx).into_iter().chain(transitive(graph, idx)) {
+        add(graph[i].path.to_string_lossy().as_bytes());
+        add(graph[i].text.as_bytes());
+    }
+
+    hash.finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[test]
+fn test_directives() {
+    let text = r#"
+#include "common.x"
+  #  include <sub/types.x>
+%#include <rpc/rpc.h>
+#define FOO
+#include "unterminated.x
+"#;
+    assert_eq!(directives(text), vec!["common.x", "sub/types.x"]);
+}
@@ -25,17 +25,30 @@ extern crate nom;
 #[macro_use]
 extern crate bitflags;
 
+extern crate sha2;
+
+use std::collections::HashSet;
 use std::env;
 use std::fmt::Display;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::result;
 
 use xdr::Result;
 
+mod config;
+pub use config::Config;
+
+mod includes;
+
+mod symbols;
+pub use symbols::{malformed, symbol_at, symbols, Malformed, Symbol};
+
 mod spec;
-use spec::{Emit, Emitpack, Symtab};
+use spec::{Defn, Emit, Emitpack, Symtab};
+pub use spec::{Rename, Unused};
 
 fn result_option<T, E>(resopt: result::Result<Option<T>, E>) -> Option<result::Result<T, E>> {
     match resopt {
@@ -49,7 +62,23 @@ fn result_option<T, E>(resopt: result::Result<Option<T>, E>) -> Option<result::R
 ///
 /// `infile` is simply a string used in error messages; it may be empty. `input` is a read stream of
 /// the specification, and `output` is where the generated code is sent.
-pub fn generate<In, Out>(infile: &str, mut input: In, mut output: Out) -> Result<()>
+pub fn generate<In, Out>(infile: &str, input: In, output: Out) -> Result<()>
+where
+    In: Read,
+    Out: Write,
+{
+    generate_with(infile, input, output, &Config::default())
+}
+
+/// Generate Rust code from an RFC4506 XDR specification, with options
+///
+/// As with `generate()`, but the generated code is controlled by `config`.
+pub fn generate_with<In, Out>(
+    infile: &str,
+    mut input: In,
+    output: Out,
+    config: &Config,
+) -> Result<()>
 where
     In: Read,
     Out: Write,
@@ -58,18 +87,86 @@ where
 
     input.read_to_string(&mut source)?;
 
-    let xdr = match spec::specification(&source) {
-        Ok(defns) => Symtab::new(&defns),
-        Err(e) => return Err(xdr::Error::from(format!("parse error: {}", e))),
+    generate_source(infile, &source, Vec::new(), output, config)
+}
+
+// Parse `source` into definitions, with the span of each, skipping those which can't be parsed
+// if `config` allows it
+fn parse(infile: &str, source: &str, config: &Config) -> Result<Vec<(Range<usize>, Defn)>> {
+    if !config.skip_malformed {
+        return spec::spanned_specification(source)
+            .map_err(|e| xdr::Error::from(format!("parse error: {}", e)));
+    }
+
+    let (defns, errors) = spec::recovering_specification(source);
+    for (span, msg) in errors {
+        warn!(
+            "{}:{}: skipped: {}",
+            infile,
+            spec::line_at(source, span.start),
+            msg
+        );
+    }
+    Ok(defns)
+}
+
+// Generate Rust code for the definitions in `source`, which may refer to the `included`
+// definitions without generating them
+fn generate_source<Out: Write>(
+    infile: &str,
+    source: &str,
+    included: Vec<Defn>,
+    mut output: Out,
+    config: &Config,
+) -> Result<()> {
+    if config.status_unions && config.describe {
+        return Err(xdr::Error::from(
+            "status unions can't be described".to_string(),
+        ));
+    }
+
+    let (xdr, own, prefixes) = match parse(infile, source, config) {
+        Ok(defns) => {
+            let names: Vec<_> = included
+                .iter()
+                .chain(defns.iter().map(|(_, d)| d))
+                .map(Defn::name)
+                .collect();
+            let prefixes = spec::Prefixes::new(&names, config)?;
+            let defns: Vec<_> = defns
+                .iter()
+                .map(|(span, d)| (span.clone(), prefixes.defn(d)))
+                .collect();
+
+            let own: HashSet<String> = defns.iter().map(|(_, d)| d.name().to_string()).collect();
+            let mut all: Vec<_> = included.iter().map(|d| prefixes.defn(d)).collect();
+            all.extend(defns.iter().map(|(_, d)| d.clone()));
+            let mut symtab = Symtab::new(&all, &prefixes.config(config));
+            let lines: Vec<_> = defns
+                .iter()
+                .map(|(span, d)| (spec::line_at(source, span.start), d.clone()))
+                .collect();
+            symtab.locate(infile, &lines);
+            symtab.annotate(source, &defns)?;
+            (symtab, own, prefixes)
+        }
+        Err(e) => return Err(e),
     };
 
     let xdr = xdr;
+    let own = |name: &String| own.contains(name);
+
+    spec::check_overrides(&xdr)?;
+    spec::check_ints(&xdr)?;
+
+    // The constants and types to define in `module`, or at the top level
+    let definitions = |module: Option<&str>| -> Result<Vec<quote::Tokens>> {
+        let mine = |name: &String| own(name) && prefixes.module(name) == module;
 
-    let res: Vec<_> = {
         let consts = xdr
             .constants()
             .filter_map(|(c, &(v, ref scope))| {
-                if scope.is_none() {
+                if scope.is_none() && mine(c) {
                     Some(spec::Const(c.clone(), v))
                 } else {
                     None
@@ -79,29 +176,87 @@ where
 
         let typespecs = xdr
             .typespecs()
+            .filter(|&(n, _)| mine(n))
             .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
             .map(|c| c.define(&xdr));
 
         let typesyns = xdr
             .typesyns()
+            .filter(|&(n, _)| mine(n))
             .map(|(n, ty)| spec::Typesyn(n.clone(), ty.clone()))
             .map(|c| c.define(&xdr));
 
+        consts.chain(typespecs).chain(typesyns).collect()
+    };
+
+    let res: Vec<_> = {
+        let defns = definitions(None)?.into_iter().map(Ok);
+
+        let bitmasks = if xdr.config().bitmasks {
+            spec::bitmasks(&xdr)
+        } else {
+            Vec::new()
+        };
+        let bitmasks = bitmasks
+            .iter()
+            .filter(|b| own(&b.flags[0].1))
+            .map(|b| Ok(b.define()));
+
         let packers = xdr
             .typespecs()
+            .filter(|&(n, _)| own(n))
             .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
             .filter_map(|c| result_option(c.pack(&xdr)));
 
         let unpackers = xdr
             .typespecs()
+            .filter(|&(n, _)| own(n))
             .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
             .filter_map(|c| result_option(c.unpack(&xdr)));
 
-        consts
-            .chain(typespecs)
-            .chain(typesyns)
+        let skippers = xdr
+            .typespecs()
+            .filter(|&(n, _)| own(n))
+            .filter(|_| xdr.config().skip)
+            .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
+            .filter_map(|c| result_option(c.skip(&xdr)));
+
+        let arbitraries = xdr
+            .typespecs()
+            .filter(|&(n, _)| own(n))
+            .filter(|_| xdr.config().arbitrary)
+            .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
+            .filter_map(|c| result_option(c.arbitrary(&xdr)));
+
+        let describers = xdr
+            .typespecs()
+            .filter(|&(n, _)| own(n))
+            .filter(|_| xdr.config().describe)
+            .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
+            .filter_map(|c| result_option(c.describe(&xdr)));
+
+        let borrowed = xdr
+            .typespecs()
+            .filter(|&(n, _)| own(n))
+            .filter(|_| xdr.config().borrowed)
+            .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
+            .filter_map(|c| result_option(c.borrowed(&xdr)));
+
+        let headers = xdr
+            .typespecs()
+            .filter(|&(n, _)| own(n))
+            .map(|(n, ty)| spec::Typespec(n.clone(), ty.clone()))
+            .filter_map(|c| result_option(c.header(&xdr)));
+
+        defns
+            .chain(bitmasks)
             .chain(packers)
             .chain(unpackers)
+            .chain(skippers)
+            .chain(arbitraries)
+            .chain(describers)
+            .chain(borrowed)
+            .chain(headers)
             .collect::<Result<Vec<_>>>()?
     };
 
@@ -122,6 +277,307 @@ where
         let _ = writeln!(output, "{}\n", it.as_str());
     }
 
+    for module in prefixes.modules() {
+        let items = definitions(Some(module))?;
+        if items.is_empty() {
+            continue;
+        }
+        let module = quote::Ident::new(module);
+        let it = quote! {
+            #[allow(unused_imports)]
+            use self::#module::*;
+
+            pub mod #module {
+                #[allow(unused_imports)]
+                use super::*;
+
+                #(#items)*
+            }
+        };
+        let _ = writeln!(output, "{}\n", it.as_str());
+    }
+
+    if config.schema_fingerprint {
+        let bytes = spec::fingerprint(&xdr);
+        let bytes = bytes.iter();
+        let it = quote! {
+            /// SHA-256 fingerprint of the specification this code was generated from.
+            pub const SCHEMA_FINGERPRINT: [u8; 32] = [#(#bytes),*];
+        };
+        let _ = writeln!(output, "{}\n", it.as_str());
+    }
+
+    Ok(())
+}
+
+/// Compute the fingerprint of an XDR specification
+///
+/// The fingerprint is a SHA-256 hash of a normalized form of the specification, so it's only
+/// affected by changes to the definitions themselves, and not by comments, formatting, the order
+/// of definitions, or whether sizes and values are given as constants or literals. Two programs
+/// generated from specifications with the same fingerprint use the same encoding and names.
+///
+/// This is the same as the `SCHEMA_FINGERPRINT` constant generated with
+/// `Config::schema_fingerprint()`.
+pub fn schema_fingerprint<In: Read>(mut input: In) -> Result<[u8; 32]> {
+    let mut source = String::new();
+
+    input.read_to_string(&mut source)?;
+
+    match spec::located_specification(&source) {
+        Ok(defns) => {
+            let defns = defns.into_iter().map(|(_, defn)| defn).collect();
+            Ok(spec::fingerprint(&Symtab::new(&defns, &Config::default())))
+        }
+        Err(e) => Err(xdr::Error::from(format!("parse error: {}", e))),
+    }
+}
+
+/// Write a map from the definitions in an XDR specification to where they're defined
+///
+/// This is a machine-readable form of the locations `Config::source_locations()` adds to the
+/// generated code. Each line of `output` is the name of a constant or type, a tab, and then
+/// `infile:line`, in the order they're defined. The generated items have the same names, apart
+/// from those which are Rust keywords, which have a `_` appended.
+pub fn source_map<In, Out>(infile: &str, mut input: In, mut output: Out) -> Result<()>
+where
+    In: Read,
+    Out: Write,
+{
+    let mut source = String::new();
+
+    input.read_to_string(&mut source)?;
+
+    let defns = match spec::located_specification(&source) {
+        Ok(defns) => defns,
+        Err(e) => return Err(xdr::Error::from(format!("parse error: {}", e))),
+    };
+
+    for (line, defn) in defns {
+        writeln!(output, "{}\t{}:{}", defn.name(), infile, line)?;
+    }
+
+    Ok(())
+}
+
+/// Find the identifiers renamed in the generated code
+///
+/// Most names in the generated code are the same as in the specification, but those which are
+/// Rust keywords have a `_` appended, and union cases with numeric values are named for the value
+/// (`Const1`, `Const_1` for `-1`, and so on), with the reason `keyword` or `numeric-case`.
+///
+/// Names which only differ by case from an earlier one in the same scope are also included, with
+/// the reason `case-collision` and the earlier name in place of the generated name. They aren't
+/// renamed, but they will clash in languages which aren't case-sensitive.
+pub fn renames(source: &str) -> Result<Vec<Rename>> {
+    let defns: Vec<_> = match spec::located_specification(source) {
+        Ok(defns) => defns.into_iter().map(|(_, defn)| defn).collect(),
+        Err(e) => return Err(xdr::Error::from(format!("parse error: {}", e))),
+    };
+
+    Ok(spec::renames(&defns))
+}
+
+/// Write a report of the identifiers renamed in the generated code
+///
+/// This is `renames()` as text. Each line of `output` is tab-separated: the type the name is in
+/// (empty for top-level definitions), the name in the specification, the generated name, and why
+/// it was changed.
+pub fn rename_report<In, Out>(mut input: In, mut output: Out) -> Result<()>
+where
+    In: Read,
+    Out: Write,
+{
+    let mut source = String::new();
+
+    input.read_to_string(&mut source)?;
+
+    for rename in renames(&source)? {
+        writeln!(
+            output,
+            "{}\t{}\t{}\t{}",
+            rename.scope.as_ref().map_or("", String::as_str),
+            rename.from,
+            rename.to,
+            rename.reason
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Find the definitions in an XDR specification which are never used
+///
+/// A definition is used if it's one of `roots`, or if a used definition refers to it, whether as
+/// a type, the size of an array, a union case or the value of an enum member. If `roots` is empty,
+/// every struct, union and enum is used, so only unused typedefs and constants are found. They're
+/// returned in the order they're defined.
+pub fn unused(source: &str, roots: &[&str]) -> Result<Vec<Unused>> {
+    let defns = match spec::located_specification(source) {
+        Ok(defns) => defns,
+        Err(e) => return Err(xdr::Error::from(format!("parse error: {}", e))),
+    };
+    let symtab = Symtab::new(
+        &defns.iter().map(|(_, d)| d.clone()).collect(),
+        &Config::default(),
+    );
+    if let Some(root) = roots
+        .iter()
+        .find(|r| !defns.iter().any(|(_, d)| d.name() == **r))
+    {
+        return Err(xdr::Error::from(format!("unknown root {}", root)));
+    }
+
+    Ok(spec::unused(&defns, &symtab, roots))
+}
+
+/// Write a report of the definitions in an XDR specification which are never used
+///
+/// This is `unused()` as text. Each line of `output` is tab-separated: the kind of definition,
+/// its name, and `infile:line`.
+pub fn unused_report<In, Out>(
+    infile: &str,
+    mut input: In,
+    roots: &[&str],
+    mut output: Out,
+) -> Result<()>
+where
+    In: Read,
+    Out: Write,
+{
+    let mut source = String::new();
+
+    input.read_to_string(&mut source)?;
+
+    for unused in unused(&source, roots)? {
+        writeln!(
+            output,
+            "{}\t{}\t{}:{}",
+            unused.kind, unused.name, infile, unused.line
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Generate regression tests from sample payloads
+///
+/// Each file in the `samples` directory is a packed value of the type named by the start of its
+/// file name, up to the first `.`; for example `foo.bin` or `foo.capture-1` hold a `foo`. For each
+/// one, a `#[test]` function is written to `output` which checks that the sample unpacks as that
+/// type with no trailing data, and that packing the result again gives exactly the same bytes.
+///
+/// The tests refer to the types and to `xdr_codec` by name, so they should be included in the
+/// same module as the code generated from the specification. The samples are embedded with
+/// `include_bytes!()`, so they must stay where they are. Files whose names start with `.` are
+/// ignored; any other file not named for a type in the specification is an error.
+pub fn golden_tests<In, Out>(
+    infile: &str,
+    mut input: In,
+    samples: &Path,
+    mut output: Out,
+) -> Result<()>
+where
+    In: Read,
+    Out: Write,
+{
+    let mut source = String::new();
+
+    input.read_to_string(&mut source)?;
+
+    let defns = match spec::located_specification(&source) {
+        Ok(defns) => defns,
+        Err(e) => return Err(xdr::Error::from(format!("parse error: {}", e))),
+    };
+    let types: HashSet<_> = defns
+        .iter()
+        .filter(|(_, defn)| !matches!(defn, spec::Defn::Const(..)))
+        .map(|(_, defn)| defn.name())
+        .collect();
+
+    let mut files = Vec::new();
+    for ent in fs::read_dir(samples)? {
+        let ent = ent?;
+        if ent.file_type()?.is_file() && !ent.file_name().to_string_lossy().starts_with('.') {
+            files.push(ent.path());
+        }
+    }
+    files.sort();
+
+    let _ = writeln!(
+        output,
+        r#"
+// GENERATED CODE
+//
+// Golden tests for {} generated by xdrgen from {}.
+//
+// DO NOT EDIT
+"#,
+        infile,
+        samples.display()
+    );
+
+    let mut names = HashSet::new();
+    for file in files {
+        let fname = file
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| xdr::Error::from(format!("bad sample name {}", file.display())))?;
+        let ty = fname.split('.').next().unwrap_or(fname);
+        if !types.contains(ty) {
+            return Err(xdr::Error::from(format!(
+                "sample {}: no type {} in {}",
+                fname, ty, infile
+            )));
+        }
+
+        let test: String = fname
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if !names.insert(test.clone()) {
+            return Err(xdr::Error::from(format!(
+                "sample {}: duplicate test name golden_{}",
+                fname, test
+            )));
+        }
+
+        let path = fs::canonicalize(&file)?;
+        let path = path
+            .to_str()
+            .ok_or_else(|| xdr::Error::from(format!("bad sample path {}", path.display())))?;
+
+        writeln!(
+            output,
+            r#"#[test]
+fn golden_{test}() {{
+    use xdr_codec::{{Pack, Unpack}};
+
+    let name = {fname:?};
+    let bytes: &[u8] = include_bytes!({path:?});
+    let mut input = bytes;
+    let (val, _) = <{ty} as Unpack<_>>::unpack(&mut input)
+        .unwrap_or_else(|e| panic!("unpacking {{}}: {{}}", name, e));
+    assert!(input.is_empty(), "{{}} bytes of trailing data in {{}}", input.len(), name);
+
+    let mut buf = Vec::new();
+    val.pack(&mut buf).unwrap_or_else(|e| panic!("packing {{}}: {{}}", name, e));
+    assert_eq!(buf, bytes, "packing {{}} gave different bytes", name);
+}}
+"#,
+            test = test,
+            path = path,
+            ty = spec::quote_ident(ty),
+            fname = fname,
+        )?;
+    }
+
     Ok(())
 }
 
@@ -153,7 +609,18 @@ pub fn compile<P>(infile: P) -> Result<()>
 where
     P: AsRef<Path> + Display,
 {
-    let input = File::open(&infile)?;
+    compile_with(infile, &Config::default())
+}
+
+/// Generate Rust code from an XDR specification into OUT_DIR, with options
+///
+/// As with `compile()`, but the generated code is controlled by `config`.
+pub fn compile_with<P>(infile: P, config: &Config) -> Result<()>
+where
+    P: AsRef<Path> + Display,
+{
+    let mut source = String::new();
+    File::open(&infile)?.read_to_string(&mut source)?;
 
     let mut outdir = PathBuf::from(env::var("OUT_DIR").unwrap_or(String::from(".")));
     let outfile = PathBuf::from(infile.as_ref())
@@ -168,9 +635,183 @@ where
 
     let output = File::create(outdir)?;
 
-    generate(
+    if config.skip_malformed {
+        warn_malformed(&infile, &source);
+    }
+
+    generate_with(
         infile.as_ref().as_os_str().to_str().unwrap_or("<unknown>"),
-        input,
+        source.as_bytes(),
         output,
+        config,
     )
 }
+
+// Tell cargo about the definitions in `source` which are skipped, so the build shows them
+fn warn_malformed<P: AsRef<Path>>(infile: P, source: &str) {
+    for m in malformed(source) {
+        println!(
+            "cargo:warning={}:{}: skipped: {}",
+            infile.as_ref().display(),
+            m.line,
+            m.message
+        );
+    }
+}
+
+/// Generate a module for an XDR specification and for each file it includes
+///
+/// `#include "file.x"` (or `<file.x>`) directives in `infile` are followed, relative to the
+/// including file, and each file's definitions are generated into `outdir` as `{stem}_xdr.rs`.
+/// Definitions from included files are used, but not generated again. `{stem}_xdr_modules.rs`,
+/// named for `infile`, puts each file's code in a module named for its stem, which uses the
+/// modules for the files it includes and the enclosing module, so types which aren't defined in
+/// any of the files can be defined there:
+///
+/// ```ignore
+/// mod nfs {
+///    include!(concat!(env!("OUT_DIR"), "/nfs_xdr_modules.rs"));
+/// }
+/// ```
+///
+/// Each generated file starts with a hash of its inputs: its own text and that of the files it
+/// includes, and `config`. A file whose hash hasn't changed isn't written again, so only the
+/// modules affected by an edit are regenerated. Returns the path of every file in the include
+/// graph, starting with `infile`.
+pub fn generate_includes<P, Q>(infile: P, outdir: Q, config: &Config) -> Result<Vec<PathBuf>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let graph = includes::graph(infile.as_ref())?;
+    let outdir = outdir.as_ref();
+
+    let mut modules: Vec<String> = Vec::new();
+    for src in &graph {
+        let module = includes::module(&src.path);
+        if modules.contains(&module) {
+            return Err(xdr::Error::from(format!(
+                "{}: module {} is already generated for another file",
+                src.path.display(),
+                module
+            )));
+        }
+        modules.push(module);
+    }
+
+    let defns = graph
+        .iter()
+        .map(|src| {
+            let infile = src.path.display().to_string();
+            match parse(&infile, &src.text, config) {
+                Ok(defns) => Ok(defns.into_iter().map(|(_, defn)| defn).collect()),
+                Err(e) => Err(xdr::Error::from(format!("{}: {}", infile, e))),
+            }
+        })
+        .collect::<Result<Vec<Vec<Defn>>>>()?;
+
+    let mut mods = Vec::new();
+    writeln!(
+        mods,
+        r#"
+// GENERATED CODE
+//
+// Modules generated from {} by xdrgen.
+//
+// DO NOT EDIT
+"#,
+        graph[0].path.display()
+    )?;
+
+    for (idx, src) in graph.iter().enumerate() {
+        let uses = includes::transitive(&graph, idx);
+
+        let header = format!(
+            "// xdrgen inputs {}\n",
+            includes::inputs_hash(&graph, idx, config)
+        );
+        let outfile = outdir.join(format!("{}_xdr.rs", modules[idx]));
+        let current = fs::read_to_string(&outfile)
+            .map(|code| code.starts_with(&header))
+            .unwrap_or(false);
+        if !current {
+            let included = uses.iter().flat_map(|&i| defns[i].clone()).collect();
+            let mut code = header.into_bytes();
+            generate_source(
+                &src.path.display().to_string(),
+                &src.text,
+                included,
+                &mut code,
+                config,
+            )?;
+            fs::write(&outfile, code)?;
+        }
+
+        writeln!(mods, "#[allow(dead_code)]\npub mod {} {{", modules[idx])?;
+        writeln!(mods, "    #[allow(unused_imports)]\n    use xdr_codec;")?;
+        writeln!(mods, "    #[allow(unused_imports)]\n    use super::*;")?;
+        for &i in &uses {
+            writeln!(
+                mods,
+                "    #[allow(unused_imports)]\n    use super::{}::*;",
+                modules[i]
+            )?;
+        }
+        writeln!(mods, "\n    include!(\"{}_xdr.rs\");\n}}\n", modules[idx])?;
+    }
+
+    let modsfile = outdir.join(format!("{}_xdr_modules.rs", modules[0]));
+    if fs::read(&modsfile).ok().as_ref() != Some(&mods) {
+        fs::write(&modsfile, mods)?;
+    }
+
+    Ok(graph.into_iter().map(|src| src.path).collect())
+}
+
+/// Generate modules for an XDR specification and the files it includes into OUT_DIR
+///
+/// This is `generate_includes()` for a build.rs script, as `compile()` is for `generate()`. It
+/// also tells cargo to run the script again if any of the files changes.
+pub fn compile_includes<P: AsRef<Path>>(infile: P) -> Result<()> {
+    compile_includes_with(infile, &Config::default())
+}
+
+/// Generate modules for an XDR specification and the files it includes into OUT_DIR, with options
+///
+/// As with `compile_includes()`, but the generated code is controlled by `config`.
+pub fn compile_includes_with<P: AsRef<Path>>(infile: P, config: &Config) -> Result<()> {
+    let outdir = PathBuf::from(env::var("OUT_DIR").unwrap_or(String::from(".")));
+
+    for path in generate_includes(infile, outdir, config)? {
+        println!("cargo:rerun-if-changed={}", path.display());
+        if config.skip_malformed {
+            warn_malformed(&path, &fs::read_to_string(&path)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the include graph of an XDR specification as make dependencies
+///
+/// Each line of `output` is a file, a colon, and the files it includes directly, separated by
+/// spaces, for `infile` and then every file it includes, directly or indirectly. Includes are
+/// found as they are by `generate_includes()`.
+pub fn include_graph<P, Out>(infile: P, mut output: Out) -> Result<()>
+where
+    P: AsRef<Path>,
+    Out: Write,
+{
+    let graph = includes::graph(infile.as_ref())?;
+    let escape = |path: &Path| path.display().to_string().replace(' ', "\\ ");
+
+    for src in &graph {
+        write!(output, "{}:", escape(&src.path))?;
+        for &i in &src.includes {
+            write!(output, " {}", escape(&graph[i].path))?;
+        }
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+// Bit mask recognition
+//
+// Specifications often define the flags in a bit mask as a group of constants sharing a prefix,
+// such as `MODE_READ = 1`, `MODE_WRITE = 2` and `MODE_EXEC = 4`, leaving it to the user to or
+// them together into an `unsigned int`. With `Config::bitmasks()`, each group is also generated
+// as a flags type named for the prefix, which packs and unpacks as that `unsigned int`.
+//
+// A group is all the top-level constants with a given prefix (ending in `_`) whose values are
+// distinct powers of two, of which there must be at least two. Each constant is put in the group
+// for the shortest prefix for which that holds, so `FILE_MODE_READ` and `FILE_MODE_WRITE` make
+// `FILE_MODE` if there are also `FILE_TYPE_*` constants with the same values. Groups whose name
+// is already used, or whose flags wouldn't be identifiers, are left alone.
+
+use std::collections::BTreeMap;
+
+use quote::Tokens;
+
+use super::{quote_ident, Symtab};
+
+// A group of constants making up a bit mask
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmask {
+    pub name: String,
+    // Each flag's name without the prefix, its constant's name, and its value
+    pub flags: Vec<(String, String, u32)>,
+}
+
+fn is_flag(val: i64) -> bool {
+    val > 0 && val <= 1 << 31 && val & (val - 1) == 0
+}
+
+// Prefixes of `name` ending in `_`, shortest first, leaving something after the prefix
+fn prefixes(name: &str) -> impl Iterator<Item = &str> {
+    name.match_indices('_')
+        .map(move |(idx, _)| &name[..idx + 1])
+        .filter(move |prefix| prefix.len() < name.len() && prefix.len() > 1)
+}
+
+// The bit masks made by the constants in `symtab`, in order of name
+pub fn bitmasks(symtab: &Symtab) -> Vec<Bitmask> {
+    let flags: Vec<(&str, u32)> = symtab
+        .constants()
+        .filter(|&(_, &(val, ref scope))| scope.is_none() && is_flag(val))
+        .map(|(name, &(val, _))| (name.as_str(), val as u32))
+        .collect();
+
+    // Whether the flags with `prefix` have distinct values, and there's more than one
+    let valid = |prefix: &str| {
+        let mut vals: Vec<_> = flags
+            .iter()
+            .filter(|&&(name, _)| name.starts_with(prefix))
+            .map(|&(_, val)| val)
+            .collect();
+        let len = vals.len();
+        vals.sort();
+        vals.dedup();
+        len > 1 && vals.len() == len
+    };
+
+    let mut groups: BTreeMap<&str, Vec<(&str, u32)>> = BTreeMap::new();
+    for &(name, val) in &flags {
+        if let Some(prefix) = prefixes(name).find(|prefix| valid(prefix)) {
+            groups.entry(prefix).or_default().push((name, val));
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|(prefix, consts)| {
+            let name = prefix.trim_end_matches('_');
+            let taken = symtab.getconst(&name.to_string()).is_some()
+                || symtab
+                    .typespecs()
+                    .chain(symtab.typesyns())
+                    .any(|(n, _)| n == name);
+            let flags: Vec<_> = consts
+                .into_iter()
+                .map(|(cname, val)| (cname[prefix.len()..].to_string(), cname.to_string(), val))
+                .collect();
+            let idents = flags
+                .iter()
+                .all(|(flag, _, _)| flag.starts_with(|c: char| c.is_alphabetic() || c == '_'));
+
+            if taken || !idents {
+                None
+            } else {
+                Some(Bitmask {
+                    name: name.to_string(),
+                    flags,
+                })
+            }
+        })
+        .collect()
+}
+
+impl Bitmask {
+    pub fn define(&self) -> Tokens {
+        let name = quote_ident(&self.name);
+        let doc = format!("Flags from the `{}_*` constants.", self.name);
+        let flags: Vec<_> = self
+            .flags
+            .iter()
+            .map(|(flag, cname, val)| {
+                let flag = quote_ident(flag);
+                let doc = format!("`{}`", cname);
+                quote!(#[doc = #doc] pub const #flag: #name = #name(#val);)
+            })
+            .collect();
+        let all = self.flags.iter().fold(0, |all, &(_, _, val)| all | val);
+
+        let op = |tr: &str, method: &str, expr: Tokens| {
+            let tr = quote_ident(tr);
+            let method = quote_ident(method);
+            quote! {
+                impl ::std::ops::#tr for #name {
+                    type Output = #name;
+                    fn #method(self, other: #name) -> #name { #name(#expr) }
+                }
+            }
+        };
+        let assign = |tr: &str, method: &str, expr: Tokens| {
+            let tr = quote_ident(tr);
+            let method = quote_ident(method);
+            quote! {
+                impl ::std::ops::#tr for #name {
+                    fn #method(&mut self, other: #name) { self.0 = #expr; }
+                }
+            }
+        };
+        let ops = vec![
+            op("BitOr", "bitor", quote!(self.0 | other.0)),
+            op("BitAnd", "bitand", quote!(self.0 & other.0)),
+            op("BitXor", "bitxor", quote!(self.0 ^ other.0)),
+            op("Sub", "sub", quote!(self.0 & !other.0)),
+            assign("BitOrAssign", "bitor_assign", quote!(self.0 | other.0)),
+            assign("BitAndAssign", "bitand_assign", quote!(self.0 & other.0)),
+            assign("BitXorAssign", "bitxor_assign", quote!(self.0 ^ other.0)),
+            assign("SubAssign", "sub_assign", quote!(self.0 & !other.0)),
+        ];
+
+        quote! {
+            #[doc = #doc]
+            ///
+            /// Bits which aren't defined flags are kept when unpacking, so they can be passed on.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+            pub struct #name(pub u32);
+
+            impl #name {
+                #(#flags)*
+
+                /// No flags set.
+                pub fn empty() -> #name { #name(0) }
+
+                /// All the defined flags set.
+                pub fn all() -> #name { #name(#all) }
+
+                /// The flags in `bits`, or `None` if any of them aren't defined.
+                pub fn from_bits(bits: u32) -> Option<#name> {
+                    if bits & !#all == 0 { Some(#name(bits)) } else { None }
+                }
+
+                /// The flags in `bits`, ignoring any which aren't defined.
+                pub fn from_bits_truncate(bits: u32) -> #name { #name(bits & #all) }
+
+                /// The raw value of the flags.
+                pub fn bits(&self) -> u32 { self.0 }
+
+                /// Whether no flags are set.
+                pub fn is_empty(&self) -> bool { self.0 == 0 }
+
+                /// Whether all the flags in `other` are set.
+                pub fn contains(&self, other: #name) -> bool { self.0 & other.0 == other.0 }
+
+                /// Whether any of the flags in `other` are set.
+                pub fn intersects(&self, other: #name) -> bool { self.0 & other.0 != 0 }
+
+                /// Set the flags in `other`.
+                pub fn insert(&mut self, other: #name) { self.0 |= other.0 }
+
+                /// Clear the flags in `other`.
+                pub fn remove(&mut self, other: #name) { self.0 &= !other.0 }
+            }
+
+            #(#ops)*
+
+            impl ::std::ops::Not for #name {
+                type Output = #name;
+                fn not(self) -> #name { #name(!self.0 & #all) }
+            }
+
+            impl<Out: xdr_codec::Write> xdr_codec::Pack<Out> for #name {
+                #[inline]
+                fn pack(&self, out: &mut Out) -> xdr_codec::Result<usize> {
+                    xdr_codec::Pack::pack(&self.0, out)
+                }
+            }
+
+            impl<In: xdr_codec::Read> xdr_codec::Unpack<In> for #name {
+                #[inline]
+                fn unpack(input: &mut In) -> xdr_codec::Result<(#name, usize)> {
+                    let (bits, sz) = xdr_codec::Unpack::unpack(input)?;
+                    Ok((#name(bits), sz))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Borrowed variants of structs
+//
+// Unpacking a struct copies its opaque and string data out of the input. When the input is
+// already in memory, that copy can be avoided: with `Config::borrowed()`, each struct with
+// opaque or string fields also gets a `FooRef<'a>` variant whose fields of those types borrow
+// from the input, unpacked with `xdr_codec::slice::UnpackRef` from a `SliceCursor`.
+//
+// Only the struct's own fields borrow, including through typedefs; fields of other types, such
+// as nested structs or arrays of strings, are unpacked as their owned types. `into_owned()` (and
+// `From`) copies a `FooRef` into a `Foo`, and a `FooRef` can be made from a `&Foo` to share code
+// between the two. Structs with lazy fields don't have a borrowed variant.
+
+use quote::Tokens;
+
+use super::{quote_ident, Decl, Result, Symtab, Type, Typespec, Value};
+
+// How a field is represented in the borrowed variant
+enum Field {
+    // Variable-length opaque data, as `&[u8]`
+    Opaque(Tokens),
+    // Fixed-length opaque data or string, as `&[u8]`, with the length
+    Fixed(Tokens),
+    // Variable-length string, as `&str`
+    Str(Tokens),
+    // Variable-length string, as `&[u8]` for `xdr_codec::XdrString`
+    XdrStr(Tokens),
+    // Anything else, which is unpacked as its owned type
+    Owned,
+}
+
+// The field representation for `ty`, looking through typedefs which aren't overridden, and the
+// newtype wrapping the owned value if it's defined by a typedef
+fn field(ty: &Type, symtab: &Symtab) -> (Field, Option<String>) {
+    use self::Type::*;
+
+    let maxsz = |maxsz: &::std::option::Option<Value>| match *maxsz {
+        None => quote!(None),
+        Some(ref mx) => {
+            let mx = mx.as_token(symtab);
+            quote!(Some(#mx as usize))
+        }
+    };
+
+    let f = match ty {
+        Flex(ty, mx) => match **ty {
+            Opaque => Field::Opaque(maxsz(mx)),
+            String if symtab.config().use_xdrstring() => Field::XdrStr(maxsz(mx)),
+            String => Field::Str(maxsz(mx)),
+            _ => Field::Owned,
+        },
+        Array(ty, sz) => match **ty {
+            Opaque | String => Field::Fixed(sz.as_token(symtab)),
+            _ => Field::Owned,
+        },
+        Ident(name, _) if !symtab.config().overrides.contains_key(name) => {
+            // Typedefs of arrays are newtypes, and other typedefs are aliases
+            if let Some(ty) = symtab.typespecs.get(name) {
+                return match field(ty, symtab) {
+                    (Field::Owned, _) => (Field::Owned, None),
+                    (f, _) => (f, Some(name.clone())),
+                };
+            }
+            match symtab.typesyns.get(name) {
+                Some(ty) => return field(ty, symtab),
+                None => Field::Owned,
+            }
+        }
+        _ => Field::Owned,
+    };
+    (f, None)
+}
+
+impl Typespec {
+    // The borrowed variant of a struct, if it has any fields which can borrow
+    pub fn borrowed(&self, symtab: &Symtab) -> Result<Option<Tokens>> {
+        let decls = match self.1 {
+            // Lazy fields keep their encoding, so there's nothing to borrow
+            Type::Struct(_) if symtab.has_lazy(&self.0) => return Ok(None),
+            Type::Struct(ref decls) => decls,
+            _ => return Ok(None),
+        };
+        let fields: Vec<_> = decls
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::Named(name, ty) => {
+                    let (f, newtype) = field(ty, symtab);
+                    Some((name, ty, f, newtype))
+                }
+                Decl::Void => None,
+            })
+            .collect();
+        if fields.iter().all(|(_, _, f, _)| matches!(f, Field::Owned)) {
+            return Ok(None);
+        }
+
+        let refname = format!("{}Ref", self.0);
+        if symtab.typespec(&refname).is_some() {
+            return Err(format!(
+                "can't generate {} for {}: the name is already used",
+                refname, self.0
+            )
+            .into());
+        }
+
+        let name = quote_ident(&self.0);
+        let refident = quote_ident(&refname);
+        let derive = self.1.derivable(symtab, None);
+        let doc = format!("`{}`, borrowing its opaque and string data.", self.0);
+
+        let mut defs = Vec::new();
+        let mut unpacks = Vec::new();
+        let mut owned = Vec::new();
+        let mut borrows = Vec::new();
+        for (fname, ty, f, newtype) in fields {
+            let field = quote_ident(fname);
+            let path = format!("{}.{}", self.0, fname);
+            let val = match newtype {
+                Some(_) => quote!(v.#field.0),
+                None => quote!(v.#field),
+            };
+            let (tok, unpack, own, borrow) = match f {
+                Field::Opaque(mx) => (
+                    quote!(&'a [u8]),
+                    quote!(cur.opaque_flex(#mx)),
+                    quote!(self.#field.to_vec()),
+                    quote!(&#val[..]),
+                ),
+                Field::Fixed(sz) => (
+                    quote!(&'a [u8]),
+                    quote!(cur.opaque_array(#sz as usize)),
+                    quote!({
+                        let mut buf = [0u8; #sz as usize];
+                        buf.copy_from_slice(self.#field);
+                        buf
+                    }),
+                    quote!(&#val[..]),
+                ),
+                Field::Str(mx) if symtab.config().strict_strings => (
+                    quote!(&'a str),
+                    quote!(cur.string_strict(#mx)),
+                    quote!(self.#field.to_string()),
+                    quote!(#val.as_str()),
+                ),
+                Field::Str(mx) => (
+                    quote!(&'a str),
+                    quote!(cur.string(#mx)),
+                    quote!(self.#field.to_string()),
+                    quote!(#val.as_str()),
+                ),
+                Field::XdrStr(mx) => (
+                    quote!(&'a [u8]),
+                    quote!(cur.opaque_flex(#mx)),
+                    quote!(xdr_codec::XdrString::from(self.#field)),
+                    quote!(&#val[..]),
+                ),
+                Field::Owned => {
+                    let unpack = ty.unpacker(symtab);
+                    (
+                        ty.as_token(symtab)?,
+                        quote!({
+                            let input = &mut *cur;
+                            let (v, _) = #unpack;
+                            Ok(v)
+                        }),
+                        quote!(self.#field),
+                        quote!(v.#field.clone()),
+                    )
+                }
+            };
+
+            let own = match newtype {
+                Some(newtype) => {
+                    let newtype = quote_ident(&newtype);
+                    quote!(#newtype(#own))
+                }
+                None => own,
+            };
+
+            defs.push(quote!(pub #field: #tok,));
+            unpacks.push(quote!(#field: xdr_codec::in_field(#path, || #unpack)?,));
+            owned.push(quote!(#field: #own,));
+            borrows.push(quote!(#field: #borrow,));
+        }
+
+        let into_doc = format!("Copy the borrowed data into an owned `{}`.", self.0);
+
+        Ok(Some(quote! {
+            #[doc = #doc]
+            #derive
+            pub struct #refident<'a> { #(#defs)* }
+
+            impl<'a> xdr_codec::slice::UnpackRef<'a> for #refident<'a> {
+                fn unpack_ref(cur: &mut xdr_codec::slice::SliceCursor<'a>) -> xdr_codec::Result<Self> {
+                    Ok(#refident { #(#unpacks)* })
+                }
+            }
+
+            impl<'a> #refident<'a> {
+                #[doc = #into_doc]
+                pub fn into_owned(self) -> #name {
+                    #name { #(#owned)* }
+                }
+            }
+
+            impl<'a> ::std::convert::From<#refident<'a>> for #name {
+                fn from(v: #refident<'a>) -> #name { v.into_owned() }
+            }
+
+            impl<'a> ::std::convert::From<&'a #name> for #refident<'a> {
+                fn from(v: &'a #name) -> #refident<'a> {
+                    #refident { #(#borrows)* }
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,141 @@
+// Schema fingerprints
+//
+// A fingerprint is the SHA-256 hash of a normalized text form of the specification. The text
+// only depends on the definitions themselves: they're in order of name rather than in the order
+// they were written, and sizes, enum values and union cases are resolved to numbers, so
+// comments, layout, ordering, and whether a value is written as a constant or a literal make no
+// difference. Anything which affects the encoding, or the names in the generated code, changes
+// the fingerprint.
+
+use std::fmt::Write;
+
+use sha2::{Digest, Sha256};
+
+use super::{Decl, EnumDefn, Symtab, Type, UnionCase, Value};
+
+// The normalized text of all the definitions in `symtab`
+pub fn normalized(symtab: &Symtab) -> String {
+    let mut out = String::new();
+
+    for (name, &(val, ref scope)) in symtab.constants() {
+        if scope.is_none() {
+            let _ = writeln!(out, "const {} = {};", name, val);
+        }
+    }
+    for (name, ty) in symtab.typespecs().chain(symtab.typesyns()) {
+        let _ = write!(out, "type {} = ", name);
+        ty.normalize(symtab, &mut out);
+        out.push_str(";\n");
+    }
+
+    out
+}
+
+// SHA-256 of the normalized definitions in `symtab`
+pub fn fingerprint(symtab: &Symtab) -> [u8; 32] {
+    Sha256::digest(normalized(symtab).as_bytes()).into()
+}
+
+impl Value {
+    fn normalize(&self, symtab: &Symtab, out: &mut String) {
+        match (self.as_i64(symtab), self) {
+            (Some(v), _) => {
+                let _ = write!(out, "{}", v);
+            }
+            // Not defined in the specification
+            (None, Value::Ident(id)) => out.push_str(id),
+            (None, Value::Const(v)) => {
+                let _ = write!(out, "{}", v);
+            }
+        }
+    }
+}
+
+impl Decl {
+    fn normalize(&self, symtab: &Symtab, out: &mut String) {
+        match *self {
+            Decl::Void => out.push_str("void"),
+            Decl::Named(ref name, ref ty) => {
+                ty.normalize(symtab, out);
+                let _ = write!(out, " {}", name);
+            }
+        }
+    }
+}
+
+impl Type {
+    fn normalize(&self, symtab: &Symtab, out: &mut String) {
+        use self::Type::*;
+
+        match *self {
+            UInt => out.push_str("unsigned int"),
+            Int => out.push_str("int"),
+            UHyper => out.push_str("unsigned hyper"),
+            Hyper => out.push_str("hyper"),
+            Float => out.push_str("float"),
+            Double => out.push_str("double"),
+            Quadruple => out.push_str("quadruple"),
+            Bool => out.push_str("bool"),
+            Opaque => out.push_str("opaque"),
+            String => out.push_str("string"),
+
+            Enum(ref defs) => {
+                out.push_str("enum {");
+                for EnumDefn(name, _) in defs {
+                    let _ = write!(out, " {} = ", name);
+                    Value::ident(name).normalize(symtab, out);
+                    out.push(',');
+                }
+                out.push_str(" }");
+            }
+            Struct(ref decls) => {
+                out.push_str("struct {");
+                for decl in decls {
+                    out.push(' ');
+                    decl.normalize(symtab, out);
+                    out.push(';');
+                }
+                out.push_str(" }");
+            }
+            Union(ref sel, ref cases, ref defl) => {
+                out.push_str("union switch (");
+                sel.normalize(symtab, out);
+                out.push_str(") {");
+                for UnionCase(val, decl) in cases {
+                    out.push_str(" case ");
+                    val.normalize(symtab, out);
+                    out.push_str(": ");
+                    decl.normalize(symtab, out);
+                    out.push(';');
+                }
+                if let Some(ref defl) = *defl {
+                    out.push_str(" default: ");
+                    defl.normalize(symtab, out);
+                    out.push(';');
+                }
+                out.push_str(" }");
+            }
+
+            Option(ref ty) => {
+                ty.normalize(symtab, out);
+                out.push('*');
+            }
+            Array(ref ty, ref sz) => {
+                ty.normalize(symtab, out);
+                out.push('[');
+                sz.normalize(symtab, out);
+                out.push(']');
+            }
+            Flex(ref ty, ref sz) => {
+                ty.normalize(symtab, out);
+                out.push('<');
+                if let Some(ref sz) = *sz {
+                    sz.normalize(symtab, out);
+                }
+                out.push('>');
+            }
+
+            Ident(ref name, _) => out.push_str(name),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Struct headers
+//
+// A struct with a `/* @header */` comment between two of its fields gets a `fooHeader` struct of
+// the fields before the comment, and implements `xdr_codec::header::UnpackHeader` with it. The
+// header is defined and unpacked as though it were in the specification; the rest of the struct
+// is unpacked as it would be in `foo`'s own `Unpack`, given the header's fields.
+
+use quote::Tokens;
+
+use super::{quote_ident, Decl, Emit, Emitpack, Error, Result, Symtab, Type, Typespec};
+
+impl Typespec {
+    // The header of a struct with a `@header` pragma, and its `UnpackHeader` implementation
+    pub fn header(&self, symtab: &Symtab) -> Result<Option<Tokens>> {
+        let decls = match self.1 {
+            Type::Struct(ref decls) => decls,
+            _ => return Ok(None),
+        };
+        let split = match symtab.pragma(&self.0, "header") {
+            Some(pragma) => pragma.field,
+            None => return Ok(None),
+        };
+
+        let hdrname = format!("{}Header", self.0);
+        if symtab.typespecs.contains_key(&hdrname) || symtab.typesyns.contains_key(&hdrname) {
+            return Err(Error::from(format!(
+                "can't generate the header of {}: {} is already defined",
+                self.0, hdrname
+            )));
+        }
+
+        let header = Typespec(hdrname.clone(), Type::Struct(decls[..split].to_vec()));
+        let define = header.define(symtab)?;
+        let unpack = header.unpack(symtab)?;
+
+        let name = quote_ident(&self.0);
+        let hdrident = quote_ident(&hdrname);
+        let fields: Vec<Tokens> = decls
+            .iter()
+            .enumerate()
+            .filter_map(|(i, decl)| match *decl {
+                Decl::Named(ref field, ref ty) => Some((i, field, ty)),
+                Decl::Void => None,
+            })
+            .map(|(i, field, ty)| {
+                let ident = quote_ident(field);
+                if i < split {
+                    return Ok(quote!(#ident: header.#ident,));
+                }
+                let unpack = if symtab.is_lazy(&self.0, i) {
+                    ty.lazy_unpacker(symtab)?
+                } else {
+                    ty.unpacker(symtab)
+                };
+                let path = format!("{}.{}", self.0, field);
+                Ok(quote!(#ident: {
+                    let (v, fsz) = xdr_codec::in_field(#path, || Ok(#unpack))?;
+                    sz += fsz;
+                    v
+                },))
+            })
+            .collect::<Result<_>>()?;
+
+        let body = quote!(#name { #(#fields)* });
+        let body = if symtab.config().validate {
+            quote!({
+                let v = #body;
+                xdr_codec::Validate::validate(&v)?;
+                v
+            })
+        } else {
+            body
+        };
+
+        Ok(Some(quote! {
+            #define
+
+            #unpack
+
+            impl<In: xdr_codec::Read> xdr_codec::header::UnpackHeader<In> for #name {
+                type Header = #hdrident;
+
+                fn unpack_rest(header: #hdrident, input: &mut In)
+                               -> xdr_codec::Result<(#name, usize)> {
+                    let mut sz = 0;
+                    Ok((#body, sz))
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,71 @@
+// Lazy fields
+//
+// A struct field with a `/* @lazy */` comment before it is an `xdr_codec::lazy::Lazy<T>` rather
+// than a `T`. When the struct is unpacked, the field is skipped over, keeping its encoding, and
+// it's only decoded when it's first used. Skipping uses the same code as `Config::skip()`, so
+// lazy fields of types defined in the specification need their `Skip` implementations too.
+//
+// Lazy fields aren't `Copy`, so neither are structs containing them. `Config::borrowed()` leaves
+// out the borrowed variant of structs with lazy fields, and they can't be generated with
+// `Config::arbitrary()`.
+
+use quote::Tokens;
+
+use super::{Decl, Error, Result, Symtab, Type};
+
+impl Type {
+    // The Rust type of a lazy field of this type
+    pub fn lazy_token(&self, symtab: &Symtab) -> Result<Tokens> {
+        let tok = self.as_token(symtab)?;
+        Ok(quote!(xdr_codec::lazy::Lazy<#tok>))
+    }
+
+    // Unpack a lazy field of this type, keeping its encoding, as `unpacker()` does for others
+    pub fn lazy_unpacker(&self, symtab: &Symtab) -> Result<Tokens> {
+        let skip = self.skipper_from(symtab, quote!(xdr_codec::lazy::Capture<&mut In>))?;
+        let unpack = self.unpacker(symtab);
+
+        Ok(quote!(xdr_codec::lazy::Lazy::capture(
+            input,
+            |input| Ok(#skip),
+            |input: &mut &[u8]| Ok(#unpack),
+        )?))
+    }
+}
+
+impl Symtab {
+    // Whether field `field` of struct `name` is lazy
+    pub fn is_lazy(&self, name: &str, field: usize) -> bool {
+        self.pragmas(name, "lazy").any(|p| p.field == field)
+    }
+
+    // Whether struct `name` has any lazy fields
+    pub fn has_lazy(&self, name: &str) -> bool {
+        self.pragmas(name, "lazy").next().is_some()
+    }
+}
+
+// Check the lazy fields of struct `name` can be generated
+pub fn check(symtab: &Symtab, name: &str, decls: &[Decl]) -> Result<()> {
+    if !symtab.has_lazy(name) {
+        return Ok(());
+    }
+    if symtab.config().arbitrary {
+        return Err(Error::from(format!(
+            "struct {} has lazy fields, which can't be generated with arbitrary",
+            name
+        )));
+    }
+    for (i, decl) in decls.iter().enumerate() {
+        if let Decl::Named(ref field, Type::Ident(ref ty, _)) = *decl {
+            let skippable = symtab.config().skip || !symtab.typespecs.contains_key(ty);
+            if symtab.is_lazy(name, i) && !skippable {
+                return Err(Error::from(format!(
+                    "lazy field {}.{} needs `Skip` for {}, so skipping must be enabled",
+                    name, field, ty
+                )));
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,40 @@
 use std::collections::btree_map::{BTreeMap, Iter};
 use std::collections::{HashMap, HashSet};
 use std::io::{stderr, Write};
+use std::ops::Range;
 
 use std::result;
 
 use quote::{self, ToTokens, Tokens};
 
+mod bitmask;
+mod borrowed;
+mod fingerprint;
+mod header;
+mod lazy;
+mod pragma;
+mod prefix;
+mod unused;
 mod xdr_nom;
 
 use xdr::Error;
 
 pub type Result<T> = result::Result<T, Error>;
 
+pub use self::bitmask::bitmasks;
+pub use self::fingerprint::fingerprint;
+use self::pragma::Pragma;
+pub use self::prefix::Prefixes;
+pub use self::unused::{unused, Unused};
+pub use self::xdr_nom::line_at;
+pub use self::xdr_nom::located_specification;
+pub use self::xdr_nom::recovering_specification;
+pub use self::xdr_nom::spanned_specification;
+#[cfg(test)]
 pub use self::xdr_nom::specification;
 
 use super::result_option;
+use super::Config;
 
 bitflags! {
     pub struct Derives: u32 {
@@ -72,7 +92,7 @@ lazy_static! {
     };
 }
 
-fn quote_ident<S: AsRef<str>>(id: S) -> quote::Ident {
+pub(crate) fn quote_ident<S: AsRef<str>>(id: S) -> quote::Ident {
     let id = id.as_ref();
 
     if (*KEYWORDS).contains(id) {
@@ -270,6 +290,10 @@ impl Type {
             &Ident(ref id, None) => {
                 match symtab.typespec(id) {
                     None => Derives::empty(), // unknown, really
+                    // Lazy fields aren't `Copy`
+                    Some(ty) if symtab.has_lazy(id) => {
+                        ty.derivable(symtab, Some(memo)) & !Derives::COPY
+                    }
                     Some(ref ty) => ty.derivable(symtab, Some(memo)),
                 }
             }
@@ -292,6 +316,21 @@ impl Type {
         let res = match self {
             &Enum(_) => quote!((*#val as i32).pack(out)?),
 
+            _ if self.widened(symtab).is_some() => {
+                let wire = self.widened(symtab).unwrap();
+                let rust = self.as_token(symtab)?;
+                quote!(xdr_codec::Pack::pack(&xdr_codec::narrow::<#wire, #rust>(&#val)?, out)?)
+            }
+
+            Option(ty) if ty.widened(symtab).is_some() => {
+                let wire = ty.widened(symtab).unwrap();
+                let rust = ty.as_token(symtab)?;
+                quote!(xdr_codec::Pack::pack(&match #val {
+                    Some(v) => Some(xdr_codec::narrow::<#wire, #rust>(&v)?),
+                    None => None,
+                }, out)?)
+            }
+
             &Flex(ref ty, ref maxsz) => {
                 let ty = ty.as_ref();
                 let maxsz = match maxsz {
@@ -303,7 +342,30 @@ impl Type {
                 };
                 match ty {
                     &Opaque => quote!(xdr_codec::pack_opaque_flex(&#val, #maxsz, out)?),
+                    &String if symtab.config().use_xdrstring() => {
+                        quote!(xdr_codec::pack_opaque_flex(&#val, #maxsz, out)?)
+                    }
                     &String => quote!(xdr_codec::pack_string(&#val, #maxsz, out)?),
+                    ty if ty.widened(symtab).is_some() => {
+                        let wire = ty.widened(symtab).unwrap();
+                        let rust = ty.as_token(symtab)?;
+                        quote!(xdr_codec::bulk::pack_flex(
+                            &#val.iter()
+                                .map(|v| xdr_codec::narrow::<#wire, #rust>(v))
+                                .collect::<xdr_codec::Result<Vec<_>>>()?,
+                            #maxsz,
+                            out,
+                        )?)
+                    }
+                    _ if self.is_bounded_vec(symtab)
+                        || self.is_inline_vec(symtab)
+                        || self.is_opt_array(symtab) =>
+                    {
+                        quote!(#val.pack(out)?)
+                    }
+                    &Int | &UInt | &Hyper | &UHyper | &Float | &Double => {
+                        quote!(xdr_codec::bulk::pack_flex(&#val, #maxsz, out)?)
+                    }
                     _ => quote!(xdr_codec::pack_flex(&#val, #maxsz, out)?),
                 }
             }
@@ -314,10 +376,25 @@ impl Type {
                     &Opaque | &String => {
                         quote!(xdr_codec::pack_opaque_array(&#val[..], #val.len(), out)?)
                     }
+                    ty if ty.widened(symtab).is_some() => {
+                        let wire = ty.widened(symtab).unwrap();
+                        let rust = ty.as_token(symtab)?;
+                        quote!({
+                            let v = #val.iter()
+                                .map(|v| xdr_codec::narrow::<#wire, #rust>(v))
+                                .collect::<xdr_codec::Result<Vec<_>>>()?;
+                            xdr_codec::pack_array(&v[..], v.len(), out, None)?
+                        })
+                    }
                     _ => quote!(xdr_codec::pack_array(&#val[..], #val.len(), out, None)?),
                 }
             }
 
+            Ident(name, _) if symtab.shim(name).is_some() => {
+                let shim = quote::Ident::new(symtab.shim(name).unwrap());
+                quote!(#shim::pack(&#val, out)?)
+            }
+
             _ => quote!(#val.pack(out)?),
         };
 
@@ -335,10 +412,143 @@ impl Type {
         }
     }
 
+    // Whether this is represented as an `xdr_codec::BoundedVec`
+    fn is_bounded_vec(&self, symtab: &Symtab) -> bool {
+        use self::Type::*;
+
+        match *self {
+            Flex(ref ty, Some(_)) => match *ty.as_ref() {
+                Opaque | String => false,
+                ref ty if ty.widened(symtab).is_some() => false,
+                _ => {
+                    symtab.config().bounded_vecs
+                        && !self.is_inline_vec(symtab)
+                        && !self.is_opt_array(symtab)
+                }
+            },
+            _ => false,
+        }
+    }
+
+    // Whether this is represented as an `xdr_codec::inline::ArrayVec`
+    fn is_inline_vec(&self, symtab: &Symtab) -> bool {
+        use self::Type::*;
+
+        match *self {
+            Flex(ref ty, Some(ref mx)) => match *ty.as_ref() {
+                Opaque | String => false,
+                ref ty if ty.widened(symtab).is_some() => false,
+                _ if self.is_opt_array(symtab) => false,
+                _ => match (symtab.config().inline_vecs, mx.as_i64(symtab)) {
+                    (Some(max), Some(mx)) => mx >= 0 && mx as usize <= max,
+                    _ => false,
+                },
+            },
+            _ => false,
+        }
+    }
+
+    // Whether this is represented as an `xdr_codec::OptArray`
+    fn is_opt_array(&self, symtab: &Symtab) -> bool {
+        use self::Type::*;
+
+        match *self {
+            Flex(ref ty, Some(ref mx)) => match *ty.as_ref() {
+                Opaque | String => false,
+                ref ty if ty.widened(symtab).is_some() => false,
+                _ => symtab.config().opt_arrays && mx.as_i64(symtab) == Some(1),
+            },
+            _ => false,
+        }
+    }
+
+    // The XDR type an `int` or `unsigned int` is packed as, if it's mapped to a wider Rust type
+    // with `Config::int_type()` or `Config::uint_type()`, looking through typedefs
+    fn widened(&self, symtab: &Symtab) -> Option<Tokens> {
+        use self::Type::*;
+
+        let config = symtab.config();
+        match *self {
+            Int if matches!(config.int_type.as_deref(), Some(t) if t != "i32") => Some(quote!(i32)),
+            UInt if matches!(config.uint_type.as_deref(), Some(t) if t != "u32") => {
+                Some(quote!(u32))
+            }
+            Ident(ref name, _)
+                if !config.overrides.contains_key(name) && !config.newtypes.contains(name) =>
+            {
+                symtab.typesyns.get(name).and_then(|ty| ty.widened(symtab))
+            }
+            _ => None,
+        }
+    }
+
+    // Whether this is a widened integer, or an optional value or array of them, which are packed
+    // element by element rather than with their Rust type's `Pack`
+    fn has_widened(&self, symtab: &Symtab) -> bool {
+        use self::Type::*;
+
+        match *self {
+            Option(ref ty) | Array(ref ty, _) | Flex(ref ty, _) => ty.widened(symtab).is_some(),
+            _ => self.widened(symtab).is_some(),
+        }
+    }
+
+    // The success and failure arms of a union represented as an `xdr_codec::StatusUnion`: one
+    // switched on an `int`, `unsigned int` or enum status, with a single case for zero and a
+    // `default`
+    fn status_union(&self, symtab: &Symtab) -> Option<(&Decl, &Decl)> {
+        use self::Type::*;
+
+        if !symtab.config().status_unions {
+            return None;
+        }
+
+        match *self {
+            Union(ref selector, ref cases, Some(ref defl)) if cases.len() == 1 => {
+                let status = match **selector {
+                    Decl::Named(_, Int) | Decl::Named(_, UInt) => true,
+                    Decl::Named(_, Ident(ref id, _)) => {
+                        matches!(symtab.typespec(id), Some(&Enum(_)))
+                    }
+                    _ => false,
+                };
+                let UnionCase(ref val, ref ok) = cases[0];
+                // `StatusUnion` packs its arms with their Rust types
+                let widened = [ok, &**defl].iter().any(|decl| match **decl {
+                    Decl::Named(_, ref ty) => ty.has_widened(symtab),
+                    Decl::Void => false,
+                });
+
+                if status && !widened && val.as_i64(symtab) == Some(0) {
+                    Some((ok, defl))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
     fn unpacker(&self, symtab: &Symtab) -> Tokens {
         use self::Type::*;
 
         match self {
+            _ if self.widened(symtab).is_some() => {
+                let wire = self.widened(symtab).unwrap();
+                quote!({
+                    let (v, sz): (#wire, usize) = xdr_codec::Unpack::unpack(input)?;
+                    (::std::convert::From::from(v), sz)
+                })
+            }
+
+            Option(ty) if ty.widened(symtab).is_some() => {
+                let wire = ty.widened(symtab).unwrap();
+                quote!({
+                    let (v, sz): (Option<#wire>, usize) = xdr_codec::Unpack::unpack(input)?;
+                    (v.map(::std::convert::From::from), sz)
+                })
+            }
+
             &Array(ref ty, ref value) => {
                 let ty = ty.as_ref();
                 let value = value.as_token(symtab);
@@ -346,52 +556,24 @@ impl Type {
                 match ty {
                     &Opaque | &String => {
                         quote!({
-                            let mut buf: [u8; #value as usize] = unsafe { ::std::mem::uninitialized() };
+                            let mut buf = [0u8; #value as usize];
                             let sz = xdr_codec::unpack_opaque_array(input, &mut buf[..], #value as usize)?;
                             (buf, sz)
                         })
                     }
+                    ty if ty.widened(symtab).is_some() => {
+                        let wire = ty.widened(symtab).unwrap();
+                        quote!({
+                            let (buf, sz): ([#wire; #value as usize], usize) = xdr_codec::unpack_array_fixed(input)?;
+                            (buf.map(::std::convert::From::from), sz)
+                        })
+                    }
                     ty => {
                         let ty = ty.as_token(symtab).unwrap();
-                        // Create the return array as uninitialized, since we don't know what to initialize it until
-                        // we can deserialize values. We don't even have a guaranteed value we can populate it with, since
-                        // the type may not implement Default (and it would be a waste anyway, since they're going to be
-                        // replaced).
-                        //
-                        // However, having an uninitialized array makes for lots of awkward corner cases.
-                        // Even in the common case, we can't simply use `unpack_array`, as it will replace each element
-                        // by assignment, but that will Drop any existing value - but in this case that will be undefined
-                        // as they're uninitialized. So we need to use `unpack_array_with` that allows us to specify a function
-                        // which does the initializing assignment. In this case we use `ptr::write` which overwrites memory
-                        // without Dropping the current contents.
-                        //
-                        // With that solved, we also need to deal with the error cases, where the array could be partially
-                        // initialized. For this case, `unpack_array_with` also takes a drop function which deinitializes
-                        // the partially initialized elements, so the array is left uninitialized in the failure case.
-                        // We can then just use `mem::forget` to dispose of the whole thing.
-                        //
-                        // We also need to catch panics to make sure the buf is forgotten. It may be partially initialized then
-                        // it may leak, but that's better than calling Drop on uninitialized elements.
+                        // `unpack_array_fixed` builds the array from the unpacked elements, so there's
+                        // no need for an initial value, and nothing to clean up on failure.
                         quote!({
-                            #[inline]
-                            fn uninit_ptr_setter<T>(p: &mut T, v: T) {
-                                unsafe { ::std::ptr::write(p, v) }
-                            }
-                            #[inline]
-                            fn uninit_ptr_dropper<T>(p: &mut T) {
-                                unsafe { ::std::ptr::drop_in_place(p) }
-                            }
-                            let mut buf: [#ty; #value as usize] = unsafe { ::std::mem::uninitialized() };
-                            let res = ::std::panic::catch_unwind(
-                                ::std::panic::AssertUnwindSafe(||
-                                    xdr_codec::unpack_array_with(
-                                        input, &mut buf[..], #value as usize, uninit_ptr_setter, uninit_ptr_dropper, None)));
-
-                            let sz = match res {
-                                Ok(Ok(sz)) => sz,
-                                Ok(Err(err)) => { ::std::mem::forget(buf); return Err(err); }
-                                Err(panic) => { ::std::mem::forget(buf); ::std::panic::resume_unwind(panic);  }
-                            };
+                            let (buf, sz): ([#ty; #value as usize], usize) = xdr_codec::unpack_array_fixed(input)?;
                             (buf, sz)
                         })
                     }
@@ -409,35 +591,252 @@ impl Type {
                 };
 
                 match ty {
+                    &String if symtab.config().use_xdrstring() => {
+                        quote!(xdr_codec::unpack_xdrstring(input, #maxsz)?)
+                    }
+                    &String if symtab.config().strict_strings => {
+                        quote!(xdr_codec::unpack_string_strict(input, #maxsz)?)
+                    }
                     &String => quote!(xdr_codec::unpack_string(input, #maxsz)?),
                     &Opaque => quote!(xdr_codec::unpack_opaque_flex(input, #maxsz)?),
+                    ty if ty.widened(symtab).is_some() => {
+                        let wire = ty.widened(symtab).unwrap();
+                        quote!({
+                            let (v, sz): (Vec<#wire>, usize) = xdr_codec::bulk::unpack_flex(input, #maxsz)?;
+                            (v.into_iter().map(::std::convert::From::from).collect(), sz)
+                        })
+                    }
+                    _ if self.is_bounded_vec(symtab)
+                        || self.is_inline_vec(symtab)
+                        || self.is_opt_array(symtab) =>
+                    {
+                        quote!(xdr_codec::Unpack::unpack(input)?)
+                    }
+                    &Int | &UInt | &Hyper | &UHyper | &Float | &Double => {
+                        quote!(xdr_codec::bulk::unpack_flex(input, #maxsz)?)
+                    }
                     _ => quote!(xdr_codec::unpack_flex(input, #maxsz)?),
                 }
             }
 
+            Ident(name, _) if symtab.shim(name).is_some() => {
+                let shim = quote::Ident::new(symtab.shim(name).unwrap());
+                quote!(#shim::unpack(input)?)
+            }
+
             _ => quote!(xdr_codec::Unpack::unpack(input)?),
         }
     }
 
+    fn skipper(&self, symtab: &Symtab) -> Result<Tokens> {
+        self.skipper_from(symtab, quote!(In))
+    }
+
+    // As `skipper()`, with `input` of type `reader` rather than `In`
+    fn skipper_from(&self, symtab: &Symtab, reader: Tokens) -> Result<Tokens> {
+        use self::Type::*;
+
+        let maxsz = |maxsz: &::std::option::Option<Value>| match *maxsz {
+            None => quote!(None),
+            Some(ref mx) => {
+                let mx = mx.as_token(symtab);
+                quote!(Some(#mx as usize))
+            }
+        };
+
+        let res = match *self {
+            Array(ref ty, ref value) => {
+                let value = value.as_token(symtab);
+                match *ty.as_ref() {
+                    Opaque | String => {
+                        quote!(xdr_codec::skip_opaque_array(input, #value as usize)?)
+                    }
+                    ref ty => {
+                        let ty = ty.wire_token(symtab)?;
+                        quote!(xdr_codec::skip_array::<#reader, #ty>(input, #value as usize)?)
+                    }
+                }
+            }
+
+            Flex(ref ty, ref mx) => {
+                let mx = maxsz(mx);
+                match *ty.as_ref() {
+                    Opaque | String => quote!(xdr_codec::skip_opaque_flex(input, #mx)?),
+                    ref ty => {
+                        let ty = ty.wire_token(symtab)?;
+                        quote!(xdr_codec::skip_flex::<#reader, #ty>(input, #mx)?)
+                    }
+                }
+            }
+
+            ref ty => {
+                let ty = ty.wire_token(symtab)?;
+                quote!(<#ty as xdr_codec::Skip<#reader>>::skip(input)?)
+            }
+        };
+        Ok(res)
+    }
+
+    fn arbitrary(&self, symtab: &Symtab) -> Tokens {
+        use self::Type::*;
+
+        match *self {
+            Flex(ref ty, ref maxsz) => {
+                let mx = match *maxsz {
+                    None => quote!(None),
+                    Some(ref mx) => {
+                        let mx = mx.as_token(symtab);
+                        quote!(Some(#mx as usize))
+                    }
+                };
+                match *ty.as_ref() {
+                    Opaque => quote!(xdr_codec::arbitrary::opaque_flex(u, #mx)?),
+                    String if symtab.config().use_xdrstring() => {
+                        quote!(xdr_codec::arbitrary::xdrstring(u, #mx)?)
+                    }
+                    String if symtab.config().strict_strings => {
+                        quote!(xdr_codec::arbitrary::ascii_string(u, #mx)?)
+                    }
+                    String => quote!(xdr_codec::arbitrary::string(u, #mx)?),
+                    ref ty if ty.widened(symtab).is_some() => {
+                        let wire = ty.widened(symtab).unwrap();
+                        quote!(xdr_codec::arbitrary::flex::<#wire>(u, #mx)?
+                            .into_iter()
+                            .map(::std::convert::From::from)
+                            .collect())
+                    }
+                    _ if self.is_bounded_vec(symtab) || self.is_opt_array(symtab) => {
+                        quote!(xdr_codec::arbitrary::Arbitrary::arbitrary(u)?)
+                    }
+                    _ if self.is_inline_vec(symtab) => {
+                        quote!(xdr_codec::arbitrary::flex(u, #mx)?.into_iter().collect())
+                    }
+                    _ => quote!(xdr_codec::arbitrary::flex(u, #mx)?),
+                }
+            }
+
+            Array(ref ty, ref sz) if ty.widened(symtab).is_some() => {
+                let wire = ty.widened(symtab).unwrap();
+                let sz = sz.as_token(symtab);
+                quote!(<[#wire; #sz as usize] as xdr_codec::arbitrary::Arbitrary>::arbitrary(u)?
+                    .map(::std::convert::From::from))
+            }
+
+            Option(ref ty) if ty.widened(symtab).is_some() => {
+                let wire = ty.widened(symtab).unwrap();
+                quote!(<Option<#wire> as xdr_codec::arbitrary::Arbitrary>::arbitrary(u)?
+                    .map(::std::convert::From::from))
+            }
+
+            _ if self.widened(symtab).is_some() => {
+                let wire = self.widened(symtab).unwrap();
+                quote!(::std::convert::From::from(
+                    <#wire as xdr_codec::arbitrary::Arbitrary>::arbitrary(u)?
+                ))
+            }
+
+            _ => quote!(xdr_codec::arbitrary::Arbitrary::arbitrary(u)?),
+        }
+    }
+
+    // `xdr_codec::describe::TypeDesc` for this type
+    fn describe(&self, symtab: &Symtab) -> Result<Tokens> {
+        use self::Type::*;
+
+        let size = |ty: &Type| match *ty {
+            Array(_, ref sz) => {
+                let sz = sz.as_token(symtab);
+                quote!(xdr_codec::describe::Size::Fixed(#sz as usize))
+            }
+            Flex(_, Some(ref mx)) => {
+                let mx = mx.as_token(symtab);
+                quote!(xdr_codec::describe::Size::Flex(Some(#mx as usize)))
+            }
+            _ => quote!(xdr_codec::describe::Size::Flex(None)),
+        };
+
+        let res = match *self {
+            Int => quote!(xdr_codec::describe::TypeDesc::Int),
+            UInt => quote!(xdr_codec::describe::TypeDesc::UInt),
+            Hyper => quote!(xdr_codec::describe::TypeDesc::Hyper),
+            UHyper => quote!(xdr_codec::describe::TypeDesc::UHyper),
+            Float => quote!(xdr_codec::describe::TypeDesc::Float),
+            Double => quote!(xdr_codec::describe::TypeDesc::Double),
+            Quadruple => quote!(xdr_codec::describe::TypeDesc::Quadruple),
+            Bool => quote!(xdr_codec::describe::TypeDesc::Bool),
+
+            Array(ref ty, _) | Flex(ref ty, _) => {
+                let size = size(self);
+                match *ty.as_ref() {
+                    Opaque => quote!(xdr_codec::describe::TypeDesc::Opaque(#size)),
+                    String => quote!(xdr_codec::describe::TypeDesc::String(#size)),
+                    ref ty => {
+                        let desc = ty.describe(symtab)?;
+                        quote!(xdr_codec::describe::TypeDesc::Array(Box::new(#desc), #size))
+                    }
+                }
+            }
+
+            Option(ref ty) => {
+                let desc = ty.describe(symtab)?;
+                quote!(xdr_codec::describe::TypeDesc::Option(Box::new(#desc)))
+            }
+
+            // Type synonyms are described as the type they name
+            Ident(ref name, _) => match symtab.typesyns.get(name) {
+                Some(ty) => ty.describe(symtab)?,
+                None => {
+                    let id = quote_ident(name);
+                    quote!(xdr_codec::describe::TypeDesc::Named(
+                        #name,
+                        <#id as xdr_codec::describe::Describe>::describe
+                    ))
+                }
+            },
+
+            _ => return Err(format!("can't describe unnamed type {:?}", self).into()),
+        };
+        Ok(res)
+    }
+
     fn as_token(&self, symtab: &Symtab) -> Result<Tokens> {
+        self.type_token(symtab, true)
+    }
+
+    // The Rust type for this type, using the configured overrides for named types if `overrides`
+    // is set, or otherwise the generated types, which have the same encoding
+    fn wire_token(&self, symtab: &Symtab) -> Result<Tokens> {
+        self.type_token(symtab, false)
+    }
+
+    fn type_token(&self, symtab: &Symtab, overrides: bool) -> Result<Tokens> {
         use self::Type::*;
 
         let ret = match self {
+            &Int | &UInt if overrides && self.widened(symtab).is_some() => {
+                let rust = match *self {
+                    Int => &symtab.config().int_type,
+                    _ => &symtab.config().uint_type,
+                };
+                let id = quote::Ident::new(rust.as_ref().unwrap().as_str());
+                quote!(#id)
+            }
             &Int => quote!(i32),
             &UInt => quote!(u32),
             &Hyper => quote!(i64),
             &UHyper => quote!(u64),
             &Float => quote!(f32),
             &Double => quote!(f64),
-            &Quadruple => quote!(f128),
+            &Quadruple => quote!(xdr_codec::Quadruple),
             &Bool => quote!(bool),
 
+            &String if symtab.config().use_xdrstring() => quote!(xdr_codec::XdrString),
             &String => quote!(String),
             &Opaque => quote!(Vec<u8>),
 
             &Option(ref ty) => {
                 let ty = ty.as_ref();
-                let tok = ty.as_token(symtab)?;
+                let tok = ty.type_token(symtab, overrides)?;
                 if ty.is_boxed(symtab) {
                     quote!(Option<Box<#tok>>)
                 } else {
@@ -453,30 +852,59 @@ impl Type {
                         quote!([u8; #sztok as usize])
                     }
                     ref ty => {
-                        let tytok = ty.as_token(symtab)?;
+                        let tytok = ty.type_token(symtab, overrides)?;
                         let sztok = sz.as_token(symtab);
                         quote!([#tytok; #sztok as usize])
                     }
                 }
             }
 
-            &Flex(ref ty, _) => {
+            &Flex(ref ty, ref maxsz) => {
+                let bounded = self.is_bounded_vec(symtab);
+                let inline = self.is_inline_vec(symtab);
+                let optarray = self.is_opt_array(symtab);
                 let ty = ty.as_ref();
                 match ty {
+                    &String if symtab.config().use_xdrstring() => quote!(xdr_codec::XdrString),
                     &String => quote!(String),
                     &Opaque => quote!(Vec<u8>),
                     ref ty => {
-                        let tok = ty.as_token(symtab)?;
-                        quote!(Vec<#tok>)
+                        let tok = ty.type_token(symtab, overrides)?;
+                        match *maxsz {
+                            Some(ref mx) if bounded => {
+                                let mx = mx.as_token(symtab);
+                                quote!(xdr_codec::BoundedVec<#tok, { #mx as usize }>)
+                            }
+                            Some(ref mx) if inline => {
+                                let mx = mx.as_token(symtab);
+                                quote!(xdr_codec::inline::ArrayVec<#tok, { #mx as usize }>)
+                            }
+                            Some(_) if optarray && ty.is_boxed(symtab) => {
+                                quote!(xdr_codec::OptArray<Box<#tok>>)
+                            }
+                            Some(_) if optarray => quote!(xdr_codec::OptArray<#tok>),
+                            _ => quote!(Vec<#tok>),
+                        }
                     }
                 }
             }
 
-            &Ident(ref name, _) => {
-                let id = quote_ident(name.as_str());
-                quote!(#id)
+            // Typedefs of widened integers are packed as the XDR type
+            &Ident(..) if !overrides && self.widened(symtab).is_some() => {
+                self.widened(symtab).unwrap()
             }
 
+            &Ident(ref name, _) => match symtab.config().overrides.get(name) {
+                Some((rust_type, _)) if overrides => {
+                    let id = quote::Ident::new(rust_type.as_str());
+                    quote!(#id)
+                }
+                _ => {
+                    let id = quote_ident(name.as_str());
+                    quote!(#id)
+                }
+            },
+
             _ => return Err(format!("can't have unnamed type {:?}", self).into()),
         };
         Ok(ret)
@@ -506,6 +934,13 @@ impl Decl {
         Decl::Named(id.as_ref().to_string(), ty)
     }
 
+    fn name(&self) -> Option<&str> {
+        match *self {
+            Decl::Void => None,
+            Decl::Named(ref name, _) => Some(name),
+        }
+    }
+
     fn name_as_ident(&self) -> Option<(quote::Ident, &Type)> {
         use self::Decl::*;
         match self {
@@ -568,6 +1003,213 @@ impl Defn {
     fn constant<S: AsRef<str>>(id: S, v: i64) -> Defn {
         Defn::Const(id.as_ref().to_string(), v)
     }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            Defn::Typespec(ref name, _) | Defn::Typesyn(ref name, _) | Defn::Const(ref name, _) => {
+                name
+            }
+        }
+    }
+
+    // `const`, `typedef`, `enum`, `struct` or `union`
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Defn::Const(..) => "const",
+            Defn::Typespec(_, Type::Enum(_)) => "enum",
+            Defn::Typespec(_, Type::Struct(_)) => "struct",
+            Defn::Typespec(_, Type::Union(..)) => "union",
+            Defn::Typespec(..) | Defn::Typesyn(..) => "typedef",
+        }
+    }
+}
+
+// Check the configured type overrides are for types in the specification, and that types with
+// shims aren't used in arrays or optional values, where there's no way to pack them with the shim
+pub fn check_overrides(symtab: &Symtab) -> Result<()> {
+    for name in symtab.config().overrides.keys() {
+        if symtab.typespec(name).is_none() {
+            return Err(Error::from(format!("can't override unknown type {}", name)));
+        }
+    }
+    for (_, ty) in symtab.typespecs().chain(symtab.typesyns()) {
+        ty.check_shims(symtab, false)?;
+    }
+    Ok(())
+}
+
+// Check the Rust types for integers given with `Config::int_type()` and `Config::uint_type()`,
+// and that each typedef given with `Config::newtype()` is of an integer type
+pub fn check_ints(symtab: &Symtab) -> Result<()> {
+    let config = symtab.config();
+    let check = |xdrtype: &str, rust_type: &Option<String>, allowed: &[&str]| match *rust_type {
+        Some(ref t) if !allowed.contains(&t.as_str()) => Err(Error::from(format!(
+            "can't use {} for {}: expected one of {}",
+            t,
+            xdrtype,
+            allowed.join(", ")
+        ))),
+        _ => Ok(()),
+    };
+    check("int", &config.int_type, &["i32", "i64", "i128"])?;
+    check(
+        "unsigned int",
+        &config.uint_type,
+        &["u32", "u64", "u128", "i64", "i128"],
+    )?;
+
+    for name in &config.newtypes {
+        match symtab.typesyns.get(name) {
+            Some(&Type::Int) | Some(&Type::UInt) | Some(&Type::Hyper) | Some(&Type::UHyper)
+                if !config.overrides.contains_key(name) => {}
+            _ => {
+                return Err(Error::from(format!(
+                    "can't make a newtype of {}: it isn't a typedef of an integer type",
+                    name
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Type {
+    fn check_shims(&self, symtab: &Symtab, contained: bool) -> Result<()> {
+        use self::Type::*;
+
+        match *self {
+            Ident(ref name, _) if contained && symtab.shim(name).is_some() => {
+                Err(Error::from(format!(
+                    "type {} is packed with a shim, so can't be an element or optional",
+                    name
+                )))
+            }
+            Option(ref ty) | Array(ref ty, _) | Flex(ref ty, _) => ty.check_shims(symtab, true),
+            Struct(ref decls) => decls.iter().try_for_each(|decl| decl.check_shims(symtab)),
+            Union(ref sel, ref cases, ref defl) => {
+                sel.check_shims(symtab)?;
+                for case in cases {
+                    case.1.check_shims(symtab)?;
+                }
+                match *defl {
+                    Some(ref defl) => defl.check_shims(symtab),
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Decl {
+    fn check_shims(&self, symtab: &Symtab) -> Result<()> {
+        match *self {
+            Decl::Void => Ok(()),
+            Decl::Named(_, ref ty) => ty.check_shims(symtab, false),
+        }
+    }
+}
+
+/// An identifier whose generated name differs from its name in the specification, or which
+/// differs from another only by case
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Rename {
+    /// The type the name is in, or `None` for top-level definitions
+    pub scope: Option<String>,
+    /// The name in the specification
+    pub from: String,
+    /// The generated name, or for a `case-collision` the name it collides with
+    pub to: String,
+    /// Why it's reported: `keyword`, `numeric-case` or `case-collision`
+    pub reason: &'static str,
+}
+
+impl Rename {
+    fn new(scope: Option<&str>, from: &str, to: String, reason: &'static str) -> Rename {
+        Rename {
+            scope: scope.map(str::to_string),
+            from: from.to_string(),
+            to,
+            reason,
+        }
+    }
+}
+
+// Every renamed identifier in the specification, in order of definition. Names are only changed
+// when they're Rust keywords, or for union cases with numeric values, which are named for the
+// value. Names which differ only by case are reported, but left as they are.
+pub fn renames(defns: &[Defn]) -> Vec<Rename> {
+    let mut ret = Vec::new();
+
+    fn ident(ret: &mut Vec<Rename>, scope: Option<&str>, name: &str) {
+        let to = quote_ident(name);
+        if to.as_ref() != name {
+            ret.push(Rename::new(scope, name, to.as_ref().to_string(), "keyword"));
+        }
+    }
+
+    // Identifiers in the same scope which only differ by case
+    fn collisions<'a, I>(ret: &mut Vec<Rename>, scope: Option<&str>, names: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for name in names {
+            match seen.get(&name.to_lowercase()) {
+                Some(&prev) if prev != name => {
+                    ret.push(Rename::new(scope, name, prev.to_string(), "case-collision"))
+                }
+                Some(_) => (),
+                None => {
+                    seen.insert(name.to_lowercase(), name);
+                }
+            }
+        }
+    }
+
+    for defn in defns {
+        let name = defn.name();
+        ident(&mut ret, None, name);
+
+        let ty = match *defn {
+            Defn::Typespec(_, ref ty) | Defn::Typesyn(_, ref ty) => ty,
+            Defn::Const(..) => continue,
+        };
+        let scope = Some(name);
+
+        match *ty {
+            Type::Enum(ref defs) => {
+                for EnumDefn(member, _) in defs {
+                    ident(&mut ret, scope, member);
+                }
+                collisions(&mut ret, scope, defs.iter().map(|d| d.0.as_str()));
+            }
+            Type::Struct(ref decls) => {
+                let fields: Vec<_> = decls.iter().filter_map(Decl::name).collect();
+                for field in &fields {
+                    ident(&mut ret, scope, field);
+                }
+                collisions(&mut ret, scope, fields);
+            }
+            Type::Union(_, ref cases, _) => {
+                for UnionCase(val, _) in cases {
+                    match *val {
+                        Value::Ident(ref id) => ident(&mut ret, scope, id),
+                        Value::Const(v) => ret.push(Rename::new(
+                            scope,
+                            &v.to_string(),
+                            val.as_ident().as_ref().to_string(),
+                            "numeric-case",
+                        )),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    collisions(&mut ret, None, defns.iter().map(Defn::name));
+
+    ret
 }
 
 pub trait Emit {
@@ -577,14 +1219,18 @@ pub trait Emit {
 pub trait Emitpack: Emit {
     fn pack(&self, symtab: &Symtab) -> Result<Option<Tokens>>;
     fn unpack(&self, symtab: &Symtab) -> Result<Option<Tokens>>;
+    fn skip(&self, symtab: &Symtab) -> Result<Option<Tokens>>;
+    fn arbitrary(&self, symtab: &Symtab) -> Result<Option<Tokens>>;
+    fn describe(&self, symtab: &Symtab) -> Result<Option<Tokens>>;
 }
 
 impl Emit for Const {
-    fn define(&self, _: &Symtab) -> Result<Tokens> {
+    fn define(&self, symtab: &Symtab) -> Result<Tokens> {
         let name = quote_ident(&self.0);
         let val = &self.1;
+        let doc = symtab.location_doc(&self.0);
 
-        Ok(quote!(pub const #name: i64 = #val;))
+        Ok(quote!(#doc pub const #name: i64 = #val;))
     }
 }
 
@@ -593,7 +1239,83 @@ impl Emit for Typesyn {
         let ty = &self.1;
         let name = quote_ident(&self.0);
         let tok = ty.as_token(symtab)?;
-        Ok(quote!(pub type #name = #tok;))
+        let doc = symtab.location_doc(&self.0);
+
+        if !symtab.config().newtypes.contains(&self.0) {
+            return Ok(quote!(#doc pub type #name = #tok;));
+        }
+
+        // A newtype, which needs its own implementations
+        let derive = ty.derivable(symtab, None);
+        let pack = ty.packer(quote!(self.0), symtab)?;
+        let unpack = ty.unpacker(symtab);
+        let unpack = quote!({ let (v, usz) = #unpack; sz = usz; #name(v) });
+        let unpack = if symtab.config().validate {
+            quote!({
+                let v = #unpack;
+                xdr_codec::Validate::validate(&v)?;
+                v
+            })
+        } else {
+            unpack
+        };
+        let skip = if symtab.config().skip {
+            let skip = ty.skipper(symtab)?;
+            quote! {
+                impl<In: xdr_codec::Read> xdr_codec::Skip<In> for #name {
+                    fn skip(input: &mut In) -> xdr_codec::Result<usize> {
+                        Ok(#skip)
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+        let arbitrary = if symtab.config().arbitrary {
+            let arb = ty.arbitrary(symtab);
+            quote! {
+                impl<'a> xdr_codec::arbitrary::Arbitrary<'a> for #name {
+                    fn arbitrary(u: &mut xdr_codec::arbitrary::Unstructured<'a>)
+                                 -> xdr_codec::arbitrary::Result<#name> {
+                        Ok(#name(#arb))
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        Ok(quote! {
+            #doc
+            #derive
+            pub struct #name(pub #tok);
+
+            impl ::std::convert::From<#tok> for #name {
+                fn from(v: #tok) -> #name { #name(v) }
+            }
+
+            impl ::std::convert::From<#name> for #tok {
+                fn from(v: #name) -> #tok { v.0 }
+            }
+
+            impl<Out: xdr_codec::Write> xdr_codec::Pack<Out> for #name {
+                #[inline]
+                fn pack(&self, out: &mut Out) -> xdr_codec::Result<usize> {
+                    Ok(#pack)
+                }
+            }
+
+            impl<In: xdr_codec::Read> xdr_codec::Unpack<In> for #name {
+                #[inline]
+                fn unpack(input: &mut In) -> xdr_codec::Result<(#name, usize)> {
+                    let mut sz = 0;
+                    Ok((#unpack, sz))
+                }
+            }
+
+            #skip
+            #arbitrary
+        })
     }
 }
 
@@ -606,36 +1328,114 @@ impl Emit for Typespec {
 
         let ret = match ty {
             &Enum(ref edefs) => {
-                let defs: Vec<_> = edefs
+                let members: Vec<_> = edefs
                     .iter()
                     .filter_map(|&EnumDefn(ref field, _)| {
                         if let Some((val, Some(_))) = symtab.getconst(field) {
-                            Some((quote_ident(field), val as isize))
+                            Some((field.as_str(), quote_ident(field), val as isize))
                         } else {
                             None
                         }
                     })
-                    .map(|(field, val)| quote!(#field = #val,))
                     .collect();
+                let defs: Vec<_> = members
+                    .iter()
+                    .map(|&(_, ref field, val)| quote!(#field = #val,))
+                    .collect();
+                let names: Vec<_> = members
+                    .iter()
+                    .map(|&(xdrname, ref field, _)| quote!(#name::#field => #xdrname,))
+                    .collect();
+                let values: Vec<_> = members
+                    .iter()
+                    .map(|(_, field, _)| {
+                        quote!(x if x == #name::#field as i32 => Ok(#name::#field),)
+                    })
+                    .collect();
+                let fromnames: Vec<_> = members
+                    .iter()
+                    .map(|&(xdrname, ref field, _)| quote!(#xdrname => Ok(#name::#field),))
+                    .collect();
+                let xdrname = self.0.as_str();
 
                 let derive = ty.derivable(symtab, None);
-                quote!(#derive pub enum #name { #(#defs)* })
+                quote! {
+                    #derive
+                    pub enum #name { #(#defs)* }
+
+                    impl #name {
+                        /// The name of this value in the specification.
+                        pub fn as_str(&self) -> &'static str {
+                            match *self { #(#names)* }
+                        }
+                    }
+
+                    impl ::std::convert::TryFrom<i32> for #name {
+                        type Error = xdr_codec::Error;
+
+                        fn try_from(v: i32) -> xdr_codec::Result<Self> {
+                            match v {
+                                #(#values)*
+                                v => Err(xdr_codec::Error::invalidenum(v)),
+                            }
+                        }
+                    }
+
+                    impl ::std::convert::From<#name> for i32 {
+                        fn from(v: #name) -> i32 { v as i32 }
+                    }
+
+                    impl ::std::str::FromStr for #name {
+                        type Err = xdr_codec::Error;
+
+                        fn from_str(s: &str) -> xdr_codec::Result<Self> {
+                            match s {
+                                #(#fromnames)*
+                                _ => Err(xdr_codec::Error::from(
+                                    format!("invalid {} name: '{}'", #xdrname, s))),
+                            }
+                        }
+                    }
+                }
             }
 
             &Struct(ref decls) => {
                 let decls: Vec<_> = decls
                     .iter()
-                    .filter_map(|decl| result_option(decl.as_token(symtab)))
-                    .map(|res| res.map(|(field, ty)| quote!(pub #field: #ty,)))
+                    .enumerate()
+                    .filter_map(|(i, decl)| match *decl {
+                        Decl::Named(ref field, ref ty) if symtab.is_lazy(&self.0, i) => {
+                            let field = quote_ident(field);
+                            Some(ty.lazy_token(symtab).map(|ty| quote!(pub #field: #ty,)))
+                        }
+                        _ => result_option(decl.as_token(symtab))
+                            .map(|res| res.map(|(field, ty)| quote!(pub #field: #ty,))),
+                    })
                     .collect::<Result<Vec<_>>>()?;
 
-                let derive = ty.derivable(symtab, None);
+                let mut derive = ty.derivable(symtab, None);
+                if symtab.has_lazy(&self.0) {
+                    derive &= !Derives::COPY;
+                }
                 quote! {
                     #derive
                     pub struct #name { #(#decls)* }
                 }
             }
 
+            _ if ty.status_union(symtab).is_some() => {
+                let (ok, err) = ty.status_union(symtab).unwrap();
+                let arm = |decl: &Decl| -> Result<Tokens> {
+                    Ok(match decl.as_token(symtab)? {
+                        Some((_, tok)) => tok,
+                        None => quote!(()),
+                    })
+                };
+                let (ok, err) = (arm(ok)?, arm(err)?);
+
+                quote!(pub type #name = xdr_codec::StatusUnion<#ok, #err>;)
+            }
+
             &Union(ref selector, ref cases, ref defl) => {
                 let selector = selector.as_ref();
                 use self::Decl::*;
@@ -720,10 +1520,10 @@ impl Emit for Typespec {
                                 let name = quote_ident(name);
                                 cases.push(quote!(default { #name: #tok },))
                             } else {
-                                cases.push(quote!(default(#tok),))
+                                cases.push(quote!(default(i32, #tok),))
                             }
                         }
-                        &Void => cases.push(quote!(default,)),
+                        &Void => cases.push(quote!(default(i32),)),
                     }
                 }
 
@@ -748,7 +1548,8 @@ impl Emit for Typespec {
                 quote!(pub type #name = #tok;)
             }
         };
-        Ok(ret)
+        let doc = symtab.location_doc(&self.0);
+        Ok(quote!(#doc #ret))
     }
 }
 
@@ -762,6 +1563,9 @@ impl Emitpack for Typespec {
         let mut directive = quote!();
 
         let body: Tokens = match ty {
+            // Implemented by `xdr_codec::StatusUnion`
+            _ if ty.status_union(symtab).is_some() => return Ok(None),
+
             &Enum(_) => {
                 directive = quote!(#[inline]);
                 ty.packer(quote!(self), symtab)?
@@ -770,12 +1574,17 @@ impl Emitpack for Typespec {
             &Struct(ref decl) => {
                 let decls: Vec<_> = decl
                     .iter()
-                    .filter_map(|d| match d {
+                    .enumerate()
+                    .filter_map(|(i, d)| match d {
                         &Void => None,
-                        &Named(ref name, ref ty) => Some((quote_ident(name), ty)),
+                        &Named(ref name, ref ty) => Some((i, quote_ident(name), ty)),
                     })
-                    .map(|(field, ty)| {
-                        let p = ty.packer(quote!(self.#field), symtab).unwrap();
+                    .map(|(i, field, ty)| {
+                        let p = if symtab.is_lazy(&self.0, i) {
+                            quote!(xdr_codec::Pack::pack(&self.#field, out)?)
+                        } else {
+                            ty.packer(quote!(self.#field), symtab).unwrap()
+                        };
                         quote!(#p + )
                     })
                     .collect();
@@ -805,17 +1614,12 @@ impl Emitpack for Typespec {
 
                 if let &Some(ref decl) = defl {
                     let decl = decl.as_ref();
-                    // Can't cast a value-carrying enum to i32
-                    let default = match decl {
-                        &Void => {
-                            quote! {
-                                &#name::default => return Err(xdr_codec::Error::invalidcase(-1)),
-                            }
-                        }
-                        &Named(_, _) => {
-                            quote! {
-                                &#name::default(_) => return Err(xdr_codec::Error::invalidcase(-1)),
-                            }
+                    // The default arm keeps the discriminant it was unpacked with
+                    let default = match *decl {
+                        Void => quote!(&#name::default(disc) => disc.pack(out)?,),
+                        Named(_, ref ty) => {
+                            let pack = ty.packer(quote!(val), symtab)?;
+                            quote!(&#name::default(disc, ref val) => disc.pack(out)? + #pack,)
                         }
                     };
 
@@ -860,6 +1664,8 @@ impl Emitpack for Typespec {
         let mut directive = quote!();
 
         let body = match ty {
+            _ if ty.status_union(symtab).is_some() => return Ok(None),
+
             &Enum(ref defs) => {
                 directive = quote!(#[inline]);
                 let matchdefs: Vec<_> = defs
@@ -896,12 +1702,26 @@ impl Emitpack for Typespec {
             &Struct(ref decls) => {
                 let decls: Vec<_> = decls
                     .iter()
-                    .filter_map(|decl| decl.name_as_ident())
-                    .map(|(field, ty)| {
-                        let unpack = ty.unpacker(symtab);
-                        quote!(#field: { let (v, fsz) = #unpack; sz += fsz; v },)
+                    .enumerate()
+                    .filter_map(|(i, decl)| match *decl {
+                        Decl::Named(ref name, ref ty) => Some((i, name, ty)),
+                        Decl::Void => None,
                     })
-                    .collect();
+                    .map(|(i, name, ty)| {
+                        let field = quote_ident(name);
+                        let unpack = if symtab.is_lazy(&self.0, i) {
+                            ty.lazy_unpacker(symtab)?
+                        } else {
+                            ty.unpacker(symtab)
+                        };
+                        let path = format!("{}.{}", self.0, name);
+                        Ok(quote!(#field: {
+                            let (v, fsz) = xdr_codec::in_field(#path, || Ok(#unpack))?;
+                            sz += fsz;
+                            v
+                        },))
+                    })
+                    .collect::<Result<_>>()?;
 
                 quote!(#name { #(#decls)* })
             }
@@ -922,8 +1742,13 @@ impl Emitpack for Typespec {
                                 &Void => quote!(x if x == (#disc as i32) => #name::#label,),
                                 &Named(_, ref ty) => {
                                     let unpack = ty.unpacker(symtab);
+                                    let arm = format!("{}::{}", self.0, label);
                                     //quote!(#disc => #name::#label({ let (v, fsz) = #unpack; sz += fsz; v }),)
-                                    quote!(x if x == (#disc as i32) => #name::#label({ let (v, fsz) = #unpack; sz += fsz; v }),)
+                                    quote!(x if x == (#disc as i32) => #name::#label({
+                                        let (v, fsz) = xdr_codec::in_field(#arm, || Ok(#unpack))?;
+                                        sz += fsz;
+                                        v
+                                    }),)
                                 },
                             };
                             Ok(ret)
@@ -933,11 +1758,12 @@ impl Emitpack for Typespec {
                 if let &Some(ref decl) = defl {
                     let decl = decl.as_ref();
                     let defl = match decl {
-                        &Void => quote!(_ => #name::default),
+                        &Void => quote!(v => #name::default(v)),
                         &Named(_, ref ty) => {
                             let unpack = ty.unpacker(symtab);
-                            quote!(_ => #name::default({
-                                let (v, csz) = #unpack;
+                            let arm = format!("{}::default", self.0);
+                            quote!(v => #name::default(v, {
+                                let (v, csz) = xdr_codec::in_field(#arm, || Ok(#unpack))?;
                                 sz += csz;
                                 v
                             }))
@@ -971,6 +1797,16 @@ impl Emitpack for Typespec {
             _ => return Err(Error::from(format!("unimplemented ty={:?}", ty))),
         };
 
+        let body = if symtab.config().validate {
+            quote!({
+                let v = #body;
+                xdr_codec::Validate::validate(&v)?;
+                v
+            })
+        } else {
+            body
+        };
+
         Ok(Some(quote! {
             impl<In: xdr_codec::Read> xdr_codec::Unpack<In> for #name {
                 #directive
@@ -981,6 +1817,325 @@ impl Emitpack for Typespec {
             }
         }))
     }
+
+    fn skip(&self, symtab: &Symtab) -> Result<Option<Tokens>> {
+        use self::Decl::*;
+        use self::Type::*;
+
+        let name = quote_ident(&self.0);
+        let ty = &self.1;
+
+        let body = match *ty {
+            _ if ty.status_union(symtab).is_some() => return Ok(None),
+
+            Enum(_) => quote!(<i32 as xdr_codec::Skip<In>>::skip(input)?),
+
+            Struct(ref decls) => {
+                let decls = decls
+                    .iter()
+                    .filter_map(|decl| decl.name_as_ident())
+                    .map(|(_, ty)| {
+                        let skip = ty.skipper(symtab)?;
+                        Ok(quote!(#skip + ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                quote!(#(#decls)* 0)
+            }
+
+            Union(ref sel, ref cases, ref defl) => {
+                let mut matches = cases
+                    .iter()
+                    .map(|case| {
+                        let UnionCase(ref val, ref decl) = *case;
+                        let disc = match val.as_i64(symtab) {
+                            Some(v) => v as i32,
+                            None => {
+                                return Err(Error::from(format!(
+                                    "discriminant value {:?} unknown",
+                                    val
+                                )))
+                            }
+                        };
+
+                        let skip = match *decl {
+                            Void => quote!(0),
+                            Named(_, ref ty) => ty.skipper(symtab)?,
+                        };
+                        Ok(quote!(x if x == (#disc as i32) => #skip,))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                let defl = match *defl {
+                    Some(ref decl) => match **decl {
+                        Void => quote!(_ => 0),
+                        Named(_, ref ty) => {
+                            let skip = ty.skipper(symtab)?;
+                            quote!(_ => #skip)
+                        }
+                    },
+                    None => quote!(v => return Err(xdr_codec::Error::invalidcase(v as i32))),
+                };
+                matches.push(defl);
+
+                let selunpack = match **sel {
+                    Void => panic!("void switch selector?"),
+                    Named(_, ref ty) => ty.unpacker(symtab),
+                };
+
+                quote!({
+                    let (sel, dsz): (i32, _) = #selunpack;
+                    dsz + match sel { #(#matches)* }
+                })
+            }
+
+            Option(_) | Flex(_, _) | Array(_, _) => ty.skipper(symtab)?,
+
+            Ident(_, _) => return Ok(None),
+
+            _ if ty.is_prim(symtab) => return Ok(None),
+            _ => return Err(Error::from(format!("unimplemented ty={:?}", ty))),
+        };
+
+        Ok(Some(quote! {
+            impl<In: xdr_codec::Read> xdr_codec::Skip<In> for #name {
+                fn skip(input: &mut In) -> xdr_codec::Result<usize> {
+                    Ok(#body)
+                }
+            }
+        }))
+    }
+
+    fn arbitrary(&self, symtab: &Symtab) -> Result<Option<Tokens>> {
+        use self::Decl::*;
+        use self::Type::*;
+
+        let name = quote_ident(&self.0);
+        let ty = &self.1;
+
+        let body = match *ty {
+            _ if ty.status_union(symtab).is_some() => return Ok(None),
+
+            Enum(ref defs) => {
+                let variants: Vec<_> = defs
+                    .iter()
+                    .filter_map(|def| {
+                        if let Some((_, Some(_))) = symtab.getconst(&def.0) {
+                            let tok = quote_ident(&def.0);
+                            Some(quote!(#name::#tok,))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                quote!(*u.choose(&[#(#variants)*])?)
+            }
+
+            Struct(ref decls) => {
+                let decls: Vec<_> = decls
+                    .iter()
+                    .filter_map(|decl| decl.name_as_ident())
+                    .map(|(field, ty)| {
+                        let arb = ty.arbitrary(symtab);
+                        quote!(#field: #arb,)
+                    })
+                    .collect();
+
+                quote!(#name { #(#decls)* })
+            }
+
+            Union(_, ref cases, ref defl) => {
+                // The default arm only round-trips with a discriminant that no case has, so only
+                // generate it if there's nothing else
+                let mut arms: Vec<_> = cases
+                    .iter()
+                    .map(|case| {
+                        let UnionCase(ref val, ref decl) = *case;
+                        let label = val.as_ident();
+
+                        match *decl {
+                            Void => quote!(#name::#label),
+                            Named(_, ref ty) => {
+                                let arb = ty.arbitrary(symtab);
+                                quote!(#name::#label(#arb))
+                            }
+                        }
+                    })
+                    .collect();
+
+                if arms.is_empty() {
+                    if let Some(ref decl) = *defl {
+                        let disc = quote!(xdr_codec::arbitrary::Arbitrary::arbitrary(u)?);
+                        arms.push(match **decl {
+                            Void => quote!(#name::default(#disc)),
+                            Named(_, ref ty) if ty.is_boxed(symtab) => {
+                                let arb = ty.arbitrary(symtab);
+                                quote!(#name::default(#disc, Box::new(#arb)))
+                            }
+                            Named(_, ref ty) => {
+                                let arb = ty.arbitrary(symtab);
+                                quote!(#name::default(#disc, #arb))
+                            }
+                        });
+                    }
+                }
+
+                let last = arms.len() - 1;
+                let arms: Vec<_> = arms
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, arm)| quote!(#i => #arm,))
+                    .collect();
+
+                quote!(match u.int_in_range(0..=#last)? {
+                    #(#arms)*
+                    _ => unreachable!(),
+                })
+            }
+
+            Flex(..) | Array(..) => {
+                let arb = ty.arbitrary(symtab);
+                quote!(#name(#arb))
+            }
+
+            Ident(_, _) => return Ok(None),
+
+            _ if ty.is_prim(symtab) => return Ok(None),
+            _ => return Err(Error::from(format!("unimplemented ty={:?}", ty))),
+        };
+
+        Ok(Some(quote! {
+            impl<'a> xdr_codec::arbitrary::Arbitrary<'a> for #name {
+                fn arbitrary(u: &mut xdr_codec::arbitrary::Unstructured<'a>)
+                             -> xdr_codec::arbitrary::Result<#name> {
+                    Ok(#body)
+                }
+            }
+        }))
+    }
+
+    fn describe(&self, symtab: &Symtab) -> Result<Option<Tokens>> {
+        use self::Decl::*;
+        use self::Type::*;
+
+        let name = quote_ident(&self.0);
+        let xdrname = &self.0;
+        let ty = &self.1;
+
+        let field = |fname: &str, ty: &Type| -> Result<Tokens> {
+            let desc = ty.describe(symtab)?;
+            Ok(quote!(xdr_codec::describe::Field { name: #fname, ty: #desc }))
+        };
+
+        let kind = match *ty {
+            Enum(ref defs) => {
+                let values: Vec<_> = defs
+                    .iter()
+                    .filter_map(|def| {
+                        if let Some((val, Some(_))) = symtab.getconst(&def.0) {
+                            let field = def.0.as_str();
+                            Some(quote!(xdr_codec::describe::EnumValue {
+                                name: #field,
+                                value: #val,
+                            },))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                quote!(xdr_codec::describe::Kind::Enum(vec![#(#values)*]))
+            }
+
+            Struct(ref decls) => {
+                let fields = decls
+                    .iter()
+                    .filter_map(|decl| match *decl {
+                        Void => None,
+                        Named(ref fname, ref ty) => Some(field(fname, ty)),
+                    })
+                    .map(|res| res.map(|f| quote!(#f,)))
+                    .collect::<Result<Vec<_>>>()?;
+
+                quote!(xdr_codec::describe::Kind::Struct(vec![#(#fields)*]))
+            }
+
+            Union(ref sel, ref cases, ref defl) => {
+                let selector = match **sel {
+                    Void => return Err(Error::from(format!("union {} has no selector", xdrname))),
+                    Named(ref fname, ref ty) => field(fname, ty)?,
+                };
+                let arm = |decl: &Decl| -> Result<Tokens> {
+                    match *decl {
+                        Void => Ok(quote!(None)),
+                        Named(ref fname, ref ty) => {
+                            let f = field(fname, ty)?;
+                            Ok(quote!(Some(#f)))
+                        }
+                    }
+                };
+
+                let mut descs = cases
+                    .iter()
+                    .map(|case| {
+                        let UnionCase(ref val, ref decl) = *case;
+                        let variant = val.as_ident().to_string();
+                        let value = match val.as_i64(symtab) {
+                            Some(v) => v,
+                            None => {
+                                return Err(Error::from(format!(
+                                    "discriminant value {:?} unknown",
+                                    val
+                                )))
+                            }
+                        };
+                        let arm = arm(decl)?;
+                        Ok(quote!(xdr_codec::describe::Case {
+                            value: Some(#value),
+                            variant: #variant,
+                            field: #arm,
+                        },))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                if let Some(ref decl) = *defl {
+                    let arm = arm(decl)?;
+                    descs.push(quote!(xdr_codec::describe::Case {
+                        value: None,
+                        variant: "default",
+                        field: #arm,
+                    },));
+                }
+
+                quote!(xdr_codec::describe::Kind::Union {
+                    selector: #selector,
+                    cases: vec![#(#descs)*],
+                })
+            }
+
+            Flex(..) | Array(..) => {
+                let desc = ty.describe(symtab)?;
+                quote!(xdr_codec::describe::Kind::Newtype(#desc))
+            }
+
+            Ident(_, _) => return Ok(None),
+
+            _ if ty.is_prim(symtab) => return Ok(None),
+            _ => return Err(Error::from(format!("unimplemented ty={:?}", ty))),
+        };
+
+        Ok(Some(quote! {
+            impl xdr_codec::describe::Describe for #name {
+                fn describe() -> xdr_codec::describe::Descriptor {
+                    xdr_codec::describe::Descriptor {
+                        name: #xdrname,
+                        kind: #kind,
+                    }
+                }
+            }
+        }))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -988,14 +2143,20 @@ pub struct Symtab {
     consts: BTreeMap<String, (i64, Option<String>)>,
     typespecs: BTreeMap<String, Type>,
     typesyns: BTreeMap<String, Type>,
+    locations: BTreeMap<String, String>,
+    pragmas: BTreeMap<String, Vec<Pragma>>,
+    config: Config,
 }
 
 impl Symtab {
-    pub fn new(defns: &Vec<Defn>) -> Symtab {
+    pub fn new(defns: &Vec<Defn>, config: &Config) -> Symtab {
         let mut ret = Symtab {
             consts: BTreeMap::new(),
             typespecs: BTreeMap::new(),
             typesyns: BTreeMap::new(),
+            locations: BTreeMap::new(),
+            pragmas: BTreeMap::new(),
+            config: config.clone(),
         };
 
         ret.update_consts(&defns);
@@ -1003,6 +2164,59 @@ impl Symtab {
         ret
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Record the line each definition is on in `infile`
+    pub fn locate(&mut self, infile: &str, defns: &[(usize, Defn)]) {
+        for &(line, ref defn) in defns {
+            self.locations
+                .insert(defn.name().to_string(), format!("{}:{}", infile, line));
+        }
+    }
+
+    // Record the pragmas in the structs among `defns`, whose spans are in `source`
+    pub fn annotate(&mut self, source: &str, defns: &[(Range<usize>, Defn)]) -> Result<()> {
+        for (name, pragmas) in pragma::pragmas(source, defns) {
+            if let Some(Type::Struct(decls)) = self.typespecs.get(&name) {
+                pragma::check(&name, &pragmas, decls)?;
+            }
+            self.pragmas.insert(name, pragmas);
+        }
+        for (_, defn) in defns {
+            if let Some(Type::Struct(decls)) = self.typespecs.get(defn.name()) {
+                lazy::check(self, defn.name(), decls)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The pragmas called `pragma` on struct `name`
+    fn pragmas(&self, name: &str, pragma: &'static str) -> impl Iterator<Item = &Pragma> {
+        self.pragmas
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(move |p| p.name == pragma)
+    }
+
+    // The pragma called `pragma` on struct `name`, if it has one
+    fn pragma(&self, name: &str, pragma: &'static str) -> ::std::option::Option<&Pragma> {
+        self.pragmas(name, pragma).next()
+    }
+
+    // Doc attribute giving the location of the definition of `name`, if enabled
+    fn location_doc(&self, name: &str) -> Tokens {
+        match self.locations.get(name) {
+            Some(loc) if self.config.source_locations => {
+                let doc = format!("Defined at {}", loc);
+                quote!(#[doc = #doc])
+            }
+            _ => quote!(),
+        }
+    }
+
     fn update_consts(&mut self, defns: &Vec<Defn>) {
         for defn in defns {
             match defn {
@@ -1092,6 +2306,21 @@ impl Symtab {
     pub fn typesyns(&self) -> Iter<String, Type> {
         self.typesyns.iter()
     }
+
+    // The shim module for packing and unpacking the named type, if it's overridden with one,
+    // either directly or through typedefs
+    fn shim(&self, name: &str) -> Option<&str> {
+        let mut name = name;
+        loop {
+            if let Some((_, shim)) = self.config.overrides.get(name) {
+                return shim.as_ref().map(String::as_str);
+            }
+            match self.typesyns.get(name) {
+                Some(Type::Ident(syn, _)) => name = syn,
+                _ => return None,
+            }
+        }
+    }
 }
 
 #[cfg(test)]
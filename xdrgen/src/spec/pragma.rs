@@ -0,0 +1,136 @@
+// Pragmas
+//
+// Some code is only generated when it's asked for on particular fields of a struct. That's done
+// with comments such as `/* @header */` or `/* @lazy */` in the specification, so it's still accepted by rpcgen
+// and other tools. The parser drops comments, so they're found by scanning the source text of
+// each struct. A pragma applies to the position it's at, given as the number of fields before
+// it, so `@header` ends the header there and `@lazy` applies to the field after it. Those outside
+// structs, and other comments starting with `@`, are left alone.
+
+use std::ops::Range;
+
+use super::{Decl, Defn, Result, Type};
+
+// The pragmas xdrgen understands
+const PRAGMAS: &[&str] = &["header", "lazy"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pragma {
+    pub name: &'static str,
+    // The number of fields before the pragma
+    pub field: usize,
+}
+
+// The pragma a comment's text is, if any
+fn pragma(comment: &[u8]) -> ::std::option::Option<&'static str> {
+    let text = ::std::str::from_utf8(comment).ok()?.trim();
+    let name = text.strip_prefix('@')?;
+    PRAGMAS.iter().cloned().find(|&p| p == name)
+}
+
+// The pragmas in `text`, the source of a struct definition
+fn scan(text: &[u8]) -> Vec<Pragma> {
+    let find = |from: usize, pat: &[u8]| {
+        text[from..]
+            .windows(pat.len())
+            .position(|w| w == pat)
+            .map(|pos| from + pos)
+    };
+
+    let mut ret = Vec::new();
+    let mut depth = 0;
+    let mut field = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let comment = if text[i..].starts_with(b"/*") {
+            let end = find(i + 2, b"*/").unwrap_or(text.len());
+            Some((i + 2..end, end + 2))
+        } else if text[i..].starts_with(b"//") {
+            let end = find(i, b"\n").unwrap_or(text.len());
+            Some((i + 2..end, end))
+        } else {
+            None
+        };
+
+        match comment {
+            Some((body, next)) => {
+                if depth == 1 {
+                    if let Some(name) = pragma(&text[body]) {
+                        ret.push(Pragma { name, field });
+                    }
+                }
+                i = next;
+                continue;
+            }
+            None => match text[i] {
+                b'{' => depth += 1,
+                b'}' => depth -= 1,
+                b';' if depth == 1 => field += 1,
+                _ => (),
+            },
+        }
+        i += 1;
+    }
+    ret
+}
+
+// The pragmas in each struct among `defns`, whose spans are in `source`, by name
+pub fn pragmas(source: &str, defns: &[(Range<usize>, Defn)]) -> Vec<(String, Vec<Pragma>)> {
+    defns
+        .iter()
+        .filter_map(|(span, defn)| match *defn {
+            Defn::Typespec(ref name, Type::Struct(_)) => {
+                let found = scan(&source.as_bytes()[span.clone()]);
+                if found.is_empty() {
+                    None
+                } else {
+                    Some((name.clone(), found))
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Check the pragmas on struct `name` are somewhere they make sense
+pub fn check(name: &str, pragmas: &[Pragma], decls: &[Decl]) -> Result<()> {
+    let fields = decls.len();
+    let mut headers = pragmas.iter().filter(|p| p.name == "header");
+    if let Some(header) = headers.next() {
+        if headers.next().is_some() {
+            return Err(format!("struct {} has more than one @header", name).into());
+        }
+        if header.field == 0 || header.field >= fields {
+            return Err(format!("@header in struct {} must be between two fields", name).into());
+        }
+    }
+    let header = pragmas.iter().find(|p| p.name == "header");
+    for lazy in pragmas.iter().filter(|p| p.name == "lazy") {
+        match decls.get(lazy.field) {
+            Some(&Decl::Named(..)) => (),
+            _ => return Err(format!("@lazy in struct {} must be before a field", name).into()),
+        }
+        if header.is_some_and(|h| lazy.field < h.field) {
+            return Err(format!("@lazy in struct {} must be after its @header", name).into());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_scan() {
+    let text = b"struct foo {
+    int a; /* not a pragma */
+    int b; // @header
+    union switch (int x) { case 0: int y; /* @header */ default: void; } u;
+    /* @unknown */
+    int c;
+}";
+    assert_eq!(
+        scan(text),
+        vec![Pragma {
+            name: "header",
+            field: 2
+        }]
+    );
+}
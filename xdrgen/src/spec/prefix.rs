@@ -0,0 +1,189 @@
+// Prefix stripping
+//
+// C has a single namespace, so specifications often prefix every name with the protocol's, such
+// as `nfs3_status` and `nfs3_fh`. With `Config::strip_prefix()`, the prefix is removed from the
+// names of the definitions, which can also be put in a module of their own. Definitions are
+// renamed before anything is generated, so the rest of the generator only sees the new names;
+// the top level and the modules see each other's items through glob imports.
+//
+// Stripping a prefix mustn't give two definitions the same name, or leave something which isn't
+// an identifier. Those definitions keep their prefix with `Config::keep_prefix_collisions()`, and
+// are an error otherwise.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::{Decl, Defn, EnumDefn, Result, Type, UnionCase, Value};
+use Config;
+
+// The new names of the definitions with prefixes, and their modules
+pub struct Prefixes {
+    // Each renamed definition's new name and module, by its name in the specification
+    renames: HashMap<String, (String, Option<String>)>,
+    // The module of each definition which goes in one, by its new name
+    modules: HashMap<String, String>,
+}
+
+fn is_ident(name: &str) -> bool {
+    match name.chars().next() {
+        Some('_') => name.len() > 1,
+        Some(c) => c.is_ascii_alphabetic(),
+        None => false,
+    }
+}
+
+impl Prefixes {
+    // Strip the configured prefixes from `names`, the names of all the definitions
+    pub fn new(names: &[&str], config: &Config) -> Result<Prefixes> {
+        let mut seen = HashSet::new();
+        let names: Vec<&str> = names.iter().cloned().filter(|n| seen.insert(*n)).collect();
+
+        let mut renames = HashMap::new();
+        for &name in &names {
+            let prefix = config
+                .prefixes
+                .iter()
+                .filter(|(prefix, _)| name.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len());
+            if let Some((prefix, module)) = prefix {
+                let rest = &name[prefix.len()..];
+                if is_ident(rest) {
+                    renames.insert(name.to_string(), (rest.to_string(), module.clone()));
+                } else if !config.keep_prefix_collisions {
+                    return Err(format!(
+                        "can't strip {} from {}: {:?} isn't an identifier",
+                        prefix, name, rest
+                    )
+                    .into());
+                }
+            }
+        }
+
+        // Keeping one definition's prefix can make it clash with another, so repeat until
+        // there's nothing more to keep
+        loop {
+            let mut prefixes = Prefixes {
+                renames,
+                modules: HashMap::new(),
+            };
+            let mut count = HashMap::new();
+            for &name in &names {
+                *count.entry(prefixes.name(name)).or_insert(0) += 1;
+            }
+            let clash = names.iter().find(|&&name| {
+                prefixes.renames.contains_key(name) && count[&prefixes.name(name)] > 1
+            });
+            match clash {
+                None => {
+                    if let Some(module) = prefixes.modules().find(|m| count.contains_key(*m)) {
+                        return Err(
+                            format!("module {} has the same name as a definition", module).into(),
+                        );
+                    }
+                    prefixes.modules = prefixes
+                        .renames
+                        .values()
+                        .filter_map(|(name, module)| Some((name.clone(), module.clone()?)))
+                        .collect();
+                    return Ok(prefixes);
+                }
+                Some(&name) if config.keep_prefix_collisions => {
+                    prefixes.renames.remove(name);
+                }
+                Some(&name) => {
+                    return Err(format!(
+                        "can't strip the prefix from {}: {} is already defined",
+                        name,
+                        prefixes.name(name)
+                    )
+                    .into())
+                }
+            }
+            renames = prefixes.renames;
+        }
+    }
+
+    fn name(&self, name: &str) -> String {
+        match self.renames.get(name) {
+            Some((name, _)) => name.clone(),
+            None => name.to_string(),
+        }
+    }
+
+    // The module the definition now called `name` goes in, if any
+    pub fn module(&self, name: &str) -> ::std::option::Option<&str> {
+        self.modules.get(name).map(String::as_str)
+    }
+
+    // The modules definitions go in, in order
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        let modules: BTreeSet<_> = self
+            .renames
+            .values()
+            .filter_map(|(_, module)| module.as_ref().map(String::as_str))
+            .collect();
+        modules.into_iter()
+    }
+
+    fn value(&self, val: &Value) -> Value {
+        match *val {
+            Value::Ident(ref name) => Value::Ident(self.name(name)),
+            Value::Const(c) => Value::Const(c),
+        }
+    }
+
+    fn decl(&self, decl: &Decl) -> Decl {
+        match *decl {
+            Decl::Void => Decl::Void,
+            Decl::Named(ref name, ref ty) => Decl::Named(name.clone(), self.ty(ty)),
+        }
+    }
+
+    fn ty(&self, ty: &Type) -> Type {
+        use self::Type::*;
+
+        match *ty {
+            Enum(ref defs) => Enum(
+                defs.iter()
+                    .map(|EnumDefn(name, val)| {
+                        EnumDefn(name.clone(), val.as_ref().map(|v| self.value(v)))
+                    })
+                    .collect(),
+            ),
+            Struct(ref decls) => Struct(decls.iter().map(|d| self.decl(d)).collect()),
+            Union(ref sel, ref cases, ref defl) => Union(
+                Box::new(self.decl(sel)),
+                cases
+                    .iter()
+                    .map(|UnionCase(val, decl)| UnionCase(self.value(val), self.decl(decl)))
+                    .collect(),
+                defl.as_ref().map(|d| Box::new(self.decl(d))),
+            ),
+            Option(ref ty) => Option(Box::new(self.ty(ty))),
+            Array(ref ty, ref sz) => Array(Box::new(self.ty(ty)), self.value(sz)),
+            Flex(ref ty, ref sz) => Flex(Box::new(self.ty(ty)), sz.as_ref().map(|v| self.value(v))),
+            Ident(ref name, derives) => Ident(self.name(name), derives),
+            ref ty => ty.clone(),
+        }
+    }
+
+    // `defn` with its name, and the names it refers to, stripped
+    pub fn defn(&self, defn: &Defn) -> Defn {
+        match *defn {
+            Defn::Typespec(ref name, ref ty) => Defn::Typespec(self.name(name), self.ty(ty)),
+            Defn::Typesyn(ref name, ref ty) => Defn::Typesyn(self.name(name), self.ty(ty)),
+            Defn::Const(ref name, val) => Defn::Const(self.name(name), val),
+        }
+    }
+
+    // `config` with the types it names stripped, to match the definitions
+    pub fn config(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        config.overrides = config
+            .overrides
+            .into_iter()
+            .map(|(name, ty)| (self.name(&name), ty))
+            .collect();
+        config.newtypes = config.newtypes.iter().map(|n| self.name(n)).collect();
+        config
+    }
+}
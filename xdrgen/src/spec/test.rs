@@ -1,5 +1,8 @@
-use super::super::generate;
-use super::specification;
+use super::super::{
+    generate, generate_with, malformed, rename_report, schema_fingerprint, source_map, symbol_at,
+    symbols, unused, unused_report, Config, Rename, Unused,
+};
+use super::{bitmasks, specification, Symtab};
 use std::io::Cursor;
 
 #[test]
@@ -250,3 +253,872 @@ union foo switch (int x) {
     println!("spec {:?}", s);
     assert!(s.is_ok())
 }
+
+#[test]
+fn validate() {
+    let spec = r#"
+        struct foo { int a; };
+        union bar switch (int x) { case 0: foo f; };
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("Validate"));
+
+    let mut out = Vec::new();
+    let config = Config::new().validate(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert_eq!(out.matches("xdr_codec :: Validate :: validate").count(), 2);
+}
+
+#[test]
+fn strict_strings() {
+    let spec = r#"
+        struct foo { string a<>; string b<10>; };
+"#;
+
+    let mut out = Vec::new();
+    let config = Config::new().strict_strings(true).xdr_strings(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert_eq!(out.matches("xdr_codec :: unpack_string_strict").count(), 2);
+    assert!(!out.contains("XdrString"));
+}
+
+#[test]
+fn skip() {
+    let spec = r#"
+        struct foo { int a; opaque b<>; };
+        union bar switch (int x) { case 0: foo f; default: void; };
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("Skip"));
+
+    let mut out = Vec::new();
+    let config = Config::new().skip(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert_eq!(out.matches("xdr_codec :: Skip < In > for").count(), 2);
+    assert!(out.contains("xdr_codec :: skip_opaque_flex"));
+}
+
+#[test]
+fn arbitrary() {
+    let spec = r#"
+        enum kind { A = 1, B = 2 };
+        struct foo { kind k; opaque b<4>; string s<>; int v<3>; };
+        union bar switch (int x) { case 0: foo f; case 1: void; default: void; };
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("Arbitrary"));
+
+    let mut out = Vec::new();
+    let config = Config::new().arbitrary(true).strict_strings(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert_eq!(out.matches("xdr_codec :: arbitrary :: Arbitrary < 'a > for").count(), 3);
+    assert!(out.contains("u . choose ( & [ kind :: A , kind :: B , ] )"));
+    assert!(out.contains("xdr_codec :: arbitrary :: opaque_flex ( u , Some ( 4i64 as usize ) )"));
+    assert!(out.contains("xdr_codec :: arbitrary :: ascii_string ( u , None )"));
+    assert!(out.contains("xdr_codec :: arbitrary :: flex ( u , Some ( 3i64 as usize ) )"));
+    let bar = out.split("Arbitrary < 'a > for bar").nth(1).unwrap();
+    assert!(bar.contains("0usize => bar :: Const0 ("));
+    assert!(!bar.contains("bar :: default"));
+}
+
+#[test]
+fn bounded_vecs() {
+    let spec = r#"
+        const MAX = 4;
+        struct foo { int a<3>; int b<>; opaque c<5>; string d<6>; foo e<MAX>; };
+        typedef unsigned hyper bar<2>;
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("BoundedVec"));
+
+    let mut out = Vec::new();
+    let config = Config::new().bounded_vecs(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub a : xdr_codec :: BoundedVec < i32 , { 3i64 as usize } >"));
+    assert!(out.contains("pub b : Vec < i32 >"));
+    assert!(out.contains("pub c : Vec < u8 >"));
+    assert!(out.contains("pub d : String"));
+    assert!(out.contains("pub e : xdr_codec :: BoundedVec < foo , { MAX as usize } >"));
+    assert!(out.contains("pub struct bar ( pub xdr_codec :: BoundedVec < u64 , { 2i64 as usize } > )"));
+}
+
+#[test]
+fn inline_vecs() {
+    let spec = r#"
+        const MAX = 4;
+        struct foo { int a<3>; int b<>; opaque c<2>; int d<MAX>; hyper e<5>; };
+"#;
+
+    let mut out = Vec::new();
+    let config = Config::new().inline_vecs(Some(4)).bounded_vecs(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub a : xdr_codec :: inline :: ArrayVec < i32 , { 3i64 as usize } >"));
+    assert!(out.contains("pub b : Vec < i32 >"));
+    assert!(out.contains("pub c : Vec < u8 >"));
+    assert!(out.contains("pub d : xdr_codec :: inline :: ArrayVec < i32 , { MAX as usize } >"));
+    assert!(out.contains("pub e : xdr_codec :: BoundedVec < i64 , { 5i64 as usize } >"));
+}
+
+#[test]
+fn opt_arrays() {
+    let spec = r#"
+        const ONE = 1;
+        struct foo { int a<1>; int b<2>; opaque c<1>; hyper d<ONE>; foo e<1>; foo *f; };
+"#;
+
+    let mut out = Vec::new();
+    let config = Config::new().opt_arrays(true).inline_vecs(Some(4));
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub a : xdr_codec :: OptArray < i32 >"));
+    assert!(out.contains("pub b : xdr_codec :: inline :: ArrayVec < i32 , { 2i64 as usize } >"));
+    assert!(out.contains("pub c : Vec < u8 >"));
+    assert!(out.contains("pub d : xdr_codec :: OptArray < i64 >"));
+    assert!(out.contains("pub e : xdr_codec :: OptArray < Box < foo >>"));
+    assert!(out.contains("pub f : Option < Box < foo >>"));
+}
+
+#[test]
+fn bulk_arrays() {
+    let spec = r#"
+        struct foo { int a<>; unsigned hyper b<10>; double c<>; bool d<>; int e[3]; };
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("xdr_codec :: bulk :: pack_flex ( & self . a , None , out )"));
+    assert!(out.contains("xdr_codec :: bulk :: unpack_flex ( input , Some ( 10i64 as usize ) )"));
+    assert!(out.contains("xdr_codec :: bulk :: pack_flex ( & self . c , None , out )"));
+    assert!(out.contains("xdr_codec :: pack_flex ( & self . d , None , out )"));
+    assert!(out.contains("xdr_codec :: pack_array ( & self . e [ .. ]"));
+}
+
+#[test]
+fn enum_names() {
+    let spec = "enum type { A = 1, self = 2 };";
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("type_ :: self_ => \"self\" ,"));
+    assert!(out.contains("impl :: std :: convert :: TryFrom < i32 > for type_"));
+    assert!(out.contains("impl :: std :: convert :: From < type_ > for i32"));
+    assert!(out.contains("\"A\" => Ok ( type_ :: A ) ,"));
+}
+
+#[test]
+fn status_unions() {
+    let spec = r#"
+        enum stat { OK = 0, ENOENT = 2 };
+        struct fail { int x; };
+        union a switch (stat s) { case OK: hyper v; default: fail f; };
+        union b switch (unsigned int s) { case 0: void; default: void; };
+        union c switch (stat s) { case ENOENT: hyper v; default: void; };
+        union d switch (stat s) { case OK: hyper v; case ENOENT: void; default: void; };
+        union e switch (hyper s) { case 0: hyper v; default: void; };
+        union f switch (stat s) { case OK: hyper v; };
+"#;
+
+    let mut out = Vec::new();
+    let config = Config::new().status_unions(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub type a = xdr_codec :: StatusUnion < i64 , fail >"));
+    assert!(out.contains("pub type b = xdr_codec :: StatusUnion < ( ) , ( ) >"));
+    assert!(!out.contains("Pack < Out > for a "));
+    assert!(out.contains("pub enum c "));
+    assert!(out.contains("pub enum d "));
+    assert!(out.contains("pub enum e "));
+    assert!(out.contains("pub enum f "));
+
+    let mut out = Vec::new();
+    let config = config.describe(true);
+    assert!(generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).is_err());
+}
+
+#[test]
+fn describe() {
+    let spec = r#"
+        const MAX = 4;
+        enum kind { A = 1, B = 2 };
+        typedef unsigned int num;
+        struct foo { num a; opaque b[MAX]; string c<>; foo *next; other d<2>; };
+        union bar switch (kind k) { case A: int i; case B: void; default: hyper h; };
+        typedef foo foos<>;
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("Describe"));
+
+    let mut out = Vec::new();
+    let config = Config::new().describe(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert_eq!(out.matches("impl xdr_codec :: describe :: Describe for").count(), 4);
+    assert!(out.contains("name : \"a\" , ty : xdr_codec :: describe :: TypeDesc :: UInt"));
+    assert!(out.contains("TypeDesc :: Opaque ( xdr_codec :: describe :: Size :: Fixed ( MAX as usize ) )"));
+    assert!(out.contains("TypeDesc :: String ( xdr_codec :: describe :: Size :: Flex ( None ) )"));
+    assert!(out.contains("TypeDesc :: Option ( Box :: new ( xdr_codec :: describe :: TypeDesc :: Named ( \"foo\" ,"));
+    assert!(out.contains("< other as xdr_codec :: describe :: Describe > :: describe"));
+    assert!(out.contains("EnumValue { name : \"B\" , value : 2i64 , }"));
+    assert!(out.contains("value : Some ( 1i64 ) , variant : \"A\" ,"));
+    assert!(out.contains("value : None , variant : \"default\" ,"));
+    assert!(out.contains("Kind :: Newtype ( xdr_codec :: describe :: TypeDesc :: Array ("));
+}
+
+#[test]
+fn field_context() {
+    let spec = r#"
+        struct foo { int a; opaque b<>; };
+        union bar switch (int x) { case 0: foo f; case 1: void; default: int d; };
+"#;
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("xdr_codec :: in_field ( \"foo.a\" ,"));
+    assert!(out.contains("xdr_codec :: in_field ( \"foo.b\" ,"));
+    assert!(out.contains("xdr_codec :: in_field ( \"bar::Const0\" ,"));
+    assert!(out.contains("xdr_codec :: in_field ( \"bar::default\" ,"));
+}
+
+#[test]
+fn source_locations() {
+    let spec = r#"
+const MAX = 4;
+
+/* a struct */
+struct foo { int a; };
+enum type { A, B };
+typedef foo foos<MAX>;
+typedef int num;
+"#;
+
+    let mut out = Vec::new();
+    generate("simple.x", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("Defined at"));
+
+    let mut out = Vec::new();
+    let config = Config::new().source_locations(true);
+    generate_with("simple.x", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("# [ doc = \"Defined at simple.x:2\" ] pub const MAX"));
+    assert!(out.contains("# [ doc = \"Defined at simple.x:5\" ] #[derive("));
+    assert!(out.contains("# [ doc = \"Defined at simple.x:6\" ] #[derive("));
+    assert!(out.contains("# [ doc = \"Defined at simple.x:7\" ] #[derive("));
+    assert!(out.contains("# [ doc = \"Defined at simple.x:8\" ] pub type num"));
+
+    let mut out = Vec::new();
+    source_map("simple.x", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "MAX\tsimple.x:2\nfoo\tsimple.x:5\ntype\tsimple.x:6\nfoos\tsimple.x:7\nnum\tsimple.x:8\n"
+    );
+}
+
+#[test]
+fn renames() {
+    let spec = r#"
+const Max = 4;
+const MAX = 5;
+struct foo { int ref; int Ab; int aB; int c; };
+enum type { A, B, self };
+union bar switch (int x) { case 1: int a; case -2: void; case Max: void; };
+union baz switch (type t) { case self: void; default: void; };
+"#;
+
+    let mut out = Vec::new();
+    rename_report(Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "foo\tref\tref_\tkeyword\n\
+         foo\taB\tAb\tcase-collision\n\
+         \ttype\ttype_\tkeyword\n\
+         type\tself\tself_\tkeyword\n\
+         bar\t1\tConst1\tnumeric-case\n\
+         bar\t-2\tConst_2\tnumeric-case\n\
+         baz\tself\tself_\tkeyword\n\
+         \tMAX\tMax\tcase-collision\n"
+    );
+
+    let mut out = Vec::new();
+    rename_report(Cursor::new(b"struct foo { int a; };"), &mut out).unwrap();
+    assert!(out.is_empty());
+
+    let found = super::super::renames(spec).unwrap();
+    assert_eq!(found.len(), 8);
+    assert_eq!(
+        found[2],
+        Rename {
+            scope: None,
+            from: "type".to_string(),
+            to: "type_".to_string(),
+            reason: "keyword",
+        }
+    );
+}
+
+#[test]
+fn unused_definitions() {
+    let spec = r#"
+const MAX = 4;
+const OLD_MAX = 8;
+const ONE = 1;
+enum kind { A = ONE, B };
+typedef int count;
+typedef opaque handle<MAX>;
+typedef int legacy;
+struct foo { count n; handle h; };
+union bar switch (int x) { case B: void; default: void; };
+struct old { legacy l; };
+"#;
+    let report = |roots: &[&str]| {
+        let mut out = Vec::new();
+        unused_report("s.x", Cursor::new(spec.as_bytes()), roots, &mut out)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    // Everything is used by some struct, union or enum, apart from OLD_MAX
+    assert_eq!(report(&[]).unwrap(), "const\tOLD_MAX\ts.x:3\n");
+
+    // Using bar only uses kind through its case label, and ONE through kind
+    assert_eq!(
+        report(&["bar"]).unwrap(),
+        "const\tMAX\ts.x:2\n\
+         const\tOLD_MAX\ts.x:3\n\
+         typedef\tcount\ts.x:6\n\
+         typedef\thandle\ts.x:7\n\
+         typedef\tlegacy\ts.x:8\n\
+         struct\tfoo\ts.x:9\n\
+         struct\told\ts.x:11\n"
+    );
+    assert_eq!(
+        report(&["foo", "bar"]).unwrap(),
+        "const\tOLD_MAX\ts.x:3\n\
+         typedef\tlegacy\ts.x:8\n\
+         struct\told\ts.x:11\n"
+    );
+
+    let err = report(&["nope"]).unwrap_err();
+    assert_eq!(err.to_string(), "unknown root nope");
+
+    assert_eq!(
+        unused(spec, &[]).unwrap(),
+        vec![Unused {
+            name: "OLD_MAX".to_string(),
+            kind: "const",
+            line: 3,
+        }]
+    );
+}
+
+#[test]
+fn fingerprints() {
+    let spec = r#"
+const MAX = 4;
+enum kind { A = 1, B };
+struct foo { int a<MAX>; kind k; foo *next; };
+union bar switch (kind k) { case A: opaque o[MAX]; case B: void; default: hyper h; };
+"#;
+    // The same, apart from comments, layout, order, and literal sizes
+    let same = r#"
+union bar switch (kind k) { case 1: opaque o[4]; case 2: void; default: hyper h; };
+/* foo */
+struct foo {
+    int a<4>;
+    kind k;
+    foo *next;
+};
+enum kind { A = 1, B = 2 };
+const MAX = 4;
+"#;
+    let different = spec.replace("hyper h", "unsigned hyper h");
+
+    let fp = |spec: &str| schema_fingerprint(Cursor::new(spec.as_bytes())).unwrap();
+    assert_eq!(fp(spec), fp(same));
+    assert_ne!(fp(spec), fp(&different));
+
+    let defns = specification(spec).unwrap();
+    let symtab = Symtab::new(&defns, &Config::default());
+    assert_eq!(
+        super::fingerprint::normalized(&symtab),
+        "const MAX = 4;\n\
+         type bar = union switch (kind k) { case 1: opaque[4] o; case 2: void; default: hyper h; };\n\
+         type foo = struct { int<4> a; kind k; foo* next; };\n\
+         type kind = enum { A = 1, B = 2, };\n"
+    );
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("SCHEMA_FINGERPRINT"));
+
+    let mut out = Vec::new();
+    let config = Config::new().schema_fingerprint(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let bytes: Vec<_> = fp(spec).iter().map(|b| format!("{}u8", b)).collect();
+    assert!(out.contains(&format!(
+        "pub const SCHEMA_FINGERPRINT : [ u8 ; 32 ] = [ {} ] ;",
+        bytes.join(" , ")
+    )));
+}
+
+#[test]
+fn bitmask_groups() {
+    let spec = r#"
+const MODE_READ = 1;
+const MODE_WRITE = 2;
+const MODE_EXEC_ONLY = 0x8;
+const FILE_TYPE_DIR = 1;
+const FILE_TYPE_REG = 2;
+const FILE_PERM_R = 1;
+const FILE_PERM_W = 2;
+const MAX_LEN = 4;
+const ONE_A = 1;
+const ONE_B = 3;
+const TAKEN_A = 1;
+const TAKEN_B = 2;
+const NUM_1 = 1;
+const NUM_2 = 2;
+struct TAKEN { int x; };
+enum E { E_A = 1, E_B = 2 };
+"#;
+
+    let defns = specification(spec).unwrap();
+    let symtab = Symtab::new(&defns, &Config::default());
+    let groups: Vec<_> = bitmasks(&symtab)
+        .into_iter()
+        .map(|b| {
+            let flags: Vec<_> = b
+                .flags
+                .into_iter()
+                .map(|(flag, _, val)| (flag, val))
+                .collect();
+            (b.name, flags)
+        })
+        .collect();
+    let flags =
+        |fs: &[(&str, u32)]| -> Vec<_> { fs.iter().map(|&(f, v)| (f.to_string(), v)).collect() };
+
+    assert_eq!(
+        groups,
+        vec![
+            ("FILE_PERM".to_string(), flags(&[("R", 1), ("W", 2)])),
+            ("FILE_TYPE".to_string(), flags(&[("DIR", 1), ("REG", 2)])),
+            (
+                "MODE".to_string(),
+                flags(&[("EXEC_ONLY", 8), ("READ", 1), ("WRITE", 2)])
+            ),
+        ]
+    );
+
+    let mut out = Vec::new();
+    let config = Config::new().bitmasks(true);
+    generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub struct MODE ( pub u32 ) ;"));
+    assert!(out.contains("pub const READ : MODE = MODE ( 1u32 ) ;"));
+    assert!(out.contains("pub const MODE_READ : i64 = 1i64 ;"));
+
+    let mut out = Vec::new();
+    generate("", Cursor::new(spec.as_bytes()), &mut out).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("pub struct MODE"));
+}
+
+#[test]
+fn type_overrides() {
+    let spec = r#"
+struct time { unsigned int s; };
+typedef time stamp;
+struct foo { time a; stamp b; };
+"#;
+    let gen = |config: &Config| {
+        let mut out = Vec::new();
+        generate_with("", Cursor::new(spec.as_bytes()), &mut out, config)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    let out =
+        gen(&Config::new().override_type("time", "std::time::SystemTime", Some("shim"))).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub struct time {"));
+    assert!(out.contains("pub type stamp = std::time::SystemTime ;"));
+    assert!(out.contains("pub a : std::time::SystemTime ,"));
+    assert!(out.contains("pub b : stamp ,"));
+    assert!(out.contains("shim :: pack ( & self . b , out ) ?"));
+    assert!(out.contains("shim :: unpack ( input ) ?"));
+
+    let out = gen(&Config::new().override_type("time", "Time", None)).unwrap();
+    assert!(out.contains("pub a : Time ,"));
+    assert!(out.contains("self . a . pack ( out ) ?"));
+
+    let err = gen(&Config::new().override_type("nope", "Time", None)).unwrap_err();
+    assert_eq!(err.to_string(), "can't override unknown type nope");
+
+    let spec = "struct time { int s; }; struct foo { time a<>; };";
+    let mut out = Vec::new();
+    let config = Config::new().override_type("time", "Time", Some("shim"));
+    let err = generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "type time is packed with a shim, so can't be an element or optional"
+    );
+}
+
+#[test]
+fn borrowed_structs() {
+    let spec = r#"
+typedef opaque handle<64>;
+struct foo { opaque a<>; string b<10>; handle c; opaque d[4]; int e; };
+typedef string name<>;
+struct bar { int x; name names<4>; foo f; };
+"#;
+    let gen = |config: &Config| {
+        let mut out = Vec::new();
+        generate_with("", Cursor::new(spec.as_bytes()), &mut out, config)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    let out = gen(&Config::new()).unwrap();
+    assert!(!out.contains("fooRef"));
+
+    let out = gen(&Config::new().borrowed(true)).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub struct fooRef < 'a >"));
+    assert!(out.contains("pub a : & 'a [ u8 ] ,"));
+    assert!(out.contains("pub b : & 'a str ,"));
+    assert!(out.contains("pub c : & 'a [ u8 ] ,"));
+    assert!(out.contains("pub d : & 'a [ u8 ] ,"));
+    assert!(out.contains("pub e : i32 ,"));
+    assert!(out.contains("cur . opaque_flex ( Some ( 64i64 as usize ) )"));
+    assert!(out.contains("cur . string ( Some ( 10i64 as usize ) )"));
+    assert!(out.contains("cur . opaque_array ( 4i64 as usize )"));
+    // Only the struct's own strings borrow
+    assert!(!out.contains("barRef"));
+
+    let out = gen(&Config::new().borrowed(true).strict_strings(true)).unwrap();
+    assert!(out.contains("cur . string_strict ( Some ( 10i64 as usize ) )"));
+
+    let out = gen(&Config::new().borrowed(true).xdr_strings(true)).unwrap();
+    assert!(out.contains("pub b : & 'a [ u8 ] ,"));
+    assert!(out.contains("xdr_codec :: XdrString :: from ( self . b )"));
+
+    let spec = "struct foo { opaque a<>; }; struct fooRef { int x; };";
+    let mut out = Vec::new();
+    let config = Config::new().borrowed(true);
+    let err = generate_with("", Cursor::new(spec.as_bytes()), &mut out, &config).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't generate fooRef for foo: the name is already used"
+    );
+}
+
+#[test]
+fn int_mapping() {
+    let spec = r#"
+typedef int offset;
+typedef unsigned int uid;
+struct foo { int a; unsigned int b; offset c; uid d; hyper e; };
+"#;
+    let gen = |config: &Config| {
+        let mut out = Vec::new();
+        generate_with("", Cursor::new(spec.as_bytes()), &mut out, config)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    let out = gen(&Config::new()).unwrap();
+    assert!(out.contains("pub a : i32 ,"));
+    assert!(out.contains("pub type uid = u32 ;"));
+
+    let out = gen(&Config::new().int_type("i64").newtype("uid")).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub a : i64 ,"));
+    assert!(out.contains("pub b : u32 ,"));
+    assert!(out.contains("pub type offset = i64 ;"));
+    assert!(out.contains("pub struct uid ( pub u32 ) ;"));
+    assert!(out.contains("pub e : i64 ,"));
+    assert!(out.contains("xdr_codec :: narrow :: < i32 , i64 > ( & self . a ) ?"));
+    assert!(out.contains("xdr_codec :: narrow :: < i32 , offset > ( & self . c ) ?"));
+
+    // Mapping to the XDR type changes nothing
+    assert_eq!(
+        gen(&Config::new().int_type("i32").uint_type("u32")).unwrap(),
+        gen(&Config::new()).unwrap()
+    );
+
+    let err = gen(&Config::new().int_type("u64")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't use u64 for int: expected one of i32, i64, i128"
+    );
+    let err = gen(&Config::new().uint_type("i32")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't use i32 for unsigned int: expected one of u32, u64, u128, i64, i128"
+    );
+    let err = gen(&Config::new().newtype("foo")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't make a newtype of foo: it isn't a typedef of an integer type"
+    );
+}
+
+#[test]
+fn prefix_stripping() {
+    let spec = r#"
+const NFS3_FHSIZE = 8;
+typedef opaque nfs3_fh<NFS3_FHSIZE>;
+struct nfs3_attr { nfs3_fh fh; int mode; };
+struct reply { nfs3_attr attr; };
+"#;
+    let gen = |spec: &str, config: &Config| {
+        let mut out = Vec::new();
+        generate_with("", Cursor::new(spec.as_bytes()), &mut out, config)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    let out = gen(
+        spec,
+        &Config::new()
+            .strip_prefix("nfs3_", None)
+            .strip_prefix("NFS3_", None),
+    )
+    .unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub const FHSIZE : i64 = 8i64 ;"));
+    assert!(out.contains("pub struct fh ( pub Vec < u8 > ) ;"));
+    assert!(out.contains("pub struct attr { pub fh : fh ,"));
+    assert!(out.contains("pub struct reply { pub attr : attr ,"));
+    assert!(out.contains("Some ( FHSIZE as usize )"));
+    assert!(!out.contains("use self ::"));
+
+    let out = gen(spec, &Config::new().strip_prefix("nfs3_", Some("nfs3"))).unwrap();
+    println!("{}", out);
+    assert!(out.contains("use self :: nfs3 :: * ;"));
+    assert!(out.contains("pub mod nfs3 { # [ allow ( unused_imports ) ] use super :: * ; #[derive"));
+    assert!(out.contains("pub struct attr { pub fh : fh ,"));
+    assert!(out.contains("pub const NFS3_FHSIZE : i64 = 8i64 ;"));
+    assert!(out.contains("for attr {"));
+
+    // Options naming types use the names in the specification
+    let out = gen(
+        spec,
+        &Config::new()
+            .strip_prefix("nfs3_", None)
+            .override_type("nfs3_fh", "Handle", None),
+    )
+    .unwrap();
+    assert!(out.contains("pub fh : Handle ,"));
+
+    let spec = "typedef int nfs3_status; typedef int status;";
+    let err = gen(spec, &Config::new().strip_prefix("nfs3_", None)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't strip the prefix from nfs3_status: status is already defined"
+    );
+
+    let spec = "typedef int nfs3_status; typedef int status; typedef int nfs3_;";
+    let out = gen(
+        spec,
+        &Config::new()
+            .strip_prefix("nfs3_", None)
+            .keep_prefix_collisions(true),
+    )
+    .unwrap();
+    assert!(out.contains("pub type nfs3_status = i32 ;"));
+    assert!(out.contains("pub type status = i32 ;"));
+    assert!(out.contains("pub type nfs3_ = i32 ;"));
+
+    let spec = "typedef int nfs3_;";
+    let err = gen(spec, &Config::new().strip_prefix("nfs3_", None)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't strip nfs3_ from nfs3_: \"\" isn't an identifier"
+    );
+
+    let spec = "typedef int nfs3_a; struct nfs3 { int x; };";
+    let err = gen(spec, &Config::new().strip_prefix("nfs3_", Some("nfs3"))).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "module nfs3 has the same name as a definition"
+    );
+}
+
+#[test]
+fn symbol_lookup() {
+    let spec = r#"const MAX = 0x10;
+/* struct foo */ struct foo {
+    int MAX_x;
+    opaque data<MAX>;
+};
+typedef foo foos<MAX>;
+union res switch (int s) { case 0: foo f; default: void; };
+"#;
+
+    let syms = symbols(spec).unwrap();
+    let found: Vec<_> = syms
+        .iter()
+        .map(|s| (s.kind, &spec[s.name_span.clone()], s.line))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("const", "MAX", 1),
+            ("struct", "foo", 2),
+            ("typedef", "foos", 6),
+            ("union", "res", 7),
+        ]
+    );
+    assert_eq!(&spec[syms[0].span.clone()], "const MAX = 0x10;");
+    assert!(spec[syms[1].span.clone()].starts_with("struct foo {"));
+    assert!(spec[syms[1].span.clone()].ends_with("};"));
+
+    let at = |offset| symbol_at(spec, offset).unwrap().map(|s| s.name);
+    let data = spec.find("data").unwrap();
+    assert_eq!(at(data), Some("foo".to_string()));
+    assert_eq!(at(spec.find("foos").unwrap()), Some("foos".to_string()));
+    // Comments between definitions aren't part of either
+    assert_eq!(at(spec.find("/*").unwrap()), None);
+
+    assert!(symbols("struct foo {").is_err());
+}
+
+#[test]
+fn skip_malformed() {
+    let spec = r#"const MAX = 4;
+struct broken { int; };
+union worse switch (int s) { case 0: int; };
+typedef opaque data<MAX>;
+"#;
+    let gen = |config: &Config| {
+        let mut out = Vec::new();
+        generate_with("", Cursor::new(spec.as_bytes()), &mut out, config)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    assert!(gen(&Config::new()).is_err());
+
+    let out = gen(&Config::new().skip_malformed(true)).unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub const MAX : i64 = 4i64 ;"));
+    assert!(out.contains("pub struct data ( pub Vec < u8 > ) ;"));
+    assert!(!out.contains("broken"));
+    assert!(!out.contains("worse"));
+
+    let found: Vec<_> = malformed(spec)
+        .iter()
+        .map(|m| (&spec[m.span.clone()], m.line))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("struct broken { int; };", 2),
+            ("union worse switch (int s) { case 0: int; };", 3),
+        ]
+    );
+    assert!(malformed("const MAX = 4;").is_empty());
+}
+
+#[test]
+fn header_pragma() {
+    let gen = |spec: &str| {
+        let mut out = Vec::new();
+        generate("", Cursor::new(spec.as_bytes()), &mut out)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    let out = gen(r#"
+struct msg {
+    unsigned int xid;
+    int proc; /* @header */
+    opaque body<>;
+};
+struct plain { int a; /* @param a is just a comment */ int b; };
+"#)
+    .unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub struct msgHeader { pub xid : u32 , pub proc_ : i32 , }"));
+    assert!(out.contains("xdr_codec :: header :: UnpackHeader < In > for msg"));
+    assert!(out.contains("xid : header . xid ,"));
+    assert!(out.contains("xdr_codec :: in_field ( \"msg.body\" ,"));
+    assert!(!out.contains("plainHeader"));
+
+    for spec in &[
+        "struct msg { /* @header */ int a; int b; };",
+        "struct msg { int a; int b; /* @header */ };",
+        "struct msg { int a; /* @header */ int b; /* @header */ int c; };",
+        "struct msg { int a; /* @header */ int b; }; typedef int msgHeader;",
+    ] {
+        assert!(gen(spec).is_err(), "{}", spec);
+    }
+}
+
+#[test]
+fn lazy_pragma() {
+    let gen = |spec: &str, config: &Config| {
+        let mut out = Vec::new();
+        generate_with("", Cursor::new(spec.as_bytes()), &mut out, config)
+            .map(|()| String::from_utf8(out).unwrap())
+    };
+
+    let out = gen(
+        r#"
+struct pt { int x; /* @lazy */ int y; };
+struct outer { pt p; };
+"#,
+        &Config::new(),
+    )
+    .unwrap();
+    println!("{}", out);
+    assert!(out.contains("pub y : xdr_codec :: lazy :: Lazy < i32 > ,"));
+    assert!(out.contains("xdr_codec :: Pack :: pack ( & self . y , out ) ?"));
+    assert!(out.contains("xdr_codec :: lazy :: Lazy :: capture ( input ,"));
+    // Neither is `Copy`, though an `int` is
+    assert!(out.contains("#[derive( Clone , Debug , Eq , PartialEq )] pub struct pt {"));
+    assert!(out.contains("#[derive( Clone , Debug , Eq , PartialEq )] pub struct outer {"));
+
+    let lazy_struct = "struct inner { int a; }; struct s { /* @lazy */ inner i; };";
+    assert!(gen(lazy_struct, &Config::new()).is_err());
+    assert!(gen(lazy_struct, &Config::new().skip(true)).is_ok());
+    assert!(gen(lazy_struct, &Config::new().skip(true).arbitrary(true)).is_err());
+    let out = gen(lazy_struct, &Config::new().skip(true).borrowed(true)).unwrap();
+    assert!(!out.contains("sRef"));
+
+    for spec in &[
+        "struct s { int a; /* @lazy */ };",
+        "struct s { int a; /* @lazy */ void; };",
+        "struct s { /* @lazy */ int a; /* @header */ int b; };",
+    ] {
+        assert!(gen(spec, &Config::new()).is_err(), "{}", spec);
+    }
+}
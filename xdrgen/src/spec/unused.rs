@@ -0,0 +1,117 @@
+// Unused definitions
+//
+// Specifications which have been extended over many years tend to accumulate typedefs and
+// constants which nothing refers to any more. A definition is used if it's one of the roots, the
+// types the application actually sends and receives, or if a used definition refers to it: as a
+// type, as the size of an array, as a union case or as the value of an enum member. Referring to
+// an enum member uses the enum.
+//
+// Without any given roots, every struct, union and enum is a root, so only typedefs and constants
+// can be unused.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Decl, Defn, EnumDefn, Symtab, Type, UnionCase, Value};
+
+/// A definition which is never used
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Unused {
+    /// Its name
+    pub name: String,
+    /// `const`, `typedef`, `enum`, `struct` or `union`
+    pub kind: &'static str,
+    /// The (1-based) line it starts on
+    pub line: usize,
+}
+
+// The definitions `val` refers to
+fn value_refs(val: &Value, symtab: &Symtab, refs: &mut Vec<String>) {
+    if let Value::Ident(ref id) = *val {
+        match symtab.getconst(id) {
+            // An enum member
+            Some((_, Some(scope))) => refs.push(scope),
+            _ => refs.push(id.clone()),
+        }
+    }
+}
+
+fn decl_refs(decl: &Decl, symtab: &Symtab, refs: &mut Vec<String>) {
+    if let Decl::Named(_, ref ty) = *decl {
+        type_refs(ty, symtab, refs);
+    }
+}
+
+// The definitions `ty` refers to
+fn type_refs(ty: &Type, symtab: &Symtab, refs: &mut Vec<String>) {
+    use self::Type::*;
+
+    match *ty {
+        Ident(ref name, _) => refs.push(name.clone()),
+        Option(ref ty) | Flex(ref ty, None) => type_refs(ty, symtab, refs),
+        Array(ref ty, ref sz) | Flex(ref ty, Some(ref sz)) => {
+            type_refs(ty, symtab, refs);
+            value_refs(sz, symtab, refs);
+        }
+        Enum(ref defs) => {
+            for EnumDefn(_, val) in defs {
+                if let Some(ref val) = *val {
+                    value_refs(val, symtab, refs);
+                }
+            }
+        }
+        Struct(ref decls) => {
+            for decl in decls {
+                decl_refs(decl, symtab, refs);
+            }
+        }
+        Union(ref sel, ref cases, ref defl) => {
+            decl_refs(sel, symtab, refs);
+            for UnionCase(val, decl) in cases {
+                value_refs(val, symtab, refs);
+                decl_refs(decl, symtab, refs);
+            }
+            if let Some(ref defl) = *defl {
+                decl_refs(defl, symtab, refs);
+            }
+        }
+        _ => (),
+    }
+}
+
+// The definitions in `defns` which aren't used by `roots`, or by any struct, union or enum if
+// there are no roots, in order of definition
+pub fn unused(defns: &[(usize, Defn)], symtab: &Symtab, roots: &[&str]) -> Vec<Unused> {
+    let byname: HashMap<&str, &Defn> = defns.iter().map(|(_, d)| (d.name(), d)).collect();
+
+    let mut pending: Vec<String> = if roots.is_empty() {
+        defns
+            .iter()
+            .filter(|(_, d)| !matches!(d.kind(), "const" | "typedef"))
+            .map(|(_, d)| d.name().to_string())
+            .collect()
+    } else {
+        roots.iter().map(|r| r.to_string()).collect()
+    };
+
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if !used.insert(name.clone()) {
+            continue;
+        }
+        if let Some(&Defn::Typespec(_, ref ty)) | Some(&Defn::Typesyn(_, ref ty)) =
+            byname.get(name.as_str())
+        {
+            type_refs(ty, symtab, &mut pending);
+        }
+    }
+
+    defns
+        .iter()
+        .filter(|(_, d)| !used.contains(d.name()))
+        .map(|(line, d)| Unused {
+            name: d.name().to_string(),
+            kind: d.kind(),
+            line: *line,
+        })
+        .collect()
+}
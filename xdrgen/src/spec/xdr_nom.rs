@@ -2,6 +2,7 @@
 use nom::IResult::*;
 use nom::{is_digit, is_space, not_line_ending, Err, ErrorKind, IResult, Needed};
 
+use std::ops::Range;
 use std::str;
 
 use super::{Decl, Defn, Derives, EnumDefn, Type, UnionCase, Value};
@@ -24,9 +25,103 @@ fn eof(input: &[u8]) -> IResult<&[u8], ()> {
     }
 }
 
+#[cfg(test)]
 pub fn specification(input: &str) -> Result<Vec<Defn>, String> {
-    match spec(input.as_bytes()) {
-        Done(_, spec) => Ok(spec),
+    located_specification(input).map(|defns| defns.into_iter().map(|(_, defn)| defn).collect())
+}
+
+// The (1-based) line byte `offset` of `input` is on.
+pub fn line_at(input: &str, offset: usize) -> usize {
+    input[..offset].matches('\n').count() + 1
+}
+
+// Parse a specification, along with the (1-based) line each definition starts on.
+pub fn located_specification(input: &str) -> Result<Vec<(usize, Defn)>, String> {
+    spanned_specification(input).map(|defns| {
+        defns
+            .into_iter()
+            .map(|(span, defn)| (line_at(input, span.start), defn))
+            .collect()
+    })
+}
+
+// The length of a malformed definition at the start of `input`: up to and including the next `;`
+// which isn't inside braces, or the rest of the input. Comments and directives are skipped as
+// `ws` skips them, so any `;` or braces in them don't count.
+fn malformed_len(input: &[u8]) -> usize {
+    let mut depth = 0;
+    let mut idx = 0;
+    while idx < input.len() {
+        let rest = ws(&input[idx..]);
+        if rest.len() < input.len() - idx {
+            idx = input.len() - rest.len();
+            continue;
+        }
+        match input[idx] {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b';' if depth <= 0 => return idx + 1,
+            _ => (),
+        }
+        idx += 1;
+    }
+    input.len()
+}
+
+// Definitions, and malformed regions with a description, with the byte range of each
+type Recovered = (Vec<(Range<usize>, Defn)>, Vec<(Range<usize>, String)>);
+
+// Parse a specification, skipping definitions which can't be parsed rather than failing. Gives
+// the byte range of each definition, as `spanned_specification()` does, and of each malformed
+// region, with a description.
+pub fn recovering_specification(input: &str) -> Recovered {
+    let bytes = input.as_bytes();
+    let mut defns = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut rest = match directive(bytes) {
+        Done(rest, _) => rest,
+        _ => bytes,
+    };
+    loop {
+        rest = ws(rest);
+        if rest.is_empty() {
+            break;
+        }
+        let start = bytes.len() - rest.len();
+        match definition(rest) {
+            Done(after, defn) => {
+                defns.push((start..bytes.len() - after.len(), defn));
+                rest = after;
+            }
+            _ => {
+                let len = malformed_len(rest);
+                let text = &input[start..start + len];
+                let first = text.lines().next().unwrap_or("").trim();
+                errors.push((start..start + len, format!("can't parse `{}`", first)));
+                rest = &rest[len..];
+            }
+        }
+    }
+
+    (defns, errors)
+}
+
+// Parse a specification, along with the byte range of each definition, from its first token to
+// the end of its `;`.
+pub fn spanned_specification(input: &str) -> Result<Vec<(Range<usize>, Defn)>, String> {
+    let input = input.as_bytes();
+
+    match located_spec(input) {
+        Done(_, spec) => Ok(spec
+            .into_iter()
+            .map(|(at, defn, after)| {
+                // Definitions start with any spaces and comments before them
+                let start = input.len() - ws(at).len();
+                let end = input.len() - after.len();
+                (start..end, defn)
+            })
+            .collect()),
         Error(Err::Position(kind, input)) => Err(format!(
             "{:?}: {}",
             kind,
@@ -37,9 +132,27 @@ pub fn specification(input: &str) -> Result<Vec<Defn>, String> {
     }
 }
 
+// The input remaining at this point, which locates whatever is parsed next.
+fn here(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    Done(input, input)
+}
+
+named!(
+    located_definition<&[u8], (&[u8], Defn, &[u8])>,
+    do_parse!(at: here >> defn: definition >> after: here >> (at, defn, after))
+);
+
+named!(
+    located_spec<&[u8], Vec<(&[u8], Defn, &[u8])>>,
+    do_parse!(opt!(directive) >> defns: many0!(located_definition) >> spaces >> eof >> (defns))
+);
+
 named!(
     spec<Vec<Defn>>,
-    do_parse!(opt!(directive) >> defns: many0!(definition) >> spaces >> eof >> (defns))
+    map!(located_spec, |defns: Vec<(&[u8], Defn, &[u8])>| defns
+        .into_iter()
+        .map(|(_, defn, _)| defn)
+        .collect())
 );
 
 #[test]
@@ -96,6 +209,78 @@ enum bop { a = 2, b = 1 };
     );
 }
 
+#[test]
+fn test_spanned_spec() {
+    let input = "const a = 1;\n\n/* b */ typedef int b ;\n";
+    let spec = spanned_specification(input).unwrap();
+    assert_eq!(spec.len(), 2);
+    assert_eq!(&input[spec[0].0.clone()], "const a = 1;");
+    assert_eq!(&input[spec[1].0.clone()], "typedef int b ;");
+}
+
+#[test]
+fn test_recovering_spec() {
+    let input =
+        "const a = 1;\nstruct b { int; };\nunion c { x } garbage;\ntypedef int d;\nenum e {";
+    let (defns, errors) = recovering_specification(input);
+    assert_eq!(
+        defns,
+        vec!(
+            (0..12, Defn::constant("a", 1)),
+            (55..69, Defn::typesyn("d", Type::Int)),
+        )
+    );
+    let errors: Vec<_> = errors
+        .into_iter()
+        .map(|(span, msg)| (&input[span], msg))
+        .collect();
+    assert_eq!(
+        errors,
+        vec!(
+            (
+                "struct b { int; };",
+                "can't parse `struct b { int; };`".to_string()
+            ),
+            (
+                "union c { x } garbage;",
+                "can't parse `union c { x } garbage;`".to_string()
+            ),
+            ("enum e {", "can't parse `enum e {`".to_string()),
+        )
+    );
+
+    // `;` and braces in comments don't end a malformed definition
+    let input = "struct b { int; /* }; */ x // };\n};\ntypedef int d;";
+    let (defns, errors) = recovering_specification(input);
+    assert_eq!(defns, vec!((36..50, Defn::typesyn("d", Type::Int))));
+    let errors: Vec<_> = errors
+        .into_iter()
+        .map(|(span, msg)| (&input[span], msg))
+        .collect();
+    assert_eq!(
+        errors,
+        vec!((
+            "struct b { int; /* }; */ x // };\n};",
+            "can't parse `struct b { int; /* }; */ x // };`".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_located_spec() {
+    assert_eq!(
+        located_specification("const a = 1;\n\n/* b\n */ typedef int b;\nenum c {\n x };"),
+        Ok(vec!(
+            (1, Defn::constant("a", 1)),
+            (4, Defn::typesyn("b", Type::Int)),
+            (
+                5,
+                Defn::typespec("c", Type::Enum(vec!(EnumDefn::new("x", None))))
+            ),
+        ))
+    );
+}
+
 named!(
     definition<Defn>,
     alt!(type_def => { |t| t } |
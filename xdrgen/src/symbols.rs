@@ -0,0 +1,127 @@
+// Symbols
+//
+// Editors and language servers for .x files need to know where definitions are in the source,
+// not just what they are. The parser gives the byte range of each definition, and its name is
+// the first identifier in it with that name, outside comments. They also need to know where the
+// source is malformed, while it's being edited.
+
+use std::ops::Range;
+
+use xdr::{Error, Result};
+
+use spec;
+
+/// A definition in an XDR specification, and where it is in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The definition's name in the specification
+    pub name: String,
+    /// The kind of definition: `const`, `typedef`, `enum`, `struct` or `union`
+    pub kind: &'static str,
+    /// The bytes of the source the definition spans, from its first keyword to its `;`
+    pub span: Range<usize>,
+    /// The bytes of its name
+    pub name_span: Range<usize>,
+    /// The (1-based) line it starts on
+    pub line: usize,
+}
+
+/// A region of an XDR specification which can't be parsed as a definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Malformed {
+    /// The bytes of the source it spans, up to the next `;` which isn't inside braces
+    pub span: Range<usize>,
+    /// The (1-based) line it starts on
+    pub line: usize,
+    /// What's wrong with it
+    pub message: String,
+}
+
+fn is_ident(c: u8, first: bool) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || (!first && c.is_ascii_digit())
+}
+
+// Where the identifier `name` first appears in `text`, outside comments
+fn find_ident(text: &[u8], name: &str) -> Option<Range<usize>> {
+    let find = |from: usize, pat: &[u8]| {
+        text[from..]
+            .windows(pat.len())
+            .position(|w| w == pat)
+            .map(|pos| from + pos)
+    };
+
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with(b"/*") {
+            i = find(i + 2, b"*/").map_or(text.len(), |end| end + 2);
+        } else if text[i..].starts_with(b"//") {
+            i = find(i, b"\n").unwrap_or(text.len());
+        } else if is_ident(text[i], true) || text[i].is_ascii_digit() {
+            // Numbers are skipped whole, so `0x1f` doesn't contain `x1f`
+            let start = i;
+            i += 1;
+            while i < text.len() && is_ident(text[i], false) {
+                i += 1;
+            }
+            if &text[start..i] == name.as_bytes() {
+                return Some(start..i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// List the definitions in an XDR specification, in order, with where they are in `source`
+///
+/// This is intended for tools such as editors and language servers. Positions are byte offsets
+/// into `source`.
+pub fn symbols(source: &str) -> Result<Vec<Symbol>> {
+    let defns = spec::spanned_specification(source)
+        .map_err(|e| Error::from(format!("parse error: {}", e)))?;
+
+    Ok(defns
+        .into_iter()
+        .map(|(span, defn)| {
+            let name_span = match find_ident(&source.as_bytes()[span.clone()], defn.name()) {
+                Some(name) => span.start + name.start..span.start + name.end,
+                None => span.clone(),
+            };
+            Symbol {
+                name: defn.name().to_string(),
+                kind: defn.kind(),
+                line: spec::line_at(source, span.start),
+                span,
+                name_span,
+            }
+        })
+        .collect())
+}
+
+/// Find the definition in an XDR specification which byte `offset` of `source` is within
+///
+/// Returns `None` if the offset is between definitions, in spaces or comments.
+pub fn symbol_at(source: &str, offset: usize) -> Result<Option<Symbol>> {
+    Ok(symbols(source)?
+        .into_iter()
+        .find(|sym| sym.span.contains(&offset)))
+}
+
+/// Find the regions of an XDR specification which can't be parsed as definitions
+///
+/// These are the regions skipped by `Config::skip_malformed()`. Each runs from where a
+/// definition was expected up to the next `;` which isn't inside braces, so the definitions after
+/// it can still be parsed. An empty result means the whole specification parses.
+pub fn malformed(source: &str) -> Vec<Malformed> {
+    let (_, errors) = spec::recovering_specification(source);
+
+    errors
+        .into_iter()
+        .map(|(span, message)| Malformed {
+            line: spec::line_at(source, span.start),
+            span,
+            message,
+        })
+        .collect()
+}
@@ -5,38 +5,377 @@ extern crate env_logger;
 extern crate xdrgen;
 
 use std::fs::File;
-use std::io::{stderr, stdin, stdout};
-use std::io::{BufReader, Write};
+use std::io::{sink, stderr, stdin, stdout};
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
 
-use clap::App;
+use clap::{App, Arg};
 
-use xdrgen::generate;
+use xdrgen::{
+    generate_includes, generate_with, golden_tests, include_graph, malformed, rename_report,
+    renames, schema_fingerprint, source_map, unused, unused_report, Config, Rename, Unused,
+};
 
 fn main() {
     let _ = env_logger::init();
 
     let matches = App::new("XDR code generator")
         .version(env!("CARGO_PKG_VERSION"))
-        .arg_from_usage("[FILE] 'Set .x file'")
+        .after_help(
+            "Diagnostics only go to stderr. The exit status is 0 on success, 1 if there were errors, and 2 for bad arguments.",
+        )
+        .arg_from_usage("[FILE] 'Set .x file, or - for stdin'")
+        .arg_from_usage("--validate 'Validate values with xdr_codec::Validate after unpacking'")
+        .arg_from_usage(
+            "--xdr-strings 'Use xdr_codec::XdrString for strings which may not be UTF-8'",
+        )
+        .arg_from_usage("--strict-strings 'Only accept printable ASCII in unpacked strings'")
+        .arg_from_usage("--skip 'Implement xdr_codec::Skip to skip values without unpacking'")
+        .arg_from_usage(
+            "--arbitrary 'Implement arbitrary::Arbitrary (needs the xdr-codec arbitrary feature)'",
+        )
+        .arg_from_usage("--describe 'Implement xdr_codec::describe::Describe for runtime introspection'")
+        .arg_from_usage("--bounded-vecs 'Use xdr_codec::BoundedVec for bounded element arrays'")
+        .arg_from_usage(
+            "--inline-vecs=[MAX] 'Use xdr_codec::inline::ArrayVec for element arrays bounded by at most MAX (needs the xdr-codec arrayvec feature)'",
+        )
+        .arg_from_usage("--opt-arrays 'Use xdr_codec::OptArray for element arrays of at most one element'")
+        .arg_from_usage("--status-unions 'Use xdr_codec::StatusUnion for unions switched on a zero status'")
+        .arg_from_usage("--source-locations 'Document where each generated item is defined'")
+        .arg_from_usage(
+            "--schema-fingerprint 'Generate SCHEMA_FINGERPRINT, a hash of the specification'",
+        )
+        .arg_from_usage(
+            "--bitmasks 'Generate flags types for groups of constants which look like bit masks'",
+        )
+        .arg_from_usage(
+            "--borrowed 'Also generate a FooRef variant of each struct borrowing its opaque and string data'",
+        )
+        .arg(
+            Arg::from_usage(
+                "--override=[NAME=TYPE] 'Use Rust type TYPE for XDR type NAME; TYPE may be followed by a comma and the path of a shim module to pack and unpack it'",
+            )
+            .multiple_occurrences(true),
+        )
+        .arg_from_usage("--int-type=[TYPE] 'Use Rust type TYPE, such as i64, for XDR ints'")
+        .arg_from_usage(
+            "--uint-type=[TYPE] 'Use Rust type TYPE, such as u64, for XDR unsigned ints'",
+        )
+        .arg(
+            Arg::from_usage(
+                "--newtype=[NAME] 'Generate the integer typedef NAME as a newtype rather than an alias'",
+            )
+            .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::from_usage(
+                "--strip-prefix=[PREFIX] 'Strip PREFIX from the names of definitions; PREFIX may be followed by = and a module to put them in'",
+            )
+            .multiple_occurrences(true),
+        )
+        .arg_from_usage(
+            "--keep-prefix-collisions 'Keep the prefix of definitions whose stripped name is already used, rather than failing'",
+        )
+        .arg_from_usage(
+            "--skip-malformed 'Skip definitions which can't be parsed, with a warning, and generate the rest'",
+        )
+        .arg_from_usage(
+            "--source-map=[MAPFILE] 'Also write where each definition is to MAPFILE, one per line'",
+        )
+        .arg_from_usage("--renames 'Summarize identifiers which were renamed on stderr'")
+        .arg_from_usage(
+            "--rename-report=[REPORTFILE] 'Also write each renamed identifier to REPORTFILE, one per line'",
+        )
+        .arg_from_usage("--unused 'Report typedefs and constants which are never used on stderr'")
+        .arg(
+            Arg::from_usage(
+                "--root=[NAME] 'With --unused, a type the application uses; then unused structs, unions and enums are reported too'",
+            )
+            .multiple_occurrences(true),
+        )
+        .arg_from_usage(
+            "--out-dir=[DIR] 'Generate a module for FILE and each file it includes into DIR instead, rewriting only those whose inputs changed'",
+        )
+        .arg_from_usage(
+            "--include-graph 'Write the files FILE includes as make dependencies instead of code'",
+        )
+        .arg(
+            Arg::from_usage(
+                "--emit=[WHAT] 'What to write to stdout: the Rust code (the default), the source map, the rename or unused definition report, the schema fingerprint in hex, or nothing, only checking FILE'",
+            )
+            .possible_values(["rust", "source-map", "renames", "unused", "fingerprint", "none"]),
+        )
+        .arg_from_usage("--quiet 'Only report errors on stderr'")
+        .arg_from_usage(
+            "--json-diagnostics 'Report diagnostics on stderr as JSON objects, one per line'",
+        )
+        .arg_from_usage(
+            "--golden-tests=[DIR] 'Write round-trip tests for the sample payloads in DIR instead of code'",
+        )
         .get_matches();
 
+    let diag = Diagnostics {
+        quiet: matches.is_present("quiet"),
+        json: matches.is_present("json-diagnostics"),
+    };
+
+    let inline_vecs = match matches.value_of("inline-vecs").map(str::parse) {
+        None => None,
+        Some(Ok(max)) => Some(max),
+        Some(Err(e)) => diag.fail("xdrgen", &format!("bad --inline-vecs limit: {}", e)),
+    };
+
+    let mut config = Config::new();
+    for spec in matches.values_of("override").into_iter().flatten() {
+        config = match spec.split_once('=') {
+            Some((name, ty)) => match ty.split_once(',') {
+                Some((ty, shim)) => config.override_type(name, ty, Some(shim)),
+                None => config.override_type(name, ty, None),
+            },
+            None => diag.fail(
+                "xdrgen",
+                &format!("bad --override {}: expected NAME=TYPE", spec),
+            ),
+        };
+    }
+
+    if let Some(ty) = matches.value_of("int-type") {
+        config = config.int_type(ty);
+    }
+    if let Some(ty) = matches.value_of("uint-type") {
+        config = config.uint_type(ty);
+    }
+    for name in matches.values_of("newtype").into_iter().flatten() {
+        config = config.newtype(name);
+    }
+
+    for spec in matches.values_of("strip-prefix").into_iter().flatten() {
+        config = match spec.split_once('=') {
+            Some((prefix, module)) => config.strip_prefix(prefix, Some(module)),
+            None => config.strip_prefix(spec, None),
+        };
+    }
+
+    let config = config
+        .validate(matches.is_present("validate"))
+        .xdr_strings(matches.is_present("xdr-strings"))
+        .strict_strings(matches.is_present("strict-strings"))
+        .skip(matches.is_present("skip"))
+        .arbitrary(matches.is_present("arbitrary"))
+        .describe(matches.is_present("describe"))
+        .bounded_vecs(matches.is_present("bounded-vecs"))
+        .inline_vecs(inline_vecs)
+        .opt_arrays(matches.is_present("opt-arrays"))
+        .status_unions(matches.is_present("status-unions"))
+        .source_locations(matches.is_present("source-locations"))
+        .schema_fingerprint(matches.is_present("schema-fingerprint"))
+        .bitmasks(matches.is_present("bitmasks"))
+        .borrowed(matches.is_present("borrowed"))
+        .keep_prefix_collisions(matches.is_present("keep-prefix-collisions"))
+        .skip_malformed(matches.is_present("skip-malformed"));
+
     let output = stdout();
-    let mut err = stderr();
-
-    let res = if let Some(fname) = matches.value_of("FILE") {
-        let f = match File::open(fname) {
-            Ok(f) => f,
-            Err(e) => {
-                let _ = writeln!(&mut err, "Failed to open {}: {}", fname, e);
-                std::process::exit(1);
-            }
+    let fname = match matches.value_of("FILE") {
+        None | Some("-") => None,
+        Some(fname) => Some(fname),
+    };
+
+    // These follow includes, so they need the path of the specification
+    if matches.is_present("out-dir") || matches.is_present("include-graph") {
+        let fname = match fname {
+            Some(fname) => fname,
+            None => diag.fail("xdrgen", "--out-dir and --include-graph need a FILE"),
         };
-        generate(fname, BufReader::new(f), output)
-    } else {
-        generate("stdin", BufReader::new(stdin()), output)
+        let res = match matches.value_of("out-dir") {
+            Some(outdir) => generate_includes(fname, outdir, &config).map(|_| ()),
+            None => Ok(()),
+        }
+        .and_then(|_| {
+            if matches.is_present("include-graph") {
+                include_graph(fname, output)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = res {
+            diag.fail(fname, &e.to_string());
+        }
+        return;
+    }
+
+    // Read the whole specification up front, since the source map needs it too
+    let (fname, mut input): (&str, Box<dyn Read>) = match fname {
+        Some(fname) => match File::open(fname) {
+            Ok(f) => (fname, Box::new(BufReader::new(f))),
+            Err(e) => diag.fail(fname, &format!("can't open: {}", e)),
+        },
+        None => ("stdin", Box::new(BufReader::new(stdin()))),
     };
+    let mut source = Vec::new();
+    if let Err(e) = input.read_to_end(&mut source) {
+        diag.fail(fname, &format!("can't read: {}", e));
+    }
+
+    if let Some(samples) = matches.value_of("golden-tests") {
+        if let Err(e) = golden_tests(fname, &source[..], Path::new(samples), output) {
+            diag.fail(fname, &e.to_string());
+        }
+        return;
+    }
+
+    if matches.is_present("skip-malformed") {
+        let source = String::from_utf8_lossy(&source);
+        for m in malformed(&source) {
+            let loc = format!("{}:{}", fname, m.line);
+            diag.report("warning", &loc, &format!("skipped: {}", m.message));
+        }
+    }
+
+    let roots: Vec<_> = matches.values_of("root").into_iter().flatten().collect();
+
+    let res = match matches.value_of("emit").unwrap_or("rust") {
+        "rust" => generate_with(fname, &source[..], output, &config),
+        "source-map" => source_map(fname, &source[..], output),
+        "renames" => rename_report(&source[..], output),
+        "unused" => unused_report(fname, &source[..], &roots, output),
+        "fingerprint" => schema_fingerprint(&source[..]).and_then(|fp| {
+            let hex: String = fp.iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(output.lock(), "{}", hex)?;
+            Ok(())
+        }),
+        _ => generate_with(fname, &source[..], sink(), &config),
+    }
+    .and_then(|_| match matches.value_of("source-map") {
+        Some(mapfile) => source_map(fname, &source[..], File::create(mapfile)?),
+        None => Ok(()),
+    })
+    .and_then(|_| {
+        if matches.is_present("renames") {
+            summarize_renames(fname, &renames(&String::from_utf8_lossy(&source))?, &diag);
+        }
+        if let Some(reportfile) = matches.value_of("rename-report") {
+            rename_report(&source[..], File::create(reportfile)?)?;
+        }
+
+        if matches.is_present("unused") {
+            let source = String::from_utf8_lossy(&source);
+            summarize_unused(fname, &unused(&source, &roots)?, &diag);
+        }
+        Ok(())
+    });
 
     if let Err(e) = res {
-        let _ = writeln!(&mut err, "Failed: {}", e);
+        diag.fail(fname, &e.to_string());
+    }
+}
+
+// Where diagnostics go: stderr, as text or JSON, so stdout only ever has the output
+struct Diagnostics {
+    // Only report errors
+    quiet: bool,
+    json: bool,
+}
+
+// `s` as a JSON string
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Diagnostics {
+    // Report `message` about `location`, a file or `file:line`
+    fn report(&self, level: &str, location: &str, message: &str) {
+        if self.quiet && level != "error" {
+            return;
+        }
+        let _ = if self.json {
+            writeln!(
+                stderr(),
+                "{{\"level\":{},\"location\":{},\"message\":{}}}",
+                json_string(level),
+                json_string(location),
+                json_string(message)
+            )
+        } else if level == "note" {
+            writeln!(stderr(), "{}: {}", location, message)
+        } else {
+            writeln!(stderr(), "{}: {}: {}", location, level, message)
+        };
     }
+
+    // Report an error and exit with a failure status
+    fn fail(&self, location: &str, message: &str) -> ! {
+        self.report("error", location, message);
+        std::process::exit(1);
+    }
+}
+
+// Describe each renamed identifier
+fn summarize_renames(fname: &str, renames: &[Rename], diag: &Diagnostics) {
+    let mut renamed = 0;
+    let mut collisions = 0;
+
+    for rename in renames {
+        let (from, to) = (&rename.from, &rename.to);
+        let from = match rename.scope {
+            Some(ref scope) => format!("{}::{}", scope, from),
+            None => from.to_string(),
+        };
+
+        match rename.reason {
+            "keyword" => diag.report(
+                "note",
+                fname,
+                &format!("{} renamed to {} (Rust keyword)", from, to),
+            ),
+            "numeric-case" => diag.report("note", fname, &format!("case {} named {}", from, to)),
+            _ => diag.report(
+                "warning",
+                fname,
+                &format!("{} differs from {} only by case", from, to),
+            ),
+        }
+        if rename.reason == "case-collision" {
+            collisions += 1;
+        } else {
+            renamed += 1;
+        }
+    }
+
+    diag.report(
+        "note",
+        fname,
+        &format!(
+            "{} identifiers renamed, {} differing only by case",
+            renamed, collisions
+        ),
+    );
+}
+
+// Describe each unused definition
+fn summarize_unused(fname: &str, unused: &[Unused], diag: &Diagnostics) {
+    for defn in unused {
+        let loc = format!("{}:{}", fname, defn.line);
+        diag.report(
+            "warning",
+            &loc,
+            &format!("{} {} is never used", defn.kind, defn.name),
+        );
+    }
+
+    diag.report(
+        "note",
+        fname,
+        &format!("{} unused definitions", unused.len()),
+    );
 }
@@ -9,9 +9,26 @@ use std::io::{Cursor, Write};
 use std::process::Command;
 
 use xdr_codec::Result;
-use xdrgen::generate;
+use xdrgen::{generate_with, Config};
 
 fn build_test(name: &str, xdr_spec: &str) -> Result<()> {
+    build_test_with(name, xdr_spec, &Config::default(), "")
+}
+
+// Generate code for `xdr_spec` with `config`, and build it along with `extra` code in the
+// same module.
+fn build_test_with(name: &str, xdr_spec: &str, config: &Config, extra: &str) -> Result<()> {
+    build_test_features(name, xdr_spec, config, extra, &[])
+}
+
+// As `build_test_with`, enabling `features` of xdr-codec.
+fn build_test_features(
+    name: &str,
+    xdr_spec: &str,
+    config: &Config,
+    extra: &str,
+    features: &[&str],
+) -> Result<()> {
     let tempdir = tempdir::TempDir::new("build").expect("Failed to make tempdir");
     let dir = tempdir.path();
 
@@ -35,13 +52,14 @@ name = "test"
 path = "{}"
 
 [dependencies]
-xdr-codec = {{ path = "{}" }}
+xdr-codec = {{ path = "{}", features = {:?} }}
 "#,
         mainfile.as_os_str().to_string_lossy(),
         std::env::current_dir()?
             .join("../xdr-codec")
             .as_os_str()
-            .to_string_lossy()
+            .to_string_lossy(),
+        features
     );
 
     let template = format!(
@@ -52,11 +70,13 @@ extern crate xdr_codec;
 mod test {{
     use xdr_codec;
     include!("{}");
+    {}
 }}
 
 fn main() {{}}
 "#,
-        testfile.as_os_str().to_string_lossy()
+        testfile.as_os_str().to_string_lossy(),
+        extra
     );
 
     {
@@ -73,7 +93,7 @@ fn main() {{}}
 
     {
         let test = File::create(&testfile)?;
-        generate(name, Cursor::new(xdr_spec.as_bytes()), test)?;
+        generate_with(name, Cursor::new(xdr_spec.as_bytes()), test, config)?;
     }
 
     let compile = {
@@ -159,6 +179,155 @@ default:
     }
 }
 
+#[test]
+fn union_default_repack() {
+    let name = "union_default_repack";
+    let spec = r#"
+enum kind { A = 1, B = 2 };
+union foo switch (int bar) { case 1: int val; default: opaque buf<>; };
+union blat switch (kind k) { case A: void; default: void; };
+"#;
+    let extra = r#"
+#[test]
+fn repack() {
+    use xdr_codec::{Pack, Unpack};
+
+    for input in vec![
+        vec![0, 0, 0, 7, 0, 0, 0, 2, 1, 2, 0, 0],
+        vec![0xff, 0xff, 0xff, 0xfe, 0, 0, 0, 0],
+    ] {
+        let (v, sz) = foo::unpack(&mut &input[..]).unwrap();
+        assert_eq!(sz, input.len());
+        let mut out = Vec::new();
+        assert_eq!(v.pack(&mut out).unwrap(), sz);
+        assert_eq!(out, input);
+    }
+
+    let input = vec![0, 0, 0, 3];
+    let (v, _) = blat::unpack(&mut &input[..]).unwrap();
+    assert_eq!(v, blat::default(3));
+    let mut out = Vec::new();
+    v.pack(&mut out).unwrap();
+    assert_eq!(out, input);
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::default(), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn header() {
+    let name = "header";
+    let spec = r#"
+struct msg {
+    unsigned int xid;
+    int proc;
+    /* @header */
+    opaque body<>;
+    string note<>;
+};
+"#;
+    let extra = r#"
+#[test]
+fn unpack_header() {
+    use xdr_codec::header::{unpack_header, UnpackHeader};
+    use xdr_codec::{Pack, Unpack};
+
+    let m = msg {
+        xid: 7,
+        proc_: 3,
+        body: vec![1; 100],
+        note: "hi".to_string(),
+    };
+    let mut buf = Vec::new();
+    m.pack(&mut buf).unwrap();
+
+    let (hdr, rest) = unpack_header::<msg>(&buf).unwrap();
+    assert_eq!(hdr, msgHeader { xid: 7, proc_: 3 });
+    assert_eq!(rest, &buf[8..]);
+
+    let mut input = rest;
+    let (whole, sz) = msg::unpack_rest(hdr, &mut input).unwrap();
+    assert_eq!(sz, buf.len() - 8);
+    assert_eq!(whole, m);
+    assert_eq!(msg::unpack(&mut &buf[..]).unwrap(), (m, buf.len()));
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::default(), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn lazy() {
+    let name = "lazy";
+    let spec = r#"
+struct inner { int a; string s<>; };
+struct big {
+    unsigned int id;
+    /* @lazy */
+    opaque data<1024>;
+    /* @lazy */
+    inner in;
+    /* @lazy */
+    int nums<>;
+};
+"#;
+    let extra = r#"
+#[test]
+fn lazy_fields() {
+    use xdr_codec::lazy::Lazy;
+    use xdr_codec::{Pack, Unpack};
+
+    let b = big {
+        id: 1,
+        data: Lazy::from_raw(vec![0, 0, 0, 3, 1, 2, 3, 0], |input| {
+            xdr_codec::unpack_opaque_flex(input, Some(1024))
+        }),
+        in_: Lazy::new(inner {
+            a: 2,
+            s: "hi".to_string(),
+        })
+        .unwrap(),
+        nums: Lazy::new(vec![4, 5]).unwrap(),
+    };
+    let mut buf = Vec::new();
+    let sz = b.pack(&mut buf).unwrap();
+    assert_eq!(sz, 4 + 8 + 12 + 12);
+
+    let (u, usz) = big::unpack(&mut &buf[..]).unwrap();
+    assert_eq!(usz, sz);
+    assert_eq!(u, b);
+    assert!(!u.in_.is_decoded());
+    assert_eq!(u.in_.raw(), &buf[12..24]);
+    assert_eq!(u.in_.get().unwrap().s, "hi");
+    assert!(u.in_.is_decoded());
+    assert_eq!(u.data.get().unwrap(), &vec![1, 2, 3]);
+    assert_eq!(u.nums.clone().into_inner().unwrap(), vec![4, 5]);
+
+    let mut out = Vec::new();
+    u.pack(&mut out).unwrap();
+    assert_eq!(out, buf);
+
+    // Bad data in a lazy field is only found when it's used, unless it can't be skipped
+    let mut bad = buf.clone();
+    bad[20] = 0xff;
+    let (u, _) = big::unpack(&mut &bad[..]).unwrap();
+    assert!(u.in_.get().is_err());
+    bad[27] = 9;
+    assert!(big::unpack(&mut &bad[..]).is_err());
+}
+"#;
+
+    let config = Config::new().skip(true);
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
 #[test]
 fn simple() {
     let name = "simple";
@@ -239,6 +408,31 @@ fn enums() {
     }
 }
 
+#[test]
+fn enum_names() {
+    let name = "enum_names";
+    let spec = r#"
+        enum type { A = 1, B = -2, self = 3 };
+    "#;
+    let extra = r#"
+#[test]
+fn names() {
+    use std::convert::TryFrom;
+
+    assert_eq!(type_::try_from(-2).unwrap(), type_::B);
+    assert!(type_::try_from(2).is_err());
+    assert_eq!(i32::from(type_::self_), 3);
+    assert_eq!(type_::self_.as_str(), "self");
+    assert_eq!("A".parse::<type_>().unwrap(), type_::A);
+    assert!("a".parse::<type_>().is_err());
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::default(), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
 #[test]
 fn unions() {
     let name = "unions";
@@ -318,3 +512,921 @@ fn derive_float() {
         panic!("test {} failed: {}", name, e);
     }
 }
+
+#[test]
+fn validate() {
+    let name = "validate";
+    let spec = r#"
+enum kind { A = 1, B = 2 };
+struct foo { kind k; unsigned int v<>; };
+union bar switch (kind k) { case A: foo f; case B: void; };
+typedef foo foos<10>;
+"#;
+    let extra = r#"
+impl xdr_codec::Validate for kind {}
+impl xdr_codec::Validate for bar {}
+impl xdr_codec::Validate for foos {}
+impl xdr_codec::Validate for foo {
+    fn validate(&self) -> xdr_codec::Result<()> {
+        if self.v.is_empty() {
+            Err(xdr_codec::Error::validation("empty"))
+        } else {
+            Ok(())
+        }
+    }
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::new().validate(true), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn schema_fingerprint() {
+    let name = "schema_fingerprint";
+    let spec = r#"
+const MAX = 4;
+struct foo { int a<MAX>; hyper b; };
+"#;
+    let extra = r#"
+fn check() -> [u8; 32] {
+    SCHEMA_FINGERPRINT
+}
+"#;
+
+    let config = Config::new().schema_fingerprint(true);
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn bitmasks() {
+    let name = "bitmasks";
+    let spec = r#"
+const MODE_READ = 1;
+const MODE_WRITE = 2;
+const MODE_EXEC = 4;
+struct file { unsigned int mode; };
+"#;
+    let extra = r#"
+#[test]
+fn flags() {
+    use xdr_codec::{pack, unpack};
+
+    let mode = MODE::READ | MODE::EXEC;
+    assert!(mode.contains(MODE::READ));
+    assert!(!mode.intersects(MODE::WRITE));
+    assert_eq!(mode.bits(), (MODE_READ | MODE_EXEC) as u32);
+    assert_eq!(!mode, MODE::WRITE);
+    assert_eq!(MODE::from_bits(8), None);
+    assert_eq!(MODE::from_bits_truncate(9), MODE::READ);
+
+    let mut buf = Vec::new();
+    pack(&mode, &mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 5]);
+    let f: file = unpack(&mut &buf[..]).unwrap();
+    assert_eq!(MODE(f.mode), mode);
+    // Undefined bits are kept
+    assert_eq!(unpack::<_, MODE>(&mut &[0, 0, 1, 1][..]).unwrap().bits(), 0x101);
+}
+"#;
+
+    let config = Config::new().bitmasks(true);
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn type_overrides() {
+    let name = "type_overrides";
+    let spec = r#"
+struct nfstime3 { unsigned int seconds; unsigned int nseconds; };
+typedef unsigned hyper fileid3;
+typedef nfstime3 mtime3;
+struct attr { fileid3 id; mtime3 mtime; nfstime3 atime; };
+union res switch (int s) { case 0: attr a; case 1: nfstime3 t; default: void; };
+"#;
+    let extra = r#"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Id(pub u64);
+
+impl<Out: xdr_codec::Write> xdr_codec::Pack<Out> for Id {
+    fn pack(&self, out: &mut Out) -> xdr_codec::Result<usize> {
+        self.0.pack(out)
+    }
+}
+
+impl<In: xdr_codec::Read> xdr_codec::Unpack<In> for Id {
+    fn unpack(input: &mut In) -> xdr_codec::Result<(Id, usize)> {
+        let (v, sz) = xdr_codec::Unpack::unpack(input)?;
+        Ok((Id(v), sz))
+    }
+}
+
+mod time_shim {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use xdr_codec;
+    use super::nfstime3;
+
+    pub fn pack<Out: xdr_codec::Write>(val: &SystemTime, out: &mut Out) -> xdr_codec::Result<usize> {
+        let d = val.duration_since(UNIX_EPOCH).map_err(|_| "time before epoch".to_string())?;
+        let t = nfstime3 { seconds: d.as_secs() as u32, nseconds: d.subsec_nanos() };
+        xdr_codec::Pack::pack(&t, out)
+    }
+
+    pub fn unpack<In: xdr_codec::Read>(input: &mut In) -> xdr_codec::Result<(SystemTime, usize)> {
+        let (t, sz): (nfstime3, usize) = xdr_codec::Unpack::unpack(input)?;
+        Ok((UNIX_EPOCH + Duration::new(t.seconds as u64, t.nseconds), sz))
+    }
+}
+
+#[test]
+fn overrides() {
+    use std::time::{Duration, UNIX_EPOCH};
+    use xdr_codec::{pack, unpack};
+
+    let a = attr {
+        id: Id(7),
+        mtime: UNIX_EPOCH + Duration::new(1, 2),
+        atime: UNIX_EPOCH + Duration::new(3, 4),
+    };
+    let mut buf = Vec::new();
+    pack(&res::Const0(a.clone()), &mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4]);
+    match unpack(&mut &buf[..]).unwrap() {
+        res::Const0(ref b) => assert_eq!(*b, a),
+        r => panic!("unexpected {:?}", r),
+    }
+}
+"#;
+
+    let config = Config::new()
+        .override_type("fileid3", "Id", None)
+        .override_type("nfstime3", "std::time::SystemTime", Some("time_shim"));
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn borrowed() {
+    let name = "borrowed";
+    let spec = r#"
+typedef opaque handle<64>;
+struct attr { unsigned int mode; };
+struct entry { handle fh; string name<255>; opaque verf[8]; attr a; int tags<>; };
+"#;
+    let extra = r#"
+#[test]
+fn borrowed() {
+    use xdr_codec::pack;
+    use xdr_codec::slice::SliceCursor;
+
+    let e = entry {
+        fh: handle(vec![1, 2, 3]),
+        name: "file".to_string(),
+        verf: [9; 8],
+        a: attr { mode: 0o644 },
+        tags: vec![5, 6],
+    };
+    let mut buf = Vec::new();
+    pack(&e, &mut buf).unwrap();
+
+    let mut cur = SliceCursor::new(&buf);
+    let r: entryRef = cur.unpack_ref().unwrap();
+    assert_eq!(cur.remaining(), 0);
+    assert_eq!(r.fh, &[1, 2, 3][..]);
+    assert_eq!(r.name, "file");
+    assert_eq!(r.verf, &[9; 8][..]);
+    // The borrowed data points into the buffer
+    let range = buf.as_ptr_range();
+    assert!(range.contains(&r.name.as_ptr()));
+    assert_eq!(r.a, e.a);
+
+    assert_eq!(entryRef::from(&e), r);
+    assert_eq!(r.clone().into_owned(), e);
+    assert_eq!(entry::from(r), e);
+
+    // Errors name the field, as for the owned type
+    let mut bad = buf.clone();
+    bad[12] = 0xff;
+    let err = SliceCursor::new(&bad).unpack_ref::<entryRef>().unwrap_err();
+    assert!(err.to_string().contains("entry.name"), "{}", err);
+}
+"#;
+
+    let config = Config::new().borrowed(true);
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn xdr_strings() {
+    let name = "xdr_strings";
+    let spec = r#"
+typedef string name<255>;
+struct foo { string a<>; string b<10>; name c; string d[4]; };
+union bar switch (int x) { case 0: string s<>; default: void; };
+"#;
+    let extra = r#"
+fn check(f: &foo) -> Option<&str> {
+    let _: &xdr_codec::XdrString = &f.c.0;
+    f.a.to_str()
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::new().xdr_strings(true), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn quadruple() {
+    let name = "quadruple";
+    let spec = r#"
+typedef quadruple quad;
+struct foo { quadruple a; quad b; quadruple c[2]; quadruple d<>; quadruple *e; };
+union bar switch (int x) { case 0: quadruple q; default: void; };
+"#;
+
+    if let Err(e) = build_test(name, spec) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn skip() {
+    let name = "skip";
+    let spec = r#"
+enum kind { A = 1, B = 2 };
+struct foo { kind k; unsigned int v<>; string s<10>; opaque o[3]; hyper h[2]; foo *next; };
+union bar switch (kind k) { case A: foo f; case B: void; };
+typedef foo foos<10>;
+typedef opaque blob[5];
+"#;
+    let extra = r#"
+#[test]
+fn skip_all() {
+    use xdr_codec::{Pack, Skip};
+
+    let f = || foo {
+        k: kind::A,
+        v: vec![1, 2],
+        s: "hello".into(),
+        o: [1, 2, 3],
+        h: [4, 5],
+        next: Some(Box::new(foo { k: kind::B, v: vec![], s: "".into(), o: [0; 3], h: [0; 2], next: None })),
+    };
+    let vals = (foos(vec![f(), f()]), bar::A(f()), bar::B, blob([9; 5]));
+
+    let mut buf = Vec::new();
+    let sz = vals.0.pack(&mut buf).unwrap() + vals.1.pack(&mut buf).unwrap()
+        + vals.2.pack(&mut buf).unwrap() + vals.3.pack(&mut buf).unwrap();
+    42u32.pack(&mut buf).unwrap();
+
+    let mut input = &buf[..];
+    let skipped = foos::skip(&mut input).unwrap() + bar::skip(&mut input).unwrap()
+        + bar::skip(&mut input).unwrap() + blob::skip(&mut input).unwrap();
+    assert_eq!(skipped, sz);
+    assert_eq!(xdr_codec::unpack::<_, u32>(&mut input).unwrap(), 42);
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::new().skip(true), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn arbitrary() {
+    let name = "arbitrary";
+    let spec = r#"
+enum kind { A = 1, B = 2 };
+struct foo { kind k; unsigned int v<2>; string s<10>; opaque o[3]; opaque p<5>; hyper h[2]; foo *next; };
+union bar switch (kind k) { case A: foo f; case B: void; };
+typedef foo foos<3>;
+struct top { foos f; bar b; };
+"#;
+    let extra = r#"
+#[test]
+fn arbitrary_roundtrip() {
+    use xdr_codec::arbitrary::{Arbitrary, Unstructured};
+    use xdr_codec::{Pack, Unpack};
+
+    let data: Vec<u8> = (0..8192u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut u = Unstructured::new(&data);
+
+    for _ in 0..20 {
+        let v = top::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        v.pack(&mut buf).unwrap();
+
+        let (w, sz) = top::unpack(&mut &buf[..]).unwrap();
+        assert_eq!(sz, buf.len());
+        let mut rebuf = Vec::new();
+        w.pack(&mut rebuf).unwrap();
+        assert_eq!(buf, rebuf);
+    }
+}
+"#;
+
+    let config = Config::new().arbitrary(true);
+    if let Err(e) = build_test_features(name, spec, &config, extra, &["arbitrary"]) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn bounded_vecs() {
+    let name = "bounded_vecs";
+    let spec = r#"
+const MAX = 2;
+struct foo { int a<3>; opaque o<4>; };
+typedef foo foos<MAX>;
+union bar switch (int x) { case 0: hyper h<1>; default: void; };
+"#;
+    let extra = r#"
+#[test]
+fn bounded_roundtrip() {
+    use std::convert::TryFrom;
+    use xdr_codec::arbitrary::{Arbitrary, Unstructured};
+    use xdr_codec::{BoundedVec, Pack, Skip, Unpack};
+
+    let mut a = BoundedVec::new();
+    a.push(1).unwrap();
+    let f = foo { a, o: vec![1, 2] };
+    assert!(f.a.clone().push(2).is_ok());
+
+    let v = foos(BoundedVec::try_from(vec![f.clone(), f]).unwrap());
+    let mut buf = Vec::new();
+    let sz = v.pack(&mut buf).unwrap();
+    assert_eq!(foos::unpack(&mut &buf[..]).unwrap(), (v, sz));
+    assert_eq!(foos::skip(&mut &buf[..]).unwrap(), sz);
+
+    // Over the limit
+    let mut buf = Vec::new();
+    3u32.pack(&mut buf).unwrap();
+    assert!(foos::unpack(&mut &buf[..]).is_err());
+
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut u = Unstructured::new(&data);
+    for _ in 0..20 {
+        let v = bar::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        v.pack(&mut buf).unwrap();
+        assert_eq!(bar::unpack(&mut &buf[..]).unwrap().0, v);
+    }
+}
+"#;
+
+    let config = Config::new().bounded_vecs(true).skip(true).arbitrary(true);
+    if let Err(e) = build_test_features(name, spec, &config, extra, &["arbitrary"]) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn inline_vecs() {
+    let name = "inline_vecs";
+    let spec = r#"
+const MAX = 2;
+struct foo { int a<3>; int b<>; };
+typedef foo foos<MAX>;
+union bar switch (int x) { case 0: hyper h<1>; default: void; };
+"#;
+    let extra = r#"
+#[test]
+fn inline_roundtrip() {
+    use xdr_codec::arbitrary::{Arbitrary, Unstructured};
+    use xdr_codec::inline::ArrayVec;
+    use xdr_codec::{Pack, Skip, Unpack};
+
+    let mut a = ArrayVec::new();
+    a.push(1);
+    let f = foo { a, b: vec![2; 10] };
+
+    let v = foos(ArrayVec::from([f.clone(), f]));
+    let mut buf = Vec::new();
+    let sz = v.pack(&mut buf).unwrap();
+    assert_eq!(foos::unpack(&mut &buf[..]).unwrap(), (v, sz));
+    assert_eq!(foos::skip(&mut &buf[..]).unwrap(), sz);
+
+    // Over the limit
+    let mut buf = Vec::new();
+    3u32.pack(&mut buf).unwrap();
+    assert!(foos::unpack(&mut &buf[..]).is_err());
+
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut u = Unstructured::new(&data);
+    for _ in 0..20 {
+        let v = bar::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        v.pack(&mut buf).unwrap();
+        assert_eq!(bar::unpack(&mut &buf[..]).unwrap().0, v);
+    }
+}
+"#;
+
+    let config = Config::new()
+        .inline_vecs(Some(8))
+        .skip(true)
+        .arbitrary(true);
+    if let Err(e) = build_test_features(name, spec, &config, extra, &["arbitrary", "arrayvec"]) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn opt_arrays() {
+    let name = "opt_arrays";
+    let spec = r#"
+struct node { int val; hyper next<1>; };
+typedef node maybe<1>;
+union bar switch (int x) { case 0: hyper h<1>; default: void; };
+"#;
+    let extra = r#"
+#[test]
+fn optarray_roundtrip() {
+    use xdr_codec::arbitrary::{Arbitrary, Unstructured};
+    use xdr_codec::{OptArray, Pack, Skip, Unpack};
+
+    let v = maybe(OptArray(Some(Box::new(node { val: 1, next: OptArray(Some(2)) }))));
+    let mut buf = Vec::new();
+    let sz = v.pack(&mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2]);
+    assert_eq!(maybe::unpack(&mut &[0, 0, 0, 0][..]).unwrap(), (maybe(OptArray(None)), 4));
+    assert_eq!(maybe::unpack(&mut &buf[..]).unwrap(), (v, sz));
+    assert_eq!(maybe::skip(&mut &buf[..]).unwrap(), sz);
+
+    // More than one element
+    let mut buf = Vec::new();
+    2u32.pack(&mut buf).unwrap();
+    assert!(maybe::unpack(&mut &buf[..]).is_err());
+
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut u = Unstructured::new(&data);
+    for _ in 0..20 {
+        let v = bar::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        v.pack(&mut buf).unwrap();
+        assert_eq!(bar::unpack(&mut &buf[..]).unwrap().0, v);
+    }
+}
+"#;
+
+    let config = Config::new().opt_arrays(true).skip(true).arbitrary(true);
+    if let Err(e) = build_test_features(name, spec, &config, extra, &["arbitrary"]) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn int_types() {
+    let name = "int_types";
+    let spec = r#"
+typedef int offset;
+typedef unsigned int uid;
+typedef hyper ino;
+struct rec {
+    int a;
+    unsigned int b;
+    offset c;
+    int d<4>;
+    unsigned int e[2];
+    int *f;
+    offset g<>;
+    int one<1>;
+    uid owner;
+    ino node;
+};
+union res switch (int s) { case 0: int v; default: void; };
+"#;
+    let extra = r#"
+#[test]
+fn wide_ints() {
+    use xdr_codec::arbitrary::{Arbitrary, Unstructured};
+    use xdr_codec::{Pack, Skip, Unpack};
+
+    let r = rec {
+        a: -1,
+        b: 0xffff_ffff,
+        c: 5,
+        d: vec![1, -2],
+        e: [3, 4],
+        f: Some(6),
+        g: vec![7],
+        one: vec![8],
+        owner: uid::from(9),
+        node: ino(10),
+    };
+    let _: (i64, u64, i64, u64, i64) = (r.a, r.b, r.c, r.owner.into(), r.node.0);
+
+    let mut buf = Vec::new();
+    let sz = r.pack(&mut buf).unwrap();
+    assert_eq!(&buf[..12], &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 5]);
+    assert_eq!(sz, 4 * 3 + 12 + 8 + 8 + 8 + 8 + 4 + 8);
+    assert_eq!(rec::unpack(&mut &buf[..]).unwrap(), (r.clone(), sz));
+    assert_eq!(rec::skip(&mut &buf[..]).unwrap(), sz);
+
+    // Values which don't fit aren't packed
+    let big = rec { a: 1 << 40, ..r.clone() };
+    let err = big.pack(&mut Vec::new()).unwrap_err();
+    assert!(err.to_string().contains("out of range"), "{}", err);
+    let neg = rec { e: [0, 1 << 32], ..r.clone() };
+    assert!(neg.pack(&mut Vec::new()).is_err());
+
+    // Not a status union, since its arm is widened
+    let v = res::Const0(-3);
+    let mut buf = Vec::new();
+    v.pack(&mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 0, 0xff, 0xff, 0xff, 0xfd]);
+    assert_eq!(res::unpack(&mut &buf[..]).unwrap().0, v);
+
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut u = Unstructured::new(&data);
+    for _ in 0..20 {
+        let v = rec::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        v.pack(&mut buf).unwrap();
+        assert_eq!(rec::unpack(&mut &buf[..]).unwrap().0, v);
+    }
+}
+"#;
+
+    let config = Config::new()
+        .int_type("i64")
+        .uint_type("u64")
+        .newtype("uid")
+        .newtype("ino")
+        .opt_arrays(true)
+        .status_unions(true)
+        .skip(true)
+        .arbitrary(true);
+    if let Err(e) = build_test_features(name, spec, &config, extra, &["arbitrary"]) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn status_unions() {
+    let name = "status_unions";
+    let spec = r#"
+enum stat { OK = 0, ENOENT = 2, EIO = 5 };
+struct readok { opaque data<>; bool eof; };
+union readres switch (stat status) { case OK: readok resok; default: void; };
+struct reply { unsigned int xid; readres res; };
+"#;
+    let extra = r#"
+#[test]
+fn status_roundtrip() {
+    use xdr_codec::arbitrary::{Arbitrary, Unstructured};
+    use xdr_codec::{Pack, Skip, StatusUnion, Unpack};
+
+    let ok = reply { xid: 1, res: StatusUnion::ok(readok { data: vec![1, 2], eof: true }) };
+    let mut buf = Vec::new();
+    let sz = ok.pack(&mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(reply::unpack(&mut &buf[..]).unwrap(), (ok, sz));
+    assert_eq!(reply::skip(&mut &buf[..]).unwrap(), sz);
+
+    // The status of a failure is kept
+    let fail = reply { xid: 2, res: StatusUnion::err(stat::EIO as i32, ()) };
+    let mut buf = Vec::new();
+    let sz = fail.pack(&mut buf).unwrap();
+    assert_eq!(buf, vec![0, 0, 0, 2, 0, 0, 0, 5]);
+    let (v, _) = reply::unpack(&mut &buf[..]).unwrap();
+    assert_eq!(v.res.into_result(), Err((stat::EIO as i32, ())));
+    assert_eq!(reply::skip(&mut &buf[..]).unwrap(), sz);
+
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut u = Unstructured::new(&data);
+    for _ in 0..20 {
+        let v = reply::arbitrary(&mut u).unwrap();
+        let mut buf = Vec::new();
+        v.pack(&mut buf).unwrap();
+        assert_eq!(reply::unpack(&mut &buf[..]).unwrap().0, v);
+    }
+}
+"#;
+
+    let config = Config::new().status_unions(true).skip(true).arbitrary(true);
+    if let Err(e) = build_test_features(name, spec, &config, extra, &["arbitrary"]) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn describe() {
+    let name = "describe";
+    let spec = r#"
+const MAX = 2;
+enum kind { A = 1, B = 2 };
+typedef int num;
+struct foo { num a; opaque b[4]; foo *next; };
+union bar switch (kind k) { case A: foo f<MAX>; case B: void; };
+typedef bar bars<>;
+"#;
+    let extra = r#"
+#[test]
+fn descriptors() {
+    use xdr_codec::describe::{Describe, Kind, Size, TypeDesc};
+
+    let d = foo::describe();
+    assert_eq!(d.name, "foo");
+    match d.field("a").unwrap().ty {
+        TypeDesc::Int => (),
+        ref ty => panic!("bad a {:?}", ty),
+    }
+    match d.field("b").unwrap().ty {
+        TypeDesc::Opaque(Size::Fixed(4)) => (),
+        ref ty => panic!("bad b {:?}", ty),
+    }
+
+    assert_eq!(kind::describe().enum_name(2), Some("B"));
+
+    let d = bar::describe();
+    match d.case(1).unwrap().field.as_ref().unwrap().ty {
+        TypeDesc::Array(ref elem, Size::Flex(Some(2))) => {
+            assert_eq!(elem.descriptor().unwrap().name, "foo")
+        }
+        ref ty => panic!("bad f {:?}", ty),
+    }
+    assert!(d.case(2).unwrap().field.is_none());
+    assert!(d.case(3).is_none());
+
+    match bars::describe().kind {
+        Kind::Newtype(TypeDesc::Array(_, Size::Flex(None))) => (),
+        ref kind => panic!("bad bars {:?}", kind),
+    }
+}
+"#;
+
+    let config = Config::new().describe(true);
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn field_context() {
+    let name = "field_context";
+    let spec = r#"
+struct inner { unsigned int v<2>; };
+struct outer { int i; inner in; };
+union top switch (int x) { case 1: outer o; };
+"#;
+    let extra = r#"
+#[test]
+fn context() {
+    use xdr_codec::{ErrorKind, Pack, Unpack};
+
+    let mut buf = Vec::new();
+    1i32.pack(&mut buf).unwrap();
+    5i32.pack(&mut buf).unwrap();
+    (&[1u32, 2, 3][..]).pack(&mut buf).unwrap();
+
+    let err = top::unpack(&mut &buf[..]).unwrap_err();
+    assert_eq!(err.chained().to_string(), "top::Const1: outer.in: inner.v: invalid array len: '2'");
+    match *err.root_kind() {
+        ErrorKind::InvalidLen(2) => (),
+        ref kind => panic!("bad kind {:?}", kind),
+    }
+}
+"#;
+
+    if let Err(e) = build_test_with(name, spec, &Config::default(), extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}
+
+#[test]
+fn golden_tests() {
+    use xdr_codec::Pack;
+
+    let name = "golden_tests";
+    let spec = r#"
+struct foo { int a; string b<>; };
+typedef foo foos<2>;
+enum type { A = 1, B = 2 };
+"#;
+
+    let tempdir = tempdir::TempDir::new("samples").expect("Failed to make tempdir");
+    let dir = tempdir.path();
+    let sample = |fname: &str, bytes: &[u8]| {
+        File::create(dir.join(fname))
+            .and_then(|mut f| f.write_all(bytes))
+            .unwrap()
+    };
+
+    let mut foo = Vec::new();
+    1i32.pack(&mut foo).unwrap();
+    xdr_codec::pack_string("hi", None, &mut foo).unwrap();
+    sample("foo.bin", &foo);
+    let mut foos = Vec::new();
+    2u32.pack(&mut foos).unwrap();
+    foos.extend_from_slice(&foo);
+    foos.extend_from_slice(&foo);
+    sample("foos.capture-1", &foos);
+    sample("type.bin", &[0, 0, 0, 2]);
+    sample(".hidden", b"ignored");
+
+    let mut extra = Vec::new();
+    xdrgen::golden_tests(name, Cursor::new(spec.as_bytes()), dir, &mut extra).unwrap();
+    let extra = String::from_utf8(extra).unwrap();
+    println!("{}", extra);
+    assert!(extra.contains("fn golden_foo_bin()"));
+    assert!(extra.contains("fn golden_foos_capture_1()"));
+    assert!(extra.contains("<type_ as Unpack<_>>"));
+    assert!(!extra.contains("hidden"));
+
+    if let Err(e) = build_test_with(name, spec, &Config::default(), &extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+
+    // Samples must be named for a type
+    sample("bar.bin", &foo);
+    let err =
+        xdrgen::golden_tests(name, Cursor::new(spec.as_bytes()), dir, &mut Vec::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "sample bar.bin: no type bar in golden_tests"
+    );
+}
+
+#[test]
+fn golden_tests_fail() {
+    let name = "golden_tests_fail";
+    let spec = "struct foo { int a; };";
+
+    let tempdir = tempdir::TempDir::new("samples").expect("Failed to make tempdir");
+    let dir = tempdir.path();
+    // Trailing data
+    File::create(dir.join("foo.bin"))
+        .and_then(|mut f| f.write_all(&[0, 0, 0, 1, 0, 0, 0, 2]))
+        .unwrap();
+
+    let mut extra = Vec::new();
+    xdrgen::golden_tests(name, Cursor::new(spec.as_bytes()), dir, &mut extra).unwrap();
+    let extra = String::from_utf8(extra).unwrap();
+
+    assert!(build_test_with(name, spec, &Config::default(), &extra).is_err());
+}
+
+#[test]
+fn includes() {
+    let name = "includes";
+
+    let tempdir = tempdir::TempDir::new("includes").expect("Failed to make tempdir");
+    let dir = tempdir.path();
+    let outdir = dir.join("out");
+    create_dir_all(dir.join("sub")).unwrap();
+    create_dir_all(&outdir).unwrap();
+    let spec = |fname: &str, text: &str| {
+        File::create(dir.join(fname))
+            .and_then(|mut f| f.write_all(text.as_bytes()))
+            .unwrap()
+    };
+
+    spec(
+        "sub/base.x",
+        "typedef unsigned int uint32;\nconst MAXNAME = 255;\n",
+    );
+    spec(
+        "sub/common.x",
+        r#"#include "base.x"
+struct fhandle { opaque data<MAXNAME>; uint32 gen; };
+enum status { OK = 0, ERR = 1 };
+"#,
+    );
+    spec(
+        "nfs.x",
+        r#"#include <sub/common.x>
+struct lookupres { status st; fhandle fh; string name<MAXNAME>; uint32 count; };
+"#,
+    );
+
+    let mut graph = Vec::new();
+    xdrgen::include_graph(dir.join("nfs.x"), &mut graph).unwrap();
+    let graph = String::from_utf8(graph).unwrap();
+    assert_eq!(
+        graph.replace(&format!("{}/", dir.display()), ""),
+        "nfs.x: sub/common.x\nsub/common.x: sub/base.x\nsub/base.x:\n"
+    );
+
+    let config = Config::new();
+    let inputs = xdrgen::generate_includes(dir.join("nfs.x"), &outdir, &config).unwrap();
+    assert_eq!(inputs.len(), 3);
+
+    let code = std::fs::read_to_string(outdir.join("nfs_xdr.rs")).unwrap();
+    assert!(code.contains("pub struct lookupres"));
+    assert!(!code.contains("pub struct fhandle"));
+    assert!(!code.contains("MAXNAME : i64"));
+
+    // Only the outputs whose inputs changed are written again
+    let modified = || -> Vec<_> {
+        ["base", "common", "nfs"]
+            .iter()
+            .map(|module| {
+                std::fs::metadata(outdir.join(format!("{}_xdr.rs", module)))
+                    .and_then(|m| m.modified())
+                    .unwrap()
+            })
+            .collect()
+    };
+    let before = modified();
+    xdrgen::generate_includes(dir.join("nfs.x"), &outdir, &config).unwrap();
+    let after = modified();
+    assert_eq!(before, after);
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    spec(
+        "sub/common.x",
+        r#"#include "base.x"
+struct fhandle { opaque data<MAXNAME>; uint32 gen; };
+enum status { OK = 0, ERR = 1, STALE = 2 };
+"#,
+    );
+    xdrgen::generate_includes(dir.join("nfs.x"), &outdir, &config).unwrap();
+    let after = modified();
+    assert_eq!(before[0], after[0]);
+    assert!(before[1] != after[1]);
+    assert!(before[2] != after[2]);
+
+    let extra = format!(
+        r#"
+include!("{}");
+
+#[test]
+fn modules() {{
+    let res = nfs::lookupres {{
+        st: common::status::STALE,
+        fh: common::fhandle {{ data: vec![1, 2], gen: 3 }},
+        name: "x".to_string(),
+        count: base::MAXNAME as base::uint32,
+    }};
+    let mut buf = Vec::new();
+    xdr_codec::pack(&res, &mut buf).unwrap();
+    let res: nfs::lookupres = xdr_codec::unpack(&mut &buf[..]).unwrap();
+    assert_eq!(res.fh.data, vec![1, 2]);
+    assert_eq!(res.count, 255);
+}}
+"#,
+        outdir.join("nfs_xdr_modules.rs").display()
+    );
+
+    if let Err(e) = build_test_with(name, "\n", &config, &extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+
+    // Missing includes are reported
+    spec("nfs.x", "#include \"missing.x\"\n");
+    let err = xdrgen::generate_includes(dir.join("nfs.x"), &outdir, &config).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{}: can't find included file missing.x",
+            dir.join("nfs.x").display()
+        )
+    );
+}
+
+#[test]
+fn prefixes() {
+    let name = "prefixes";
+    let spec = r#"
+const NFS3_FHSIZE = 8;
+typedef opaque nfs3_fh<NFS3_FHSIZE>;
+enum nfs3_stat { NFS3_OK = 0, NFS3ERR_PERM = 1 };
+struct nfs3_attr { nfs3_fh fh; unsigned int mode; };
+union nfs3_res switch (nfs3_stat status) { case NFS3_OK: nfs3_attr attr; default: void; };
+struct reply { nfs3_res res; };
+"#;
+    let extra = r#"
+#[test]
+fn prefix_modules() {
+    use xdr_codec::{Pack, Unpack};
+
+    let r = reply {
+        res: nfs3::res::NFS3_OK(nfs3::attr {
+            fh: nfs3::fh(vec![1; FHSIZE as usize]),
+            mode: 0o644,
+        }),
+    };
+    let mut buf = Vec::new();
+    let sz = r.pack(&mut buf).unwrap();
+    assert_eq!(reply::unpack(&mut &buf[..]).unwrap(), (r, sz));
+    assert_eq!(nfs3::stat::NFS3ERR_PERM.as_str(), "NFS3ERR_PERM");
+}
+"#;
+    let config = Config::new()
+        .strip_prefix("nfs3_", Some("nfs3"))
+        .strip_prefix("NFS3_", None);
+
+    if let Err(e) = build_test_with(name, spec, &config, extra) {
+        panic!("test {} failed: {}", name, e);
+    }
+}